@@ -0,0 +1,123 @@
+// Checks every literal `invoke` payload in the frontend against the Rust command it calls.
+// Tauri 1 commands take their arguments by camelCase key, so `{ profile_id }` for a
+// `profile_id: i64` parameter fails at runtime with a missing-argument error.
+
+import { test } from 'node:test';
+import assert from 'node:assert/strict';
+import { readFileSync, readdirSync, statSync } from 'node:fs';
+import { dirname, join, relative } from 'node:path';
+import { fileURLToPath } from 'node:url';
+
+const root = join(dirname(fileURLToPath(import.meta.url)), '..');
+
+// Components whose calls are checked. Older components still send snake_case keys
+// and are added here as they are fixed.
+const CHECKED = [
+  'src/components/PasswordManager.tsx',
+  'src/components/OTPAuthenticator.tsx',
+  'src/components/WebScraper.tsx',
+];
+
+// Arguments injected by Tauri rather than sent by the frontend
+const INJECTED = /^(State|AppHandle|Window|tauri::State|tauri::AppHandle|tauri::Window)\b/;
+
+const camelCase = (name) => name.replace(/_([a-z0-9])/g, (_, c) => c.toUpperCase());
+
+// Command name -> { required, optional } camelCase argument names
+function commands() {
+  const source = readFileSync(join(root, 'src-tauri/src/main.rs'), 'utf8');
+  const result = new Map();
+  const pattern = /#\[tauri::command\]\s*(?:pub\s+)?(?:async\s+)?fn\s+(\w+)\s*(?:<[^>]*>)?\s*\(([^)]*)\)/g;
+  for (const [, name, params] of source.matchAll(pattern)) {
+    const required = [];
+    const optional = [];
+    for (const param of params.split(/,(?![^<]*>)/)) {
+      const match = param.trim().match(/^(?:mut\s+)?(\w+)\s*:\s*(.+)$/s);
+      if (!match || INJECTED.test(match[2].trim())) continue;
+      (match[2].trim().startsWith('Option<') ? optional : required).push(camelCase(match[1]));
+    }
+    result.set(name, { required, optional });
+  }
+  return result;
+}
+
+function sourceFiles(dir) {
+  return readdirSync(dir).flatMap((entry) => {
+    const path = join(dir, entry);
+    if (statSync(path).isDirectory()) return sourceFiles(path);
+    return /\.tsx?$/.test(entry) ? [path] : [];
+  });
+}
+
+// Top-level keys of an object literal body, or null if it isn't a plain literal
+function objectKeys(body) {
+  const keys = [];
+  let depth = 0;
+  let current = '';
+  for (const char of body + ',') {
+    if ('{[('.includes(char)) depth++;
+    if ('}])'.includes(char)) depth--;
+    if (char === ',' && depth === 0) {
+      const entry = current.trim();
+      current = '';
+      if (!entry) continue;
+      if (entry.startsWith('...')) return null;
+      const key = entry.match(/^(\w+)\s*(?::|$)/);
+      if (!key) return null;
+      keys.push(key[1]);
+    } else {
+      current += char;
+    }
+  }
+  return keys;
+}
+
+// `invoke('name', { ... })` calls with a literal command name and payload
+function invokeCalls() {
+  const calls = [];
+  for (const file of sourceFiles(join(root, 'src'))) {
+    if (!CHECKED.includes(relative(root, file))) continue;
+    const source = readFileSync(file, 'utf8');
+    const pattern = /\binvoke(?:<[^(]*?>)?\(\s*'(\w+)'\s*(?:,\s*\{)?/g;
+    for (const match of source.matchAll(pattern)) {
+      const line = source.slice(0, match.index).split('\n').length;
+      const where = `${relative(root, file)}:${line}`;
+      if (!match[0].endsWith('{')) {
+        calls.push({ command: match[1], keys: [], where });
+        continue;
+      }
+      // Find the matching closing brace of the payload
+      let depth = 1;
+      let end = match.index + match[0].length;
+      while (depth > 0 && end < source.length) {
+        if (source[end] === '{') depth++;
+        if (source[end] === '}') depth--;
+        end++;
+      }
+      const keys = objectKeys(source.slice(match.index + match[0].length, end - 1));
+      if (keys) calls.push({ command: match[1], keys, where });
+    }
+  }
+  return calls;
+}
+
+test('invoke payloads match the Rust command arguments', () => {
+  const known = commands();
+  const problems = [];
+  for (const { command, keys, where } of invokeCalls()) {
+    const args = known.get(command);
+    if (!args) {
+      problems.push(`${where}: unknown command '${command}'`);
+      continue;
+    }
+    for (const key of keys) {
+      if (!args.required.includes(key) && !args.optional.includes(key)) {
+        problems.push(`${where}: '${command}' has no argument '${key}'`);
+      }
+    }
+    for (const key of args.required) {
+      if (!keys.includes(key)) problems.push(`${where}: '${command}' is missing '${key}'`);
+    }
+  }
+  assert.deepEqual(problems, []);
+});
@@ -2,6 +2,19 @@
 // Handles embeddings and LLM inference
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+pub const DEFAULT_EMBEDDING_MODEL: &str = "all-minilm";
+pub const DEFAULT_GENERATION_MODEL: &str = "llama3.2:3b";
+
+/// Event emitted to the frontend for every streamed token (and once more when finished)
+pub const GENERATION_EVENT: &str = "ai-generation";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EmbeddingRequest {
@@ -21,37 +34,578 @@ pub struct InferenceRequest {
     pub stream: bool,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InferenceResponse {
+    pub model: String,
+    pub response: String,
+    pub done: bool,
+}
+
+/// One line of Ollama's NDJSON stream
+#[derive(Debug, Clone, Deserialize)]
+pub struct InferenceChunk {
+    #[serde(default)]
+    pub response: String,
+    #[serde(default)]
+    pub done: bool,
+    pub error: Option<String>,
+}
+
+/// Payload of `GENERATION_EVENT`, keyed by the caller's request id
+#[derive(Debug, Clone, Serialize)]
+pub struct GenerationEvent {
+    pub request_id: String,
+    pub token: String,
+    pub done: bool,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+/// Connection settings for the local Ollama server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiConfig {
+    pub base_url: String,
+    pub embedding_model: String,
+    pub generation_model: String,
+    pub timeout_secs: u64,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        AiConfig {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            generation_model: DEFAULT_GENERATION_MODEL.to_string(),
+            // Small local models can take a while on CPU-only machines
+            timeout_secs: 120,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AiStatus {
+    pub running: bool,
+    pub config: AiConfig,
+}
+
+#[derive(Debug)]
+pub enum AiError {
+    /// Ollama could not be reached (not installed, not started, wrong URL)
+    Unavailable(String),
+    /// The request took longer than the configured timeout
+    Timeout,
+    /// Ollama answered with a non-success status code
+    Api { status: u16, message: String },
+    /// The response body did not have the expected shape
+    InvalidResponse(String),
+    /// Nothing to send (empty prompt or text)
+    EmptyInput,
+    /// The caller cancelled a streaming generation
+    Cancelled,
+}
+
+impl fmt::Display for AiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AiError::Unavailable(e) => write!(f, "Ollama is not reachable: {}", e),
+            AiError::Timeout => write!(f, "Ollama request timed out"),
+            AiError::Api { status, message } => write!(f, "Ollama returned {}: {}", status, message),
+            AiError::InvalidResponse(e) => write!(f, "Unexpected response from Ollama: {}", e),
+            AiError::EmptyInput => write!(f, "Input text is empty"),
+            AiError::Cancelled => write!(f, "Generation was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AiError {}
+
+impl From<reqwest::Error> for AiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AiError::Timeout
+        } else if e.is_connect() {
+            AiError::Unavailable(e.to_string())
+        } else if e.is_decode() {
+            AiError::InvalidResponse(e.to_string())
+        } else {
+            AiError::Unavailable(e.to_string())
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct OllamaClient {
-    base_url: String,
+    config: AiConfig,
     client: reqwest::Client,
 }
 
 impl OllamaClient {
     pub fn new() -> Self {
-        OllamaClient {
-            base_url: "http://localhost:11434".to_string(),
-            client: reqwest::Client::new(),
+        Self::with_config(AiConfig::default())
+    }
+
+    pub fn with_config(config: AiConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+            .build()
+            .unwrap_or_default();
+
+        OllamaClient { config, client }
+    }
+
+    pub fn config(&self) -> &AiConfig {
+        &self.config
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Generate an embedding vector for `text` with the configured embedding model
+    pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, AiError> {
+        if text.trim().is_empty() {
+            return Err(AiError::EmptyInput);
+        }
+
+        let request = EmbeddingRequest {
+            model: self.config.embedding_model.clone(),
+            prompt: text.to_string(),
+        };
+
+        let response = self.client
+            .post(self.endpoint("/api/embeddings"))
+            .json(&request)
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        let body: EmbeddingResponse = response.json().await?;
+        if body.embedding.is_empty() {
+            return Err(AiError::InvalidResponse(format!(
+                "model '{}' returned an empty embedding",
+                self.config.embedding_model
+            )));
         }
+
+        Ok(body.embedding)
     }
 
-    pub async fn generate_embedding(&self, text: &str) -> Result<Vec<f32>, String> {
-        // TODO: Call Ollama API for embeddings
-        // Default model: all-MiniLM-L6-v2
-        Ok(vec![])
+    /// Run a non-streaming completion. Uses the configured generation model when `model` is None.
+    pub async fn generate(&self, prompt: &str, model: Option<&str>) -> Result<String, AiError> {
+        if prompt.trim().is_empty() {
+            return Err(AiError::EmptyInput);
+        }
+
+        let request = InferenceRequest {
+            model: model.unwrap_or(&self.config.generation_model).to_string(),
+            prompt: prompt.to_string(),
+            stream: false,
+        };
+
+        let response = self.client
+            .post(self.endpoint("/api/generate"))
+            .json(&request)
+            .send()
+            .await?;
+        let response = check_status(response).await?;
+
+        let body: InferenceResponse = response.json().await?;
+        Ok(body.response)
     }
 
-    pub async fn generate(&self, prompt: &str, model: &str) -> Result<String, String> {
-        // TODO: Call Ollama API for text generation
-        // Default model: llama3.2:3b
-        Ok(String::new())
+    /// Run a streaming completion, calling `on_token` for every chunk Ollama sends.
+    /// Returns the full text once the model reports `done`, or `AiError::Cancelled`
+    /// as soon as `cancel` fires.
+    pub async fn generate_stream<F>(
+        &self,
+        prompt: &str,
+        model: Option<&str>,
+        cancel: &CancelToken,
+        mut on_token: F,
+    ) -> Result<String, AiError>
+    where
+        F: FnMut(&str),
+    {
+        if prompt.trim().is_empty() {
+            return Err(AiError::EmptyInput);
+        }
+
+        let request = InferenceRequest {
+            model: model.unwrap_or(&self.config.generation_model).to_string(),
+            prompt: prompt.to_string(),
+            stream: true,
+        };
+
+        let send = self.client
+            .post(self.endpoint("/api/generate"))
+            .json(&request)
+            .send();
+        let response = tokio::select! {
+            _ = cancel.cancelled() => return Err(AiError::Cancelled),
+            response = send => response?,
+        };
+        let mut response = check_status(response).await?;
+
+        let mut decoder = NdjsonDecoder::default();
+        let mut text = String::new();
+
+        loop {
+            let bytes = tokio::select! {
+                _ = cancel.cancelled() => return Err(AiError::Cancelled),
+                bytes = response.chunk() => bytes?,
+            };
+
+            // A closed stream without a `done` line still yields whatever arrived
+            let chunks = match bytes {
+                Some(bytes) => decoder.push(&bytes)?,
+                None => {
+                    let rest = decoder.finish()?;
+                    if rest.is_empty() {
+                        return Ok(text);
+                    }
+                    rest
+                }
+            };
+
+            for chunk in chunks {
+                if let Some(error) = chunk.error {
+                    return Err(AiError::Api { status: 200, message: error });
+                }
+                if !chunk.response.is_empty() {
+                    on_token(&chunk.response);
+                    text.push_str(&chunk.response);
+                }
+                if chunk.done {
+                    return Ok(text);
+                }
+            }
+        }
     }
 
     pub async fn is_running(&self) -> bool {
         // Check if Ollama is running
         self.client
-            .get(&format!("{}/api/tags", self.base_url))
+            .get(self.endpoint("/api/tags"))
+            .timeout(Duration::from_secs(2))
             .send()
             .await
             .is_ok()
     }
 }
+
+impl Default for OllamaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Splits a byte stream into newline-delimited JSON chunks.
+/// Lines can arrive split across network reads, so partial data is buffered.
+#[derive(Default)]
+pub struct NdjsonDecoder {
+    buffer: Vec<u8>,
+}
+
+impl NdjsonDecoder {
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<InferenceChunk>, AiError> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut chunks = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(chunk) = parse_line(&line)? {
+                chunks.push(chunk);
+            }
+        }
+        Ok(chunks)
+    }
+
+    /// Parse whatever is left once the stream has ended (a last line without `\n`)
+    pub fn finish(&mut self) -> Result<Vec<InferenceChunk>, AiError> {
+        let line = std::mem::take(&mut self.buffer);
+        Ok(parse_line(&line)?.into_iter().collect())
+    }
+}
+
+fn parse_line(line: &[u8]) -> Result<Option<InferenceChunk>, AiError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(line)
+        .map(Some)
+        .map_err(|e| AiError::InvalidResponse(e.to_string()))
+}
+
+// ==================== Cancellation ====================
+
+/// Cheap, cloneable cancellation flag that can also be awaited
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    inner: Arc<CancelInner>,
+}
+
+#[derive(Default)]
+struct CancelInner {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once `cancel` has been called
+    pub async fn cancelled(&self) {
+        loop {
+            // Register before checking the flag so a concurrent cancel() is not missed
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// In-flight streaming generations, so the frontend can cancel them by request id
+#[derive(Clone, Default)]
+pub struct GenerationRegistry {
+    active: Arc<Mutex<HashMap<String, CancelToken>>>,
+}
+
+impl GenerationRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&self, request_id: &str) -> CancelToken {
+        let token = CancelToken::new();
+        if let Ok(mut active) = self.active.lock() {
+            // A reused id cancels the generation it replaces
+            if let Some(previous) = active.insert(request_id.to_string(), token.clone()) {
+                previous.cancel();
+            }
+        }
+        token
+    }
+
+    /// Cancel a generation, returns false if no generation with that id is running
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.active.lock().ok().and_then(|mut a| a.remove(request_id)) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, request_id: &str, token: &CancelToken) {
+        if let Ok(mut active) = self.active.lock() {
+            // Only remove our own entry, a newer request may have reused the id
+            if active.get(request_id).is_some_and(|t| Arc::ptr_eq(&t.inner, &token.inner)) {
+                active.remove(request_id);
+            }
+        }
+    }
+}
+
+/// Turn non-2xx responses into `AiError::Api`, keeping Ollama's `{"error": "..."}` message
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, AiError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|v| v["error"].as_str().map(String::from))
+        .unwrap_or(body);
+
+    Err(AiError::Api {
+        status: status.as_u16(),
+        message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal HTTP server that answers a single request and hands back what it received
+    async fn mock_ollama(status: &'static str, body: &'static str) -> (String, tokio::task::JoinHandle<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 4096];
+
+            // Read headers, then as much body as Content-Length says
+            let (head_len, content_length) = loop {
+                let n = socket.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+                if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                    let head = String::from_utf8_lossy(&buf[..pos]).to_lowercase();
+                    let len = head
+                        .lines()
+                        .find_map(|l| l.strip_prefix("content-length:"))
+                        .and_then(|v| v.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    break (pos + 4, len);
+                }
+            };
+            while buf.len() < head_len + content_length {
+                let n = socket.read(&mut chunk).await.unwrap();
+                buf.extend_from_slice(&chunk[..n]);
+            }
+
+            let head = String::from_utf8_lossy(&buf[..head_len]).to_string();
+            let request_line = head.lines().next().unwrap_or_default().to_string();
+            let json = serde_json::from_slice(&buf[head_len..]).unwrap_or(serde_json::Value::Null);
+
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            (request_line, json)
+        });
+
+        (format!("http://{}", addr), handle)
+    }
+
+    fn client_for(base_url: String) -> OllamaClient {
+        OllamaClient::with_config(AiConfig {
+            base_url,
+            embedding_model: "test-embed".to_string(),
+            generation_model: "test-llm".to_string(),
+            timeout_secs: 5,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_generate_embedding_request_shape() {
+        let (url, server) = mock_ollama("200 OK", r#"{"embedding":[0.5,-1.0,0.25]}"#).await;
+        let client = client_for(url);
+
+        let embedding = client.generate_embedding("hello world").await.unwrap();
+        assert_eq!(embedding, vec![0.5, -1.0, 0.25]);
+
+        let (request_line, body) = server.await.unwrap();
+        assert!(request_line.starts_with("POST /api/embeddings "));
+        assert_eq!(body["model"], "test-embed");
+        assert_eq!(body["prompt"], "hello world");
+    }
+
+    #[tokio::test]
+    async fn test_generate_request_shape() {
+        let (url, server) = mock_ollama(
+            "200 OK",
+            r#"{"model":"test-llm","created_at":"2024-01-01T00:00:00Z","response":"Hi there","done":true}"#,
+        ).await;
+        let client = client_for(url);
+
+        let text = client.generate("Say hi", None).await.unwrap();
+        assert_eq!(text, "Hi there");
+
+        let (request_line, body) = server.await.unwrap();
+        assert!(request_line.starts_with("POST /api/generate "));
+        assert_eq!(body["model"], "test-llm");
+        assert_eq!(body["prompt"], "Say hi");
+        assert_eq!(body["stream"], false);
+    }
+
+    #[tokio::test]
+    async fn test_api_error_is_typed() {
+        let (url, _server) = mock_ollama("404 Not Found", r#"{"error":"model 'x' not found"}"#).await;
+        let client = client_for(url);
+
+        match client.generate("Say hi", Some("x")).await {
+            Err(AiError::Api { status, message }) => {
+                assert_eq!(status, 404);
+                assert_eq!(message, "model 'x' not found");
+            }
+            other => panic!("expected Api error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_forwards_tokens() {
+        let (url, server) = mock_ollama(
+            "200 OK",
+            "{\"response\":\"Hel\",\"done\":false}\n{\"response\":\"lo\",\"done\":false}\n{\"response\":\"\",\"done\":true}\n",
+        ).await;
+        let client = client_for(url);
+
+        let mut tokens = Vec::new();
+        let text = client
+            .generate_stream("Say hello", None, &CancelToken::new(), |t| tokens.push(t.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(text, "Hello");
+        assert_eq!(tokens, vec!["Hel", "lo"]);
+
+        let (_, body) = server.await.unwrap();
+        assert_eq!(body["stream"], true);
+    }
+
+    #[tokio::test]
+    async fn test_generate_stream_cancelled() {
+        let client = client_for("http://127.0.0.1:9".to_string());
+        let token = CancelToken::new();
+        token.cancel();
+
+        let result = client.generate_stream("Say hello", None, &token, |_| {}).await;
+        assert!(matches!(result, Err(AiError::Cancelled)));
+    }
+
+    #[test]
+    fn test_ndjson_decoder_handles_split_lines() {
+        let mut decoder = NdjsonDecoder::default();
+        assert!(decoder.push(b"{\"response\":\"a\",\"do").unwrap().is_empty());
+
+        let chunks = decoder.push(b"ne\":false}\n{\"response\":\"b\"}").unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].response, "a");
+
+        let rest = decoder.finish().unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].response, "b");
+        assert!(!rest[0].done);
+    }
+
+    #[test]
+    fn test_registry_cancel() {
+        let registry = GenerationRegistry::new();
+        let token = registry.register("req-1");
+        assert!(registry.cancel("req-1"));
+        assert!(token.is_cancelled());
+        assert!(!registry.cancel("req-1"));
+    }
+
+    #[tokio::test]
+    async fn test_empty_input_rejected() {
+        let client = client_for("http://127.0.0.1:9".to_string());
+        assert!(matches!(client.generate_embedding("   ").await, Err(AiError::EmptyInput)));
+    }
+}
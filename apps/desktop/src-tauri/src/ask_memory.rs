@@ -0,0 +1,467 @@
+// Ask-my-memory for EarthMemory
+// Answers natural-language questions from the user's own indexed pages, notes and scraped pages
+// Retrieval-augmented: relevant chunks are put in the prompt and cited back by page id and URL
+
+use rusqlite::{Connection, Result, params};
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{AiError, OllamaClient};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::indexer::chunk_text;
+use crate::knowledge_graph::{bytes_to_embedding, cosine_similarity};
+
+/// Chunks placed in the prompt
+const MAX_CONTEXT_CHUNKS: usize = 8;
+/// Chunks taken from any single page or note, so one long page can't crowd out the rest
+const MAX_CHUNKS_PER_DOCUMENT: usize = 2;
+/// Candidate documents fetched per source before chunk scoring
+const CANDIDATES_PER_SOURCE: i64 = 20;
+const CHUNK_WORDS: usize = 120;
+const CHUNK_OVERLAP: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CitationSource {
+    Page,
+    PageNote,
+    ScrapedPage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Citation {
+    /// The `[n]` marker used in the answer
+    pub index: usize,
+    pub source: CitationSource,
+    /// pages.id for pages and notes, scraped_pages.id for scraped pages
+    pub page_id: i64,
+    pub note_id: Option<i64>,
+    pub url: String,
+    pub title: String,
+    pub excerpt: String,
+    pub score: f64,
+    /// Whether the model actually referred to this source in its answer
+    pub cited: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryAnswer {
+    pub question: String,
+    pub answer: String,
+    pub citations: Vec<Citation>,
+    pub model: String,
+}
+
+/// A piece of text that may go into the prompt
+#[derive(Debug, Clone)]
+pub struct ContextChunk {
+    pub source: CitationSource,
+    pub page_id: i64,
+    pub note_id: Option<i64>,
+    pub url: String,
+    pub title: String,
+    pub text: String,
+    pub score: f64,
+}
+
+#[derive(Clone)]
+pub struct MemoryQa {
+    db: DbPool,
+}
+
+impl MemoryQa {
+    pub fn new(db: DbPool) -> Self {
+        MemoryQa { db }
+    }
+
+    /// Find the chunks most relevant to `question`. Lexical retrieval always runs; chunk
+    /// embeddings from the background indexer are used when a question embedding is given.
+    pub fn retrieve(&self, question: &str, profile_id: i64, question_embedding: Option<&[f32]>) -> Result<Vec<ContextChunk>> {
+        let conn = self.db.get()?;
+        let keywords = question_keywords(question);
+        let mut chunks: Vec<ContextChunk> = Vec::new();
+
+        if !keywords.is_empty() {
+            let match_expr = keywords
+                .iter()
+                .map(|k| format!("\"{}\"", k))
+                .collect::<Vec<_>>()
+                .join(" OR ");
+
+            // Pages
+            let mut stmt = conn.prepare(
+                "SELECT p.id, p.url, p.title, p.content, p.summary
+                 FROM pages_fts JOIN pages p ON p.id = pages_fts.rowid
+                 WHERE pages_fts MATCH ?1 AND p.profile_id = ?2
+                 ORDER BY pages_fts.rank
+                 LIMIT ?3"
+            )?;
+            let rows = stmt.query_map(params![match_expr, profile_id, CANDIDATES_PER_SOURCE], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+            for row in rows {
+                let (id, url, title, content, summary) = row?;
+                let text = match summary {
+                    Some(summary) if !summary.trim().is_empty() => format!("{}\n{}", summary, content),
+                    _ => content,
+                };
+                push_lexical_chunks(&mut chunks, &keywords, CitationSource::Page, id, None, &url, &title, &text);
+            }
+
+            // Notes, cited through the page they belong to
+            let mut stmt = conn.prepare(
+                "SELECT n.id, n.page_id, n.content, p.url, p.title
+                 FROM page_notes_fts
+                 JOIN page_notes n ON n.id = page_notes_fts.rowid
+                 JOIN pages p ON p.id = n.page_id
+                 WHERE page_notes_fts MATCH ?1 AND p.profile_id = ?2
+                 ORDER BY page_notes_fts.rank
+                 LIMIT ?3"
+            )?;
+            let rows = stmt.query_map(params![match_expr, profile_id, CANDIDATES_PER_SOURCE], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?;
+            for row in rows {
+                let (note_id, page_id, content, url, title) = row?;
+                push_lexical_chunks(&mut chunks, &keywords, CitationSource::PageNote, page_id, Some(note_id), &url, &title, &content);
+            }
+
+            // Scraped pages have no full-text index, match any keyword
+            let conditions: Vec<String> = (0..keywords.len())
+                .map(|i| format!("LOWER(COALESCE(sp.title, '') || ' ' || COALESCE(sp.content, '')) LIKE ?{}", i + 2))
+                .collect();
+            let sql = format!(
+                "SELECT sp.id, sp.url, sp.title, sp.content
+                 FROM scraped_pages sp JOIN scraping_jobs sj ON sp.job_id = sj.id
+                 WHERE sj.profile_id = ?1 AND ({})
+                 ORDER BY sp.scraped_at DESC
+                 LIMIT {}",
+                conditions.join(" OR "),
+                CANDIDATES_PER_SOURCE
+            );
+            let mut values: Vec<rusqlite::types::Value> = vec![profile_id.into()];
+            values.extend(keywords.iter().map(|k| format!("%{}%", k).into()));
+
+            if table_exists(&conn, "scraped_pages")? {
+                let mut stmt = conn.prepare(&sql)?;
+                let rows = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    ))
+                })?;
+                for row in rows {
+                    let (id, url, title, content) = row?;
+                    let title = title.unwrap_or_else(|| url.clone());
+                    push_lexical_chunks(&mut chunks, &keywords, CitationSource::ScrapedPage, id, None, &url, &title, &content);
+                }
+            }
+        }
+
+        if let Some(question_embedding) = question_embedding {
+            if table_exists(&conn, "embedding_chunks")? {
+                let mut stmt = conn.prepare(
+                    "SELECT c.source_id, c.content, c.embedding, p.url, p.title
+                     FROM embedding_chunks c JOIN pages p ON p.id = c.source_id
+                     WHERE c.source = 'pages' AND p.profile_id = ?1"
+                )?;
+                let rows = stmt.query_map(params![profile_id], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        bytes_to_embedding(&row.get::<_, Vec<u8>>(2)?),
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                    ))
+                })?;
+                for row in rows {
+                    let (page_id, text, embedding, url, title) = row?;
+                    if let Some(similarity) = cosine_similarity(question_embedding, &embedding) {
+                        chunks.push(ContextChunk {
+                            source: CitationSource::Page,
+                            page_id,
+                            note_id: None,
+                            url,
+                            title,
+                            text,
+                            score: similarity as f64,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(select_chunks(chunks))
+    }
+
+    /// Answer a question from the profile's memory with the local model
+    pub async fn ask(
+        &self,
+        client: &OllamaClient,
+        question: &str,
+        profile_id: i64,
+    ) -> AppResult<MemoryAnswer> {
+        if question.trim().is_empty() {
+            return Err(AppError::Ai(AiError::EmptyInput));
+        }
+
+        // Semantic retrieval is a bonus; keyword retrieval alone still works without embeddings
+        let question_embedding = client.generate_embedding(question).await.ok();
+        let chunks = self.retrieve(question, profile_id, question_embedding.as_deref())?;
+        let model = client.config().generation_model.clone();
+
+        if chunks.is_empty() {
+            return Ok(MemoryAnswer {
+                question: question.to_string(),
+                answer: "I couldn't find anything about that in your memory.".to_string(),
+                citations: Vec::new(),
+                model,
+            });
+        }
+
+        let answer = client.generate(&build_prompt(question, &chunks), None).await?;
+        let cited = cited_indices(&answer);
+
+        let citations = chunks
+            .into_iter()
+            .enumerate()
+            .map(|(i, chunk)| Citation {
+                index: i + 1,
+                source: chunk.source,
+                page_id: chunk.page_id,
+                note_id: chunk.note_id,
+                url: chunk.url,
+                title: chunk.title,
+                excerpt: chunk.text.chars().take(300).collect(),
+                score: chunk.score,
+                cited: cited.contains(&(i + 1)),
+            })
+            .collect();
+
+        Ok(MemoryAnswer {
+            question: question.to_string(),
+            answer: answer.trim().to_string(),
+            citations,
+            model,
+        })
+    }
+}
+
+// ==================== Helper Functions ====================
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "by", "can", "could", "did", "do", "does",
+    "for", "from", "had", "has", "have", "how", "i", "in", "is", "it", "its", "me", "my", "of", "on",
+    "or", "read", "saw", "should", "tell", "that", "the", "there", "this", "to", "was", "we", "were",
+    "what", "when", "where", "which", "who", "why", "will", "with", "would", "you", "your",
+];
+
+/// Lowercased content words of a question
+pub fn question_keywords(question: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for word in question.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+        if word.len() > 1 && !STOPWORDS.contains(&word) && !keywords.iter().any(|k| k == word) {
+            keywords.push(word.to_string());
+        }
+    }
+    keywords
+}
+
+/// Split a document into chunks and keep those that mention at least one keyword
+#[allow(clippy::too_many_arguments)]
+fn push_lexical_chunks(
+    chunks: &mut Vec<ContextChunk>,
+    keywords: &[String],
+    source: CitationSource,
+    page_id: i64,
+    note_id: Option<i64>,
+    url: &str,
+    title: &str,
+    text: &str,
+) {
+    let title_lower = title.to_lowercase();
+    for chunk in chunk_text(text, CHUNK_WORDS, CHUNK_OVERLAP, 50) {
+        let lower = chunk.to_lowercase();
+        let matched = keywords.iter().filter(|k| lower.contains(k.as_str())).count();
+        if matched == 0 {
+            continue;
+        }
+
+        // Share of keywords covered, with a nudge for title matches and repeated hits
+        let title_hits = keywords.iter().filter(|k| title_lower.contains(k.as_str())).count();
+        let hits: usize = keywords.iter().map(|k| lower.matches(k.as_str()).count()).sum();
+        let score = matched as f64 / keywords.len() as f64
+            + 0.1 * title_hits as f64 / keywords.len() as f64
+            + 0.02 * (hits.min(10) as f64);
+
+        chunks.push(ContextChunk {
+            source,
+            page_id,
+            note_id,
+            url: url.to_string(),
+            title: title.to_string(),
+            text: chunk,
+            score,
+        });
+    }
+}
+
+/// Best chunks first, without duplicates and with a per-document cap
+fn select_chunks(mut chunks: Vec<ContextChunk>) -> Vec<ContextChunk> {
+    chunks.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut selected: Vec<ContextChunk> = Vec::new();
+    for chunk in chunks {
+        if selected.len() >= MAX_CONTEXT_CHUNKS {
+            break;
+        }
+        let same_document = |c: &ContextChunk| {
+            c.source == chunk.source && c.page_id == chunk.page_id && c.note_id == chunk.note_id
+        };
+        if selected.iter().any(|c| same_document(c) && c.text == chunk.text) {
+            continue;
+        }
+        if selected.iter().filter(|c| same_document(c)).count() >= MAX_CHUNKS_PER_DOCUMENT {
+            continue;
+        }
+        selected.push(chunk);
+    }
+    selected
+}
+
+fn build_prompt(question: &str, chunks: &[ContextChunk]) -> String {
+    let mut prompt = String::from(
+        "You answer questions using only the sources below, which come from the user's saved pages and notes.\n\
+         Cite the sources you use with their number in square brackets, e.g. [1] or [2][3].\n\
+         If the sources don't contain the answer, say that you don't know instead of guessing.\n\n\
+         Sources:\n",
+    );
+
+    for (i, chunk) in chunks.iter().enumerate() {
+        let kind = match chunk.source {
+            CitationSource::Page => "page",
+            CitationSource::PageNote => "note on page",
+            CitationSource::ScrapedPage => "scraped page",
+        };
+        prompt.push_str(&format!(
+            "[{}] ({}) {} <{}>\n{}\n\n",
+            i + 1,
+            kind,
+            chunk.title,
+            chunk.url,
+            chunk.text
+        ));
+    }
+
+    prompt.push_str(&format!("Question: {}\nAnswer:", question.trim()));
+    prompt
+}
+
+/// Source numbers referenced as `[n]` in the answer
+fn cited_indices(answer: &str) -> Vec<usize> {
+    let mut indices = Vec::new();
+    let mut rest = answer;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        if let Some(end) = rest.find(']') {
+            // Also accept "[1, 2]"
+            for part in rest[..end].split(',') {
+                if let Ok(n) = part.trim().parse::<usize>() {
+                    if !indices.contains(&n) {
+                        indices.push(n);
+                    }
+                }
+            }
+        }
+    }
+    indices
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{IndexedPage, MemoryManager};
+
+    fn setup(name: &str) -> (MemoryManager, MemoryQa) {
+        let db = crate::migrations::test_database(&format!("ask_{}", name));
+        (MemoryManager::new(db.clone()), MemoryQa::new(db))
+    }
+
+    fn page(url: &str, title: &str, content: &str) -> IndexedPage {
+        IndexedPage {
+            id: None,
+            url: url.to_string(),
+            title: title.to_string(),
+            content: Some(content.to_string()),
+            summary: None,
+            indexed_at: String::new(),
+            last_visited: String::new(),
+            visit_count: 1,
+            is_favorite: false,
+            tags: None,
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn test_question_keywords() {
+        assert_eq!(question_keywords("What did I read about Rust's borrow checker?"), vec!["rust", "borrow", "checker"]);
+    }
+
+    #[test]
+    fn test_cited_indices() {
+        assert_eq!(cited_indices("Rust is fast [1]. See also [2, 3] and [1]. [x]"), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_retrieve_from_pages_notes_and_scraped_pages() {
+        let (memory, qa) = setup("retrieve");
+        let rust = memory.index_page(&page("https://rust-lang.org", "Rust", "The borrow checker enforces ownership rules."), 1).unwrap();
+        memory.index_page(&page("https://garden.example.com", "Garden", "Tomatoes need sun."), 1).unwrap();
+        memory.index_page(&page("https://other.example.com", "Other profile", "borrow checker"), 2).unwrap();
+        let note = memory.add_note(rust.id.unwrap(), "Lifetimes confused me at first", 1).unwrap();
+
+        let conn = qa.db.get().unwrap();
+        conn.execute(
+            "INSERT INTO scraping_jobs (profile_id, name, base_url, created_at) VALUES (1, 'docs', 'https://doc.rust-lang.org', '1')",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO scraped_pages (job_id, url, title, content, scraped_at) VALUES (1, 'https://doc.rust-lang.org/book', 'Book', 'Lifetimes and the borrow checker', '1')",
+            [],
+        ).unwrap();
+
+        let chunks = qa.retrieve("How does the borrow checker treat lifetimes?", 1, None).unwrap();
+        assert!(chunks.iter().all(|c| c.url != "https://other.example.com" && c.url != "https://garden.example.com"));
+        assert!(chunks.iter().any(|c| c.source == CitationSource::Page && c.page_id == rust.id.unwrap()));
+        assert!(chunks.iter().any(|c| c.source == CitationSource::PageNote && c.note_id == note.id));
+        // The scraped page covers every keyword, so it ranks first
+        assert_eq!(chunks[0].source, CitationSource::ScrapedPage);
+
+        let prompt = build_prompt("How does the borrow checker treat lifetimes?", &chunks);
+        assert!(prompt.contains("[1] (scraped page) Book <https://doc.rust-lang.org/book>"));
+    }
+}
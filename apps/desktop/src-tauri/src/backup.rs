@@ -0,0 +1,1048 @@
+// Profile backup and restore for EarthServers Local
+// A backup is one versioned JSON archive with every per-profile table, a manifest and a
+// SHA-256 checksum per table, optionally encrypted with a password. Restores go into a
+// new profile or merge into an existing one; rows that already exist there are resolved
+// with a ConflictStrategy. Derived data (embeddings, entities, page links) is left out
+// and rebuilt by the background indexers after a restore.
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::multimedia::{decrypt_data, encrypt_data};
+use crate::tasks::TaskContext;
+
+pub const BACKUP_FORMAT: &str = "earthservers-backup";
+pub const ENCRYPTED_BACKUP_FORMAT: &str = "earthservers-backup-encrypted";
+pub const BACKUP_VERSION: i64 = 1;
+
+/// One row, column name to value; blobs are `{"$blob": base64}`
+type BackupRow = BTreeMap<String, serde_json::Value>;
+
+// ==================== Types ====================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub format: String,
+    pub version: i64,
+    /// Schema the rows were read from; newer backups are refused
+    pub schema_version: i64,
+    pub created_at: String,
+    pub profile: BackupProfile,
+    pub tables: Vec<BackupTable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupProfile {
+    pub name: String,
+    pub icon: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupTable {
+    pub name: String,
+    pub rows: usize,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupArchive {
+    manifest: BackupManifest,
+    tables: BTreeMap<String, Vec<BackupRow>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedArchive {
+    format: String,
+    version: i64,
+    /// The plain archive, AES-256-GCM encrypted with the backup password
+    data: String,
+}
+
+/// What to do with a backed-up row that already exists in the target profile
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    /// Keep the existing row
+    Skip,
+    /// Replace the existing row with the backed-up one
+    Overwrite,
+    /// Keep both. Rows whose key must be unique are renamed ("Work (2)") where they
+    /// have a name, and otherwise keep the existing row (pages and domains by URL).
+    Duplicate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreOptions {
+    /// Merge into this profile; None creates a new profile named after the backup
+    pub target_profile_id: Option<i64>,
+    pub strategy: ConflictStrategy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TableRestoreReport {
+    pub table: String,
+    pub inserted: i64,
+    pub updated: i64,
+    pub skipped: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub profile_id: i64,
+    pub created_profile: bool,
+    pub tables: Vec<TableRestoreReport>,
+}
+
+// ==================== Tables ====================
+
+struct TableSpec {
+    name: &'static str,
+    /// Selects the profile's rows, parents before children (?1 = profile id)
+    select: &'static str,
+    /// Integer primary key; restored rows get new ids and references to them are remapped
+    id: Option<&'static str>,
+    /// Column holding the profile id, rewritten to the target profile
+    profile: Option<&'static str>,
+    /// Columns holding ids of rows in an earlier table (or this one)
+    references: &'static [(&'static str, &'static str)],
+    /// Columns that identify the same row in the target profile
+    key: &'static [&'static str],
+    /// The key has a UNIQUE constraint, so a second copy can't be inserted as-is
+    unique: bool,
+    /// Text column renamed to make a duplicate of a unique row
+    label: Option<&'static str>,
+}
+
+/// Every table holding per-profile data, in restore order
+const TABLES: &[TableSpec] = &[
+    TableSpec {
+        name: "privacy_settings",
+        select: "SELECT * FROM privacy_settings WHERE profile_id = ?1",
+        id: None,
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "themes",
+        select: "SELECT * FROM themes WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "name"],
+        unique: true,
+        label: Some("name"),
+    },
+    TableSpec {
+        // The embedding column is left to the indexer
+        name: "pages",
+        select: "SELECT id, url, title, content, visited_at, profile_id, summary, tags, indexed_at,
+                        visit_count, is_favorite
+                 FROM pages WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "url"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "page_notes",
+        select: "SELECT n.* FROM page_notes n JOIN pages p ON p.id = n.page_id
+                 WHERE p.profile_id = ?1 ORDER BY n.id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[("page_id", "pages")],
+        key: &["page_id", "content"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "page_summaries",
+        select: "SELECT s.* FROM page_summaries s JOIN pages p ON p.id = s.page_id
+                 WHERE p.profile_id = ?1 ORDER BY s.page_id",
+        id: None,
+        profile: None,
+        references: &[("page_id", "pages")],
+        key: &["page_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "edges",
+        select: "SELECT * FROM edges WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[("source_id", "pages"), ("target_id", "pages")],
+        key: &["source_id", "target_id", "relation"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "domains",
+        select: "SELECT * FROM domains WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "url"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "domain_lists",
+        select: "SELECT * FROM domain_lists WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "name"],
+        unique: true,
+        label: Some("name"),
+    },
+    TableSpec {
+        name: "list_domains",
+        select: "SELECT ld.* FROM list_domains ld JOIN domain_lists l ON l.id = ld.list_id
+                 WHERE l.profile_id = ?1 ORDER BY ld.list_id, ld.domain_id",
+        id: None,
+        profile: None,
+        references: &[("list_id", "domain_lists"), ("domain_id", "domains")],
+        key: &["list_id", "domain_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "domain_ratings",
+        select: "SELECT r.* FROM domain_ratings r JOIN domains d ON d.id = r.domain_id
+                 WHERE d.profile_id = ?1 ORDER BY r.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("domain_id", "domains")],
+        key: &["domain_id", "user_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "rating_categories",
+        select: "SELECT c.* FROM rating_categories c
+                 JOIN domain_ratings r ON r.id = c.domain_rating_id
+                 JOIN domains d ON d.id = r.domain_id
+                 WHERE d.profile_id = ?1 ORDER BY c.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("domain_rating_id", "domain_ratings")],
+        key: &["domain_rating_id", "category"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "domain_rating_aggregates",
+        select: "SELECT a.* FROM domain_rating_aggregates a JOIN domains d ON d.id = a.domain_id
+                 WHERE d.profile_id = ?1 ORDER BY a.domain_id",
+        id: None,
+        profile: None,
+        references: &[("domain_id", "domains")],
+        key: &["domain_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "subdomain_ratings",
+        select: "SELECT s.* FROM subdomain_ratings s JOIN domains d ON d.id = s.parent_domain_id
+                 WHERE d.profile_id = ?1 ORDER BY s.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("parent_domain_id", "domains")],
+        key: &["parent_domain_id", "subdomain"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "tabs",
+        select: "SELECT * FROM tabs WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "url", "position"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "tab_history",
+        select: "SELECT h.* FROM tab_history h JOIN tabs t ON t.id = h.tab_id
+                 WHERE t.profile_id = ?1 ORDER BY h.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("tab_id", "tabs")],
+        key: &["tab_id", "position", "url"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "split_view_config",
+        select: "SELECT * FROM split_view_config WHERE profile_id = ?1",
+        id: None,
+        profile: Some("profile_id"),
+        references: &[
+            ("pane_1_tab_id", "tabs"),
+            ("pane_2_tab_id", "tabs"),
+            ("pane_3_tab_id", "tabs"),
+            ("pane_4_tab_id", "tabs"),
+        ],
+        key: &["profile_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "bookmark_folders",
+        select: "SELECT * FROM bookmark_folders WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[("parent_id", "bookmark_folders")],
+        key: &["profile_id", "parent_id", "name"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "bookmarks",
+        select: "SELECT * FROM bookmarks WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[("folder_id", "bookmark_folders")],
+        key: &["profile_id", "folder_id", "url"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "multimedia_privacy",
+        select: "SELECT * FROM multimedia_privacy WHERE profile_id = ?1",
+        id: None,
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "multimedia_history",
+        select: "SELECT * FROM multimedia_history WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "source", "played_at"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "multimedia_playlists",
+        select: "SELECT * FROM multimedia_playlists WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "name"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "multimedia_playlist_items",
+        select: "SELECT i.* FROM multimedia_playlist_items i
+                 JOIN multimedia_playlists p ON p.id = i.playlist_id
+                 WHERE p.profile_id = ?1 ORDER BY i.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("playlist_id", "multimedia_playlists")],
+        key: &["playlist_id", "source", "position"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "scraping_jobs",
+        select: "SELECT * FROM scraping_jobs WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id", "name", "base_url"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "scraping_runs",
+        select: "SELECT r.* FROM scraping_runs r JOIN scraping_jobs j ON j.id = r.job_id
+                 WHERE j.profile_id = ?1 ORDER BY r.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("job_id", "scraping_jobs")],
+        key: &["job_id", "started_at"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "scraped_pages",
+        select: "SELECT s.* FROM scraped_pages s JOIN scraping_jobs j ON j.id = s.job_id
+                 WHERE j.profile_id = ?1 ORDER BY s.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("job_id", "scraping_jobs")],
+        key: &["job_id", "url"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "scraped_page_versions",
+        select: "SELECT v.* FROM scraped_page_versions v JOIN scraped_pages s ON s.id = v.page_id
+                 JOIN scraping_jobs j ON j.id = s.job_id WHERE j.profile_id = ?1 ORDER BY v.id",
+        id: Some("id"),
+        profile: None,
+        references: &[("page_id", "scraped_pages")],
+        key: &["page_id", "scraped_at"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        // The wrapped key is copied as-is, so the vault still opens with its master password
+        name: "password_vaults",
+        select: "SELECT * FROM password_vaults WHERE profile_id = ?1",
+        id: Some("profile_id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        // Entries point at their vault by its profile_id and stay encrypted under its key
+        name: "password_entries",
+        select: "SELECT * FROM password_entries WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: None,
+        references: &[("profile_id", "password_vaults")],
+        key: &["profile_id", "data"],
+        unique: false,
+        label: None,
+    },
+    TableSpec {
+        name: "otp_vaults",
+        select: "SELECT * FROM otp_vaults WHERE profile_id = ?1",
+        id: Some("profile_id"),
+        profile: Some("profile_id"),
+        references: &[],
+        key: &["profile_id"],
+        unique: true,
+        label: None,
+    },
+    TableSpec {
+        name: "otp_entries",
+        select: "SELECT * FROM otp_entries WHERE profile_id = ?1 ORDER BY id",
+        id: Some("id"),
+        profile: None,
+        references: &[("profile_id", "otp_vaults")],
+        key: &["profile_id", "data"],
+        unique: false,
+        label: None,
+    },
+];
+
+/// Tables whose rows hold a wrapped vault key. Entries are encrypted under that key, so a
+/// backed-up vault is never merged into a different vault the target profile already has:
+/// the vault and its entries are skipped instead.
+const VAULTS: &[&str] = &["password_vaults", "otp_vaults"];
+
+// ==================== Manager ====================
+
+#[derive(Clone)]
+pub struct BackupManager {
+    db: DbPool,
+}
+
+impl BackupManager {
+    pub fn new(db: DbPool) -> Self {
+        BackupManager { db }
+    }
+
+    /// Write every table of a profile into an archive, encrypted when `password` is given
+    pub fn create_backup(&self, profile_id: i64, password: Option<&str>, task: &TaskContext) -> AppResult<String> {
+        let conn = self.db.get()?;
+        // One read transaction, so the tables agree with each other
+        let tx = conn.unchecked_transaction()?;
+
+        let profile = tx
+            .query_row(
+                "SELECT name, icon, created_at FROM profiles WHERE id = ?1",
+                params![profile_id],
+                |row| Ok(BackupProfile { name: row.get(0)?, icon: row.get(1)?, created_at: row.get(2)? }),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Profile {}", profile_id)))?;
+
+        let mut tables = BTreeMap::new();
+        let mut manifest_tables = Vec::new();
+        for (i, spec) in TABLES.iter().enumerate() {
+            if task.is_cancelled() {
+                return Err(AppError::Cancelled);
+            }
+            task.progress(i as u64, Some(TABLES.len() as u64));
+
+            let rows = read_rows(&tx, spec.select, profile_id)?;
+            manifest_tables.push(BackupTable {
+                name: spec.name.to_string(),
+                rows: rows.len(),
+                sha256: checksum(&rows),
+            });
+            tables.insert(spec.name.to_string(), rows);
+        }
+        tx.finish()?;
+
+        let archive = BackupArchive {
+            manifest: BackupManifest {
+                format: BACKUP_FORMAT.to_string(),
+                version: BACKUP_VERSION,
+                schema_version: crate::migrations::latest_version(),
+                created_at: chrono_now(),
+                profile,
+                tables: manifest_tables,
+            },
+            tables,
+        };
+        let json = serde_json::to_string(&archive)?;
+        task.progress(TABLES.len() as u64, Some(TABLES.len() as u64));
+
+        match password {
+            Some(password) => Ok(serde_json::to_string(&EncryptedArchive {
+                format: ENCRYPTED_BACKUP_FORMAT.to_string(),
+                version: BACKUP_VERSION,
+                data: encrypt_data(&json, password)?,
+            })?),
+            None => Ok(json),
+        }
+    }
+
+    /// Check an archive and return its manifest without restoring anything
+    pub fn inspect_backup(&self, archive: &str, password: Option<&str>) -> AppResult<BackupManifest> {
+        Ok(parse_archive(archive, password)?.manifest)
+    }
+
+    /// Restore an archive in one transaction; a failed or cancelled restore changes nothing
+    pub fn restore_backup(
+        &self,
+        archive: &str,
+        password: Option<&str>,
+        options: &RestoreOptions,
+        task: &TaskContext,
+    ) -> AppResult<RestoreReport> {
+        let archive = parse_archive(archive, password)?;
+
+        let conn = self.db.get()?;
+        let tx = conn.unchecked_transaction()?;
+
+        let (profile_id, created_profile) = match options.target_profile_id {
+            Some(profile_id) => {
+                let exists = tx
+                    .query_row("SELECT 1 FROM profiles WHERE id = ?1", params![profile_id], |_| Ok(()))
+                    .optional()?
+                    .is_some();
+                if !exists {
+                    return Err(AppError::NotFound(format!("Profile {}", profile_id)));
+                }
+                (profile_id, false)
+            }
+            None => (create_profile(&tx, &archive.manifest.profile)?, true),
+        };
+
+        let total = archive.tables.values().map(|rows| rows.len() as u64).sum();
+        let mut done = 0u64;
+        let mut ids: HashMap<&'static str, HashMap<i64, i64>> = HashMap::new();
+        let mut reports = Vec::new();
+
+        for spec in TABLES {
+            let Some(rows) = archive.tables.get(spec.name) else { continue };
+            let columns = table_columns(&tx, spec.name)?;
+            let mut report = TableRestoreReport { table: spec.name.to_string(), ..Default::default() };
+
+            for row in rows {
+                if task.is_cancelled() {
+                    return Err(AppError::Cancelled);
+                }
+                match restore_row(&tx, spec, &columns, row, profile_id, options.strategy, &mut ids)? {
+                    Outcome::Inserted => report.inserted += 1,
+                    Outcome::Updated => report.updated += 1,
+                    Outcome::Skipped => report.skipped += 1,
+                }
+                done += 1;
+                task.progress(done, Some(total));
+            }
+            reports.push(report);
+        }
+
+        // A profile always has privacy settings, even from a backup that somehow lacks them
+        tx.execute(
+            "INSERT OR IGNORE INTO privacy_settings (profile_id, auto_delete_days, ai_enabled_in_incognito)
+             VALUES (?1, NULL, 0)",
+            params![profile_id],
+        )?;
+        tx.commit()?;
+
+        Ok(RestoreReport { profile_id, created_profile, tables: reports })
+    }
+}
+
+// ==================== Helper Functions ====================
+
+enum Outcome {
+    Inserted,
+    Updated,
+    Skipped,
+}
+
+fn parse_archive(archive: &str, password: Option<&str>) -> AppResult<BackupArchive> {
+    let value: serde_json::Value = serde_json::from_str(archive)?;
+
+    let value = if value["format"] == ENCRYPTED_BACKUP_FORMAT {
+        let encrypted: EncryptedArchive = serde_json::from_value(value)?;
+        let password = password
+            .ok_or_else(|| AppError::Crypto("This backup is encrypted; a password is required".to_string()))?;
+        serde_json::from_str(&decrypt_data(&encrypted.data, password)?)?
+    } else {
+        value
+    };
+
+    let archive: BackupArchive = serde_json::from_value(value)
+        .map_err(|e| AppError::Validation(format!("Not an EarthServers backup: {}", e)))?;
+    let manifest = &archive.manifest;
+
+    if manifest.format != BACKUP_FORMAT {
+        return Err(AppError::Validation(format!("Unknown backup format '{}'", manifest.format)));
+    }
+    if manifest.version > BACKUP_VERSION || manifest.schema_version > crate::migrations::latest_version() {
+        return Err(AppError::Validation(
+            "This backup was made by a newer version of EarthServers; update the app to restore it".to_string(),
+        ));
+    }
+
+    for table in &manifest.tables {
+        let rows = archive.tables.get(&table.name).map(Vec::as_slice).unwrap_or_default();
+        if rows.len() != table.rows || checksum(rows) != table.sha256 {
+            return Err(AppError::Validation(format!(
+                "Backup is corrupted: checksum mismatch in table '{}'",
+                table.name
+            )));
+        }
+    }
+    if let Some(extra) = archive.tables.keys().find(|name| !manifest.tables.iter().any(|t| &t.name == *name)) {
+        return Err(AppError::Validation(format!("Backup table '{}' is not in the manifest", extra)));
+    }
+
+    Ok(archive)
+}
+
+fn checksum(rows: &[BackupRow]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_string(rows).unwrap_or_default().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// New profile named after the backed-up one, with a suffix if the name is taken
+fn create_profile(conn: &Connection, profile: &BackupProfile) -> AppResult<i64> {
+    let mut name = profile.name.clone();
+    let mut n = 2;
+    while conn
+        .query_row("SELECT 1 FROM profiles WHERE name = ?1", params![name], |_| Ok(()))
+        .optional()?
+        .is_some()
+    {
+        name = format!("{} ({})", profile.name, n);
+        n += 1;
+    }
+
+    conn.execute(
+        "INSERT INTO profiles (name, icon, created_at, is_active) VALUES (?1, ?2, ?3, 0)",
+        params![name, profile.icon, chrono_now()],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn read_rows(conn: &Connection, sql: &str, profile_id: i64) -> AppResult<Vec<BackupRow>> {
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let mut rows = stmt.query(params![profile_id])?;
+    let mut result = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut values = BackupRow::new();
+        for (i, column) in columns.iter().enumerate() {
+            values.insert(column.clone(), to_json(row.get_ref(i)?));
+        }
+        result.push(values);
+    }
+    Ok(result)
+}
+
+fn table_columns(conn: &Connection, table: &str) -> AppResult<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{}\")", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+    Ok(columns)
+}
+
+fn restore_row(
+    conn: &Connection,
+    spec: &TableSpec,
+    columns: &HashSet<String>,
+    row: &BackupRow,
+    profile_id: i64,
+    strategy: ConflictStrategy,
+    ids: &mut HashMap<&'static str, HashMap<i64, i64>>,
+) -> AppResult<Outcome> {
+    let old_id = spec.id.and_then(|id| row.get(id)).and_then(|v| v.as_i64());
+
+    // Columns this database doesn't have (e.g. from an older schema) are dropped
+    let mut values: BTreeMap<&str, Value> = row
+        .iter()
+        .filter(|(column, _)| columns.contains(column.as_str()) && Some(column.as_str()) != spec.id)
+        .map(|(column, value)| (column.as_str(), to_sql(value)))
+        .collect();
+
+    if let Some(column) = spec.profile {
+        values.insert(column, Value::Integer(profile_id));
+    }
+
+    for (column, table) in spec.references {
+        let Some(Value::Integer(old)) = values.get(column) else { continue };
+        match ids.get(table).and_then(|map| map.get(old)) {
+            Some(new) => {
+                values.insert(column, Value::Integer(*new));
+            }
+            // The parent row wasn't restored: drop the row if it can't exist without it
+            None if spec.key.contains(column) => return Ok(Outcome::Skipped),
+            None => {
+                values.insert(column, Value::Null);
+            }
+        }
+    }
+
+    if VAULTS.contains(&spec.name) && has_other_vault(conn, spec.name, &values)? {
+        return Ok(Outcome::Skipped);
+    }
+
+    let existing = find_existing(conn, spec, &values)?;
+    let (rowid, outcome) = match (existing, strategy) {
+        (None, _) => (insert(conn, spec.name, &values)?, Outcome::Inserted),
+        (Some(rowid), ConflictStrategy::Skip) => (rowid, Outcome::Skipped),
+        (Some(rowid), ConflictStrategy::Overwrite) => {
+            update(conn, spec.name, rowid, &values)?;
+            (rowid, Outcome::Updated)
+        }
+        (Some(_), ConflictStrategy::Duplicate) if !spec.unique => (insert(conn, spec.name, &values)?, Outcome::Inserted),
+        (Some(rowid), ConflictStrategy::Duplicate) => match spec.label {
+            Some(label) => {
+                rename_duplicate(conn, spec, &mut values, label)?;
+                (insert(conn, spec.name, &values)?, Outcome::Inserted)
+            }
+            None => (rowid, Outcome::Skipped),
+        },
+    };
+
+    if let Some(old_id) = old_id {
+        ids.entry(spec.name).or_default().insert(old_id, rowid);
+    }
+    Ok(outcome)
+}
+
+fn find_existing(conn: &Connection, spec: &TableSpec, values: &BTreeMap<&str, Value>) -> AppResult<Option<i64>> {
+    let conditions: Vec<String> = spec.key.iter()
+        .enumerate()
+        .map(|(i, column)| format!("\"{}\" IS ?{}", column, i + 1))
+        .collect();
+    let sql = format!("SELECT rowid FROM \"{}\" WHERE {} LIMIT 1", spec.name, conditions.join(" AND "));
+    let params = spec.key.iter().map(|column| values.get(column).cloned().unwrap_or(Value::Null));
+
+    Ok(conn.prepare_cached(&sql)?.query_row(params_from_iter(params), |row| row.get(0)).optional()?)
+}
+
+/// The target profile already has a vault of this kind, wrapped with a different key
+fn has_other_vault(conn: &Connection, table: &str, values: &BTreeMap<&str, Value>) -> AppResult<bool> {
+    let existing: Option<Value> = conn
+        .query_row(
+            &format!("SELECT wrapped_key FROM \"{}\" WHERE profile_id = ?1", table),
+            params_from_iter(values.get("profile_id")),
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(existing.is_some_and(|key| Some(&key) != values.get("wrapped_key")))
+}
+
+fn insert(conn: &Connection, table: &str, values: &BTreeMap<&str, Value>) -> AppResult<i64> {
+    let columns: Vec<String> = values.keys().map(|column| format!("\"{}\"", column)).collect();
+    let placeholders: Vec<String> = (1..=values.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!(
+        "INSERT INTO \"{}\" ({}) VALUES ({})",
+        table,
+        columns.join(", "),
+        placeholders.join(", ")
+    );
+    conn.prepare_cached(&sql)?.execute(params_from_iter(values.values()))?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn update(conn: &Connection, table: &str, rowid: i64, values: &BTreeMap<&str, Value>) -> AppResult<()> {
+    let assignments: Vec<String> = values.keys()
+        .enumerate()
+        .map(|(i, column)| format!("\"{}\" = ?{}", column, i + 1))
+        .collect();
+    let sql = format!(
+        "UPDATE \"{}\" SET {} WHERE rowid = ?{}",
+        table,
+        assignments.join(", "),
+        values.len() + 1
+    );
+    let params = values.values().cloned().chain(std::iter::once(Value::Integer(rowid)));
+    conn.prepare_cached(&sql)?.execute(params_from_iter(params))?;
+    Ok(())
+}
+
+/// "Work" becomes "Work (2)", "Work (3)", ... until the key is free
+fn rename_duplicate(
+    conn: &Connection,
+    spec: &TableSpec,
+    values: &mut BTreeMap<&str, Value>,
+    label: &'static str,
+) -> AppResult<()> {
+    let base = match values.get(label) {
+        Some(Value::Text(text)) => text.clone(),
+        _ => String::new(),
+    };
+    for n in 2.. {
+        values.insert(label, Value::Text(format!("{} ({})", base, n)));
+        if find_existing(conn, spec, values)?.is_none() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned().into(),
+        ValueRef::Blob(blob) => serde_json::json!({ "$blob": BASE64.encode(blob) }),
+    }
+}
+
+fn to_sql(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(text) => Value::Text(text.clone()),
+        serde_json::Value::Object(object) => match object.get("$blob").and_then(|b| b.as_str()) {
+            Some(encoded) => BASE64.decode(encoded).map(Value::Blob).unwrap_or(Value::Null),
+            None => Value::Text(value.to_string()),
+        },
+        serde_json::Value::Array(_) => Value::Text(value.to_string()),
+    }
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn seeded(name: &str) -> (BackupManager, DbPool) {
+        let db = crate::migrations::test_database(name);
+        db.get().unwrap().execute_batch(
+            "INSERT INTO privacy_settings (profile_id, auto_delete_days, ai_enabled_in_incognito) VALUES (1, 30, 1);
+             INSERT INTO themes (id, profile_id, name, is_active, created_at) VALUES (1, 1, 'Dark', 1, '0');
+             INSERT INTO pages (id, url, title, content, visited_at, profile_id) VALUES (10, 'https://a.example', 'A', 'alpha', '0', 1);
+             INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id) VALUES (10, 'remember this', '0', '0', 1);
+             INSERT INTO domains (id, url, category, trust_score, added_date, profile_id) VALUES (5, 'a.example', 'news', 0.25, '0', 1);
+             INSERT INTO domain_lists (id, name, created_at, profile_id) VALUES (3, 'Reading', '0', 1);
+             INSERT INTO list_domains (list_id, domain_id) VALUES (3, 5);
+             INSERT INTO tabs (id, profile_id, url, position, created_at, last_accessed) VALUES (7, 1, 'https://a.example', 0, '0', '0');
+             INSERT INTO split_view_config (profile_id, layout, pane_1_tab_id) VALUES (1, 'vertical', 7);
+             INSERT INTO bookmark_folders (id, profile_id, name, created_at) VALUES (4, 1, 'Work', '0');
+             INSERT INTO bookmarks (profile_id, title, url, folder_id, created_at, updated_at) VALUES (1, 'A', 'https://a.example', 4, '0', '0');
+             INSERT INTO multimedia_playlists (id, profile_id, name, created_at) VALUES (2, 1, 'Mix', '0');
+             INSERT INTO multimedia_playlist_items (playlist_id, source, media_type, position, added_at) VALUES (2, 'song.mp3', 'audio', 0, '0');
+             INSERT INTO scraping_jobs (id, profile_id, name, base_url, created_at) VALUES (8, 1, 'Docs', 'https://docs.example', '0');
+             INSERT INTO scraped_pages (job_id, url, content, scraped_at) VALUES (8, 'https://docs.example/x', 'x', '0');
+             INSERT INTO scraping_runs (job_id, trigger, status, started_at, pages_scraped) VALUES (8, 'manual', 'completed', '0', 1);
+             INSERT INTO password_vaults (profile_id, key_params, wrapped_key, created_at, updated_at) VALUES (1, 'params', 'wrapped', '0', '0');
+             INSERT INTO password_entries (profile_id, data, created_at, updated_at) VALUES (1, 'sealed login', '0', '0');
+             INSERT INTO otp_vaults (profile_id, key_params, wrapped_key, created_at, updated_at) VALUES (1, 'params', 'wrapped otp', '0', '0');
+             INSERT INTO otp_entries (profile_id, data, created_at, updated_at) VALUES (1, 'sealed secret', '0', '0');
+             INSERT INTO pages (url, title, visited_at, profile_id) VALUES ('https://other.example', 'Not mine', '0', 2);"
+        ).unwrap();
+        (BackupManager::new(db.clone()), db)
+    }
+
+    fn count(db: &DbPool, sql: &str) -> i64 {
+        db.get().unwrap().query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn restore(manager: &BackupManager, archive: &str, target: Option<i64>, strategy: ConflictStrategy) -> RestoreReport {
+        let options = RestoreOptions { target_profile_id: target, strategy };
+        manager.restore_backup(archive, None, &options, &TaskContext::detached("restore")).unwrap()
+    }
+
+    fn table<'a>(report: &'a RestoreReport, name: &str) -> &'a TableRestoreReport {
+        report.tables.iter().find(|t| t.table == name).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_into_new_profile() {
+        let (manager, db) = seeded("backup_round_trip");
+        let archive = manager.create_backup(1, None, &TaskContext::detached("backup")).unwrap();
+
+        let manifest = manager.inspect_backup(&archive, None).unwrap();
+        assert_eq!(manifest.profile.name, "One");
+        assert_eq!(manifest.tables.iter().find(|t| t.name == "pages").unwrap().rows, 1);
+
+        let report = restore(&manager, &archive, None, ConflictStrategy::Skip);
+        assert!(report.created_profile);
+        let new_id = report.profile_id;
+        let name: String = db.get().unwrap()
+            .query_row("SELECT name FROM profiles WHERE id = ?1", [new_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "One (2)");
+
+        // Every reference points at the restored copy, not the original rows
+        let conn = db.get().unwrap();
+        let (folder_profile, folder_name): (i64, String) = conn.query_row(
+            "SELECT f.profile_id, f.name FROM bookmarks b JOIN bookmark_folders f ON f.id = b.folder_id
+             WHERE b.profile_id = ?1",
+            [new_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((folder_profile, folder_name.as_str()), (new_id, "Work"));
+        let pane_tab_profile: i64 = conn.query_row(
+            "SELECT t.profile_id FROM split_view_config s JOIN tabs t ON t.id = s.pane_1_tab_id WHERE s.profile_id = ?1",
+            [new_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(pane_tab_profile, new_id);
+        let note_page: String = conn.query_row(
+            "SELECT p.url FROM page_notes n JOIN pages p ON p.id = n.page_id WHERE p.profile_id = ?1",
+            [new_id],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(note_page, "https://a.example");
+        let trust: f64 = conn.query_row("SELECT trust_score FROM domains WHERE profile_id = ?1", [new_id], |row| row.get(0)).unwrap();
+        assert_eq!(trust, 0.25);
+        let auto_delete: i64 = conn.query_row(
+            "SELECT auto_delete_days FROM privacy_settings WHERE profile_id = ?1", [new_id], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(auto_delete, 30);
+
+        assert_eq!(count(&db, &format!("SELECT COUNT(*) FROM list_domains l JOIN domains d ON d.id = l.domain_id WHERE d.profile_id = {}", new_id)), 1);
+        assert_eq!(count(&db, &format!("SELECT COUNT(*) FROM scraped_pages s JOIN scraping_jobs j ON j.id = s.job_id WHERE j.profile_id = {}", new_id)), 1);
+        assert_eq!(count(&db, &format!("SELECT COUNT(*) FROM scraping_runs r JOIN scraping_jobs j ON j.id = r.job_id WHERE j.profile_id = {}", new_id)), 1);
+        assert_eq!(count(&db, &format!("SELECT COUNT(*) FROM multimedia_playlist_items i JOIN multimedia_playlists p ON p.id = i.playlist_id WHERE p.profile_id = {}", new_id)), 1);
+        // Profile 2's pages stay out of it
+        assert_eq!(count(&db, &format!("SELECT COUNT(*) FROM pages WHERE profile_id = {}", new_id)), 1);
+
+        // Vaults come back byte for byte, entries under the restored vault
+        let wrapped_key: String = conn.query_row(
+            "SELECT wrapped_key FROM password_vaults WHERE profile_id = ?1", [new_id], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(wrapped_key, "wrapped");
+        let entry: String = conn.query_row(
+            "SELECT data FROM password_entries WHERE profile_id = ?1", [new_id], |row| row.get(0),
+        ).unwrap();
+        assert_eq!(entry, "sealed login");
+        let (wrapped_key, entry): (String, String) = conn.query_row(
+            "SELECT v.wrapped_key, e.data FROM otp_entries e JOIN otp_vaults v ON v.profile_id = e.profile_id
+             WHERE e.profile_id = ?1",
+            [new_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!((wrapped_key.as_str(), entry.as_str()), ("wrapped otp", "sealed secret"));
+    }
+
+    #[test]
+    fn test_vaults_are_not_merged_into_a_different_vault() {
+        let (manager, db) = seeded("backup_vaults");
+        let archive = manager.create_backup(1, None, &TaskContext::detached("backup")).unwrap();
+
+        // Same vault: entries merge as usual
+        let report = restore(&manager, &archive, Some(1), ConflictStrategy::Duplicate);
+        assert_eq!(table(&report, "password_entries").inserted, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM password_entries WHERE profile_id = 1"), 2);
+
+        // Another vault: nothing in the backup could be decrypted with its key
+        db.get().unwrap().execute_batch(
+            "INSERT INTO profiles (id, name, created_at) VALUES (3, 'Three', '0');
+             INSERT INTO password_vaults (profile_id, key_params, wrapped_key, created_at, updated_at) VALUES (3, 'params', 'other', '0', '0');"
+        ).unwrap();
+        let report = restore(&manager, &archive, Some(3), ConflictStrategy::Overwrite);
+        assert_eq!(table(&report, "password_vaults").skipped, 1);
+        assert_eq!(table(&report, "password_entries").skipped, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM password_vaults WHERE profile_id = 3 AND wrapped_key = 'other'"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM password_entries WHERE profile_id = 3"), 0);
+    }
+
+    #[test]
+    fn test_conflict_strategies() {
+        let (manager, db) = seeded("backup_conflicts");
+        let archive = manager.create_backup(1, None, &TaskContext::detached("backup")).unwrap();
+        db.get().unwrap().execute("UPDATE bookmarks SET title = 'Renamed'", []).unwrap();
+
+        let report = restore(&manager, &archive, Some(1), ConflictStrategy::Skip);
+        assert_eq!(table(&report, "bookmarks").skipped, 1);
+        assert_eq!(table(&report, "bookmarks").inserted, 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM bookmarks WHERE title = 'Renamed'"), 1);
+
+        let report = restore(&manager, &archive, Some(1), ConflictStrategy::Overwrite);
+        assert_eq!(table(&report, "bookmarks").updated, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM bookmarks WHERE title = 'A'"), 1);
+
+        let report = restore(&manager, &archive, Some(1), ConflictStrategy::Duplicate);
+        assert_eq!(table(&report, "bookmarks").inserted, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM bookmarks WHERE profile_id = 1"), 2);
+        // Unique names get a suffix; pages can't share a URL, so the existing one stays
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM themes WHERE profile_id = 1 AND name = 'Dark (2)'"), 1);
+        assert_eq!(table(&report, "pages").skipped, 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM pages WHERE profile_id = 1"), 1);
+    }
+
+    #[test]
+    fn test_checksums_and_encryption() {
+        let (manager, _db) = seeded("backup_integrity");
+
+        let archive = manager.create_backup(1, None, &TaskContext::detached("backup")).unwrap();
+        let tampered = archive.replace("remember this", "forget this");
+        let error = manager.inspect_backup(&tampered, None).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Validation);
+
+        let encrypted = manager.create_backup(1, Some("hunter2"), &TaskContext::detached("backup")).unwrap();
+        assert!(!encrypted.contains("remember this"));
+        assert_eq!(manager.inspect_backup(&encrypted, None).unwrap_err().kind(), ErrorKind::Crypto);
+        assert_eq!(manager.inspect_backup(&encrypted, Some("wrong")).unwrap_err().kind(), ErrorKind::Crypto);
+
+        let options = RestoreOptions { target_profile_id: None, strategy: ConflictStrategy::Skip };
+        let report = manager
+            .restore_backup(&encrypted, Some("hunter2"), &options, &TaskContext::detached("restore"))
+            .unwrap();
+        assert_eq!(table(&report, "page_notes").inserted, 1);
+    }
+}
@@ -1,9 +1,13 @@
 // Bookmark management for Earth Reclaim
 // Full bookmark system with folders, tags, and import/export
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+use crate::error::AppResult;
+use crate::tasks::TaskContext;
+
 fn chrono_now() -> String {
     chrono::Utc::now().to_rfc3339()
 }
@@ -69,13 +73,14 @@ pub struct FolderExportItem {
     pub parent: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct BookmarkManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl BookmarkManager {
-    pub fn new(db_path: String) -> Self {
-        BookmarkManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        BookmarkManager { db }
     }
 
     /// Add a new bookmark
@@ -88,7 +93,7 @@ impl BookmarkManager {
         tags: Vec<String>,
         notes: Option<&str>,
     ) -> Result<Bookmark> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
         let tags_json = serde_json::to_string(&tags).unwrap_or_else(|_| "[]".to_string());
 
@@ -127,14 +132,14 @@ impl BookmarkManager {
 
     /// Delete a bookmark
     pub fn delete_bookmark(&self, bookmark_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.execute("DELETE FROM bookmarks WHERE id = ?1", params![bookmark_id])?;
         Ok(())
     }
 
     /// Get all bookmarks for a profile
     pub fn get_all_bookmarks(&self, profile_id: i64) -> Result<Vec<Bookmark>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT b.id, b.profile_id, b.title, b.url, b.favicon, b.folder_id, f.name as folder_name,
                     b.tags, b.notes, b.position, b.created_at, b.updated_at
@@ -169,7 +174,7 @@ impl BookmarkManager {
 
     /// Get bookmarks by folder
     pub fn get_bookmarks_by_folder(&self, profile_id: i64, folder_id: Option<i64>) -> Result<Vec<Bookmark>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = if folder_id.is_some() {
             conn.prepare(
@@ -222,7 +227,7 @@ impl BookmarkManager {
 
     /// Search bookmarks
     pub fn search_bookmarks(&self, profile_id: i64, query: &str) -> Result<Vec<Bookmark>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let search_pattern = format!("%{}%", query.to_lowercase());
 
         let mut stmt = conn.prepare(
@@ -273,7 +278,7 @@ impl BookmarkManager {
         notes: Option<Option<&str>>,
         favicon: Option<&str>,
     ) -> Result<Bookmark> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Get current bookmark
@@ -299,7 +304,7 @@ impl BookmarkManager {
 
     /// Get a single bookmark
     pub fn get_bookmark(&self, bookmark_id: i64) -> Result<Bookmark> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.query_row(
             "SELECT b.id, b.profile_id, b.title, b.url, b.favicon, b.folder_id, f.name as folder_name,
                     b.tags, b.notes, b.position, b.created_at, b.updated_at
@@ -331,7 +336,7 @@ impl BookmarkManager {
 
     /// Check if URL is bookmarked
     pub fn is_bookmarked(&self, profile_id: i64, url: &str) -> Result<Option<i64>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.query_row(
             "SELECT id FROM bookmarks WHERE profile_id = ?1 AND url = ?2",
             params![profile_id, url],
@@ -343,7 +348,7 @@ impl BookmarkManager {
 
     /// Create a folder
     pub fn create_folder(&self, profile_id: i64, name: &str, parent_id: Option<i64>) -> Result<BookmarkFolder> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         let max_pos: i32 = conn
@@ -375,7 +380,7 @@ impl BookmarkManager {
 
     /// Get all folders for a profile
     pub fn get_all_folders(&self, profile_id: i64) -> Result<Vec<BookmarkFolder>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT f.id, f.profile_id, f.name, f.parent_id, f.position, f.created_at,
                     (SELECT COUNT(*) FROM bookmarks WHERE folder_id = f.id) as bookmark_count
@@ -401,7 +406,7 @@ impl BookmarkManager {
 
     /// Delete a folder (moves bookmarks to root)
     pub fn delete_folder(&self, folder_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Move bookmarks to root
         conn.execute(
@@ -426,13 +431,19 @@ impl BookmarkManager {
 
     /// Rename a folder
     pub fn rename_folder(&self, folder_id: i64, name: &str) -> Result<BookmarkFolder> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE bookmark_folders SET name = ?1 WHERE id = ?2",
             params![name, folder_id],
         )?;
 
+        self.get_folder(folder_id)
+    }
+
+    /// Get a single folder
+    pub fn get_folder(&self, folder_id: i64) -> Result<BookmarkFolder> {
+        let conn = self.db.get()?;
         conn.query_row(
             "SELECT f.id, f.profile_id, f.name, f.parent_id, f.position, f.created_at,
                     (SELECT COUNT(*) FROM bookmarks WHERE folder_id = f.id) as bookmark_count
@@ -488,7 +499,7 @@ impl BookmarkManager {
     }
 
     fn get_folder_name(&self, folder_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.query_row(
             "SELECT name FROM bookmark_folders WHERE id = ?1",
             params![folder_id],
@@ -544,10 +555,9 @@ impl BookmarkManager {
         Ok(html)
     }
 
-    /// Import bookmarks from JSON
-    pub fn import_bookmarks_json(&self, profile_id: i64, data: &str) -> Result<i32> {
-        let export: BookmarkExport = serde_json::from_str(data)
-            .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Invalid JSON: {}", e)))?;
+    /// Import bookmarks from JSON; a cancelled import keeps what it already added
+    pub fn import_bookmarks_json(&self, profile_id: i64, data: &str, task: &TaskContext) -> AppResult<i32> {
+        let export: BookmarkExport = serde_json::from_str(data)?;
 
         let mut imported = 0;
 
@@ -561,7 +571,13 @@ impl BookmarkManager {
         }
 
         // Import bookmarks
-        for bookmark in &export.bookmarks {
+        let total = export.bookmarks.len() as u64;
+        for (i, bookmark) in export.bookmarks.iter().enumerate() {
+            if task.is_cancelled() {
+                break;
+            }
+            task.progress(i as u64 + 1, Some(total));
+
             let folder_id = bookmark.folder.as_ref().and_then(|f| folder_name_to_id.get(f).copied());
             if self.add_bookmark(
                 profile_id,
@@ -578,13 +594,20 @@ impl BookmarkManager {
         Ok(imported)
     }
 
-    /// Import bookmarks from HTML (basic Netscape format parsing)
-    pub fn import_bookmarks_html(&self, profile_id: i64, data: &str) -> Result<i32> {
+    /// Import bookmarks from HTML (basic Netscape format parsing).
+    /// Progress counts lines, since the number of links isn't known up front.
+    pub fn import_bookmarks_html(&self, profile_id: i64, data: &str, task: &TaskContext) -> Result<i32> {
         let mut imported = 0;
         let mut current_folder: Option<String> = None;
         let mut folder_name_to_id: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
 
-        for line in data.lines() {
+        let total = data.lines().count() as u64;
+        for (i, line) in data.lines().enumerate() {
+            if task.is_cancelled() {
+                break;
+            }
+            task.progress(i as u64 + 1, Some(total));
+
             let trimmed = line.trim();
 
             // Check for folder
@@ -0,0 +1,186 @@
+// Key derivation and authenticated encryption shared by the encrypted features
+// Keys come from passphrases through Argon2id with a random salt; the salt and cost
+// parameters are stored with whatever they protect as a PHC string without the hash,
+// e.g. `$argon2id$v=19$m=19456,t=2,p=1$<salt>`, so costs can be raised later.
+// Stored passwords are full Argon2id PHC strings, checked against the parameters they
+// were hashed with. Ciphertexts are base64(nonce || AES-256-GCM output).
+
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::Rng;
+
+use crate::error::{AppError, AppResult};
+
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// OWASP's recommended Argon2id minimum: 19 MiB, 2 passes. Tests use a cheap setting.
+#[cfg(not(test))]
+const MEMORY_COST_KIB: u32 = 19 * 1024;
+#[cfg(test)]
+const MEMORY_COST_KIB: u32 = 64;
+const TIME_COST: u32 = 2;
+const PARALLELISM: u32 = 1;
+
+pub type Key = [u8; KEY_LEN];
+
+fn argon2() -> Argon2<'static> {
+    let params = Params::new(MEMORY_COST_KIB, TIME_COST, PARALLELISM, Some(KEY_LEN))
+        .expect("valid Argon2 parameters");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+/// Fresh salt and the current cost parameters, to store next to the data
+pub fn new_key_params() -> String {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    format!(
+        "$argon2id$v=19$m={},t={},p={}${}",
+        MEMORY_COST_KIB, TIME_COST, PARALLELISM, salt.as_str()
+    )
+}
+
+/// Derive the key for `secret` with parameters from `new_key_params`
+pub fn derive_key(secret: &str, key_params: &str) -> AppResult<Key> {
+    let parsed = PasswordHash::new(key_params)
+        .map_err(|e| AppError::Crypto(format!("Invalid key parameters: {}", e)))?;
+    if parsed.algorithm.as_str() != "argon2id" {
+        return Err(AppError::Crypto(format!("Unsupported key derivation '{}'", parsed.algorithm)));
+    }
+    let params = Params::try_from(&parsed)
+        .map_err(|e| AppError::Crypto(format!("Invalid key parameters: {}", e)))?;
+    let mut salt = [0u8; 64];
+    let salt = parsed
+        .salt
+        .ok_or_else(|| AppError::Crypto("Key parameters have no salt".to_string()))?
+        .decode_b64(&mut salt)
+        .map_err(|e| AppError::Crypto(format!("Invalid salt: {}", e)))?;
+
+    let mut key = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(secret.as_bytes(), salt, &mut key)
+        .map_err(|e| AppError::Crypto(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+/// Hash a password for storage, with a fresh salt and the current cost parameters
+pub fn hash_password(password: &str) -> AppResult<String> {
+    let salt = SaltString::generate(&mut rand::rngs::OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| AppError::Crypto(format!("Password hashing failed: {}", e)))
+}
+
+/// Check `password` against a `hash_password` string, using the costs recorded in it
+pub fn verify_password(password: &str, hash: &str) -> AppResult<bool> {
+    let parsed = PasswordHash::new(hash)
+        .map_err(|e| AppError::Crypto(format!("Invalid password hash: {}", e)))?;
+    match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+        Ok(()) => Ok(true),
+        Err(password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(AppError::Crypto(format!("Password verification failed: {}", e))),
+    }
+}
+
+/// Whether a stored hash (or key parameters) should be replaced on the next successful
+/// login: anything that isn't Argon2id at the current costs or better
+pub fn needs_rehash(hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(hash) else {
+        return true;
+    };
+    if parsed.algorithm.as_str() != "argon2id" {
+        return true;
+    }
+    match Params::try_from(&parsed) {
+        Ok(params) => params.m_cost() < MEMORY_COST_KIB || params.t_cost() < TIME_COST,
+        Err(_) => true,
+    }
+}
+
+/// A random key, for data keys that are stored wrapped by a passphrase key
+pub fn random_key() -> Key {
+    rand::thread_rng().gen()
+}
+
+pub fn encrypt(key: &Key, plaintext: &[u8]) -> AppResult<String> {
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|e| AppError::Crypto(format!("Failed to create cipher: {}", e)))?;
+    let nonce_bytes: [u8; NONCE_LEN] = rand::thread_rng().gen();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|e| AppError::Crypto(format!("Encryption failed: {}", e)))?;
+
+    let mut result = nonce_bytes.to_vec();
+    result.extend(ciphertext);
+    Ok(BASE64.encode(&result))
+}
+
+/// Fails with a Crypto error for the wrong key as well as for tampered data
+pub fn decrypt(key: &Key, encoded: &str) -> AppResult<Vec<u8>> {
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|e| AppError::Crypto(format!("Failed to create cipher: {}", e)))?;
+    let data = BASE64.decode(encoded)
+        .map_err(|e| AppError::Crypto(format!("Invalid base64: {}", e)))?;
+    if data.len() < NONCE_LEN {
+        return Err(AppError::Crypto("Invalid encrypted data: too short".to_string()));
+    }
+
+    cipher
+        .decrypt(Nonce::from_slice(&data[..NONCE_LEN]), &data[NONCE_LEN..])
+        .map_err(|_| AppError::Crypto("Decryption failed: wrong key or corrupted data".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_keys_depend_on_secret_and_salt() {
+        let params = new_key_params();
+        assert!(params.starts_with("$argon2id$v=19$m=64,t=2,p=1$"));
+
+        let key = derive_key("correct horse", &params).unwrap();
+        assert_eq!(key, derive_key("correct horse", &params).unwrap());
+        assert_ne!(key, derive_key("wrong horse", &params).unwrap());
+        assert_ne!(key, derive_key("correct horse", &new_key_params()).unwrap());
+
+        assert!(derive_key("x", "$pbkdf2-sha256$i=1000$c2FsdHNhbHQ").is_err());
+    }
+
+    #[test]
+    fn test_password_hashes_are_salted_and_upgradable() {
+        let hash = hash_password("hunter22").unwrap();
+        assert!(hash.starts_with("$argon2id$v=19$m=64,t=2,p=1$"));
+        assert_ne!(hash, hash_password("hunter22").unwrap(), "salts must differ");
+        assert!(verify_password("hunter22", &hash).unwrap());
+        assert!(!verify_password("hunter23", &hash).unwrap());
+        assert!(!needs_rehash(&hash));
+
+        // Weaker costs still verify but get flagged, as do legacy and non-Argon2 values
+        let salt = SaltString::generate(&mut rand::rngs::OsRng);
+        let weak = Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::new(32, 1, 1, None).unwrap())
+            .hash_password(b"hunter22", &salt)
+            .unwrap()
+            .to_string();
+        assert!(verify_password("hunter22", &weak).unwrap());
+        assert!(needs_rehash(&weak));
+        assert!(needs_rehash("5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"));
+        assert!(verify_password("x", "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8").is_err());
+    }
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let key = random_key();
+        let sealed = encrypt(&key, b"page text").unwrap();
+        assert_ne!(sealed, encrypt(&key, b"page text").unwrap(), "nonces must differ");
+        assert_eq!(decrypt(&key, &sealed).unwrap(), b"page text");
+
+        let error = decrypt(&random_key(), &sealed).unwrap_err();
+        assert_eq!(error.kind(), crate::error::ErrorKind::Crypto);
+    }
+}
@@ -0,0 +1,234 @@
+// Shared SQLite connection pool for EarthServers Local
+// Managers borrow a connection per call instead of opening the database every time.
+// Every connection runs in WAL mode with foreign keys on, a busy timeout
+// and a prepared-statement cache, so `prepare_cached` pays off across calls.
+
+use rusqlite::{Connection, Result};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Connections kept open between calls; more are opened under load and closed afterwards
+const MAX_IDLE: usize = 8;
+/// How long a writer waits for another connection's lock before failing with SQLITE_BUSY
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+#[derive(Clone)]
+pub struct DbPool {
+    inner: Arc<PoolInner>,
+}
+
+struct PoolInner {
+    path: String,
+    max_idle: usize,
+    idle: Mutex<Vec<Connection>>,
+}
+
+impl DbPool {
+    pub fn open(path: &str) -> Result<Self> {
+        Self::with_max_idle(path, MAX_IDLE)
+    }
+
+    /// A pool with `max_idle` 0 opens a fresh connection for every call
+    pub fn with_max_idle(path: &str, max_idle: usize) -> Result<Self> {
+        let pool = DbPool {
+            inner: Arc::new(PoolInner {
+                path: path.to_string(),
+                max_idle,
+                idle: Mutex::new(Vec::new()),
+            }),
+        };
+
+        // Fail at startup rather than on the first query, and switch the file to WAL once
+        drop(pool.get()?);
+        Ok(pool)
+    }
+
+    /// Borrow a connection; it goes back to the pool when dropped
+    pub fn get(&self) -> Result<PooledConnection> {
+        let idle = self.inner.idle.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        let conn = match idle {
+            Some(conn) => conn,
+            None => connect(&self.inner.path)?,
+        };
+
+        Ok(PooledConnection {
+            conn: Some(conn),
+            pool: self.inner.clone(),
+        })
+    }
+}
+
+fn connect(path: &str) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+    // WAL only needs a sync at checkpoints to stay consistent.
+    // secure_delete zeroes freed pages, so locking a profile leaves no plaintext behind.
+    conn.execute_batch(
+        "PRAGMA foreign_keys = ON;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA secure_delete = ON;"
+    )?;
+    Ok(conn)
+}
+
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    pool: Arc<PoolInner>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection already returned to the pool")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection already returned to the pool")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else { return };
+
+        // A connection left inside a transaction (e.g. after a panic) is closed, not reused
+        if !conn.is_autocommit() {
+            return;
+        }
+
+        let mut idle = self.pool.idle.lock().unwrap_or_else(|e| e.into_inner());
+        if idle.len() < self.pool.max_idle {
+            idle.push(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MemoryManager;
+    use crate::search::SearchManager;
+    use crate::tasks::TaskContext;
+    use std::time::Instant;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("earth_db_{}_{}.db", name, std::process::id()));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix));
+        }
+        path.to_string_lossy().to_string()
+    }
+
+    fn migrated_pool(name: &str, max_idle: usize) -> DbPool {
+        let path = temp_path(name);
+        crate::migrations::run(&path).unwrap();
+        let pool = DbPool::with_max_idle(&path, max_idle).unwrap();
+        pool.get().unwrap()
+            .execute("INSERT INTO profiles (id, name, created_at) VALUES (1, 'One', '0')", [])
+            .unwrap();
+        pool
+    }
+
+    #[test]
+    fn test_connections_are_configured_and_reused() {
+        let pool = migrated_pool("config", 2);
+
+        {
+            let conn = pool.get().unwrap();
+            let mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+            assert_eq!(mode, "wal");
+            let foreign_keys: i64 = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+            assert_eq!(foreign_keys, 1);
+        }
+
+        // Three at once, but only two stay open afterwards
+        let held: Vec<_> = (0..3).map(|_| pool.get().unwrap()).collect();
+        drop(held);
+        assert_eq!(pool.inner.idle.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_cascades_fire() {
+        let pool = migrated_pool("cascade", 2);
+        let conn = pool.get().unwrap();
+        conn.execute_batch(
+            "INSERT INTO tabs (profile_id, url, position, created_at, last_accessed)
+                VALUES (1, 'https://example.com', 0, '0', '0');
+             DELETE FROM profiles WHERE id = 1;"
+        ).unwrap();
+
+        let tabs: i64 = conn.query_row("SELECT COUNT(*) FROM tabs", [], |row| row.get(0)).unwrap();
+        assert_eq!(tabs, 0);
+    }
+
+    #[test]
+    fn test_open_transaction_is_not_reused() {
+        let pool = migrated_pool("transaction", 2);
+        {
+            let conn = pool.get().unwrap();
+            conn.execute_batch("BEGIN").unwrap();
+        }
+        assert!(pool.inner.idle.lock().unwrap().is_empty());
+        assert!(pool.get().unwrap().is_autocommit());
+    }
+
+    // Benchmarks: cargo test --release bench_ -- --ignored --nocapture
+    // Each compares a fresh connection per call (the old behaviour) with the pool.
+
+    fn memory_export(pages: usize) -> String {
+        let pages: Vec<serde_json::Value> = (0..pages)
+            .map(|i| serde_json::json!({
+                "url": format!("https://example.com/{}", i),
+                "title": format!("Page {}", i),
+                "content": "Lorem ipsum dolor sit amet ".repeat(20),
+                "tags": "bench",
+                "notes": [{ "content": format!("note {}", i) }]
+            }))
+            .collect();
+        serde_json::json!({ "pages": pages }).to_string()
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_import_memory() {
+        let export = memory_export(500);
+
+        for (label, max_idle) in [("connection per call", 0), ("pooled", MAX_IDLE)] {
+            let manager = MemoryManager::new(migrated_pool(&format!("bench_memory_{}", max_idle), max_idle));
+            let started = Instant::now();
+            let imported = manager.import_memory(1, &export, &TaskContext::detached("import_memory")).unwrap();
+            println!("import_memory, {} pages, {}: {:?}", imported, label, started.elapsed());
+            assert_eq!(imported, 500);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_seed_default_domains() {
+        let resource_dir = std::env::temp_dir().join(format!("earth_db_bench_resources_{}", std::process::id()));
+        let lists_dir = resource_dir.join("domain-lists");
+        std::fs::create_dir_all(&lists_dir).unwrap();
+        let domains: Vec<serde_json::Value> = (0..1000)
+            .map(|i| serde_json::json!({ "url": format!("site{}.example.com", i), "category": "news", "trust_score": 0.5 }))
+            .collect();
+        std::fs::write(
+            lists_dir.join("bench.earth"),
+            serde_json::json!({ "version": 1, "type": "domain-list", "name": "Bench", "domains": domains }).to_string(),
+        ).unwrap();
+
+        for (label, max_idle) in [("connection per call", 0), ("pooled", MAX_IDLE)] {
+            let manager = SearchManager::new(migrated_pool(&format!("bench_domains_{}", max_idle), max_idle));
+            let started = Instant::now();
+            let imported = manager.seed_default_domains(1, &resource_dir).unwrap();
+            println!("seed_default_domains, {} domains, {}: {:?}", imported, label, started.elapsed());
+            assert_eq!(imported, 1000);
+        }
+    }
+}
@@ -0,0 +1,889 @@
+// Entity index for EarthMemory
+// Extracts people, organizations, places, dates and keyphrases from saved pages
+// so memory can be browsed by topic instead of by URL
+// Local heuristics always run; the local LLM can refine names when available
+
+use regex::Regex;
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::ai::{AiError, OllamaClient};
+use crate::db::DbPool;
+
+/// Text beyond this is ignored, entity density doesn't improve with length
+const MAX_TEXT_CHARS: usize = 20_000;
+const MAX_KEYPHRASES: usize = 10;
+/// Page text sent to the model for name classification
+const MAX_LLM_INPUT_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Person,
+    Organization,
+    Place,
+    Date,
+    Keyphrase,
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 5] = [
+        EntityKind::Person,
+        EntityKind::Organization,
+        EntityKind::Place,
+        EntityKind::Date,
+        EntityKind::Keyphrase,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Person => "person",
+            EntityKind::Organization => "organization",
+            EntityKind::Place => "place",
+            EntityKind::Date => "date",
+            EntityKind::Keyphrase => "keyphrase",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        EntityKind::ALL.into_iter().find(|k| k.as_str() == value)
+    }
+}
+
+/// Where a mention was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MentionSource {
+    #[serde(rename = "pages")]
+    Page,
+    #[serde(rename = "scraped_pages")]
+    ScrapedPage,
+}
+
+impl MentionSource {
+    pub fn table(&self) -> &'static str {
+        match self {
+            MentionSource::Page => "pages",
+            MentionSource::ScrapedPage => "scraped_pages",
+        }
+    }
+
+    fn from_table(table: &str) -> Option<Self> {
+        match table {
+            "pages" => Some(MentionSource::Page),
+            "scraped_pages" => Some(MentionSource::ScrapedPage),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedEntity {
+    pub kind: EntityKind,
+    pub name: String,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    pub id: i64,
+    pub kind: EntityKind,
+    pub name: String,
+    pub mention_count: i64,
+    pub page_count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityMention {
+    pub source: MentionSource,
+    pub page_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractionReport {
+    pub pages_processed: i64,
+    pub entities_found: i64,
+}
+
+/// A page whose text hasn't been through extraction (or changed since)
+#[derive(Debug, Clone)]
+struct PendingText {
+    source: MentionSource,
+    id: i64,
+    profile_id: i64,
+    text: String,
+    hash: String,
+}
+
+#[derive(Clone)]
+pub struct EntityIndex {
+    db: DbPool,
+}
+
+impl EntityIndex {
+    pub fn new(db: DbPool) -> Self {
+        EntityIndex { db }
+    }
+
+    /// Replace the stored mentions of one page with `entities`
+    pub fn store_entities(
+        &self,
+        source: MentionSource,
+        source_id: i64,
+        profile_id: i64,
+        content_hash: &str,
+        entities: &[ExtractedEntity],
+    ) -> Result<()> {
+        let mut conn = self.db.get()?;
+        let now = chrono_now();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "DELETE FROM entity_mentions WHERE source = ?1 AND source_id = ?2",
+            params![source.table(), source_id],
+        )?;
+
+        for entity in entities {
+            let normalized = normalize_name(&entity.name);
+            if normalized.is_empty() {
+                continue;
+            }
+
+            let aliased: Option<i64> = tx.query_row(
+                "SELECT entity_id FROM entity_aliases WHERE profile_id = ?1 AND kind = ?2 AND normalized = ?3",
+                params![profile_id, entity.kind.as_str(), normalized],
+                |row| row.get(0),
+            ).optional()?;
+
+            let entity_id = match aliased {
+                Some(id) => id,
+                None => {
+                    tx.execute(
+                        "INSERT OR IGNORE INTO entities (profile_id, kind, name, normalized, created_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![profile_id, entity.kind.as_str(), entity.name, normalized, now],
+                    )?;
+                    tx.query_row(
+                        "SELECT id FROM entities WHERE profile_id = ?1 AND kind = ?2 AND normalized = ?3",
+                        params![profile_id, entity.kind.as_str(), normalized],
+                        |row| row.get(0),
+                    )?
+                }
+            };
+
+            tx.execute(
+                "INSERT INTO entity_mentions (entity_id, source, source_id, count) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(entity_id, source, source_id) DO UPDATE SET count = count + excluded.count",
+                params![entity_id, source.table(), source_id, entity.count],
+            )?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO entity_extractions (source, source_id, content_hash, extracted_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![source.table(), source_id, content_hash, now],
+        )?;
+
+        tx.commit()
+    }
+
+    /// Run heuristic extraction on one indexed page right after it was saved
+    pub fn extract_indexed_page(&self, page_id: i64) -> Result<()> {
+        let pending = self.pending_texts(None, None, Some(page_id))?;
+        for item in pending {
+            let entities = extract_entities(&item.text);
+            self.store_entities(item.source, item.id, item.profile_id, &item.hash, &entities)?;
+        }
+        Ok(())
+    }
+
+    /// Extract entities for up to `limit` of the profile's new or changed pages, using the LLM
+    /// when a client is given
+    pub async fn extract_pending(&self, profile_id: i64, limit: i64, client: Option<&OllamaClient>) -> Result<ExtractionReport> {
+        self.prune_orphans()?;
+
+        let mut report = ExtractionReport { pages_processed: 0, entities_found: 0 };
+        for item in self.pending_texts(Some(profile_id), Some(limit), None)? {
+            let mut entities = extract_entities(&item.text);
+
+            if let Some(client) = client {
+                match extract_with_llm(client, &item.text).await {
+                    Ok(llm_entities) => entities = merge_extractions(entities, llm_entities),
+                    // The heuristics are still useful on their own
+                    Err(e) => eprintln!("LLM entity extraction failed, using heuristics only: {}", e),
+                }
+            }
+
+            self.store_entities(item.source, item.id, item.profile_id, &item.hash, &entities)?;
+            report.pages_processed += 1;
+            report.entities_found += entities.len() as i64;
+        }
+
+        Ok(report)
+    }
+
+    /// Pages whose content hash differs from the last extraction, of one profile or all.
+    /// Locked profiles are skipped, since their content is ciphertext.
+    fn pending_texts(&self, profile_id: Option<i64>, limit: Option<i64>, indexed_page_id: Option<i64>) -> Result<Vec<PendingText>> {
+        let conn = self.db.get()?;
+        let mut pending = Vec::new();
+
+        let mut queries = vec![(
+            MentionSource::Page,
+            "SELECT p.id, p.profile_id, p.title, p.content, e.content_hash
+             FROM pages p
+             LEFT JOIN entity_extractions e ON e.source = 'pages' AND e.source_id = p.id
+             WHERE p.profile_id IS NOT NULL AND (?1 IS NULL OR p.id = ?1) AND (?2 IS NULL OR p.profile_id = ?2)
+               AND p.profile_id NOT IN (SELECT profile_id FROM profile_encryption WHERE locked = 1)",
+        )];
+        if indexed_page_id.is_none() && table_exists(&conn, "scraped_pages")? {
+            queries.push((
+                MentionSource::ScrapedPage,
+                "SELECT sp.id, sj.profile_id, sp.title, sp.content, e.content_hash
+                 FROM scraped_pages sp
+                 JOIN scraping_jobs sj ON sj.id = sp.job_id
+                 LEFT JOIN entity_extractions e ON e.source = 'scraped_pages' AND e.source_id = sp.id
+                 WHERE (?1 IS NULL OR sp.id = ?1) AND (?2 IS NULL OR sj.profile_id = ?2)
+                   AND sj.profile_id NOT IN (SELECT profile_id FROM profile_encryption WHERE locked = 1)",
+            ));
+        }
+
+        for (source, sql) in queries {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map(params![indexed_page_id, profile_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            })?;
+
+            for row in rows {
+                let (id, profile_id, title, content, stored_hash) = row?;
+                let text = format!("{}\n{}", title, content);
+                let hash = content_hash(&text);
+                if stored_hash.as_deref() == Some(hash.as_str()) {
+                    continue;
+                }
+                pending.push(PendingText { source, id, profile_id, text, hash });
+                if limit.is_some_and(|l| pending.len() as i64 >= l) {
+                    return Ok(pending);
+                }
+            }
+        }
+
+        Ok(pending)
+    }
+
+    /// Drop mentions and extraction records of deleted pages, then entities nobody mentions
+    fn prune_orphans(&self) -> Result<()> {
+        let conn = self.db.get()?;
+        for source in [MentionSource::Page, MentionSource::ScrapedPage] {
+            if !table_exists(&conn, source.table())? {
+                continue;
+            }
+            for table in ["entity_mentions", "entity_extractions"] {
+                conn.execute(
+                    &format!(
+                        "DELETE FROM {} WHERE source = ?1 AND source_id NOT IN (SELECT id FROM {})",
+                        table,
+                        source.table()
+                    ),
+                    params![source.table()],
+                )?;
+            }
+        }
+        conn.execute(
+            "DELETE FROM entities WHERE id NOT IN (SELECT entity_id FROM entity_mentions)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Entities of a profile, most mentioned first
+    pub fn list_entities(
+        &self,
+        profile_id: i64,
+        kind: Option<EntityKind>,
+        query: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Entity>> {
+        let conn = self.db.get()?;
+        let pattern = query.map(|q| format!("%{}%", normalize_name(q)));
+
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.kind, e.name, COALESCE(SUM(m.count), 0), COUNT(m.source_id)
+             FROM entities e
+             JOIN entity_mentions m ON m.entity_id = e.id
+             WHERE e.profile_id = ?1
+               AND (?2 IS NULL OR e.kind = ?2)
+               AND (?3 IS NULL OR e.normalized LIKE ?3)
+             GROUP BY e.id
+             ORDER BY COUNT(m.source_id) DESC, SUM(m.count) DESC, e.name ASC
+             LIMIT ?4 OFFSET ?5"
+        )?;
+
+        let rows = stmt.query_map(
+            params![profile_id, kind.map(|k| k.as_str()), pattern, limit, offset],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )?;
+
+        let mut entities = Vec::new();
+        for row in rows {
+            let (id, kind, name, mention_count, page_count) = row?;
+            if let Some(kind) = EntityKind::from_str(&kind) {
+                entities.push(Entity { id, kind, name, mention_count, page_count });
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Every page mentioning an entity
+    pub fn get_entity_pages(&self, entity_id: i64, profile_id: i64) -> Result<Vec<EntityMention>> {
+        let conn = self.db.get()?;
+        let has_scraped = table_exists(&conn, "scraped_pages")?;
+
+        let mut sql = String::from(
+            "SELECT m.source, m.source_id, p.url, p.title, m.count
+             FROM entity_mentions m
+             JOIN entities e ON e.id = m.entity_id
+             JOIN pages p ON m.source = 'pages' AND p.id = m.source_id
+             WHERE m.entity_id = ?1 AND e.profile_id = ?2",
+        );
+        if has_scraped {
+            sql.push_str(
+                " UNION ALL
+                 SELECT m.source, m.source_id, sp.url, sp.title, m.count
+                 FROM entity_mentions m
+                 JOIN entities e ON e.id = m.entity_id
+                 JOIN scraped_pages sp ON m.source = 'scraped_pages' AND sp.id = m.source_id
+                 WHERE m.entity_id = ?1 AND e.profile_id = ?2",
+            );
+        }
+        sql.push_str(" ORDER BY 5 DESC");
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![entity_id, profile_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut mentions = Vec::new();
+        for row in rows {
+            let (source, page_id, url, title, count) = row?;
+            if let Some(source) = MentionSource::from_table(&source) {
+                mentions.push(EntityMention { source, page_id, url, title, count });
+            }
+        }
+        Ok(mentions)
+    }
+
+    /// Fold `duplicate_ids` into `target_id`: mentions move over and the duplicate names
+    /// become aliases. Returns the number of entities merged.
+    pub fn merge_entities(&self, target_id: i64, duplicate_ids: &[i64], profile_id: i64) -> Result<i64> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        let target_kind: Option<String> = tx.query_row(
+            "SELECT kind FROM entities WHERE id = ?1 AND profile_id = ?2",
+            params![target_id, profile_id],
+            |row| row.get(0),
+        ).optional()?;
+        let target_kind = match target_kind {
+            Some(kind) => kind,
+            None => return Ok(0),
+        };
+
+        let mut merged = 0;
+        for &duplicate_id in duplicate_ids {
+            if duplicate_id == target_id {
+                continue;
+            }
+            let duplicate: Option<String> = tx.query_row(
+                "SELECT normalized FROM entities WHERE id = ?1 AND profile_id = ?2",
+                params![duplicate_id, profile_id],
+                |row| row.get(0),
+            ).optional()?;
+            let normalized = match duplicate {
+                Some(n) => n,
+                None => continue,
+            };
+
+            tx.execute(
+                "INSERT INTO entity_mentions (entity_id, source, source_id, count)
+                 SELECT ?1, source, source_id, count FROM entity_mentions WHERE entity_id = ?2
+                 ON CONFLICT(entity_id, source, source_id) DO UPDATE SET count = count + excluded.count",
+                params![target_id, duplicate_id],
+            )?;
+
+            // Aliases of the duplicate, and the duplicate's own name, now point at the target.
+            // The alias keeps the target's kind so a merged "Apple" place/org pair resolves once.
+            tx.execute(
+                "UPDATE entity_aliases SET entity_id = ?1 WHERE entity_id = ?2",
+                params![target_id, duplicate_id],
+            )?;
+            let duplicate_kind: String = tx.query_row(
+                "SELECT kind FROM entities WHERE id = ?1",
+                params![duplicate_id],
+                |row| row.get(0),
+            )?;
+            for kind in [duplicate_kind.as_str(), target_kind.as_str()] {
+                tx.execute(
+                    "INSERT OR REPLACE INTO entity_aliases (profile_id, kind, normalized, entity_id)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![profile_id, kind, normalized, target_id],
+                )?;
+            }
+
+            tx.execute("DELETE FROM entities WHERE id = ?1", params![duplicate_id])?;
+            merged += 1;
+        }
+
+        tx.commit()?;
+        Ok(merged)
+    }
+}
+
+// ==================== Extraction ====================
+
+const MONTHS: &str = "January|February|March|April|May|June|July|August|September|October|November|December|Jan|Feb|Mar|Apr|Jun|Jul|Aug|Sep|Sept|Oct|Nov|Dec";
+
+const HONORIFICS: &[&str] = &["mr", "mrs", "ms", "dr", "prof", "sir", "dame", "president", "ceo", "senator"];
+
+const ORG_MARKERS: &[&str] = &[
+    "inc", "corp", "corporation", "ltd", "llc", "gmbh", "company", "co", "university", "college",
+    "foundation", "institute", "association", "group", "labs", "agency", "bank", "ministry",
+    "department", "council", "committee", "society", "school", "organization", "organisation",
+];
+
+const PLACE_MARKERS: &[&str] = &[
+    "city", "county", "river", "mountain", "mount", "lake", "island", "islands", "street", "avenue",
+    "republic", "kingdom", "state", "province", "valley", "bay", "park", "ocean", "sea",
+];
+
+/// Prepositions that usually introduce a place
+const PLACE_CUES: &[&str] = &["in", "at", "from", "near", "to"];
+
+/// Capitalized words that start sentences or headings and aren't names
+const CAPITALIZED_NOISE: &[&str] = &[
+    "the", "a", "an", "this", "that", "these", "those", "it", "we", "our", "you", "your", "he", "she",
+    "they", "i", "in", "on", "at", "for", "and", "but", "or", "if", "when", "while", "what", "how",
+    "why", "with", "from", "by", "as", "after", "before", "today", "yesterday", "monday", "tuesday",
+    "wednesday", "thursday", "friday", "saturday", "sunday", "read", "see", "more",
+];
+
+const KEYPHRASE_STOPWORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "all", "also", "am", "an", "and", "any", "are", "as",
+    "at", "be", "because", "been", "before", "being", "between", "both", "but", "by", "can", "could",
+    "did", "do", "does", "doing", "down", "during", "each", "few", "for", "from", "further", "had",
+    "has", "have", "having", "he", "her", "here", "hers", "him", "his", "how", "i", "if", "in",
+    "into", "is", "it", "its", "just", "like", "may", "me", "more", "most", "much", "must", "my",
+    "new", "no", "nor", "not", "now", "of", "off", "on", "once", "one", "only", "or", "other",
+    "our", "out", "over", "own", "same", "she", "should", "so", "some", "such", "than", "that",
+    "the", "their", "them", "then", "there", "these", "they", "this", "those", "through", "to",
+    "too", "under", "until", "up", "use", "used", "using", "very", "was", "we", "were", "what",
+    "when", "where", "which", "while", "who", "whom", "why", "will", "with", "would", "you", "your",
+];
+
+/// Heuristic extraction: regex dates, capitalized-name sequences classified by cue words,
+/// and RAKE-style keyphrases
+pub fn extract_entities(text: &str) -> Vec<ExtractedEntity> {
+    let text: String = text.chars().take(MAX_TEXT_CHARS).collect();
+    let mut counts: HashMap<(EntityKind, String), (String, i64)> = HashMap::new();
+    let mut add = |kind: EntityKind, name: &str| {
+        let name = name.trim().trim_matches(|c: char| !c.is_alphanumeric()).to_string();
+        let key = (kind, normalize_name(&name));
+        if key.1.is_empty() {
+            return;
+        }
+        counts.entry(key).or_insert((name, 0)).1 += 1;
+    };
+
+    // Dates
+    let date_patterns = [
+        r"\b\d{4}-\d{2}-\d{2}\b".to_string(),
+        format!(r"\b(?:{m})\.? \d{{1,2}}(?:st|nd|rd|th)?,? \d{{4}}\b", m = MONTHS),
+        format!(r"\b\d{{1,2}}(?:st|nd|rd|th)? (?:{m}) \d{{4}}\b", m = MONTHS),
+        format!(r"\b(?:{m}) \d{{4}}\b", m = MONTHS),
+    ];
+    let mut date_spans: Vec<(usize, usize)> = Vec::new();
+    for pattern in &date_patterns {
+        let re = Regex::new(pattern).expect("valid date regex");
+        for m in re.find_iter(&text) {
+            // Longer patterns run first; skip "March 2024" inside "March 5, 2024"
+            if date_spans.iter().any(|&(s, e)| m.start() >= s && m.end() <= e) {
+                continue;
+            }
+            date_spans.push((m.start(), m.end()));
+            add(EntityKind::Date, m.as_str());
+        }
+    }
+
+    // Names: runs of capitalized words on one line, allowing a few lowercase connectors inside
+    let name_re = Regex::new(
+        r"\b[A-Z][a-zA-Z'&\.-]*(?:[ \t]+(?:(?:of|de|la|van|von|der|du|&)[ \t]+)?[A-Z][a-zA-Z'&\.-]*)*"
+    ).expect("valid name regex");
+
+    for m in name_re.find_iter(&text) {
+        if date_spans.iter().any(|&(s, e)| m.start() < e && m.end() > s) {
+            continue;
+        }
+
+        let preceding = text[..m.start()]
+            .split_whitespace()
+            .next_back()
+            .map(|w| w.trim_end_matches('.').to_lowercase())
+            .unwrap_or_default();
+
+        // A full stop ends a name unless it belongs to an honorific or an initial ("Corp. Jane" is two)
+        let mut segment: Vec<&str> = Vec::new();
+        let mut segment_preceding = preceding;
+        for word in m.as_str().split_whitespace() {
+            segment.push(word);
+            let bare = word.trim_end_matches('.');
+            let abbreviation = HONORIFICS.contains(&bare.to_lowercase().as_str()) || bare.chars().count() == 1;
+            if word.ends_with('.') && !abbreviation {
+                let last = bare.to_lowercase();
+                classify_name(std::mem::take(&mut segment), &segment_preceding, &mut add);
+                segment_preceding = last;
+            }
+        }
+        classify_name(segment, &segment_preceding, &mut add);
+    }
+
+    let mut entities: Vec<ExtractedEntity> = counts
+        .into_iter()
+        .map(|((kind, _), (name, count))| ExtractedEntity { kind, name, count })
+        .collect();
+    entities.extend(extract_keyphrases(&text, MAX_KEYPHRASES));
+    entities.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    entities
+}
+
+/// Decide what a run of capitalized words is, from honorifics, marker words and the word before it
+fn classify_name<F: FnMut(EntityKind, &str)>(mut words: Vec<&str>, preceding: &str, add: &mut F) {
+    let connectors = ["of", "de", "la", "van", "von", "der", "du", "&"];
+
+    // Strip sentence-initial noise like "The" or "In"
+    while words.first().is_some_and(|w| CAPITALIZED_NOISE.contains(&w.trim_end_matches('.').to_lowercase().as_str())) {
+        words.remove(0);
+    }
+    while words.last().is_some_and(|w| connectors.contains(w)) {
+        words.pop();
+    }
+    if words.is_empty() {
+        return;
+    }
+
+    // Honorific inside the run ("Dr. Jane Doe") or right before it
+    let mut kind_hint = None;
+    if HONORIFICS.contains(&words[0].trim_end_matches('.').to_lowercase().as_str()) {
+        words.remove(0);
+        kind_hint = Some(EntityKind::Person);
+    } else if HONORIFICS.contains(&preceding) {
+        kind_hint = Some(EntityKind::Person);
+    }
+    if words.is_empty() {
+        return;
+    }
+
+    let lower: Vec<String> = words.iter().map(|w| w.trim_end_matches('.').to_lowercase()).collect();
+    let name = words.join(" ");
+    let is_acronym = |w: &str| (2..=6).contains(&w.len()) && w.chars().all(|c| c.is_ascii_uppercase());
+    let looks_like_name = |w: &&str| w.chars().skip(1).all(|c| c.is_lowercase() || "'-.".contains(c));
+
+    let kind = if let Some(kind) = kind_hint {
+        kind
+    } else if lower.iter().any(|w| ORG_MARKERS.contains(&w.as_str())) {
+        EntityKind::Organization
+    } else if lower.iter().any(|w| PLACE_MARKERS.contains(&w.as_str())) {
+        EntityKind::Place
+    } else if words.len() == 1 {
+        if is_acronym(words[0]) {
+            EntityKind::Organization
+        } else if PLACE_CUES.contains(&preceding) {
+            EntityKind::Place
+        } else {
+            // Lone capitalized words are too ambiguous to keep
+            return;
+        }
+    } else if PLACE_CUES.contains(&preceding) {
+        EntityKind::Place
+    } else if words.len() <= 3 && words.iter().all(looks_like_name) {
+        EntityKind::Person
+    } else {
+        EntityKind::Organization
+    };
+
+    add(kind, &name);
+}
+
+/// RAKE: candidate phrases are split at stopwords and punctuation; words score
+/// degree / frequency and a phrase scores the sum of its words
+pub fn extract_keyphrases(text: &str, max: usize) -> Vec<ExtractedEntity> {
+    let mut phrases: Vec<Vec<String>> = Vec::new();
+    for fragment in text.split(|c: char| ".,;:!?()[]{}\"\n\t|/".contains(c)) {
+        let mut current: Vec<String> = Vec::new();
+        for word in fragment.split_whitespace() {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+            let is_break = word.len() < 3
+                || KEYPHRASE_STOPWORDS.contains(&word.as_str())
+                || word.chars().all(|c| c.is_ascii_digit());
+            if is_break {
+                if !current.is_empty() {
+                    phrases.push(std::mem::take(&mut current));
+                }
+            } else {
+                current.push(word);
+            }
+        }
+        if !current.is_empty() {
+            phrases.push(current);
+        }
+    }
+    phrases.retain(|p| p.len() <= 3);
+
+    let mut frequency: HashMap<&str, f64> = HashMap::new();
+    let mut degree: HashMap<&str, f64> = HashMap::new();
+    for phrase in &phrases {
+        for word in phrase {
+            *frequency.entry(word).or_default() += 1.0;
+            *degree.entry(word).or_default() += phrase.len() as f64;
+        }
+    }
+
+    let mut scored: HashMap<String, (f64, i64)> = HashMap::new();
+    for phrase in &phrases {
+        let score: f64 = phrase.iter().map(|w| degree[w.as_str()] / frequency[w.as_str()]).sum();
+        let entry = scored.entry(phrase.join(" ")).or_insert((score, 0));
+        entry.1 += 1;
+    }
+
+    // A keyphrase should recur or span several words, otherwise it's just a word
+    let mut ranked: Vec<(String, f64, i64)> = scored
+        .into_iter()
+        .filter(|(phrase, (_, count))| *count > 1 || phrase.contains(' '))
+        .map(|(phrase, (score, count))| (phrase, score * (count as f64).sqrt(), count))
+        .collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+
+    ranked
+        .into_iter()
+        .take(max)
+        .map(|(name, _, count)| ExtractedEntity { kind: EntityKind::Keyphrase, name, count })
+        .collect()
+}
+
+/// Ask the local model to classify names in the text
+pub async fn extract_with_llm(client: &OllamaClient, text: &str) -> std::result::Result<Vec<ExtractedEntity>, AiError> {
+    let excerpt: String = text.chars().take(MAX_LLM_INPUT_CHARS).collect();
+    let prompt = format!(
+        "Extract named entities from the text below. Respond with JSON only, in the form\n\
+         {{\"people\": [], \"organizations\": [], \"places\": [], \"dates\": []}}.\n\
+         Use the names exactly as written in the text.\n\n{}",
+        excerpt
+    );
+
+    let response = client.generate(&prompt, None).await?;
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str::<serde_json::Value>(&response[start..=end])
+            .map_err(|e| AiError::InvalidResponse(e.to_string()))?,
+        _ => return Err(AiError::InvalidResponse("no JSON object in model output".to_string())),
+    };
+
+    let lower_text = text.to_lowercase();
+    let mut entities = Vec::new();
+    for (key, kind) in [
+        ("people", EntityKind::Person),
+        ("organizations", EntityKind::Organization),
+        ("places", EntityKind::Place),
+        ("dates", EntityKind::Date),
+    ] {
+        for name in json[key].as_array().into_iter().flatten().filter_map(|v| v.as_str()) {
+            let name = name.trim();
+            // Drop anything the model invented
+            let count = lower_text.matches(&name.to_lowercase()).count() as i64;
+            if !name.is_empty() && count > 0 {
+                entities.push(ExtractedEntity { kind, name: name.to_string(), count });
+            }
+        }
+    }
+    Ok(entities)
+}
+
+/// Combine heuristic and LLM results. The model's classification wins for names it found;
+/// keyphrases only come from the heuristics.
+pub fn merge_extractions(heuristic: Vec<ExtractedEntity>, llm: Vec<ExtractedEntity>) -> Vec<ExtractedEntity> {
+    let llm_names: Vec<String> = llm.iter().map(|e| normalize_name(&e.name)).collect();
+    let mut merged: Vec<ExtractedEntity> = heuristic
+        .into_iter()
+        .filter(|e| e.kind == EntityKind::Keyphrase || !llm_names.contains(&normalize_name(&e.name)))
+        .collect();
+    merged.extend(llm);
+    merged
+}
+
+/// Lowercase, collapse whitespace and drop punctuation so "Acme, Inc." and "acme inc" match
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn content_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{IndexedPage, MemoryManager};
+
+    fn names(entities: &[ExtractedEntity], kind: EntityKind) -> Vec<String> {
+        let mut names: Vec<String> = entities.iter().filter(|e| e.kind == kind).map(|e| e.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_extract_entities() {
+        let text = "The report by Dr. Jane Goodall was published on March 5, 2024 by Acme Corp. \
+                    Jane Goodall later spoke in Nairobi with Alan Turing about the NASA budget. \
+                    Meeting again on 2024-06-01 near Lake Victoria. Machine learning models need \
+                    training data; machine learning is everywhere.";
+        let entities = extract_entities(text);
+
+        assert_eq!(names(&entities, EntityKind::Person), vec!["Alan Turing", "Jane Goodall"]);
+        assert_eq!(names(&entities, EntityKind::Organization), vec!["Acme Corp", "NASA"]);
+        assert_eq!(names(&entities, EntityKind::Place), vec!["Lake Victoria", "Nairobi"]);
+        assert_eq!(names(&entities, EntityKind::Date), vec!["2024-06-01", "March 5, 2024"]);
+        assert!(names(&entities, EntityKind::Keyphrase).contains(&"machine learning".to_string()));
+
+        let goodall = entities.iter().find(|e| e.name == "Jane Goodall").unwrap();
+        assert_eq!(goodall.count, 2);
+    }
+
+    #[test]
+    fn test_merge_extractions_prefers_llm_kind() {
+        let heuristic = vec![
+            ExtractedEntity { kind: EntityKind::Person, name: "Mozilla Firefox".to_string(), count: 1 },
+            ExtractedEntity { kind: EntityKind::Keyphrase, name: "web browser".to_string(), count: 2 },
+        ];
+        let llm = vec![ExtractedEntity { kind: EntityKind::Organization, name: "Mozilla Firefox".to_string(), count: 1 }];
+        let merged = merge_extractions(heuristic, llm);
+        assert_eq!(merged.len(), 2);
+        assert!(merged.iter().any(|e| e.kind == EntityKind::Organization));
+    }
+
+    #[test]
+    fn test_entity_index_list_pages_and_merge() {
+        let db = crate::migrations::test_database("entities");
+        let memory = MemoryManager::new(db.clone());
+        let index = EntityIndex::new(db);
+
+        let page = |url: &str, content: &str| IndexedPage {
+            id: None,
+            url: url.to_string(),
+            title: "Notes".to_string(),
+            content: Some(content.to_string()),
+            summary: None,
+            indexed_at: String::new(),
+            last_visited: String::new(),
+            visit_count: 1,
+            is_favorite: false,
+            tags: None,
+            profile_id: None,
+        };
+        let a = memory.index_page(&page("https://a.example.com", "Ada Lovelace wrote notes."), 1).unwrap();
+        let b = memory.index_page(&page("https://b.example.com", "Augusta Lovelace and Ada Lovelace."), 1).unwrap();
+        index.extract_indexed_page(a.id.unwrap()).unwrap();
+        index.extract_indexed_page(b.id.unwrap()).unwrap();
+
+        let people = index.list_entities(1, Some(EntityKind::Person), None, 50, 0).unwrap();
+        assert_eq!(people[0].name, "Ada Lovelace");
+        assert_eq!(people[0].page_count, 2);
+        assert_eq!(index.get_entity_pages(people[0].id, 1).unwrap().len(), 2);
+
+        let augusta = people.iter().find(|e| e.name == "Augusta Lovelace").unwrap().id;
+        assert_eq!(index.merge_entities(people[0].id, &[augusta], 1).unwrap(), 1);
+        let people = index.list_entities(1, Some(EntityKind::Person), Some("lovelace"), 50, 0).unwrap();
+        assert_eq!(people.len(), 1);
+        assert_eq!(people[0].mention_count, 3);
+
+        // Re-extraction after a content change resolves the alias to the merged entity
+        memory.index_page(&page("https://b.example.com", "Notes on Augusta Lovelace."), 1).unwrap();
+        index.extract_indexed_page(b.id.unwrap()).unwrap();
+        let people = index.list_entities(1, Some(EntityKind::Person), None, 50, 0).unwrap();
+        assert_eq!(people.len(), 1);
+        assert_eq!(people[0].page_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_locked_profiles_are_not_extracted() {
+        let db = crate::migrations::test_database("entities_locked");
+        db.get().unwrap().execute_batch(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id)
+                VALUES ('https://diary.example', 'Diary', 'Ada Lovelace visited Paris.', '0', 1);
+             INSERT INTO pages (url, title, content, visited_at, profile_id)
+                VALUES ('https://public.example', 'Public', 'Grace Hopper visited Boston.', '0', 2);
+             INSERT INTO scraping_jobs (id, profile_id, name, base_url, created_at)
+                VALUES (1, 1, 'Diary', 'https://diary.example', '0');
+             INSERT INTO scraped_pages (job_id, url, title, content, scraped_at)
+                VALUES (1, 'https://diary.example/more', 'More', 'Charles Babbage visited London.', '0');"
+        ).unwrap();
+        let locks = crate::profile_lock::ProfileLockManager::new(db.clone());
+        locks.init().unwrap();
+        locks.enable(1, "correct horse", None).unwrap();
+        locks.lock(1).unwrap();
+
+        let index = EntityIndex::new(db);
+        assert_eq!(index.extract_pending(1, 50, None).await.unwrap().pages_processed, 0);
+        assert!(index.list_entities(1, None, None, 50, 0).unwrap().is_empty());
+        assert!(index.list_entities(2, None, None, 50, 0).unwrap().is_empty(), "only the profile asked for is read");
+
+        assert_eq!(index.extract_pending(2, 50, None).await.unwrap().pages_processed, 1);
+        assert!(!index.list_entities(2, None, None, 50, 0).unwrap().is_empty());
+        assert!(index.list_entities(1, None, None, 50, 0).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+// Error type shared by every Tauri command
+// Reaches the frontend as `{ kind, message, details }`, so the UI can react to the kind
+// (a wrong password, a locked database, Ollama being down) instead of parsing messages.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+use crate::ai::AiError;
+
+pub type AppResult<T> = std::result::Result<T, AppError>;
+
+#[derive(Debug)]
+pub enum AppError {
+    /// The requested record doesn't exist
+    NotFound(String),
+    /// The caller sent something unusable (bad JSON, out-of-range value, disallowed action)
+    Validation(String),
+    /// Wrong password, or data that fails to decrypt
+    Crypto(String),
+    /// The profile is encrypted and locked until its passphrase is entered
+    ProfileLocked(i64),
+    /// A master-password vault (passwords, authenticator) is locked until its master
+    /// password is entered
+    VaultLocked { vault: &'static str, profile_id: i64 },
+    Io(std::io::Error),
+    /// A remote server couldn't be reached or answered with an error
+    Network(String),
+    Database(rusqlite::Error),
+    /// Talking to the local model failed
+    Ai(AiError),
+    /// The operation was stopped by the user
+    Cancelled,
+    /// A bug or a background thread that panicked
+    Internal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    NotFound,
+    Validation,
+    Crypto,
+    Locked,
+    Io,
+    Network,
+    Database,
+    Cancelled,
+    Internal,
+}
+
+impl AppError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            AppError::NotFound(_) => ErrorKind::NotFound,
+            AppError::Validation(_) => ErrorKind::Validation,
+            AppError::Crypto(_) => ErrorKind::Crypto,
+            AppError::ProfileLocked(_) | AppError::VaultLocked { .. } => ErrorKind::Locked,
+            AppError::Io(e) if e.kind() == std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            AppError::Io(_) => ErrorKind::Io,
+            AppError::Network(_) => ErrorKind::Network,
+            AppError::Database(_) => ErrorKind::Database,
+            AppError::Ai(AiError::EmptyInput) => ErrorKind::Validation,
+            AppError::Ai(AiError::Cancelled) | AppError::Cancelled => ErrorKind::Cancelled,
+            AppError::Ai(_) => ErrorKind::Network,
+            AppError::Internal(_) => ErrorKind::Internal,
+        }
+    }
+
+    /// Machine-readable extras, e.g. the SQLite error code or the HTTP status
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::Database(e) => e.sqlite_error_code().map(|code| {
+                serde_json::json!({ "code": format!("{:?}", code) })
+            }),
+            AppError::ProfileLocked(profile_id) => Some(serde_json::json!({ "profile_id": profile_id })),
+            AppError::VaultLocked { vault, profile_id } => {
+                Some(serde_json::json!({ "vault": vault, "profile_id": profile_id }))
+            }
+            AppError::Io(e) => Some(serde_json::json!({ "code": format!("{:?}", e.kind()) })),
+            AppError::Ai(AiError::Api { status, .. }) => Some(serde_json::json!({ "status": status })),
+            AppError::Ai(AiError::Timeout) => Some(serde_json::json!({ "timeout": true })),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::NotFound(what) => write!(f, "{} not found", what),
+            AppError::Validation(message) => write!(f, "{}", message),
+            AppError::Crypto(message) => write!(f, "{}", message),
+            AppError::ProfileLocked(_) => write!(f, "This profile is locked"),
+            AppError::VaultLocked { vault, .. } => write!(f, "The {} vault is locked", vault),
+            AppError::Io(e) => write!(f, "file error: {}", e),
+            AppError::Network(message) => write!(f, "network error: {}", message),
+            AppError::Database(e) => write!(f, "database error: {}", e),
+            AppError::Ai(e) => write!(f, "{}", e),
+            AppError::Cancelled => write!(f, "Operation was cancelled"),
+            AppError::Internal(message) => write!(f, "internal error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut error = serializer.serialize_struct("AppError", 3)?;
+        error.serialize_field("kind", &self.kind())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.serialize_field("details", &self.details())?;
+        error.end()
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => AppError::NotFound("Record".to_string()),
+            e => AppError::Database(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        AppError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(e: serde_json::Error) -> Self {
+        AppError::Validation(format!("Invalid JSON: {}", e))
+    }
+}
+
+impl From<AiError> for AppError {
+    fn from(e: AiError) -> Self {
+        AppError::Ai(e)
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        AppError::Network(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serializes_kind_message_and_details() {
+        let busy = AppError::from(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            Some("database is locked".to_string()),
+        ));
+        let value = serde_json::to_value(&busy).unwrap();
+        assert_eq!(value["kind"], "database");
+        assert_eq!(value["details"]["code"], "DatabaseBusy");
+        assert!(value["message"].as_str().unwrap().contains("database is locked"));
+
+        let wrong_password = serde_json::to_value(AppError::Crypto("Invalid password".to_string())).unwrap();
+        assert_eq!(wrong_password, serde_json::json!({
+            "kind": "crypto",
+            "message": "Invalid password",
+            "details": null,
+        }));
+    }
+
+    #[test]
+    fn test_conversions_pick_the_right_kind() {
+        assert_eq!(AppError::from(rusqlite::Error::QueryReturnedNoRows).kind(), ErrorKind::NotFound);
+        assert_eq!(AppError::from(serde_json::from_str::<i64>("{").unwrap_err()).kind(), ErrorKind::Validation);
+        assert_eq!(
+            AppError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "gone")).kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(AppError::from(AiError::Timeout).kind(), ErrorKind::Network);
+        assert_eq!(AppError::from(AiError::Cancelled).kind(), ErrorKind::Cancelled);
+    }
+}
@@ -0,0 +1,352 @@
+// Crawl frontier for the web scraper
+// URLs are visited breadth-first: shallower pages first, then in the order they were
+// found. Each URL is canonicalized and deduplicated before it's queued, and the queue is
+// kept in scraping_frontier so a crawl cut short by quitting the app picks up where it
+// stopped on the next run.
+
+use rusqlite::{Result, params};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::db::DbPool;
+
+/// Query parameters that only track where a click came from
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "_ga", "_gl", "_hsenc", "_hsmi", "mkt_tok", "oly_anon_id", "oly_enc_id",
+];
+
+/// Extensions of files that aren't web pages, so aren't worth fetching
+const SKIPPED_EXTENSIONS: &[&str] = &[
+    "7z", "avi", "bmp", "css", "csv", "dmg", "doc", "docx", "eot", "exe", "gif", "gz", "ico",
+    "iso", "jpeg", "jpg", "js", "json", "m4a", "mov", "mp3", "mp4", "ogg", "otf", "pdf", "png",
+    "ppt", "pptx", "rar", "rss", "svg", "tar", "tgz", "ttf", "wav", "webm", "webp", "woff",
+    "woff2", "xls", "xlsx", "xml", "zip",
+];
+
+/// Which links a crawl follows, relative to the job's base URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrawlScope {
+    /// Only the base URL's host
+    #[default]
+    Host,
+    /// The base URL's domain (without a leading "www.") and its subdomains
+    Domain,
+    /// Anywhere
+    Any,
+}
+
+impl CrawlScope {
+    pub fn allows(self, base: &url::Url, url: &url::Url) -> bool {
+        let (Some(base_host), Some(host)) = (base.host_str(), url.host_str()) else {
+            return false;
+        };
+        match self {
+            CrawlScope::Host => host == base_host,
+            CrawlScope::Domain => {
+                let domain = base_host.strip_prefix("www.").unwrap_or(base_host);
+                host == domain || host.strip_suffix(domain).is_some_and(|sub| sub.ends_with('.'))
+            }
+            CrawlScope::Any => true,
+        }
+    }
+}
+
+/// The URL to fetch for a link: http(s) only, without its fragment or tracking
+/// parameters. None for other schemes and for files that aren't pages.
+pub fn canonicalize(mut url: url::Url) -> Option<url::Url> {
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return None;
+    }
+    let extension = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    if extension.is_some_and(|extension| SKIPPED_EXTENSIONS.contains(&extension.as_str())) {
+        return None;
+    }
+
+    url.set_fragment(None);
+    if url.query().is_some() {
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(name, _)| !is_tracking_param(name))
+            .map(|(name, value)| (name.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+    Some(url)
+}
+
+fn is_tracking_param(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with("utm_") || TRACKING_PARAMS.contains(&name.as_str())
+}
+
+/// What two URLs for the same page have in common: the canonical URL with its query
+/// sorted, percent-escapes in upper case, and no trailing slash
+pub fn url_key(url: &url::Url) -> String {
+    let mut path = uppercase_escapes(url.path());
+    if path.len() > 1 && path.ends_with('/') {
+        path.pop();
+    }
+    let mut key = format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default());
+    if let Some(port) = url.port() {
+        key.push_str(&format!(":{}", port));
+    }
+    key.push_str(&path);
+
+    let mut pairs: Vec<(String, String)> = url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect();
+    if !pairs.is_empty() {
+        pairs.sort();
+        let query = url::form_urlencoded::Serializer::new(String::new()).extend_pairs(pairs).finish();
+        key.push('?');
+        key.push_str(&query);
+    }
+    key
+}
+
+fn uppercase_escapes(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if c == '%' {
+            out.extend(chars.by_ref().take(2).map(|c| c.to_ascii_uppercase()));
+        }
+    }
+    out
+}
+
+/// What came of visiting a URL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    Scraped,
+    Failed,
+    /// Disallowed by robots.txt or not an HTML page
+    Skipped,
+    /// Same content as a page already scraped under another URL
+    Duplicate,
+}
+
+impl Visit {
+    fn as_str(self) -> &'static str {
+        match self {
+            Visit::Scraped => "scraped",
+            Visit::Failed => "failed",
+            Visit::Skipped => "skipped",
+            Visit::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// A URL taken off the frontier
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub url: url::Url,
+    pub key: String,
+    pub depth: i32,
+}
+
+pub struct Frontier {
+    db: DbPool,
+    job_id: i64,
+    /// Queued URLs by (depth, order found), smallest first
+    queue: BinaryHeap<Reverse<(i32, i64, String, String)>>,
+    /// Keys of every URL queued or visited in this crawl
+    seen: HashSet<String>,
+    next_seq: i64,
+    scraped: i32,
+}
+
+impl Frontier {
+    /// The job's unfinished crawl, or an empty frontier to start one
+    pub fn load(db: &DbPool, job_id: i64) -> Result<Self> {
+        let mut frontier = Frontier {
+            db: db.clone(),
+            job_id,
+            queue: BinaryHeap::new(),
+            seen: HashSet::new(),
+            next_seq: 0,
+            scraped: 0,
+        };
+
+        let conn = db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT url_key, url, depth, seq, state FROM scraping_frontier WHERE job_id = ?1"
+        )?;
+        let rows = stmt.query_map(params![job_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })?;
+        for row in rows {
+            let (key, url, depth, seq, state) = row?;
+            frontier.next_seq = frontier.next_seq.max(seq + 1);
+            match state.as_str() {
+                "queued" => frontier.queue.push(Reverse((depth, seq, url, key.clone()))),
+                "scraped" => frontier.scraped += 1,
+                _ => {}
+            }
+            frontier.seen.insert(key);
+        }
+        Ok(frontier)
+    }
+
+    /// Whether this is a fresh crawl, with nothing queued or visited yet
+    pub fn is_new(&self) -> bool {
+        self.seen.is_empty()
+    }
+
+    /// Pages scraped so far in this crawl, including by earlier runs it resumed from
+    pub fn scraped(&self) -> i32 {
+        self.scraped
+    }
+
+    /// Queue `url` to visit unless the crawl has already seen it; true if it was new
+    pub fn push(&mut self, url: &url::Url, depth: i32) -> Result<bool> {
+        let key = url_key(url);
+        if self.seen.contains(&key) {
+            return Ok(false);
+        }
+
+        let seq = self.next_seq;
+        self.db.get()?.execute(
+            "INSERT OR IGNORE INTO scraping_frontier (job_id, url_key, url, depth, seq, state)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'queued')",
+            params![self.job_id, key, url.as_str(), depth, seq],
+        )?;
+        self.next_seq += 1;
+        self.queue.push(Reverse((depth, seq, url.to_string(), key.clone())));
+        self.seen.insert(key);
+        Ok(true)
+    }
+
+    /// The next URL to visit: the shallowest, then the first found
+    pub fn pop(&mut self) -> Option<Entry> {
+        while let Some(Reverse((depth, _, url, key))) = self.queue.pop() {
+            if let Ok(url) = url::Url::parse(&url) {
+                return Some(Entry { url, key, depth });
+            }
+        }
+        None
+    }
+
+    /// Record what came of visiting a popped URL, so a resumed crawl doesn't repeat it
+    pub fn finish(&mut self, entry: &Entry, visit: Visit) -> Result<()> {
+        if visit == Visit::Scraped {
+            self.scraped += 1;
+        }
+        self.db.get()?.execute(
+            "UPDATE scraping_frontier SET state = ?1 WHERE job_id = ?2 AND url_key = ?3",
+            params![visit.as_str(), self.job_id, entry.key],
+        )?;
+        Ok(())
+    }
+
+    /// Forget the crawl so the next run starts from the base URL again
+    pub fn clear(&mut self) -> Result<()> {
+        self.db.get()?.execute("DELETE FROM scraping_frontier WHERE job_id = ?1", params![self.job_id])?;
+        self.queue.clear();
+        self.seen.clear();
+        self.next_seq = 0;
+        self.scraped = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
+
+    fn url(url: &str) -> url::Url {
+        url::Url::parse(url).unwrap()
+    }
+
+    fn canonical(link: &str) -> Option<String> {
+        canonicalize(url(link)).map(String::from)
+    }
+
+    #[test]
+    fn test_canonical_urls_and_keys() {
+        assert_eq!(
+            canonical("HTTPS://Docs.Example.com:443/a/./b/../guide?utm_source=x&page=2&fbclid=y#install").as_deref(),
+            Some("https://docs.example.com/a/guide?page=2")
+        );
+        assert_eq!(canonical("https://example.com/?utm_medium=email").as_deref(), Some("https://example.com/"));
+        assert_eq!(canonical("mailto:someone@example.com"), None);
+        assert_eq!(canonical("ftp://example.com/file"), None);
+        assert_eq!(canonical("https://example.com/manual.PDF"), None);
+        assert_eq!(canonical("https://example.com/v1.2/notes").as_deref(), Some("https://example.com/v1.2/notes"));
+
+        let key = |link: &str| url_key(&canonicalize(url(link)).unwrap());
+        assert_eq!(key("https://example.com/docs/"), key("https://example.com/docs"));
+        assert_eq!(key("https://example.com/?b=2&a=1"), key("https://example.com/?a=1&b=2#top"));
+        assert_eq!(key("https://example.com/caf%c3%a9"), key("https://example.com/caf%C3%A9"));
+        assert_eq!(key("http://example.com:80/"), "http://example.com/");
+        assert_ne!(key("https://example.com/"), key("http://example.com/"));
+        assert_ne!(key("https://example.com/?page=1"), key("https://example.com/?page=2"));
+    }
+
+    #[test]
+    fn test_scopes() {
+        let base = url("https://www.example.com/docs/");
+        for (link, host, domain) in [
+            ("https://www.example.com/blog", true, true),
+            ("https://example.com/", false, true),
+            ("https://api.example.com/", false, true),
+            ("https://notexample.com/", false, false),
+            ("https://example.com.evil.net/", false, false),
+        ] {
+            assert_eq!(CrawlScope::Host.allows(&base, &url(link)), host, "{} in host scope", link);
+            assert_eq!(CrawlScope::Domain.allows(&base, &url(link)), domain, "{} in domain scope", link);
+            assert!(CrawlScope::Any.allows(&base, &url(link)));
+        }
+    }
+
+    #[test]
+    fn test_breadth_first_and_resumable() {
+        let db = test_database("frontier");
+        db.get().unwrap().execute(
+            "INSERT INTO scraping_jobs (id, profile_id, name, base_url, created_at) VALUES (1, 1, 'Docs', 'https://example.com', '0')",
+            [],
+        ).unwrap();
+
+        let mut frontier = Frontier::load(&db, 1).unwrap();
+        assert!(frontier.is_new());
+        assert!(frontier.push(&url("https://example.com/"), 0).unwrap());
+        let root = frontier.pop().unwrap();
+        for link in ["https://example.com/a/deep", "https://example.com/b", "https://example.com/c"] {
+            frontier.push(&url(link), if link.ends_with("deep") { 2 } else { 1 }).unwrap();
+        }
+        assert!(!frontier.push(&url("https://example.com/b/"), 1).unwrap(), "same page as /b");
+        assert!(!frontier.push(&url("https://example.com"), 1).unwrap(), "already visited");
+        frontier.finish(&root, Visit::Scraped).unwrap();
+        let b = frontier.pop().unwrap();
+        assert_eq!((b.url.as_str(), b.depth), ("https://example.com/b", 1));
+        frontier.finish(&b, Visit::Failed).unwrap();
+
+        // The app quits here; the next run carries on with /c, then /a/deep
+        let mut resumed = Frontier::load(&db, 1).unwrap();
+        assert!(!resumed.is_new());
+        assert_eq!(resumed.scraped(), 1);
+        assert!(!resumed.push(&url("https://example.com/b"), 1).unwrap());
+        let order: Vec<String> = std::iter::from_fn(|| resumed.pop()).map(|entry| entry.url.to_string()).collect();
+        assert_eq!(order, ["https://example.com/c", "https://example.com/a/deep"]);
+
+        resumed.clear().unwrap();
+        assert!(Frontier::load(&db, 1).unwrap().is_new());
+    }
+}
@@ -0,0 +1,506 @@
+// Background embedding indexer for EarthMemory
+// Backfills embeddings for pages that were saved without one
+// Resumable: finished chunks are stored, so a restart picks up where it stopped
+// Privacy-aware: does nothing while incognito mode is active
+
+use rusqlite::{Connection, Result, params};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::ai::{AiError, OllamaClient};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::knowledge_graph::{bytes_to_embedding, embedding_to_bytes, KnowledgeGraph};
+use crate::privacy::PrivacyManager;
+
+/// Event emitted to the frontend while the indexer works
+pub const INDEXER_PROGRESS_EVENT: &str = "embedding-indexer-progress";
+
+/// Nearest pages linked as semantically similar after a page is embedded
+const SIMILAR_PAGE_EDGES: usize = 5;
+const SIMILAR_PAGE_THRESHOLD: f32 = 0.75;
+
+/// Tables whose rows carry an `embedding` blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EmbeddingSource {
+    #[serde(rename = "pages")]
+    Page,
+}
+
+impl EmbeddingSource {
+    pub const ALL: [EmbeddingSource; 1] = [EmbeddingSource::Page];
+
+    pub fn table(&self) -> &'static str {
+        match self {
+            EmbeddingSource::Page => "pages",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexerConfig {
+    /// Pages fetched from the database per pass
+    pub batch_size: i64,
+    /// Minimum delay between two embedding requests (rate limit)
+    pub request_interval_ms: u64,
+    /// How long to sleep when there is nothing to do, Ollama is down or incognito is on
+    pub idle_interval_secs: u64,
+    /// Approximate chunk size in words
+    pub chunk_words: usize,
+    /// Words shared between consecutive chunks so sentences aren't cut blindly
+    pub chunk_overlap: usize,
+    /// Upper bound on chunks per page, very long pages are truncated
+    pub max_chunks_per_page: usize,
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        IndexerConfig {
+            batch_size: 10,
+            request_interval_ms: 250,
+            idle_interval_secs: 30,
+            chunk_words: 200,
+            chunk_overlap: 20,
+            max_chunks_per_page: 32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexerProgress {
+    /// "indexing", "idle", "paused" (incognito) or "unavailable" (Ollama down)
+    pub state: String,
+    pub pending: i64,
+    pub indexed: i64,
+    pub failed: i64,
+    pub current_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingIndexStatus {
+    pub total_pages: i64,
+    pub embedded_pages: i64,
+    pub pending_pages: i64,
+    pub stored_chunks: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PendingPage {
+    pub source: EmbeddingSource,
+    pub id: i64,
+    pub profile_id: Option<i64>,
+    pub url: String,
+    pub title: String,
+    pub content: String,
+}
+
+#[derive(Clone)]
+pub struct EmbeddingIndexer {
+    db: DbPool,
+    config: IndexerConfig,
+}
+
+impl EmbeddingIndexer {
+    pub fn new(db: DbPool) -> Self {
+        Self::with_config(db, IndexerConfig::default())
+    }
+
+    pub fn with_config(db: DbPool, config: IndexerConfig) -> Self {
+        EmbeddingIndexer { db, config }
+    }
+
+    /// Pages that still need an embedding, oldest first
+    pub fn pending_pages(&self, limit: i64) -> Result<Vec<PendingPage>> {
+        let conn = self.db.get()?;
+        let mut pages = Vec::new();
+
+        for source in EmbeddingSource::ALL {
+            if !table_exists(&conn, source.table())? {
+                continue;
+            }
+
+            let sql = format!(
+                "SELECT id, profile_id, url, title, content FROM {}
+                 WHERE embedding IS NULL AND COALESCE(content, '') != ''
+                   -- A locked profile's content is ciphertext
+                   AND profile_id NOT IN (SELECT profile_id FROM profile_encryption WHERE locked = 1)
+                 ORDER BY id ASC
+                 LIMIT ?1",
+                source.table()
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(params![limit - pages.len() as i64], |row| {
+                Ok(PendingPage {
+                    source,
+                    id: row.get(0)?,
+                    profile_id: row.get(1)?,
+                    url: row.get(2)?,
+                    title: row.get(3)?,
+                    content: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                })
+            })?;
+
+            for page in rows {
+                pages.push(page?);
+            }
+            if pages.len() as i64 >= limit {
+                break;
+            }
+        }
+
+        Ok(pages)
+    }
+
+    /// Counts across the page tables, for the settings UI
+    pub fn get_status(&self) -> Result<EmbeddingIndexStatus> {
+        let conn = self.db.get()?;
+        let mut status = EmbeddingIndexStatus {
+            total_pages: 0,
+            embedded_pages: 0,
+            pending_pages: 0,
+            stored_chunks: 0,
+        };
+
+        for source in EmbeddingSource::ALL {
+            if !table_exists(&conn, source.table())? {
+                continue;
+            }
+            let (total, embedded, pending): (i64, i64, i64) = conn.query_row(
+                &format!(
+                    "SELECT COUNT(*),
+                            COALESCE(SUM(embedding IS NOT NULL), 0),
+                            COALESCE(SUM(embedding IS NULL AND COALESCE(content, '') != ''), 0)
+                     FROM {}",
+                    source.table()
+                ),
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )?;
+            status.total_pages += total;
+            status.embedded_pages += embedded;
+            status.pending_pages += pending;
+        }
+
+        status.stored_chunks = conn.query_row("SELECT COUNT(*) FROM embedding_chunks", [], |row| row.get(0))?;
+        Ok(status)
+    }
+
+    /// Chunks already embedded for a page, as (content, embedding) by chunk index
+    fn stored_chunks(&self, page: &PendingPage) -> Result<Vec<(usize, String, Vec<f32>)>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT chunk_index, content, embedding FROM embedding_chunks
+             WHERE source = ?1 AND source_id = ?2 ORDER BY chunk_index"
+        )?;
+
+        let chunks = stmt.query_map(params![page.source.table(), page.id], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get(1)?,
+                bytes_to_embedding(&row.get::<_, Vec<u8>>(2)?),
+            ))
+        })?;
+
+        chunks.collect()
+    }
+
+    fn save_chunk(&self, page: &PendingPage, index: usize, content: &str, embedding: &[f32]) -> Result<()> {
+        let conn = self.db.get()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO embedding_chunks (source, source_id, profile_id, chunk_index, content, embedding, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                page.source.table(),
+                page.id,
+                page.profile_id,
+                index as i64,
+                content,
+                embedding_to_bytes(embedding),
+                chrono_now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Store the page-level embedding and drop chunks left over from longer, older content
+    fn finish_page(&self, page: &PendingPage, chunk_count: usize, embedding: &[f32]) -> Result<()> {
+        let conn = self.db.get()?;
+        conn.execute(
+            "DELETE FROM embedding_chunks WHERE source = ?1 AND source_id = ?2 AND chunk_index >= ?3",
+            params![page.source.table(), page.id, chunk_count as i64],
+        )?;
+        conn.execute(
+            &format!("UPDATE {} SET embedding = ?1 WHERE id = ?2", page.source.table()),
+            params![embedding_to_bytes(embedding), page.id],
+        )?;
+        Ok(())
+    }
+
+    /// Remove chunks whose page no longer exists
+    pub fn prune_orphaned_chunks(&self) -> Result<usize> {
+        let conn = self.db.get()?;
+        let mut removed = 0;
+        for source in EmbeddingSource::ALL {
+            if !table_exists(&conn, source.table())? {
+                continue;
+            }
+            removed += conn.execute(
+                &format!(
+                    "DELETE FROM embedding_chunks
+                     WHERE source = ?1 AND source_id NOT IN (SELECT id FROM {})",
+                    source.table()
+                ),
+                params![source.table()],
+            )?;
+        }
+        Ok(removed)
+    }
+
+    /// Embed one page chunk by chunk, reusing chunks stored by an earlier (interrupted) run.
+    /// Returns Ok(false) if incognito was switched on midway; the page is left for later.
+    pub async fn index_page(&self, client: &OllamaClient, page: &PendingPage) -> AppResult<bool> {
+        let text = format!("{}\n{}", page.title, page.content);
+        let chunks = chunk_text(&text, self.config.chunk_words, self.config.chunk_overlap, self.config.max_chunks_per_page);
+        if chunks.is_empty() {
+            return Ok(true);
+        }
+
+        let stored = self.stored_chunks(page)?;
+        let mut embeddings: Vec<Vec<f32>> = Vec::with_capacity(chunks.len());
+
+        for (index, chunk) in chunks.iter().enumerate() {
+            // Same text as last time: no need to ask the model again
+            if let Some((_, _, embedding)) = stored.iter().find(|(i, content, _)| *i == index && content == chunk) {
+                embeddings.push(embedding.clone());
+                continue;
+            }
+
+            if PrivacyManager::is_incognito() {
+                return Ok(false);
+            }
+
+            let embedding = client.generate_embedding(chunk).await?;
+            self.save_chunk(page, index, chunk, &embedding)?;
+            embeddings.push(embedding);
+
+            tokio::time::sleep(Duration::from_millis(self.config.request_interval_ms)).await;
+        }
+
+        let page_embedding = mean_pool(&embeddings).ok_or_else(|| {
+            AppError::Ai(AiError::InvalidResponse("chunk embeddings have different dimensions".to_string()))
+        })?;
+        self.finish_page(page, chunks.len(), &page_embedding)?;
+
+        // Pages get "semantically similar" edges once they have a vector
+        if let (EmbeddingSource::Page, Some(profile_id)) = (page.source, page.profile_id) {
+            KnowledgeGraph::new(self.db.clone()).link_similar_pages(
+                page.id,
+                profile_id,
+                SIMILAR_PAGE_EDGES,
+                SIMILAR_PAGE_THRESHOLD,
+            )?;
+        }
+        Ok(true)
+    }
+
+    /// Run forever, backfilling embeddings in small rate-limited batches.
+    /// `client` is asked for the current Ollama client on every pass so config changes apply.
+    pub async fn run<C, P>(self, client: C, on_progress: P)
+    where
+        C: Fn() -> Option<OllamaClient>,
+        P: Fn(IndexerProgress),
+    {
+        let idle = Duration::from_secs(self.config.idle_interval_secs.max(1));
+        let mut indexed = 0i64;
+        let mut failed = 0i64;
+        // Pages that failed in this session; retried after a restart
+        let mut skipped: Vec<(EmbeddingSource, i64)> = Vec::new();
+
+        if let Err(e) = self.prune_orphaned_chunks() {
+            eprintln!("Embedding indexer: failed to prune chunks: {}", e);
+        }
+
+        loop {
+            if PrivacyManager::is_incognito() {
+                on_progress(self.progress("paused", indexed, failed, None));
+                tokio::time::sleep(idle).await;
+                continue;
+            }
+
+            let client = match client() {
+                Some(c) if c.is_running().await => c,
+                _ => {
+                    on_progress(self.progress("unavailable", indexed, failed, None));
+                    tokio::time::sleep(idle).await;
+                    continue;
+                }
+            };
+
+            let batch = match self.pending_pages(self.config.batch_size + skipped.len() as i64) {
+                Ok(pages) => pages
+                    .into_iter()
+                    .filter(|p| !skipped.contains(&(p.source, p.id)))
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    eprintln!("Embedding indexer: failed to load pending pages: {}", e);
+                    tokio::time::sleep(idle).await;
+                    continue;
+                }
+            };
+
+            if batch.is_empty() {
+                on_progress(self.progress("idle", indexed, failed, None));
+                tokio::time::sleep(idle).await;
+                continue;
+            }
+
+            for page in batch {
+                on_progress(self.progress("indexing", indexed, failed, Some(page.url.clone())));
+
+                match self.index_page(&client, &page).await {
+                    Ok(true) => indexed += 1,
+                    Ok(false) => break, // incognito switched on
+                    Err(AppError::Ai(AiError::Unavailable(_))) | Err(AppError::Ai(AiError::Timeout)) => {
+                        // Ollama went away; back off and retry the same page later
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("Embedding indexer: failed to index {}: {}", page.url, e);
+                        failed += 1;
+                        skipped.push((page.source, page.id));
+                    }
+                }
+            }
+        }
+    }
+
+    fn progress(&self, state: &str, indexed: i64, failed: i64, current_url: Option<String>) -> IndexerProgress {
+        IndexerProgress {
+            state: state.to_string(),
+            pending: self.get_status().map(|s| s.pending_pages).unwrap_or(0),
+            indexed,
+            failed,
+            current_url,
+        }
+    }
+}
+
+// ==================== Helper Functions ====================
+
+/// Split text into overlapping windows of roughly `chunk_words` words
+pub fn chunk_text(text: &str, chunk_words: usize, overlap: usize, max_chunks: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+
+    let size = chunk_words.max(1);
+    let step = size.saturating_sub(overlap).max(1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < words.len() && chunks.len() < max_chunks {
+        let end = (start + size).min(words.len());
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+        start += step;
+    }
+
+    chunks
+}
+
+/// Average the chunk vectors and L2-normalize the result
+fn mean_pool(embeddings: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let dims = embeddings.first()?.len();
+    if dims == 0 || embeddings.iter().any(|e| e.len() != dims) {
+        return None;
+    }
+
+    let mut pooled = vec![0.0f32; dims];
+    for embedding in embeddings {
+        for (p, v) in pooled.iter_mut().zip(embedding) {
+            *p += v;
+        }
+    }
+
+    let norm = pooled.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        pooled.iter_mut().for_each(|v| *v /= norm);
+    }
+    Some(pooled)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text_overlaps() {
+        let text = (0..10).map(|i| i.to_string()).collect::<Vec<_>>().join(" ");
+        let chunks = chunk_text(&text, 4, 1, 10);
+        assert_eq!(chunks, vec!["0 1 2 3", "3 4 5 6", "6 7 8 9"]);
+
+        assert_eq!(chunk_text(&text, 4, 1, 2).len(), 2);
+        assert!(chunk_text("   ", 4, 1, 10).is_empty());
+    }
+
+    #[test]
+    fn test_mean_pool_normalizes() {
+        let pooled = mean_pool(&[vec![1.0, 0.0], vec![0.0, 1.0]]).unwrap();
+        assert!((pooled[0] - pooled[1]).abs() < 1e-6);
+        assert!((pooled.iter().map(|v| v * v).sum::<f32>() - 1.0).abs() < 1e-6);
+        assert!(mean_pool(&[vec![1.0], vec![1.0, 2.0]]).is_none());
+    }
+
+    #[test]
+    fn test_pending_pages_and_status() {
+        let db = crate::migrations::test_database("indexer");
+        let indexer = EmbeddingIndexer::new(db.clone());
+
+        let conn = db.get().unwrap();
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES ('https://a', 'A', 'alpha', '1', 1)",
+            [],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES ('https://b', 'B', '', '1', 1)",
+            [],
+        ).unwrap();
+
+        let pending = indexer.pending_pages(10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].url, "https://a");
+        assert_eq!(pending[0].source, EmbeddingSource::Page);
+
+        indexer.save_chunk(&pending[0], 0, "A alpha", &[1.0, 0.0]).unwrap();
+        indexer.finish_page(&pending[0], 1, &[1.0, 0.0]).unwrap();
+
+        let status = indexer.get_status().unwrap();
+        assert_eq!(status.embedded_pages, 1);
+        assert_eq!(status.pending_pages, 0);
+        assert_eq!(status.stored_chunks, 1);
+        assert!(indexer.pending_pages(10).unwrap().is_empty());
+
+        conn.execute("DELETE FROM pages WHERE url = 'https://a'", []).unwrap();
+        assert_eq!(indexer.prune_orphaned_chunks().unwrap(), 1);
+    }
+}
@@ -0,0 +1,216 @@
+// Master-password keys for the password vault and the authenticator
+// Each profile's store has a random data key, kept in `table` wrapped by a key derived
+// from its master password (see crypto.rs), the same scheme as encrypted profiles, so
+// changing the master password only rewraps the key. Unlocking keeps the data key in
+// memory until the store is locked, the app exits, or it has been idle for AUTO_LOCK_AFTER.
+
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::crypto::{self, Key, KEY_LEN};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+
+const MIN_MASTER_LEN: usize = 8;
+pub const AUTO_LOCK_AFTER: Duration = Duration::from_secs(15 * 60);
+
+struct Session {
+    key: Key,
+    last_activity: Instant,
+}
+
+#[derive(Clone)]
+pub struct MasterKeyStore {
+    db: DbPool,
+    /// Table with profile_id, key_params, wrapped_key, created_at and updated_at columns
+    table: &'static str,
+    /// Name used in messages, e.g. "password" for "The password vault is locked"
+    vault: &'static str,
+    /// Unlocked stores by profile id
+    sessions: Arc<Mutex<HashMap<i64, Session>>>,
+}
+
+impl MasterKeyStore {
+    pub fn new(db: DbPool, table: &'static str, vault: &'static str) -> Self {
+        MasterKeyStore {
+            db,
+            table,
+            vault,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn has_master(&self, profile_id: i64) -> AppResult<bool> {
+        let conn = self.db.get()?;
+        Ok(conn
+            .query_row(
+                &format!("SELECT 1 FROM {} WHERE profile_id = ?1", self.table),
+                params![profile_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some())
+    }
+
+    pub fn is_unlocked(&self, profile_id: i64) -> bool {
+        self.sessions().contains_key(&profile_id)
+    }
+
+    /// Set the profile's master password, or change it. Changing it needs the current
+    /// one; data stays as it is since only the data key is rewrapped. Unlocked afterwards.
+    pub fn set_master(&self, profile_id: i64, password: &str, current_password: Option<&str>) -> AppResult<()> {
+        if password.chars().count() < MIN_MASTER_LEN {
+            return Err(AppError::Validation(format!(
+                "The master password must be at least {} characters",
+                MIN_MASTER_LEN
+            )));
+        }
+
+        let conn = self.db.get()?;
+        let now = chrono_now();
+        let key_params = crypto::new_key_params();
+        let key = if self.has_master(profile_id)? {
+            let current = current_password.ok_or_else(|| {
+                AppError::Validation("The current master password is required to change it".to_string())
+            })?;
+            let key = self.unwrap_key(&conn, profile_id, current)?
+                .ok_or_else(|| AppError::Crypto("Wrong master password".to_string()))?;
+            let wrapped_key = crypto::encrypt(&crypto::derive_key(password, &key_params)?, &key)?;
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET key_params = ?1, wrapped_key = ?2, updated_at = ?3 WHERE profile_id = ?4",
+                    self.table
+                ),
+                params![key_params, wrapped_key, now, profile_id],
+            )?;
+            key
+        } else {
+            let exists = conn
+                .query_row("SELECT 1 FROM profiles WHERE id = ?1", params![profile_id], |_| Ok(()))
+                .optional()?
+                .is_some();
+            if !exists {
+                return Err(AppError::NotFound(format!("Profile {}", profile_id)));
+            }
+
+            let key = crypto::random_key();
+            let wrapped_key = crypto::encrypt(&crypto::derive_key(password, &key_params)?, &key)?;
+            conn.execute(
+                &format!(
+                    "INSERT INTO {} (profile_id, key_params, wrapped_key, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?4)",
+                    self.table
+                ),
+                params![profile_id, key_params, wrapped_key, now],
+            )?;
+            key
+        };
+
+        self.sessions().insert(profile_id, Session { key, last_activity: Instant::now() });
+        Ok(())
+    }
+
+    /// Unlock with the master password; false for a wrong password
+    pub fn unlock(&self, profile_id: i64, password: &str) -> AppResult<bool> {
+        let conn = self.db.get()?;
+        match self.unwrap_key(&conn, profile_id, password)? {
+            Some(key) => {
+                self.sessions().insert(profile_id, Session { key, last_activity: Instant::now() });
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    pub fn lock(&self, profile_id: i64) {
+        self.sessions().remove(&profile_id);
+    }
+
+    /// Forget every key, e.g. when the app exits
+    pub fn lock_all(&self) {
+        self.sessions().clear();
+    }
+
+    /// Lock stores unused for longer than AUTO_LOCK_AFTER; returns the profiles locked
+    pub fn lock_idle(&self) -> Vec<i64> {
+        self.lock_idle_at(Instant::now())
+    }
+
+    fn lock_idle_at(&self, now: Instant) -> Vec<i64> {
+        let mut sessions = self.sessions();
+        let idle: Vec<i64> = sessions
+            .iter()
+            .filter(|(_, session)| now.saturating_duration_since(session.last_activity) >= AUTO_LOCK_AFTER)
+            .map(|(profile_id, _)| *profile_id)
+            .collect();
+        for profile_id in &idle {
+            sessions.remove(profile_id);
+        }
+        idle
+    }
+
+    /// The unlocked store's data key; counts as activity for auto-lock
+    pub fn key(&self, profile_id: i64) -> AppResult<Key> {
+        match self.sessions().get_mut(&profile_id) {
+            Some(session) => {
+                session.last_activity = Instant::now();
+                Ok(session.key)
+            }
+            None => Err(AppError::VaultLocked { vault: self.vault, profile_id }),
+        }
+    }
+
+    /// The data key, or None for a wrong master password
+    fn unwrap_key(&self, conn: &Connection, profile_id: i64, password: &str) -> AppResult<Option<Key>> {
+        let (key_params, wrapped_key): (String, String) = conn
+            .query_row(
+                &format!("SELECT key_params, wrapped_key FROM {} WHERE profile_id = ?1", self.table),
+                params![profile_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::Validation(format!("This profile has no {} vault", self.vault)))?;
+
+        let master_key = crypto::derive_key(password, &key_params)?;
+        let Ok(key) = crypto::decrypt(&master_key, &wrapped_key) else {
+            return Ok(None);
+        };
+        let key: Key = key
+            .try_into()
+            .map_err(|_| AppError::Crypto(format!("Stored key is not {} bytes", KEY_LEN)))?;
+        Ok(Some(key))
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<i64, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
+
+    #[test]
+    fn test_idle_stores_lock() {
+        let db = test_database("key_store_idle");
+        let store = MasterKeyStore::new(db, "password_vaults", "password");
+        store.set_master(1, "correct horse", None).unwrap();
+
+        assert!(store.lock_idle_at(Instant::now() + Duration::from_secs(60)).is_empty());
+        assert_eq!(store.lock_idle_at(Instant::now() + AUTO_LOCK_AFTER), vec![1]);
+        assert!(!store.is_unlocked(1));
+        let error = store.key(1).unwrap_err();
+        assert_eq!(error.to_string(), "The password vault is locked");
+    }
+}
@@ -3,9 +3,11 @@
 // Profile-aware: all operations are scoped to the active profile
 // Privacy-aware: respects incognito mode (nothing saved when active)
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 
+use crate::db::DbPool;
 use crate::privacy::PrivacyManager;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,60 +34,146 @@ pub struct SearchResult {
     pub page: Page,
     pub relevance: f64,
     pub snippet: String,
+    /// How `relevance` was computed; only set by hybrid search
+    #[serde(default)]
+    pub score: Option<ScoreBreakdown>,
 }
 
-pub struct KnowledgeGraph {
-    db_path: String,
+/// Kind of relation between two pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeRelation {
+    /// The source page links to the target page
+    Hyperlink,
+    /// Both pages are on the same host
+    SameDomain,
+    /// The page embeddings are close
+    SemanticallySimilar,
+    /// The user connected the pages by hand
+    UserLinked,
 }
 
-impl KnowledgeGraph {
-    pub fn new(db_path: String) -> Self {
-        KnowledgeGraph { db_path }
+impl EdgeRelation {
+    pub const ALL: [EdgeRelation; 4] = [
+        EdgeRelation::Hyperlink,
+        EdgeRelation::SameDomain,
+        EdgeRelation::SemanticallySimilar,
+        EdgeRelation::UserLinked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeRelation::Hyperlink => "hyperlink",
+            EdgeRelation::SameDomain => "same_domain",
+            EdgeRelation::SemanticallySimilar => "semantically_similar",
+            EdgeRelation::UserLinked => "user_linked",
+        }
     }
 
-    pub fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+    fn from_str(value: &str) -> Option<Self> {
+        EdgeRelation::ALL.into_iter().find(|r| r.as_str() == value)
+    }
+}
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS pages (
-                id INTEGER PRIMARY KEY,
-                url TEXT NOT NULL,
-                title TEXT NOT NULL,
-                content TEXT,
-                visited_at TEXT NOT NULL,
-                embedding BLOB,
-                profile_id INTEGER,
-                UNIQUE(url, profile_id)
-            )",
-            [],
-        )?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: i64,
+    pub url: String,
+    pub title: String,
+    pub domain: Option<String>,
+    pub visited_at: String,
+}
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS notes (
-                id INTEGER PRIMARY KEY,
-                page_id INTEGER NOT NULL,
-                content TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub id: i64,
+    pub source_id: i64,
+    pub target_id: i64,
+    pub relation: EdgeRelation,
+    pub weight: f64,
+    pub created_at: String,
+}
 
-        // Create indexes for faster searches
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_pages_url ON pages(url)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_pages_profile ON pages(profile_id)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_pages_visited ON pages(visited_at)",
-            [],
-        )?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNeighbor {
+    pub node: GraphNode,
+    pub relation: EdgeRelation,
+    pub weight: f64,
+    /// True if the edge points from the neighbor to the queried page
+    pub incoming: bool,
+}
 
-        Ok(())
+/// Whole-profile graph for the frontend graph view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Same-domain edges created per added page; big sites would otherwise produce a clique
+const MAX_SAME_DOMAIN_EDGES: i64 = 20;
+/// Outbound links kept per page
+const MAX_OUTBOUND_LINKS: usize = 200;
+
+/// Weights for hybrid search. Every signal is normalized to [0, 1] before weighting,
+/// so the weights read as relative importance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankingWeights {
+    pub title: f64,
+    pub body: f64,
+    pub url: f64,
+    pub recency: f64,
+    pub semantic: f64,
+    /// Age at which the recency signal has decayed to 0.5
+    pub recency_half_life_days: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        RankingWeights {
+            title: 3.0,
+            body: 1.0,
+            url: 1.5,
+            recency: 0.5,
+            semantic: 2.0,
+            recency_half_life_days: 30.0,
+        }
+    }
+}
+
+/// Per-signal scores (before weighting) for one search result
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub title: f64,
+    pub body: f64,
+    pub url: f64,
+    pub recency: f64,
+    /// None when either the query or the page has no embedding
+    pub semantic: Option<f64>,
+    pub total: f64,
+}
+
+impl ScoreBreakdown {
+    fn weighted_total(&self, weights: &RankingWeights) -> f64 {
+        weights.title * self.title
+            + weights.body * self.body
+            + weights.url * self.url
+            + weights.recency * self.recency
+            + self.semantic.map(|s| weights.semantic * s).unwrap_or(0.0)
+    }
+}
+
+/// Lexical candidates considered per query before ranking
+const MAX_CANDIDATES: i64 = 500;
+
+#[derive(Clone)]
+pub struct KnowledgeGraph {
+    db: DbPool,
+}
+
+impl KnowledgeGraph {
+    pub fn new(db: DbPool) -> Self {
+        KnowledgeGraph { db }
     }
 
     /// Add a page to the knowledge graph
@@ -97,22 +185,39 @@ impl KnowledgeGraph {
             return Ok(None);
         }
 
-        let conn = Connection::open(&self.db_path)?;
+        let mut conn = self.db.get()?;
         let now = chrono_now();
+        let tx = conn.transaction()?;
 
-        // Use INSERT OR REPLACE to update if URL already exists for this profile
-        conn.execute(
-            "INSERT OR REPLACE INTO pages (url, title, content, visited_at, profile_id)
-             VALUES (?1, ?2, ?3, ?4, ?5)",
+        // Upsert so a revisit keeps the page id, its notes, edges and memory metadata.
+        // Changed content invalidates the embedding.
+        tx.execute(
+            "INSERT INTO pages (url, title, content, visited_at, indexed_at, profile_id)
+             VALUES (?1, ?2, ?3, ?4, ?4, ?5)
+             ON CONFLICT(url, profile_id) DO UPDATE SET
+                title = excluded.title,
+                content = excluded.content,
+                visited_at = excluded.visited_at,
+                visit_count = pages.visit_count + 1,
+                embedding = CASE WHEN pages.content IS excluded.content THEN pages.embedding ELSE NULL END",
             params![page.url, page.title, page.content, now, profile_id],
         )?;
 
-        Ok(Some(conn.last_insert_rowid()))
+        let page_id: i64 = tx.query_row(
+            "SELECT id FROM pages WHERE url = ?1 AND profile_id = ?2",
+            params![page.url, profile_id],
+            |row| row.get(0),
+        )?;
+
+        link_page(&tx, page_id, &page.url, &page.content, profile_id, &now)?;
+        tx.commit()?;
+
+        Ok(Some(page_id))
     }
 
     /// Get a page by URL for a specific profile
     pub fn get_page_by_url(&self, url: &str, profile_id: i64) -> Result<Option<Page>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, url, title, content, visited_at, embedding, profile_id
              FROM pages WHERE url = ?1 AND profile_id = ?2"
@@ -125,9 +230,9 @@ impl KnowledgeGraph {
                 id: Some(row.get(0)?),
                 url: row.get(1)?,
                 title: row.get(2)?,
-                content: row.get(3)?,
+                content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
                 visited_at: row.get(4)?,
-                embedding: None, // Skip blob for now
+                embedding: row.get::<_, Option<Vec<u8>>>(5)?.map(|b| bytes_to_embedding(&b)),
                 profile_id: row.get(6)?,
             }))
         } else {
@@ -137,49 +242,131 @@ impl KnowledgeGraph {
 
     /// Search pages by text query within a profile
     pub fn search_pages(&self, query: &str, profile_id: i64, limit: i64) -> Result<Vec<SearchResult>> {
-        let conn = Connection::open(&self.db_path)?;
-        let pattern = format!("%{}%", query.to_lowercase());
+        self.search_pages_ranked(query, profile_id, limit, None, &RankingWeights::default())
+    }
 
-        let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, visited_at, profile_id
-             FROM pages
-             WHERE profile_id = ?1
-               AND (LOWER(title) LIKE ?2 OR LOWER(content) LIKE ?2 OR LOWER(url) LIKE ?2)
-             ORDER BY visited_at DESC
-             LIMIT ?3"
-        )?;
+    /// Hybrid search: lexical hits in title/body/URL, recency and (if a query embedding is
+    /// given) vector similarity, combined with `weights`. Each result carries its breakdown.
+    pub fn search_pages_ranked(
+        &self,
+        query: &str,
+        profile_id: i64,
+        limit: i64,
+        query_embedding: Option<&[f32]>,
+        weights: &RankingWeights,
+    ) -> Result<Vec<SearchResult>> {
+        let terms = query_terms(query);
+        if (terms.is_empty() && query_embedding.is_none()) || limit <= 0 {
+            return Ok(Vec::new());
+        }
 
-        let results = stmt.query_map(params![profile_id, pattern, limit], |row| {
-            let content: String = row.get::<_, Option<String>>(3)?.unwrap_or_default();
-            let title: String = row.get(2)?;
+        let conn = self.db.get()?;
+        let mut candidates: Vec<(Page, Option<Vec<f32>>)> = Vec::new();
 
-            // Create a snippet from content or title
-            let snippet = create_snippet(&content, query, 150)
-                .unwrap_or_else(|| title.chars().take(150).collect());
+        if !terms.is_empty() {
+            // Any term anywhere makes a candidate; scoring sorts out the rest
+            let conditions: Vec<String> = (0..terms.len())
+                .map(|i| {
+                    let p = i + 2;
+                    format!("LOWER(title) LIKE ?{p} OR LOWER(content) LIKE ?{p} OR LOWER(url) LIKE ?{p}")
+                })
+                .collect();
+            let sql = format!(
+                "SELECT id, url, title, content, visited_at, embedding, profile_id
+                 FROM pages
+                 WHERE profile_id = ?1 AND ({})
+                 ORDER BY visited_at DESC
+                 LIMIT {}",
+                conditions.join(" OR "),
+                MAX_CANDIDATES
+            );
 
-            Ok(SearchResult {
-                page: Page {
-                    id: Some(row.get(0)?),
-                    url: row.get(1)?,
-                    title,
-                    content,
-                    visited_at: row.get(4)?,
-                    embedding: None,
-                    profile_id: row.get(5)?,
-                },
-                relevance: 1.0, // TODO: Implement proper relevance scoring
-                snippet,
+            let mut values: Vec<rusqlite::types::Value> = vec![profile_id.into()];
+            values.extend(terms.iter().map(|t| format!("%{}%", t).into()));
+
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map(rusqlite::params_from_iter(values), row_to_candidate)?;
+            for row in rows {
+                candidates.push(row?);
+            }
+        }
+
+        // Pages that are close in meaning but share no words with the query
+        if let Some(query_embedding) = query_embedding {
+            let seen: Vec<i64> = candidates.iter().filter_map(|(p, _)| p.id).collect();
+            let mut stmt = conn.prepare(
+                "SELECT id, url, title, content, visited_at, embedding, profile_id
+                 FROM pages WHERE profile_id = ?1 AND embedding IS NOT NULL"
+            )?;
+            let mut semantic: Vec<(f32, Page, Option<Vec<f32>>)> = Vec::new();
+            for row in stmt.query_map(params![profile_id], row_to_candidate)? {
+                let (page, embedding) = row?;
+                if seen.contains(&page.id.unwrap_or_default()) {
+                    continue;
+                }
+                if let Some(similarity) = embedding.as_deref().and_then(|e| cosine_similarity(query_embedding, e)) {
+                    semantic.push((similarity, page, embedding));
+                }
+            }
+            semantic.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            candidates.extend(semantic.into_iter().take(limit as usize).map(|(_, page, embedding)| (page, embedding)));
+        }
+
+        let now = chrono_now().parse::<i64>().unwrap_or_default();
+        let mut results: Vec<SearchResult> = candidates
+            .into_iter()
+            .map(|(page, embedding)| {
+                let mut score = ScoreBreakdown {
+                    title: field_score(&page.title, &terms, 1.0),
+                    body: field_score(&page.content, &terms, 3.0),
+                    url: field_score(&page.url, &terms, 1.0),
+                    recency: recency_score(&page.visited_at, now, weights.recency_half_life_days),
+                    semantic: match (query_embedding, embedding.as_deref()) {
+                        // Map [-1, 1] onto [0, 1] so it adds like the other signals
+                        (Some(q), Some(e)) => cosine_similarity(q, e).map(|s| ((s as f64) + 1.0) / 2.0),
+                        _ => None,
+                    },
+                    total: 0.0,
+                };
+
+                // The whole query appearing in the title is the strongest lexical signal
+                let title_lower = page.title.to_lowercase();
+                let query_lower = query.trim().to_lowercase();
+                if !query_lower.is_empty() && title_lower.trim() == query_lower {
+                    score.title = 1.0;
+                } else if terms.len() > 1 && title_lower.contains(&query_lower) {
+                    score.title = (score.title + 0.25).min(1.0);
+                }
+                score.total = score.weighted_total(weights);
+
+                let snippet = create_snippet(&page.content, query, 150)
+                    .or_else(|| terms.iter().find_map(|t| create_snippet(&page.content, t, 150)))
+                    .unwrap_or_else(|| page.title.chars().take(150).collect());
+
+                SearchResult {
+                    page,
+                    relevance: score.total,
+                    snippet,
+                    score: Some(score),
+                }
             })
-        })?;
+            .collect();
 
-        results.collect()
+        results.sort_by(|a, b| {
+            b.relevance
+                .partial_cmp(&a.relevance)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| b.page.visited_at.cmp(&a.page.visited_at))
+        });
+        results.truncate(limit as usize);
+        Ok(results)
     }
 
     /// Get all pages for a profile (for semantic search indexing)
     pub fn get_all_pages(&self, profile_id: i64) -> Result<Vec<Page>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, visited_at, profile_id
+            "SELECT id, url, title, content, visited_at, embedding, profile_id
              FROM pages WHERE profile_id = ?1 ORDER BY visited_at DESC"
         )?;
 
@@ -190,14 +377,98 @@ impl KnowledgeGraph {
                 title: row.get(2)?,
                 content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
                 visited_at: row.get(4)?,
-                embedding: None,
-                profile_id: row.get(5)?,
+                embedding: row.get::<_, Option<Vec<u8>>>(5)?.map(|b| bytes_to_embedding(&b)),
+                profile_id: row.get(6)?,
             })
         })?;
 
         pages.collect()
     }
 
+    /// Rank a profile's embedded pages by cosine similarity to `query_embedding`.
+    /// Pages without an embedding, or with one of a different dimension, are skipped.
+    pub fn semantic_search(&self, query_embedding: &[f32], profile_id: i64, k: usize) -> Result<Vec<SearchResult>> {
+        self.rank_by_embedding(query_embedding, profile_id, k, None)
+    }
+
+    /// Find the pages most similar to an already embedded page ("find pages like this one")
+    pub fn find_similar_pages(&self, page_id: i64, profile_id: i64, k: usize) -> Result<Vec<SearchResult>> {
+        let conn = self.db.get()?;
+        let blob: Option<Vec<u8>> = conn.query_row(
+            "SELECT embedding FROM pages WHERE id = ?1 AND profile_id = ?2",
+            params![page_id, profile_id],
+            |row| row.get(0),
+        )?;
+
+        match blob {
+            Some(bytes) => self.rank_by_embedding(&bytes_to_embedding(&bytes), profile_id, k, Some(page_id)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn rank_by_embedding(
+        &self,
+        query_embedding: &[f32],
+        profile_id: i64,
+        k: usize,
+        exclude_page_id: Option<i64>,
+    ) -> Result<Vec<SearchResult>> {
+        if query_embedding.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, url, title, content, visited_at, embedding, profile_id
+             FROM pages
+             WHERE profile_id = ?1 AND embedding IS NOT NULL"
+        )?;
+
+        let mut rows = stmt.query(params![profile_id])?;
+        let mut results = Vec::new();
+
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            if Some(id) == exclude_page_id {
+                continue;
+            }
+
+            let embedding = bytes_to_embedding(&row.get::<_, Vec<u8>>(5)?);
+            let similarity = match cosine_similarity(query_embedding, &embedding) {
+                Some(s) => s,
+                None => continue,
+            };
+
+            let content: String = row.get::<_, Option<String>>(3)?.unwrap_or_default();
+            let title: String = row.get(2)?;
+            let snippet = if content.is_empty() {
+                title.chars().take(150).collect()
+            } else {
+                content.chars().take(150).collect()
+            };
+
+            results.push(SearchResult {
+                page: Page {
+                    id: Some(id),
+                    url: row.get(1)?,
+                    title,
+                    content,
+                    visited_at: row.get(4)?,
+                    // Vectors are only needed for ranking, keep them out of the IPC payload
+                    embedding: None,
+                    profile_id: row.get(6)?,
+                },
+                relevance: similarity as f64,
+                snippet,
+                score: None,
+            });
+        }
+
+        results.sort_by(|a, b| b.relevance.partial_cmp(&a.relevance).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(k);
+        Ok(results)
+    }
+
     /// Add a note to a page
     pub fn add_note(&self, page_id: i64, content: &str) -> Result<Option<i64>> {
         // Check incognito mode
@@ -205,11 +476,12 @@ impl KnowledgeGraph {
             return Ok(None);
         }
 
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         conn.execute(
-            "INSERT INTO notes (page_id, content, created_at) VALUES (?1, ?2, ?3)",
+            "INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id)
+             SELECT id, ?2, ?3, ?3, profile_id FROM pages WHERE id = ?1",
             params![page_id, content, now],
         )?;
 
@@ -218,9 +490,9 @@ impl KnowledgeGraph {
 
     /// Get notes for a page
     pub fn get_notes_for_page(&self, page_id: i64) -> Result<Vec<Note>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, page_id, content, created_at FROM notes WHERE page_id = ?1 ORDER BY created_at DESC"
+            "SELECT id, page_id, content, created_at FROM page_notes WHERE page_id = ?1 ORDER BY created_at DESC"
         )?;
 
         let notes = stmt.query_map(params![page_id], |row| {
@@ -237,8 +509,8 @@ impl KnowledgeGraph {
 
     /// Delete a note
     pub fn delete_note(&self, note_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
-        let affected = conn.execute("DELETE FROM notes WHERE id = ?1", params![note_id])?;
+        let conn = self.db.get()?;
+        let affected = conn.execute("DELETE FROM page_notes WHERE id = ?1", params![note_id])?;
         Ok(affected > 0)
     }
 
@@ -248,11 +520,8 @@ impl KnowledgeGraph {
             return Ok(());
         }
 
-        let conn = Connection::open(&self.db_path)?;
-        let embedding_bytes: Vec<u8> = embedding
-            .iter()
-            .flat_map(|f| f.to_le_bytes())
-            .collect();
+        let conn = self.db.get()?;
+        let embedding_bytes = embedding_to_bytes(embedding);
 
         conn.execute(
             "UPDATE pages SET embedding = ?1 WHERE id = ?2",
@@ -261,6 +530,495 @@ impl KnowledgeGraph {
 
         Ok(())
     }
+
+    // ==================== Graph ====================
+
+    /// Connect two pages by hand
+    pub fn add_user_link(&self, source_id: i64, target_id: i64, profile_id: i64) -> Result<bool> {
+        if source_id == target_id {
+            return Ok(false);
+        }
+        let conn = self.db.get()?;
+
+        // Both pages must belong to the profile
+        let owned: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM pages WHERE id IN (?1, ?2) AND profile_id = ?3",
+            params![source_id, target_id, profile_id],
+            |row| row.get(0),
+        )?;
+        if owned != 2 {
+            return Ok(false);
+        }
+
+        insert_edge(&conn, source_id, target_id, EdgeRelation::UserLinked, 1.0, profile_id, &chrono_now())
+    }
+
+    /// Remove an edge between two pages
+    pub fn remove_edge(&self, source_id: i64, target_id: i64, relation: EdgeRelation, profile_id: i64) -> Result<bool> {
+        let conn = self.db.get()?;
+        let affected = conn.execute(
+            "DELETE FROM edges WHERE source_id = ?1 AND target_id = ?2 AND relation = ?3 AND profile_id = ?4",
+            params![source_id, target_id, relation.as_str(), profile_id],
+        )?;
+        Ok(affected > 0)
+    }
+
+    /// Replace a page's similarity edges with its `k` nearest pages above `min_similarity`
+    pub fn link_similar_pages(&self, page_id: i64, profile_id: i64, k: usize, min_similarity: f32) -> Result<usize> {
+        let similar = self.find_similar_pages(page_id, profile_id, k)?;
+        let conn = self.db.get()?;
+        let now = chrono_now();
+
+        conn.execute(
+            "DELETE FROM edges WHERE source_id = ?1 AND relation = ?2",
+            params![page_id, EdgeRelation::SemanticallySimilar.as_str()],
+        )?;
+
+        let mut linked = 0;
+        for result in similar {
+            if result.relevance < min_similarity as f64 {
+                continue;
+            }
+            if let Some(target_id) = result.page.id {
+                if insert_edge(&conn, page_id, target_id, EdgeRelation::SemanticallySimilar, result.relevance, profile_id, &now)? {
+                    linked += 1;
+                }
+            }
+        }
+        Ok(linked)
+    }
+
+    /// Pages connected to `page_id` in either direction, optionally limited to some relations
+    pub fn get_neighbors(&self, page_id: i64, profile_id: i64, relations: Option<&[EdgeRelation]>) -> Result<Vec<GraphNeighbor>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT e.relation, e.weight, e.source_id = ?1 AS outgoing,
+                    p.id, p.url, p.title, p.visited_at
+             FROM edges e
+             JOIN pages p ON p.id = CASE WHEN e.source_id = ?1 THEN e.target_id ELSE e.source_id END
+             WHERE (e.source_id = ?1 OR e.target_id = ?1) AND e.profile_id = ?2
+             ORDER BY e.weight DESC, p.visited_at DESC"
+        )?;
+
+        let rows = stmt.query_map(params![page_id, profile_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, bool>(2)?,
+                node_from_row(row, 3)?,
+            ))
+        })?;
+
+        let mut neighbors = Vec::new();
+        for row in rows {
+            let (relation, weight, outgoing, node) = row?;
+            let relation = match EdgeRelation::from_str(&relation) {
+                Some(r) => r,
+                None => continue,
+            };
+            if relations.is_some_and(|allowed| !allowed.contains(&relation)) {
+                continue;
+            }
+            neighbors.push(GraphNeighbor { node, relation, weight, incoming: !outgoing });
+        }
+        Ok(neighbors)
+    }
+
+    /// Pages that link to `page_id` (hyperlinks and manual links)
+    pub fn get_backlinks(&self, page_id: i64, profile_id: i64) -> Result<Vec<GraphNeighbor>> {
+        Ok(self
+            .get_neighbors(page_id, profile_id, Some(&[EdgeRelation::Hyperlink, EdgeRelation::UserLinked]))?
+            .into_iter()
+            .filter(|n| n.incoming)
+            .collect())
+    }
+
+    /// Fewest-hops path between two pages, ignoring edge direction.
+    /// Returns the pages along the path including both ends, or None if they aren't connected.
+    pub fn shortest_path(
+        &self,
+        from_id: i64,
+        to_id: i64,
+        profile_id: i64,
+        relations: Option<&[EdgeRelation]>,
+    ) -> Result<Option<Vec<GraphNode>>> {
+        let adjacency = self.adjacency(profile_id, relations)?;
+        let nodes = self.nodes_by_id(profile_id)?;
+        if !nodes.contains_key(&from_id) || !nodes.contains_key(&to_id) {
+            return Ok(None);
+        }
+
+        let mut previous: HashMap<i64, i64> = HashMap::new();
+        let mut visited: HashSet<i64> = HashSet::from([from_id]);
+        let mut queue: VecDeque<i64> = VecDeque::from([from_id]);
+
+        while let Some(current) = queue.pop_front() {
+            if current == to_id {
+                let mut path = vec![to_id];
+                let mut node = to_id;
+                while let Some(&prev) = previous.get(&node) {
+                    path.push(prev);
+                    node = prev;
+                }
+                path.reverse();
+                return Ok(Some(path.into_iter().filter_map(|id| nodes.get(&id).cloned()).collect()));
+            }
+
+            for &next in adjacency.get(&current).into_iter().flatten() {
+                if visited.insert(next) {
+                    previous.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Connected components of the profile's graph, largest first. Pages without edges are left out.
+    pub fn get_clusters(&self, profile_id: i64, relations: Option<&[EdgeRelation]>) -> Result<Vec<Vec<GraphNode>>> {
+        let adjacency = self.adjacency(profile_id, relations)?;
+        let nodes = self.nodes_by_id(profile_id)?;
+
+        let mut seen: HashSet<i64> = HashSet::new();
+        let mut ids: Vec<i64> = adjacency.keys().copied().collect();
+        ids.sort_unstable();
+
+        let mut clusters: Vec<Vec<GraphNode>> = Vec::new();
+        for start in ids {
+            if !seen.insert(start) {
+                continue;
+            }
+
+            let mut component = vec![start];
+            let mut stack = vec![start];
+            while let Some(current) = stack.pop() {
+                for &next in adjacency.get(&current).into_iter().flatten() {
+                    if seen.insert(next) {
+                        component.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+
+            component.sort_unstable();
+            clusters.push(component.into_iter().filter_map(|id| nodes.get(&id).cloned()).collect());
+        }
+
+        clusters.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        Ok(clusters)
+    }
+
+    /// All pages and edges of a profile
+    pub fn export_graph(&self, profile_id: i64) -> Result<GraphExport> {
+        let mut nodes: Vec<GraphNode> = self.nodes_by_id(profile_id)?.into_values().collect();
+        nodes.sort_by_key(|n| n.id);
+        Ok(GraphExport {
+            nodes,
+            edges: self.get_edges(profile_id)?,
+        })
+    }
+
+    fn get_edges(&self, profile_id: i64) -> Result<Vec<GraphEdge>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, source_id, target_id, relation, weight, created_at
+             FROM edges WHERE profile_id = ?1 ORDER BY id"
+        )?;
+
+        let rows = stmt.query_map(params![profile_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+
+        let mut edges = Vec::new();
+        for row in rows {
+            let (id, source_id, target_id, relation, weight, created_at) = row?;
+            if let Some(relation) = EdgeRelation::from_str(&relation) {
+                edges.push(GraphEdge { id, source_id, target_id, relation, weight, created_at });
+            }
+        }
+        Ok(edges)
+    }
+
+    fn nodes_by_id(&self, profile_id: i64) -> Result<HashMap<i64, GraphNode>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare("SELECT id, url, title, visited_at FROM pages WHERE profile_id = ?1")?;
+        let rows = stmt.query_map(params![profile_id], |row| node_from_row(row, 0))?;
+
+        let mut nodes = HashMap::new();
+        for node in rows {
+            let node = node?;
+            nodes.insert(node.id, node);
+        }
+        Ok(nodes)
+    }
+
+    /// Undirected adjacency lists
+    fn adjacency(&self, profile_id: i64, relations: Option<&[EdgeRelation]>) -> Result<HashMap<i64, Vec<i64>>> {
+        let mut adjacency: HashMap<i64, Vec<i64>> = HashMap::new();
+        for edge in self.get_edges(profile_id)? {
+            if relations.is_some_and(|allowed| !allowed.contains(&edge.relation)) {
+                continue;
+            }
+            adjacency.entry(edge.source_id).or_default().push(edge.target_id);
+            adjacency.entry(edge.target_id).or_default().push(edge.source_id);
+        }
+        for neighbors in adjacency.values_mut() {
+            neighbors.sort_unstable();
+            neighbors.dedup();
+        }
+        Ok(adjacency)
+    }
+}
+
+/// Record a page's outbound links and create hyperlink and same-domain edges,
+/// in both directions for links that were waiting for this page to be visited
+fn link_page(conn: &Connection, page_id: i64, url: &str, content: &str, profile_id: i64, now: &str) -> Result<()> {
+    conn.execute("DELETE FROM page_links WHERE source_id = ?1", params![page_id])?;
+    conn.execute(
+        "DELETE FROM edges WHERE source_id = ?1 AND relation = ?2",
+        params![page_id, EdgeRelation::Hyperlink.as_str()],
+    )?;
+
+    for link in extract_links(url, content) {
+        conn.execute(
+            "INSERT OR IGNORE INTO page_links (source_id, target_url) VALUES (?1, ?2)",
+            params![page_id, link],
+        )?;
+
+        // Stored page URLs aren't canonicalized, "https://a.com" must match "https://a.com/"
+        let target: Option<i64> = conn.query_row(
+            "SELECT id FROM pages WHERE (url = ?1 OR url = ?2) AND profile_id = ?3 LIMIT 1",
+            params![link, link.trim_end_matches('/'), profile_id],
+            |row| row.get(0),
+        ).optional()?;
+        if let Some(target_id) = target {
+            insert_edge(conn, page_id, target_id, EdgeRelation::Hyperlink, 1.0, profile_id, now)?;
+        }
+    }
+
+    // Earlier pages that linked here
+    let canonical = canonical_link(url).unwrap_or_else(|| url.to_string());
+    let mut stmt = conn.prepare(
+        "SELECT l.source_id FROM page_links l JOIN pages p ON p.id = l.source_id
+         WHERE l.target_url = ?1 AND p.profile_id = ?2"
+    )?;
+    let sources: Vec<i64> = stmt
+        .query_map(params![canonical, profile_id], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    for source_id in sources {
+        insert_edge(conn, source_id, page_id, EdgeRelation::Hyperlink, 1.0, profile_id, now)?;
+    }
+
+    if let Some(host) = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_lowercase)) {
+        let mut stmt = conn.prepare(
+            "SELECT id, url FROM pages WHERE profile_id = ?1 AND id != ?2 AND url LIKE ?3
+             ORDER BY visited_at DESC LIMIT ?4"
+        )?;
+        let candidates: Vec<(i64, String)> = stmt
+            .query_map(params![profile_id, page_id, format!("%{}%", host), MAX_SAME_DOMAIN_EDGES], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_>>()?;
+
+        for (other_id, other_url) in candidates {
+            let same_host = url::Url::parse(&other_url)
+                .ok()
+                .and_then(|u| u.host_str().map(str::to_lowercase))
+                .is_some_and(|h| h == host);
+            // One edge per pair, from the older page to the newer one
+            let exists: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM edges WHERE relation = ?1
+                 AND ((source_id = ?2 AND target_id = ?3) OR (source_id = ?3 AND target_id = ?2))",
+                params![EdgeRelation::SameDomain.as_str(), page_id, other_id],
+                |row| row.get(0),
+            )?;
+            if same_host && exists == 0 {
+                insert_edge(conn, other_id, page_id, EdgeRelation::SameDomain, 0.5, profile_id, now)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn insert_edge(
+    conn: &Connection,
+    source_id: i64,
+    target_id: i64,
+    relation: EdgeRelation,
+    weight: f64,
+    profile_id: i64,
+    now: &str,
+) -> Result<bool> {
+    if source_id == target_id {
+        return Ok(false);
+    }
+    let affected = conn.execute(
+        "INSERT OR IGNORE INTO edges (source_id, target_id, relation, weight, profile_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![source_id, target_id, relation.as_str(), weight, profile_id, now],
+    )?;
+    Ok(affected > 0)
+}
+
+fn node_from_row(row: &rusqlite::Row, offset: usize) -> Result<GraphNode> {
+    let url: String = row.get(offset + 1)?;
+    Ok(GraphNode {
+        id: row.get(offset)?,
+        domain: url::Url::parse(&url).ok().and_then(|u| u.host_str().map(String::from)),
+        url,
+        title: row.get(offset + 2)?,
+        visited_at: row.get(offset + 3)?,
+    })
+}
+
+/// Outbound http(s) links in page content: `href` attributes when the content is HTML,
+/// bare URLs otherwise. Relative hrefs are resolved against the page URL.
+pub fn extract_links(page_url: &str, content: &str) -> Vec<String> {
+    let base = url::Url::parse(page_url).ok();
+    let href = regex::Regex::new(r#"(?i)href\s*=\s*["']([^"'#][^"']*)["']"#).expect("valid regex");
+    let bare = regex::Regex::new(r#"https?://[^\s<>"'()\[\]]+"#).expect("valid regex");
+
+    let mut candidates: Vec<String> = href
+        .captures_iter(content)
+        .filter_map(|c| {
+            let raw = c.get(1)?.as_str().trim();
+            match &base {
+                Some(base) => base.join(raw).ok().map(|u| u.to_string()),
+                None => Some(raw.to_string()),
+            }
+        })
+        .collect();
+    candidates.extend(
+        bare.find_iter(content)
+            .map(|m| m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']).to_string()),
+    );
+
+    let own = canonical_link(page_url);
+    let mut links: Vec<String> = Vec::new();
+    for candidate in candidates {
+        if let Some(link) = canonical_link(&candidate) {
+            if Some(&link) != own.as_ref() && !links.contains(&link) {
+                links.push(link);
+            }
+        }
+        if links.len() >= MAX_OUTBOUND_LINKS {
+            break;
+        }
+    }
+    links
+}
+
+/// Normalized form used to match links against page URLs: http(s) only, no fragment
+fn canonical_link(raw: &str) -> Option<String> {
+    let mut parsed = url::Url::parse(raw).ok()?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        return None;
+    }
+    parsed.set_fragment(None);
+    Some(parsed.to_string())
+}
+
+/// Serialize an embedding as little-endian f32s (the `pages.embedding` blob format)
+pub fn embedding_to_bytes(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|f| f.to_le_bytes()).collect()
+}
+
+/// Decode a `pages.embedding` blob; trailing bytes that don't form a full f32 are ignored
+pub fn bytes_to_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Cosine similarity in [-1, 1]; None if the vectors can't be compared
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() || a.is_empty() {
+        return None;
+    }
+
+    let mut dot = 0.0f32;
+    let mut norm_a = 0.0f32;
+    let mut norm_b = 0.0f32;
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return None;
+    }
+    Some(dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+fn row_to_candidate(row: &rusqlite::Row) -> Result<(Page, Option<Vec<f32>>)> {
+    let embedding = row.get::<_, Option<Vec<u8>>>(5)?.map(|b| bytes_to_embedding(&b));
+    Ok((
+        Page {
+            id: Some(row.get(0)?),
+            url: row.get(1)?,
+            title: row.get(2)?,
+            content: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+            visited_at: row.get(4)?,
+            // Only needed for ranking, keep it out of the IPC payload
+            embedding: None,
+            profile_id: row.get(6)?,
+        },
+        embedding,
+    ))
+}
+
+/// Lowercased, de-duplicated alphanumeric terms of a query
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for term in query.to_lowercase().split(|c: char| !c.is_alphanumeric()) {
+        if !term.is_empty() && !terms.iter().any(|t| t == term) {
+            terms.push(term.to_string());
+        }
+    }
+    terms
+}
+
+/// Average over query terms of a saturating term-frequency score in [0, 1).
+/// `saturation` is the hit count that scores 0.5; longer fields get a higher value.
+fn field_score(text: &str, terms: &[String], saturation: f64) -> f64 {
+    if terms.is_empty() || text.is_empty() {
+        return 0.0;
+    }
+
+    let text = text.to_lowercase();
+    let total: f64 = terms
+        .iter()
+        .map(|term| {
+            let tf = text.matches(term.as_str()).count() as f64;
+            tf / (tf + saturation)
+        })
+        .sum();
+    total / terms.len() as f64
+}
+
+/// Exponential decay on page age: 1.0 for now, 0.5 after `half_life_days`
+fn recency_score(visited_at: &str, now: i64, half_life_days: f64) -> f64 {
+    let visited = match visited_at.parse::<i64>() {
+        Ok(v) => v,
+        Err(_) => return 0.0,
+    };
+    if half_life_days <= 0.0 {
+        return 0.0;
+    }
+
+    let age_days = (now - visited).max(0) as f64 / 86_400.0;
+    0.5f64.powf(age_days / half_life_days)
 }
 
 /// Create a snippet around the search query
@@ -290,3 +1048,169 @@ fn chrono_now() -> String {
         .unwrap_or_default();
     format!("{}", duration.as_secs())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_graph(name: &str) -> KnowledgeGraph {
+        KnowledgeGraph::new(crate::migrations::test_database(&format!("kg_{}", name)))
+    }
+
+    /// Insert directly so the test doesn't depend on the global incognito flag
+    fn insert_page(graph: &KnowledgeGraph, url: &str, title: &str, embedding: Option<&[f32]>) -> i64 {
+        let conn = graph.db.get().unwrap();
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, embedding, profile_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+            params![url, title, format!("{} content", title), chrono_now(), embedding.map(embedding_to_bytes)],
+        ).unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_embedding_roundtrip() {
+        let embedding = vec![0.25f32, -1.5, 3.0];
+        assert_eq!(bytes_to_embedding(&embedding_to_bytes(&embedding)), embedding);
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]).unwrap() - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).unwrap().abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[1.0, 0.0, 0.0]).is_none());
+        assert!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]).is_none());
+    }
+
+    #[test]
+    fn test_semantic_search_ranks_by_similarity() {
+        let graph = test_graph("semantic");
+
+        let rust = insert_page(&graph, "https://rust-lang.org", "Rust", Some(&[1.0, 0.0, 0.0]));
+        let go = insert_page(&graph, "https://go.dev", "Go", Some(&[0.6, 0.8, 0.0]));
+        insert_page(&graph, "https://example.com", "No embedding", None);
+
+        let results = graph.semantic_search(&[1.0, 0.1, 0.0], 1, 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].page.id, Some(rust));
+        assert!(results[0].relevance > results[1].relevance);
+
+        let similar = graph.find_similar_pages(rust, 1, 5).unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].page.id, Some(go));
+
+        let stored = graph.get_page_by_url("https://go.dev", 1).unwrap().unwrap();
+        assert_eq!(stored.embedding, Some(vec![0.6, 0.8, 0.0]));
+    }
+
+    #[test]
+    fn test_search_ranks_title_match_above_newer_mention() {
+        let graph = test_graph("hybrid");
+        let conn = graph.db.get().unwrap();
+        let now = chrono_now().parse::<i64>().unwrap();
+
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES (?1, ?2, ?3, ?4, 1)",
+            params!["https://tokio.rs", "Tokio", "An asynchronous runtime. Tokio tasks, tokio streams.", (now - 90 * 86_400).to_string()],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES (?1, ?2, ?3, ?4, 1)",
+            params!["https://blog.example.com/post", "Weekly notes", "Tried tokio once this week.", now.to_string()],
+        ).unwrap();
+        conn.execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES (?1, ?2, ?3, ?4, 1)",
+            params!["https://unrelated.example.com", "Gardening", "Tomatoes", now.to_string()],
+        ).unwrap();
+
+        let results = graph.search_pages("tokio", 1, 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].page.url, "https://tokio.rs");
+
+        let score = results[0].score.as_ref().unwrap();
+        assert_eq!(score.title, 1.0);
+        assert!(score.recency < results[1].score.as_ref().unwrap().recency);
+        assert!((score.total - results[0].relevance).abs() < 1e-9);
+
+        // Recency alone decides once everything else is switched off
+        let recency_only = RankingWeights { title: 0.0, body: 0.0, url: 0.0, recency: 1.0, ..Default::default() };
+        let results = graph.search_pages_ranked("tokio", 1, 10, None, &recency_only).unwrap();
+        assert_eq!(results[0].page.url, "https://blog.example.com/post");
+    }
+
+    #[test]
+    fn test_search_uses_embeddings_when_available() {
+        let graph = test_graph("hybrid_semantic");
+        insert_page(&graph, "https://a.example.com", "Rust guide", Some(&[1.0, 0.0]));
+        insert_page(&graph, "https://b.example.com", "Rust book", Some(&[0.0, 1.0]));
+        insert_page(&graph, "https://c.example.com", "Ferris", Some(&[0.9, 0.1]));
+
+        let results = graph
+            .search_pages_ranked("rust", 1, 10, Some(&[0.0, 1.0]), &RankingWeights::default())
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].page.url, "https://b.example.com");
+        // Semantic-only candidate is included but ranks below lexical matches
+        assert_eq!(results[2].page.url, "https://c.example.com");
+        assert!(results.iter().all(|r| r.score.as_ref().unwrap().semantic.is_some()));
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let content = r##"<a href="/docs#intro">Docs</a> <a href='https://other.org/x'>x</a>
+            <a href="mailto:me@example.com">mail</a> see https://bare.example.com/page. <a href="#top">top</a>"##;
+        let links = extract_links("https://example.com/index.html", content);
+        assert_eq!(links, vec![
+            "https://example.com/docs",
+            "https://other.org/x",
+            "https://bare.example.com/page",
+        ]);
+    }
+
+    #[test]
+    fn test_graph_edges_and_queries() {
+        let graph = test_graph("edges");
+        let conn = graph.db.get().unwrap();
+        let now = chrono_now();
+
+        // Page a links to b (not visited yet), b links to c; d is on the same host as a
+        let add = |url: &str, content: &str| -> i64 {
+            conn.execute(
+                "INSERT INTO pages (url, title, content, visited_at, profile_id) VALUES (?1, ?1, ?2, ?3, 1)",
+                params![url, content, now],
+            ).unwrap();
+            let id = conn.last_insert_rowid();
+            link_page(&conn, id, url, content, 1, &now).unwrap();
+            id
+        };
+        let a = add("https://a.example.com/", r#"<a href="https://b.example.org/post">b</a>"#);
+        let b = add("https://b.example.org/post", "read https://c.example.net/");
+        let c = add("https://c.example.net", "no links");
+        let d = add("https://a.example.com/about", "about");
+        let lonely = add("https://lonely.example.io", "");
+
+        let backlinks = graph.get_backlinks(b, 1).unwrap();
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].node.id, a);
+        assert_eq!(backlinks[0].relation, EdgeRelation::Hyperlink);
+
+        let neighbors = graph.get_neighbors(a, 1, Some(&[EdgeRelation::SameDomain])).unwrap();
+        assert_eq!(neighbors.len(), 1);
+        assert_eq!(neighbors[0].node.id, d);
+
+        let path = graph.shortest_path(d, c, 1, None).unwrap().unwrap();
+        assert_eq!(path.iter().map(|n| n.id).collect::<Vec<_>>(), vec![d, a, b, c]);
+        assert!(graph.shortest_path(a, lonely, 1, None).unwrap().is_none());
+        assert!(graph.shortest_path(d, c, 1, Some(&[EdgeRelation::Hyperlink])).unwrap().is_none());
+
+        assert!(graph.add_user_link(lonely, c, 1).unwrap());
+        let clusters = graph.get_clusters(1, None).unwrap();
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].len(), 5);
+
+        let export = graph.export_graph(1).unwrap();
+        assert_eq!(export.nodes.len(), 5);
+        assert_eq!(export.edges.len(), 4);
+        let json = serde_json::to_value(&export).unwrap();
+        assert_eq!(json["edges"][0]["relation"], "hyperlink");
+    }
+}
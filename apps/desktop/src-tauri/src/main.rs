@@ -15,27 +15,66 @@ mod split_view;
 mod multimedia;
 mod webview;
 mod scraper;
-
-use std::sync::Mutex;
+mod robots;
+mod frontier;
+mod indexer;
+mod summarizer;
+mod ask_memory;
+mod entities;
+mod migrations;
+mod db;
+mod error;
+mod tasks;
+mod backup;
+mod crypto;
+mod profile_lock;
+mod password_vault;
+mod key_store;
+mod otp;
+
+use std::sync::RwLock;
 use tauri::{Manager, State};
 
 use profile::{Profile, ProfileManager, PrivacySettings};
 use privacy::{PrivacyManager, HistoryEntry, HistoryStats};
-use knowledge_graph::{KnowledgeGraph, Page, SearchResult as KGSearchResult};
+use knowledge_graph::{EdgeRelation, GraphNeighbor, GraphNode, KnowledgeGraph, Page, RankingWeights, SearchResult as KGSearchResult};
 use theme::{Theme, ThemeManager, PresetTheme, get_preset_themes};
 use search::{Domain, DomainList, DomainStats, SearchManager};
-use memory::{IndexedPage, PageNote, MemoryStats, MemoryManager};
+use memory::{IndexedPage, PageNote, MemorySearchResults, MemoryStats, MemoryManager};
 use ratings::{DomainRating, RatingAggregate, RatingSummary, SubdomainRating, RatingManager, UserRatingHistory};
 use tabs::{Tab, TabHistoryEntry, TabManager};
 use bookmarks::{Bookmark, BookmarkFolder, BookmarkManager};
 use split_view::{SplitViewConfig, SplitViewManager, PaneSizes};
 use multimedia::{MediaHistoryEntry, Playlist, PlaylistItem, PrivacySettings as MediaPrivacySettings, MediaStats, MultimediaManager};
-use scraper::{ScrapingJob, ScrapedPage, ContentSelector, ScraperManager};
-
-// Application state managed by Tauri
+use scraper::{
+    ScrapingJob, ScrapedPage, ContentSelector, CrawlSettings, ScraperManager, ScrapingRun, PageVersion, PageDiff,
+    SCRAPER_PROGRESS_EVENT, SCRAPER_PAGE_CHANGED_EVENT,
+};
+use ai::{AiConfig, AiError, AiStatus, GenerationEvent, GenerationRegistry, OllamaClient, GENERATION_EVENT};
+use indexer::{EmbeddingIndexStatus, EmbeddingIndexer, INDEXER_PROGRESS_EVENT};
+use summarizer::{PageSummarizer, PageSummary, SUMMARY_EVENT};
+use ask_memory::{MemoryAnswer, MemoryQa};
+use entities::{Entity, EntityIndex, EntityKind, EntityMention, ExtractionReport};
+use db::DbPool;
+use error::{AppError, AppResult};
+use tasks::{TaskEvent, TaskInfo, TaskRegistry, TASK_EVENT};
+use backup::{BackupManager, BackupManifest, RestoreOptions};
+use profile_lock::{ProfileLockManager, ProfileLockStatus, PROFILE_LOCK_EVENT};
+use password_vault::{
+    CsvImportReport, CustomField, PasswordEntry, PasswordEntryInput, PasswordGeneratorOptions,
+    PasswordStrength, PasswordVault,
+};
+use otp::{OtpAlgorithm, OtpCode, OtpEntry, OtpEntryInput, OtpKind, OtpManager};
+
+// Application state managed by Tauri.
+// Managers are cheap handles onto the shared pool, so commands clone the one they need
+// and run it on the blocking thread pool; nothing holds a lock across a command.
 struct AppState {
-    db_path: String,
+    db: DbPool,
     profile_manager: ProfileManager,
+    profile_locks: ProfileLockManager,
+    password_vault: PasswordVault,
+    otp_manager: OtpManager,
     privacy_manager: PrivacyManager,
     knowledge_graph: KnowledgeGraph,
     theme_manager: ThemeManager,
@@ -47,104 +86,272 @@ struct AppState {
     split_view_manager: SplitViewManager,
     multimedia_manager: MultimediaManager,
     scraper_manager: ScraperManager,
+    backup_manager: BackupManager,
+    ai_client: RwLock<OllamaClient>,
+    ai_generations: GenerationRegistry,
+    embedding_indexer: EmbeddingIndexer,
+    page_summarizer: PageSummarizer,
+    entity_index: EntityIndex,
+    tasks: TaskRegistry,
+}
+
+impl AppState {
+    /// Snapshot of the client for the current AI config
+    fn ai_client(&self) -> OllamaClient {
+        self.ai_client.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// Run SQLite work on the blocking thread pool instead of an async executor thread
+async fn blocking<T, F>(f: F) -> AppResult<T>
+where
+    F: FnOnce() -> AppResult<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(f)
+        .await
+        .map_err(|e| AppError::Internal(e.to_string()))?
+}
+
+/// Forwards a task's progress to every window as TASK_EVENT
+fn task_emitter(app: tauri::AppHandle) -> impl Fn(TaskEvent) + Send + Sync + 'static {
+    move |event| {
+        let _ = app.emit_all(TASK_EVENT, event);
+    }
 }
 
 // ==================== Profile Commands ====================
 
 #[tauri::command]
-async fn get_profiles(state: State<'_, Mutex<AppState>>) -> Result<Vec<Profile>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .get_profiles()
-        .map_err(|e| e.to_string())
+async fn get_profiles(state: State<'_, AppState>) -> AppResult<Vec<Profile>> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .get_profiles()
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
-async fn get_active_profile(state: State<'_, Mutex<AppState>>) -> Result<Option<Profile>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .get_active_profile()
-        .map_err(|e| e.to_string())
+async fn get_active_profile(state: State<'_, AppState>) -> AppResult<Option<Profile>> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .get_active_profile()
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn create_profile(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     name: String,
     icon: Option<String>,
-) -> Result<Profile, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .create_profile(&name, icon.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Profile> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .create_profile(&name, icon.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn switch_profile(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Profile, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .switch_profile(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Profile> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .switch_profile(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_profile(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     name: String,
     icon: Option<String>,
-) -> Result<Profile, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .update_profile(profile_id, &name, icon.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Profile> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .update_profile(profile_id, &name, icon.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_profile(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .delete_profile(profile_id)
-        .map_err(|e| format!("Cannot delete profile: {}", e))
+) -> AppResult<()> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .delete_profile(profile_id)
+    }).await
 }
 
 #[tauri::command]
 async fn get_privacy_settings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<PrivacySettings, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .get_privacy_settings(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<PrivacySettings> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .get_privacy_settings(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_privacy_settings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     settings: PrivacySettings,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .update_privacy_settings(&settings)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .update_privacy_settings(&settings)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn export_profile(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let profile_manager = state.profile_manager.clone();
+    blocking(move || {
+        profile_manager
+            .export_profile(profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+// ==================== Profile Encryption Commands ====================
+
+#[tauri::command]
+fn get_profile_lock_status(state: State<'_, AppState>, profile_id: i64) -> ProfileLockStatus {
+    state.profile_locks.status(profile_id)
+}
+
+/// Give a profile a passphrase; it stays unlocked until locked by hand, by auto-lock or on exit
+#[tauri::command]
+async fn enable_profile_encryption(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    passphrase: String,
+    auto_lock_minutes: Option<u32>,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.enable(profile_id, &passphrase, auto_lock_minutes)).await
+}
+
+#[tauri::command]
+async fn disable_profile_encryption(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    passphrase: String,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.disable(profile_id, &passphrase)).await
+}
+
+#[tauri::command]
+async fn lock_profile(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.lock(profile_id)).await
+}
+
+#[tauri::command]
+async fn unlock_profile(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    passphrase: String,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.unlock(profile_id, &passphrase)).await
+}
+
+/// Change the passphrase and rotate the profile's data key
+#[tauri::command]
+async fn rekey_profile(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    old_passphrase: String,
+    new_passphrase: String,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.rekey(profile_id, &old_passphrase, &new_passphrase)).await
+}
+
+#[tauri::command]
+async fn set_profile_auto_lock(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    minutes: Option<u32>,
+) -> AppResult<ProfileLockStatus> {
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || profile_locks.set_auto_lock(profile_id, minutes)).await
+}
+
+// ==================== Backup Commands ====================
+
+/// Starts a background task; the archive (JSON, encrypted when a password is given)
+/// arrives in its final task event
+#[tauri::command]
+async fn create_profile_backup(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.profile_manager
-        .export_profile(profile_id)
-        .map_err(|e| e.to_string())
+    password: Option<String>,
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let backup_manager = state.backup_manager.clone();
+    Ok(state.tasks.spawn_blocking("create_profile_backup", task_emitter(app), move |task| {
+        backup_manager.create_backup(profile_id, password.as_deref(), task)
+    }))
+}
+
+/// Validates an archive and returns its manifest, so the UI can show what a restore would bring in
+#[tauri::command]
+async fn inspect_backup(
+    state: State<'_, AppState>,
+    archive: String,
+    password: Option<String>,
+) -> AppResult<BackupManifest> {
+    let backup_manager = state.backup_manager.clone();
+    blocking(move || backup_manager.inspect_backup(&archive, password.as_deref())).await
+}
+
+/// Starts a background task; the RestoreReport arrives in its final task event.
+/// A cancelled restore is rolled back.
+#[tauri::command]
+async fn restore_profile_backup(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    archive: String,
+    password: Option<String>,
+    options: RestoreOptions,
+) -> AppResult<String> {
+    if let Some(profile_id) = options.target_profile_id {
+        state.profile_locks.ensure_unlocked(profile_id)?;
+    }
+    let backup_manager = state.backup_manager.clone();
+    Ok(state.tasks.spawn_blocking("restore_profile_backup", task_emitter(app), move |task| {
+        backup_manager.restore_backup(&archive, password.as_deref(), &options, task)
+    }))
 }
 
 // ==================== Incognito Commands ====================
@@ -172,140 +379,436 @@ fn set_incognito(enabled: bool) {
 
 #[tauri::command]
 async fn get_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     search_query: Option<String>,
     limit: Option<i64>,
     offset: Option<i64>,
-) -> Result<Vec<HistoryEntry>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .get_history(
-            profile_id,
-            search_query.as_deref(),
-            limit.unwrap_or(50),
-            offset.unwrap_or(0),
-        )
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<HistoryEntry>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .get_history(
+                profile_id,
+                search_query.as_deref(),
+                limit.unwrap_or(50),
+                offset.unwrap_or(0),
+            )
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_history_entry(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     entry_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .delete_history_entry(entry_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .delete_history_entry(entry_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_history_by_date_range(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     start_date: String,
     end_date: String,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .delete_history_by_date_range(profile_id, &start_date, &end_date)
-        .map_err(|e| e.to_string())
+) -> AppResult<i64> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .delete_history_by_date_range(profile_id, &start_date, &end_date)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn clear_all_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .clear_all_history(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<i64> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .clear_all_history(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_history_stats(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<HistoryStats, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .get_history_stats(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<HistoryStats> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .get_history_stats(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn export_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.privacy_manager
-        .export_history(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let privacy_manager = state.privacy_manager.clone();
+    blocking(move || {
+        privacy_manager
+            .export_history(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== Knowledge Graph Commands ====================
 
 #[tauri::command]
 async fn add_page(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     url: String,
     title: String,
     content: String,
     profile_id: i64,
-) -> Result<Option<i64>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    let page = Page {
-        id: None,
-        url,
-        title,
-        content,
-        visited_at: String::new(),
-        embedding: None,
-        profile_id: Some(profile_id),
-    };
-    state.knowledge_graph
-        .add_page(&page, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<i64>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        let page = Page {
+            id: None,
+            url,
+            title,
+            content,
+            visited_at: String::new(),
+            embedding: None,
+            profile_id: Some(profile_id),
+        };
+        knowledge_graph
+            .add_page(&page, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn search_knowledge_graph(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     query: String,
     profile_id: i64,
     limit: Option<i64>,
-) -> Result<Vec<KGSearchResult>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.knowledge_graph
-        .search_pages(&query, profile_id, limit.unwrap_or(20))
-        .map_err(|e| e.to_string())
+    weights: Option<RankingWeights>,
+) -> AppResult<Vec<KGSearchResult>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let client = state.ai_client();
+
+    // Vector similarity is a bonus signal: fall back to lexical ranking if Ollama is unavailable
+    let query_embedding = if client.is_running().await {
+        client.generate_embedding(&query).await.ok()
+    } else {
+        None
+    };
+
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .search_pages_ranked(
+                &query,
+                profile_id,
+                limit.unwrap_or(20),
+                query_embedding.as_deref(),
+                &weights.unwrap_or_default(),
+            )
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Embed `query` with the local model and rank the profile's pages by similarity
+#[tauri::command]
+async fn semantic_search(
+    state: State<'_, AppState>,
+    query: String,
+    profile_id: i64,
+    limit: Option<usize>,
+) -> AppResult<Vec<KGSearchResult>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let client = state.ai_client();
+    let query_embedding = client
+        .generate_embedding(&query)
+        .await
+        .map_err(AppError::from)?;
+
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .semantic_search(&query_embedding, profile_id, limit.unwrap_or(20))
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn find_similar_pages(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+    limit: Option<usize>,
+) -> AppResult<Vec<KGSearchResult>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .find_similar_pages(page_id, profile_id, limit.unwrap_or(10))
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn get_page_neighbors(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+    relations: Option<Vec<EdgeRelation>>,
+) -> AppResult<Vec<GraphNeighbor>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .get_neighbors(page_id, profile_id, relations.as_deref())
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn get_page_backlinks(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+) -> AppResult<Vec<GraphNeighbor>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .get_backlinks(page_id, profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn find_graph_path(
+    state: State<'_, AppState>,
+    from_page_id: i64,
+    to_page_id: i64,
+    profile_id: i64,
+    relations: Option<Vec<EdgeRelation>>,
+) -> AppResult<Option<Vec<GraphNode>>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .shortest_path(from_page_id, to_page_id, profile_id, relations.as_deref())
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn get_graph_clusters(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    relations: Option<Vec<EdgeRelation>>,
+) -> AppResult<Vec<Vec<GraphNode>>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .get_clusters(profile_id, relations.as_deref())
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn link_pages(
+    state: State<'_, AppState>,
+    source_id: i64,
+    target_id: i64,
+    profile_id: i64,
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .add_user_link(source_id, target_id, profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn unlink_pages(
+    state: State<'_, AppState>,
+    source_id: i64,
+    target_id: i64,
+    relation: EdgeRelation,
+    profile_id: i64,
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        knowledge_graph
+            .remove_edge(source_id, target_id, relation, profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Nodes and edges as JSON for the graph view
+#[tauri::command]
+async fn export_knowledge_graph(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let knowledge_graph = state.knowledge_graph.clone();
+    blocking(move || {
+        let graph = knowledge_graph
+            .export_graph(profile_id)
+            .map_err(AppError::from)?;
+        serde_json::to_string_pretty(&graph).map_err(AppError::from)
+    }).await
+}
+
+// ==================== AI Commands ====================
+
+#[tauri::command]
+async fn get_ai_status(state: State<'_, AppState>) -> AppResult<AiStatus> {
+    let client = state.ai_client();
+    Ok(AiStatus {
+        running: client.is_running().await,
+        config: client.config().clone(),
+    })
+}
+
+#[tauri::command]
+async fn update_ai_config(
+    state: State<'_, AppState>,
+    config: AiConfig,
+) -> AppResult<AiConfig> {
+    let mut client = state.ai_client.write().unwrap_or_else(|e| e.into_inner());
+    *client = OllamaClient::with_config(config);
+    Ok(client.config().clone())
+}
+
+#[tauri::command]
+async fn generate_embedding(
+    state: State<'_, AppState>,
+    text: String,
+) -> AppResult<Vec<f32>> {
+    let client = state.ai_client();
+    client
+        .generate_embedding(&text)
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn ai_generate(
+    state: State<'_, AppState>,
+    prompt: String,
+    model: Option<String>,
+) -> AppResult<String> {
+    let client = state.ai_client();
+    client
+        .generate(&prompt, model.as_deref())
+        .await
+        .map_err(AppError::from)
+}
+
+/// Stream a completion to the frontend as `ai-generation` events tagged with `request_id`.
+/// Resolves with the full text once the model is done.
+#[tauri::command]
+async fn ai_generate_stream(
+    window: tauri::Window,
+    state: State<'_, AppState>,
+    request_id: String,
+    prompt: String,
+    model: Option<String>,
+) -> AppResult<String> {
+    let client = state.ai_client();
+    let generations = state.ai_generations.clone();
+    let cancel = generations.register(&request_id);
+
+    let result = client
+        .generate_stream(&prompt, model.as_deref(), &cancel, |token| {
+            let _ = window.emit(GENERATION_EVENT, GenerationEvent {
+                request_id: request_id.clone(),
+                token: token.to_string(),
+                done: false,
+                cancelled: false,
+                error: None,
+            });
+        })
+        .await;
+    generations.finish(&request_id, &cancel);
+
+    // Final event so listeners can clean up regardless of how the stream ended
+    let _ = window.emit(GENERATION_EVENT, GenerationEvent {
+        request_id: request_id.clone(),
+        token: String::new(),
+        done: true,
+        cancelled: matches!(result, Err(AiError::Cancelled)),
+        error: result.as_ref().err().map(|e| e.to_string()),
+    });
+
+    result.map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn cancel_ai_generation(
+    state: State<'_, AppState>,
+    request_id: String,
+) -> AppResult<bool> {
+    Ok(state.ai_generations.cancel(&request_id))
+}
+
+#[tauri::command]
+async fn get_embedding_index_status(state: State<'_, AppState>) -> AppResult<EmbeddingIndexStatus> {
+    let embedding_indexer = state.embedding_indexer.clone();
+    blocking(move || {
+        embedding_indexer
+            .get_status()
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== Domain Commands (EarthSearch) ====================
 
 #[tauri::command]
 async fn get_domains(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<Domain>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .get_domains(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Domain>> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .get_domains(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn add_domain_entry(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     url: String,
     category: String,
     trust_score: f64,
     profile_id: i64,
-) -> Result<Domain, String> {
+) -> AppResult<Domain> {
     let domain = Domain {
         id: None,
         url,
@@ -315,65 +818,75 @@ async fn add_domain_entry(
         metadata: None,
         profile_id: Some(profile_id),
     };
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .add_domain(&domain, profile_id)
-        .map_err(|e| e.to_string())
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .add_domain(&domain, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_domain(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain: Domain,
-) -> Result<Domain, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .update_domain(&domain)
-        .map_err(|e| e.to_string())
+) -> AppResult<Domain> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .update_domain(&domain)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_domain_entry(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .delete_domain(domain_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .delete_domain(domain_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn search_domain_list(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     query: String,
-) -> Result<Vec<Domain>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .search_domains(profile_id, &query)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Domain>> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .search_domains(profile_id, &query)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_domain_lists(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<DomainList>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .get_lists(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<DomainList>> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .get_lists(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn create_domain_list(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     name: String,
     description: Option<String>,
     profile_id: i64,
-) -> Result<DomainList, String> {
+) -> AppResult<DomainList> {
     let list = DomainList {
         id: None,
         name,
@@ -384,475 +897,780 @@ async fn create_domain_list(
         profile_id: Some(profile_id),
         domain_count: None,
     };
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .create_list(&list, profile_id)
-        .map_err(|e| e.to_string())
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .create_list(&list, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_domain_list(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     list_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .delete_list(list_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .delete_list(list_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_domain_stats(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<DomainStats, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .get_stats(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<DomainStats> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .get_stats(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_domain_categories(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<String>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .get_categories(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<String>> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .get_categories(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn export_domains(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .export_domains(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .export_domains(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
+/// Starts a background task; the imported count arrives in its final task event
 #[tauri::command]
 async fn import_domains(
-    state: State<'_, Mutex<AppState>>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     profile_id: i64,
     json_data: String,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .import_domains(profile_id, &json_data)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    let search_manager = state.search_manager.clone();
+    Ok(state.tasks.spawn_blocking("import_domains", task_emitter(app), move |task| {
+        search_manager
+            .import_domains(profile_id, &json_data, task)
+    }))
 }
 
 // ==================== Memory Commands (EarthMemory) ====================
 
 #[tauri::command]
 async fn get_indexed_pages(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     limit: Option<i64>,
     offset: Option<i64>,
-) -> Result<Vec<IndexedPage>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .get_pages(profile_id, limit, offset)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<IndexedPage>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .get_pages(profile_id, limit, offset)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn index_page(
-    state: State<'_, Mutex<AppState>>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
     page: IndexedPage,
     profile_id: i64,
-) -> Result<IndexedPage, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .index_page(&page, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<IndexedPage> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    let entity_index = state.entity_index.clone();
+    let profile_manager = state.profile_manager.clone();
+    let (indexed, summaries_allowed) = blocking(move || {
+        let indexed = memory_manager
+            .index_page(&page, profile_id)
+            .map_err(AppError::from)?;
+        if let Some(page_id) = indexed.id {
+            // Heuristic extraction is cheap enough to keep the entity index current inline
+            if let Err(e) = entity_index.extract_indexed_page(page_id) {
+                eprintln!("Failed to extract entities for page {}: {}", page_id, e);
+            }
+        }
+        let allowed = profile_manager
+            .get_privacy_settings(profile_id)
+            .map(|settings| PageSummarizer::is_allowed(&settings))
+            .unwrap_or(false);
+        Ok((indexed, allowed))
+    }).await?;
+
+    // Summarize in the background so indexing stays fast; the frontend listens for the event
+    if let (true, Some(page_id)) = (summaries_allowed, indexed.id) {
+        let client = state.ai_client();
+        let summarizer = state.page_summarizer.clone();
+        tauri::async_runtime::spawn(async move {
+            match summarizer.summarize_page(&client, page_id, profile_id, false).await {
+                Ok(Some(summary)) => {
+                    let _ = window.emit(SUMMARY_EVENT, summary);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Failed to summarize page {}: {}", page_id, e),
+            }
+        });
+    }
+
+    Ok(indexed)
+}
+
+#[tauri::command]
+async fn get_page_summary(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+) -> AppResult<Option<PageSummary>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let page_summarizer = state.page_summarizer.clone();
+    blocking(move || {
+        page_summarizer
+            .get_summary(page_id, profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Summarize a page now, even if its content hasn't changed since the last summary
+#[tauri::command]
+async fn regenerate_page_summary(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+) -> AppResult<Option<PageSummary>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let profile_manager = state.profile_manager.clone();
+    let settings = blocking(move || {
+        profile_manager
+            .get_privacy_settings(profile_id)
+            .map_err(AppError::from)
+    }).await?;
+
+    if PrivacyManager::is_incognito() && !settings.ai_enabled_in_incognito {
+        return Err(AppError::Validation("AI features are disabled in incognito mode for this profile".to_string()));
+    }
+
+    state.page_summarizer
+        .summarize_page(&state.ai_client(), page_id, profile_id, true)
+        .await
 }
 
 #[tauri::command]
 async fn search_memory(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     query: String,
-) -> Result<Vec<IndexedPage>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .search_pages(profile_id, &query)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<IndexedPage>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .search_pages(profile_id, &query)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Full-text memory search: phrases, `tag:`, `site:`, `-exclude`, BM25 order, highlighted snippets
+#[tauri::command]
+async fn search_memory_paged(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    query: String,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> AppResult<MemorySearchResults> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .search(profile_id, &query, limit.unwrap_or(20), offset.unwrap_or(0))
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Answer a question from the profile's pages, notes and scraped pages, with citations
+#[tauri::command]
+async fn ask_memory(
+    state: State<'_, AppState>,
+    question: String,
+    profile_id: i64,
+) -> AppResult<MemoryAnswer> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let profile_manager = state.profile_manager.clone();
+    let settings = blocking(move || {
+        profile_manager
+            .get_privacy_settings(profile_id)
+            .map_err(AppError::from)
+    }).await?;
+
+    if PrivacyManager::is_incognito() && !settings.ai_enabled_in_incognito {
+        return Err(AppError::Validation("AI features are disabled in incognito mode for this profile".to_string()));
+    }
+
+    MemoryQa::new(state.db.clone())
+        .ask(&state.ai_client(), &question, profile_id)
+        .await
+}
+
+/// Extract entities from the profile's new or changed indexed and scraped pages.
+/// `use_llm` refines names with the local model (skipped in incognito mode unless the
+/// profile allows AI there).
+#[tauri::command]
+async fn extract_entities(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    limit: Option<i64>,
+    use_llm: Option<bool>,
+) -> AppResult<ExtractionReport> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let profile_manager = state.profile_manager.clone();
+    let settings = blocking(move || {
+        profile_manager
+            .get_privacy_settings(profile_id)
+            .map_err(AppError::from)
+    }).await?;
+
+    let client = state.ai_client();
+    let use_llm = use_llm.unwrap_or(false)
+        && (!PrivacyManager::is_incognito() || settings.ai_enabled_in_incognito)
+        && client.is_running().await;
+    state.entity_index
+        .extract_pending(profile_id, limit.unwrap_or(100), if use_llm { Some(&client) } else { None })
+        .await
+        .map_err(AppError::from)
+}
+
+#[tauri::command]
+async fn list_entities(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    kind: Option<EntityKind>,
+    query: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> AppResult<Vec<Entity>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let entity_index = state.entity_index.clone();
+    blocking(move || {
+        entity_index
+            .list_entities(profile_id, kind, query.as_deref(), limit.unwrap_or(100), offset.unwrap_or(0))
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn get_entity_pages(
+    state: State<'_, AppState>,
+    entity_id: i64,
+    profile_id: i64,
+) -> AppResult<Vec<EntityMention>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let entity_index = state.entity_index.clone();
+    blocking(move || {
+        entity_index
+            .get_entity_pages(entity_id, profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn merge_entities(
+    state: State<'_, AppState>,
+    target_id: i64,
+    duplicate_ids: Vec<i64>,
+    profile_id: i64,
+) -> AppResult<i64> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let entity_index = state.entity_index.clone();
+    blocking(move || {
+        entity_index
+            .merge_entities(target_id, &duplicate_ids, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_favorite_pages(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<IndexedPage>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .get_favorites(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<IndexedPage>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .get_favorites(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn toggle_page_favorite(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     page_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .toggle_favorite(page_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .toggle_favorite(page_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_page_tags(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     page_id: i64,
     profile_id: i64,
     tags: String,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .update_tags(page_id, profile_id, &tags)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .update_tags(page_id, profile_id, &tags)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Replace a page's summary, or clear it with null
+#[tauri::command]
+async fn update_page_summary(
+    state: State<'_, AppState>,
+    page_id: i64,
+    profile_id: i64,
+    summary: Option<String>,
+) -> AppResult<()> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .update_summary(page_id, profile_id, summary.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_indexed_page(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     page_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .delete_page(page_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .delete_page(page_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn add_page_note(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     page_id: i64,
     content: String,
     profile_id: i64,
-) -> Result<PageNote, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .add_note(page_id, &content, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<PageNote> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .add_note(page_id, &content, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_page_notes(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     page_id: i64,
-) -> Result<Vec<PageNote>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .get_page_notes(page_id)
-        .map_err(|e| e.to_string())
+    profile_id: i64,
+) -> AppResult<Vec<PageNote>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .get_page_notes(page_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_page_note(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     note_id: i64,
     content: String,
     profile_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .update_note(note_id, &content, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .update_note(note_id, &content, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_page_note(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     note_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .delete_note(note_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .delete_note(note_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_memory_stats(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<MemoryStats, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .get_stats(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<MemoryStats> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .get_stats(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_memory_tags(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<String>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .get_all_tags(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<String>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    blocking(move || {
+        memory_manager
+            .get_all_tags(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
+/// Starts a background task; the export JSON arrives in its final task event
 #[tauri::command]
 async fn export_memory(
-    state: State<'_, Mutex<AppState>>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .export_memory(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    Ok(state.tasks.spawn_blocking("export_memory", task_emitter(app), move |task| {
+        memory_manager
+            .export_memory(profile_id, task)
+            .map_err(AppError::from)
+    }))
 }
 
+/// Starts a background task; the imported count arrives in its final task event
 #[tauri::command]
 async fn import_memory(
-    state: State<'_, Mutex<AppState>>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     profile_id: i64,
     json_data: String,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.memory_manager
-        .import_memory(profile_id, &json_data)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let memory_manager = state.memory_manager.clone();
+    Ok(state.tasks.spawn_blocking("import_memory", task_emitter(app), move |task| {
+        memory_manager
+            .import_memory(profile_id, &json_data, task)
+    }))
 }
 
 // ==================== Domain Seeding Command ====================
 
 #[tauri::command]
 async fn seed_default_domains(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     resource_path: String,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.search_manager
-        .seed_default_domains(profile_id, std::path::Path::new(&resource_path))
-        .map_err(|e| e.to_string())
+) -> AppResult<i64> {
+    let search_manager = state.search_manager.clone();
+    blocking(move || {
+        search_manager
+            .seed_default_domains(profile_id, std::path::Path::new(&resource_path))
+    }).await
 }
 
 // ==================== Rating Commands ====================
 
 #[tauri::command]
 async fn submit_rating(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     rating: DomainRating,
-) -> Result<DomainRating, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .submit_rating(&rating)
-        .map_err(|e| e.to_string())
+) -> AppResult<DomainRating> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .submit_rating(&rating)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_user_rating(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain_id: i64,
     user_id: String,
-) -> Result<Option<DomainRating>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_user_rating(domain_id, &user_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<DomainRating>> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_user_rating(domain_id, &user_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_domain_ratings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain_id: i64,
     limit: Option<i64>,
-) -> Result<Vec<DomainRating>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_domain_ratings(domain_id, limit)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<DomainRating>> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_domain_ratings(domain_id, limit)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_rating(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     rating_id: i64,
     user_id: String,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .delete_rating(rating_id, &user_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .delete_rating(rating_id, &user_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_rating_aggregate(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain_id: i64,
-) -> Result<Option<RatingAggregate>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_aggregate(domain_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<RatingAggregate>> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_aggregate(domain_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_rating_summary(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     domain_id: i64,
     domain_url: String,
-) -> Result<RatingSummary, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_rating_summary(domain_id, &domain_url)
-        .map_err(|e| e.to_string())
+) -> AppResult<RatingSummary> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_rating_summary(domain_id, &domain_url)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn submit_subdomain_rating(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     parent_domain_id: i64,
     subdomain: String,
     trust: f64,
     bias: f64,
-) -> Result<SubdomainRating, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .submit_subdomain_rating(parent_domain_id, &subdomain, trust, bias)
-        .map_err(|e| e.to_string())
+) -> AppResult<SubdomainRating> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .submit_subdomain_rating(parent_domain_id, &subdomain, trust, bias)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_subdomain_ratings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     parent_domain_id: i64,
-) -> Result<Vec<SubdomainRating>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_subdomain_ratings(parent_domain_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<SubdomainRating>> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_subdomain_ratings(parent_domain_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn mark_rating_helpful(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     rating_id: i64,
-) -> Result<i32, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .mark_helpful(rating_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<i32> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .mark_helpful(rating_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn report_rating(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     rating_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .report_rating(rating_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .report_rating(rating_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_user_rating_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     user_id: String,
-) -> Result<UserRatingHistory, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .get_user_history(&user_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<UserRatingHistory> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .get_user_history(&user_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn add_rating_category_scores(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     rating_id: i64,
     categories: Vec<(String, i32)>,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.rating_manager
-        .add_category_scores(rating_id, categories)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let rating_manager = state.rating_manager.clone();
+    blocking(move || {
+        rating_manager
+            .add_category_scores(rating_id, categories)
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== Theme Commands ====================
 
 #[tauri::command]
 async fn get_themes(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<Theme>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .get_themes(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Theme>> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .get_themes(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_active_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Option<Theme>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .get_active_theme(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<Theme>> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .get_active_theme(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn save_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     theme: Theme,
-) -> Result<Theme, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .save_theme(&theme)
-        .map_err(|e| e.to_string())
+) -> AppResult<Theme> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .save_theme(&theme)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn set_active_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     theme_id: i64,
-) -> Result<Theme, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .set_active_theme(profile_id, theme_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Theme> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .set_active_theme(profile_id, theme_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     theme_id: i64,
     profile_id: i64,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .delete_theme(theme_id, profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .delete_theme(theme_id, profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn apply_preset_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     preset_id: String,
-) -> Result<Theme, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .apply_preset(profile_id, &preset_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Theme> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .apply_preset(profile_id, &preset_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
@@ -862,233 +1680,297 @@ fn get_theme_presets() -> Vec<PresetTheme> {
 
 #[tauri::command]
 async fn export_theme(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     theme_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.theme_manager
-        .export_theme(theme_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    let theme_manager = state.theme_manager.clone();
+    blocking(move || {
+        theme_manager
+            .export_theme(theme_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== Tab Commands ====================
 
 #[tauri::command]
 async fn create_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     url: String,
     title: Option<String>,
-) -> Result<Tab, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .create_tab(profile_id, &url, title.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Tab> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let tab_manager = state.tab_manager.clone();
+    blocking(move || {
+        tab_manager
+            .create_tab(profile_id, &url, title.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn close_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .close_tab(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .close_tab(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_all_tabs(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<Tab>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .get_all_tabs(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Tab>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let tab_manager = state.tab_manager.clone();
+    blocking(move || {
+        tab_manager
+            .get_all_tabs(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
     title: Option<String>,
     url: Option<String>,
     favicon: Option<String>,
-) -> Result<Tab, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .update_tab(tab_id, title.as_deref(), url.as_deref(), favicon.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Tab> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .update_tab(tab_id, title.as_deref(), url.as_deref(), favicon.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn reorder_tabs(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_ids: Vec<i64>,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .reorder_tabs(tab_ids)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let tab_manager = state.tab_manager.clone();
+    blocking(move || {
+        tab_manager
+            .reorder_tabs(tab_ids)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn pin_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
     pinned: bool,
-) -> Result<Tab, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .pin_tab(tab_id, pinned)
-        .map_err(|e| e.to_string())
+) -> AppResult<Tab> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .pin_tab(tab_id, pinned)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn set_active_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<Tab, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .set_active_tab(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Tab> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .set_active_tab(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_tab_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<Vec<TabHistoryEntry>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .get_tab_history(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<TabHistoryEntry>> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .get_tab_history(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn navigate_tab_back(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<Option<String>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .navigate_back(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<String>> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .navigate_back(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn navigate_tab_forward(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<Option<String>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .navigate_forward(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<String>> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .navigate_forward(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn duplicate_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<Tab, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .duplicate_tab(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Tab> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .duplicate_tab(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn close_tabs_to_right(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     tab_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .close_tabs_to_right(tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let tab_manager = state.tab_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(tab_manager.get_tab(tab_id)?.profile_id)?;
+        tab_manager
+            .close_tabs_to_right(tab_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn close_unpinned_tabs(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.tab_manager
-        .close_unpinned_tabs(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let tab_manager = state.tab_manager.clone();
+    blocking(move || {
+        tab_manager
+            .close_unpinned_tabs(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== Bookmark Commands ====================
 
 #[tauri::command]
 async fn add_bookmark(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     title: String,
     url: String,
     folder_id: Option<i64>,
     tags: Vec<String>,
     notes: Option<String>,
-) -> Result<Bookmark, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .add_bookmark(profile_id, &title, &url, folder_id, tags, notes.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Bookmark> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .add_bookmark(profile_id, &title, &url, folder_id, tags, notes.as_deref())
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_bookmark(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     bookmark_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .delete_bookmark(bookmark_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let bookmark_manager = state.bookmark_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(bookmark_manager.get_bookmark(bookmark_id)?.profile_id)?;
+        bookmark_manager
+            .delete_bookmark(bookmark_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_all_bookmarks(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<Bookmark>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .get_all_bookmarks(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Bookmark>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .get_all_bookmarks(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_bookmarks_by_folder(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     folder_id: Option<i64>,
-) -> Result<Vec<Bookmark>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .get_bookmarks_by_folder(profile_id, folder_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Bookmark>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .get_bookmarks_by_folder(profile_id, folder_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn search_bookmarks(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     query: String,
-) -> Result<Vec<Bookmark>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .search_bookmarks(profile_id, &query)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Bookmark>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .search_bookmarks(profile_id, &query)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_bookmark(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     bookmark_id: i64,
     title: Option<String>,
     url: Option<String>,
@@ -1096,432 +1978,788 @@ async fn update_bookmark(
     tags: Option<Vec<String>>,
     notes: Option<Option<String>>,
     favicon: Option<String>,
-) -> Result<Bookmark, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .update_bookmark(
-            bookmark_id,
-            title.as_deref(),
-            url.as_deref(),
-            folder_id,
-            tags,
-            notes.as_ref().map(|n| n.as_deref()),
-            favicon.as_deref(),
-        )
-        .map_err(|e| e.to_string())
+) -> AppResult<Bookmark> {
+    let bookmark_manager = state.bookmark_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(bookmark_manager.get_bookmark(bookmark_id)?.profile_id)?;
+        bookmark_manager
+            .update_bookmark(
+                bookmark_id,
+                title.as_deref(),
+                url.as_deref(),
+                folder_id,
+                tags,
+                notes.as_ref().map(|n| n.as_deref()),
+                favicon.as_deref(),
+            )
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn is_url_bookmarked(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     url: String,
-) -> Result<Option<i64>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .is_bookmarked(profile_id, &url)
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<i64>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .is_bookmarked(profile_id, &url)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn create_bookmark_folder(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     name: String,
     parent_id: Option<i64>,
-) -> Result<BookmarkFolder, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .create_folder(profile_id, &name, parent_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<BookmarkFolder> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .create_folder(profile_id, &name, parent_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_bookmark_folders(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<BookmarkFolder>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .get_all_folders(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<BookmarkFolder>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        bookmark_manager
+            .get_all_folders(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_bookmark_folder(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     folder_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .delete_folder(folder_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let bookmark_manager = state.bookmark_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(bookmark_manager.get_folder(folder_id)?.profile_id)?;
+        bookmark_manager
+            .delete_folder(folder_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn rename_bookmark_folder(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     folder_id: i64,
     name: String,
-) -> Result<BookmarkFolder, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.bookmark_manager
-        .rename_folder(folder_id, &name)
-        .map_err(|e| e.to_string())
+) -> AppResult<BookmarkFolder> {
+    let bookmark_manager = state.bookmark_manager.clone();
+    let profile_locks = state.profile_locks.clone();
+    blocking(move || {
+        profile_locks.ensure_unlocked(bookmark_manager.get_folder(folder_id)?.profile_id)?;
+        bookmark_manager
+            .rename_folder(folder_id, &name)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn export_bookmarks(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     format: String,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    match format.as_str() {
-        "html" => state.bookmark_manager
-            .export_bookmarks_html(profile_id)
-            .map_err(|e| e.to_string()),
-        _ => state.bookmark_manager
-            .export_bookmarks_json(profile_id)
-            .map_err(|e| e.to_string()),
-    }
-}
-
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    blocking(move || {
+        match format.as_str() {
+            "html" => bookmark_manager
+                .export_bookmarks_html(profile_id)
+                .map_err(AppError::from),
+            _ => bookmark_manager
+                .export_bookmarks_json(profile_id)
+                .map_err(AppError::from),
+        }
+    }).await
+}
+
+/// Starts a background task; the imported count arrives in its final task event
 #[tauri::command]
 async fn import_bookmarks(
-    state: State<'_, Mutex<AppState>>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
     profile_id: i64,
     data: String,
     format: String,
-) -> Result<i32, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    match format.as_str() {
-        "html" => state.bookmark_manager
-            .import_bookmarks_html(profile_id, &data)
-            .map_err(|e| e.to_string()),
-        _ => state.bookmark_manager
-            .import_bookmarks_json(profile_id, &data)
-            .map_err(|e| e.to_string()),
-    }
+) -> AppResult<String> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let bookmark_manager = state.bookmark_manager.clone();
+    Ok(state.tasks.spawn_blocking("import_bookmarks", task_emitter(app), move |task| {
+        match format.as_str() {
+            "html" => bookmark_manager
+                .import_bookmarks_html(profile_id, &data, task)
+                .map_err(AppError::from),
+            _ => bookmark_manager
+                .import_bookmarks_json(profile_id, &data, task),
+        }
+    }))
 }
 
 // ==================== Split View Commands ====================
 
 #[tauri::command]
 async fn get_split_config(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .get_config(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .get_config(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn set_split_layout(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     layout: String,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .set_layout(profile_id, &layout)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .set_layout(profile_id, &layout)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn set_pane_tab(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     pane_number: i32,
     tab_id: Option<i64>,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .set_pane_tab(profile_id, pane_number, tab_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .set_pane_tab(profile_id, pane_number, tab_id)
+    }).await
 }
 
 #[tauri::command]
 async fn set_active_pane(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     pane_number: i32,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .set_active_pane(profile_id, pane_number)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .set_active_pane(profile_id, pane_number)
+    }).await
 }
 
 #[tauri::command]
 async fn cycle_pane(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     direction: i32,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .cycle_pane(profile_id, direction)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .cycle_pane(profile_id, direction)
+    }).await
 }
 
 #[tauri::command]
 async fn update_pane_sizes(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     sizes: PaneSizes,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .update_pane_sizes(profile_id, sizes)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .update_pane_sizes(profile_id, sizes)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn swap_panes(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     pane_a: i32,
     pane_b: i32,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .swap_panes(profile_id, pane_a, pane_b)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .swap_panes(profile_id, pane_a, pane_b)
+    }).await
 }
 
 #[tauri::command]
 async fn reset_split_view(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<SplitViewConfig, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.split_view_manager
-        .reset_to_single(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<SplitViewConfig> {
+    let split_view_manager = state.split_view_manager.clone();
+    blocking(move || {
+        split_view_manager
+            .reset_to_single(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 // ==================== EarthMultiMedia Commands ====================
 
 #[tauri::command]
 async fn get_media_privacy_settings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<MediaPrivacySettings, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .get_privacy_settings(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<MediaPrivacySettings> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .get_privacy_settings(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn update_media_privacy_settings(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     settings: MediaPrivacySettings,
-) -> Result<MediaPrivacySettings, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .update_privacy_settings(&settings)
-        .map_err(|e| e.to_string())
+) -> AppResult<MediaPrivacySettings> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .update_privacy_settings(&settings)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn set_media_password(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     password: String,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .set_password(profile_id, &password)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .set_password(profile_id, &password)
+    }).await
 }
 
 #[tauri::command]
 async fn verify_media_password(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     password: String,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .verify_password(profile_id, &password)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .verify_password(profile_id, &password)
+    }).await
 }
 
 #[tauri::command]
 async fn generate_media_otp_secret(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<String, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .generate_otp_secret(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<String> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .generate_otp_secret(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn verify_media_otp(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     code: String,
-) -> Result<bool, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .verify_otp(profile_id, &code)
-        .map_err(|e| e.to_string())
+) -> AppResult<bool> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .verify_otp(profile_id, &code)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn add_media_history_entry(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     entry: MediaHistoryEntry,
     password: Option<String>,
-) -> Result<Option<MediaHistoryEntry>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .add_history_entry(&entry, password.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Option<MediaHistoryEntry>> {
+    state.profile_locks.ensure_unlocked(entry.profile_id)?;
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .add_history_entry(&entry, password.as_deref())
+    }).await
 }
 
 #[tauri::command]
 async fn get_media_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     limit: i32,
     password: Option<String>,
-) -> Result<Vec<MediaHistoryEntry>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .get_history(profile_id, limit, password.as_deref())
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<MediaHistoryEntry>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .get_history(profile_id, limit, password.as_deref())
+    }).await
 }
 
 #[tauri::command]
 async fn clear_media_history(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<i32, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .clear_history(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<i32> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .clear_history(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_media_history_entry(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     entry_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .delete_history_entry(entry_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .delete_history_entry(entry_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn create_media_playlist(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     name: String,
     description: Option<String>,
     encrypted: bool,
-) -> Result<Playlist, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .create_playlist(profile_id, &name, description.as_deref(), encrypted)
-        .map_err(|e| e.to_string())
+) -> AppResult<Playlist> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .create_playlist(profile_id, &name, description.as_deref(), encrypted)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_media_playlists(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<Playlist>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .get_playlists(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<Playlist>> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .get_playlists(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_media_playlist(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     playlist_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .delete_playlist(playlist_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .delete_playlist(playlist_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn add_to_media_playlist(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     playlist_id: i64,
     source: String,
     media_type: String,
     title: Option<String>,
     thumbnail: Option<String>,
-) -> Result<PlaylistItem, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .add_to_playlist(playlist_id, &source, &media_type, title.as_deref(), thumbnail.as_deref())
-        .map_err(|e| e.to_string())
+    password: Option<String>,
+) -> AppResult<PlaylistItem> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .add_to_playlist(playlist_id, &source, &media_type, title.as_deref(), thumbnail.as_deref(), password.as_deref())
+    }).await
 }
 
 #[tauri::command]
 async fn get_media_playlist_items(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     playlist_id: i64,
-) -> Result<Vec<PlaylistItem>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .get_playlist_items(playlist_id)
-        .map_err(|e| e.to_string())
+    password: Option<String>,
+) -> AppResult<Vec<PlaylistItem>> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .get_playlist_items(playlist_id, password.as_deref())
+    }).await
 }
 
 #[tauri::command]
 async fn remove_from_media_playlist(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     item_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .remove_from_playlist(item_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .remove_from_playlist(item_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn reorder_media_playlist_items(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     playlist_id: i64,
     item_ids: Vec<i64>,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .reorder_playlist_items(playlist_id, item_ids)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .reorder_playlist_items(playlist_id, item_ids)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_media_stats(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<MediaStats, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.multimedia_manager
-        .get_stats(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<MediaStats> {
+    let multimedia_manager = state.multimedia_manager.clone();
+    blocking(move || {
+        multimedia_manager
+            .get_stats(profile_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+// ==================== Password Manager Commands ====================
+
+#[tauri::command]
+async fn has_password_manager_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<bool> {
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.has_master(profile_id)).await
+}
+
+/// Create the vault, or change its master password (`current_password` required then)
+#[tauri::command]
+async fn set_password_manager_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    password: String,
+    current_password: Option<String>,
+) -> AppResult<()> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.set_master(profile_id, &password, current_password.as_deref())).await
+}
+
+/// Unlocks the vault; false for a wrong master password
+#[tauri::command]
+async fn verify_password_manager_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    password: String,
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.unlock(profile_id, &password)).await
+}
+
+#[tauri::command]
+fn lock_password_manager(state: State<'_, AppState>, profile_id: i64) {
+    state.password_vault.lock(profile_id);
+}
+
+#[tauri::command]
+async fn get_password_entries(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<Vec<PasswordEntry>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.get_entries(profile_id)).await
+}
+
+#[tauri::command]
+async fn search_password_entries(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    query: String,
+) -> AppResult<Vec<PasswordEntry>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.search(profile_id, &query)).await
+}
+
+#[tauri::command]
+async fn add_password_entry(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    title: String,
+    username: String,
+    password: String,
+    url: Option<String>,
+    notes: Option<String>,
+    category: Option<String>,
+    custom_fields: Option<Vec<CustomField>>,
+) -> AppResult<PasswordEntry> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    let input = PasswordEntryInput { title, username, password, url, notes, category, custom_fields };
+    blocking(move || password_vault.add_entry(profile_id, &input)).await
+}
+
+/// Leaving out `custom_fields` keeps the entry's existing ones
+#[tauri::command]
+async fn update_password_entry(
+    state: State<'_, AppState>,
+    entry_id: i64,
+    title: String,
+    username: String,
+    password: String,
+    url: Option<String>,
+    notes: Option<String>,
+    category: Option<String>,
+    custom_fields: Option<Vec<CustomField>>,
+) -> AppResult<PasswordEntry> {
+    let password_vault = state.password_vault.clone();
+    let input = PasswordEntryInput { title, username, password, url, notes, category, custom_fields };
+    blocking(move || password_vault.update_entry(entry_id, &input)).await
+}
+
+#[tauri::command]
+async fn delete_password_entry(
+    state: State<'_, AppState>,
+    entry_id: i64,
+) -> AppResult<()> {
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.delete_entry(entry_id)).await
+}
+
+/// Import a CSV export from another password manager or browser
+#[tauri::command]
+async fn import_password_csv(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    csv: String,
+) -> AppResult<CsvImportReport> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let password_vault = state.password_vault.clone();
+    blocking(move || password_vault.import_csv(profile_id, &csv)).await
+}
+
+#[tauri::command]
+fn generate_password(options: Option<PasswordGeneratorOptions>) -> AppResult<String> {
+    password_vault::generate_password(&options.unwrap_or_default())
+}
+
+/// Offline estimate; the password is never sent anywhere
+#[tauri::command]
+fn estimate_password_strength(password: String) -> PasswordStrength {
+    password_vault::estimate_strength(&password)
+}
+
+// ==================== Authenticator Commands ====================
+
+#[tauri::command]
+async fn has_otp_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<bool> {
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.has_master(profile_id)).await
+}
+
+/// Create the authenticator, or change its master password (`current_password` required then)
+#[tauri::command]
+async fn set_otp_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    password: String,
+    current_password: Option<String>,
+) -> AppResult<()> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.set_master(profile_id, &password, current_password.as_deref())).await
+}
+
+/// Unlocks the authenticator; false for a wrong master password
+#[tauri::command]
+async fn verify_otp_master(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    password: String,
+) -> AppResult<bool> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.unlock(profile_id, &password)).await
+}
+
+#[tauri::command]
+fn lock_otp(state: State<'_, AppState>, profile_id: i64) {
+    state.otp_manager.lock(profile_id);
+}
+
+#[tauri::command]
+async fn get_otp_entries(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<Vec<OtpEntry>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.get_entries(profile_id)).await
+}
+
+/// Options left out default to TOTP, SHA1, 6 digits and 30 seconds
+#[tauri::command]
+async fn add_otp_entry(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    name: String,
+    issuer: Option<String>,
+    secret: String,
+    kind: Option<OtpKind>,
+    algorithm: Option<OtpAlgorithm>,
+    digits: Option<u32>,
+    period: Option<u64>,
+    counter: Option<u64>,
+) -> AppResult<OtpEntry> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    let input = OtpEntryInput { name, issuer, secret, kind, algorithm, digits, period, counter };
+    blocking(move || otp_manager.add_entry(profile_id, &input)).await
+}
+
+/// Options left out keep the entry's current values
+#[tauri::command]
+async fn update_otp_entry(
+    state: State<'_, AppState>,
+    entry_id: i64,
+    name: String,
+    issuer: Option<String>,
+    secret: String,
+    kind: Option<OtpKind>,
+    algorithm: Option<OtpAlgorithm>,
+    digits: Option<u32>,
+    period: Option<u64>,
+    counter: Option<u64>,
+) -> AppResult<OtpEntry> {
+    let otp_manager = state.otp_manager.clone();
+    let input = OtpEntryInput { name, issuer, secret, kind, algorithm, digits, period, counter };
+    blocking(move || otp_manager.update_entry(entry_id, &input)).await
+}
+
+#[tauri::command]
+async fn delete_otp_entry(
+    state: State<'_, AppState>,
+    entry_id: i64,
+) -> AppResult<()> {
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.delete_entry(entry_id)).await
+}
+
+/// Current TOTP codes with the seconds left on each
+#[tauri::command]
+async fn get_otp_codes(
+    state: State<'_, AppState>,
+    profile_id: i64,
+) -> AppResult<Vec<OtpCode>> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.get_codes(profile_id)).await
+}
+
+/// The next code of an HOTP entry; advances its counter
+#[tauri::command]
+async fn next_hotp_code(
+    state: State<'_, AppState>,
+    entry_id: i64,
+) -> AppResult<OtpCode> {
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.next_hotp_code(entry_id)).await
+}
+
+#[tauri::command]
+async fn verify_otp_code(
+    state: State<'_, AppState>,
+    entry_id: i64,
+    code: String,
+) -> AppResult<bool> {
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.verify_code(entry_id, &code)).await
+}
+
+/// Add an entry from an otpauth:// URI, e.g. a scanned setup QR code
+#[tauri::command]
+async fn import_otp_uri(
+    state: State<'_, AppState>,
+    profile_id: i64,
+    uri: String,
+) -> AppResult<OtpEntry> {
+    state.profile_locks.ensure_unlocked(profile_id)?;
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.import_uri(profile_id, &uri)).await
+}
+
+#[tauri::command]
+async fn export_otp_uri(
+    state: State<'_, AppState>,
+    entry_id: i64,
+) -> AppResult<String> {
+    let otp_manager = state.otp_manager.clone();
+    blocking(move || otp_manager.export_uri(entry_id)).await
 }
 
 // ==================== Web Scraper Commands ====================
 
 #[tauri::command]
 async fn create_scraping_job(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     name: String,
     base_url: String,
@@ -1529,77 +2767,244 @@ async fn create_scraping_job(
     max_depth: i32,
     max_pages: i32,
     content_selectors: Vec<ContentSelector>,
-) -> Result<i64, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .create_job(
-            profile_id,
-            &name,
-            &base_url,
-            url_pattern.as_deref(),
-            max_depth,
-            max_pages,
-            content_selectors,
-        )
-        .map_err(|e| e.to_string())
+) -> AppResult<i64> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .create_job(
+                profile_id,
+                &name,
+                &base_url,
+                url_pattern.as_deref(),
+                max_depth,
+                max_pages,
+                content_selectors,
+            )
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_scraping_jobs(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
-) -> Result<Vec<ScrapingJob>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .get_jobs(profile_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<ScrapingJob>> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .get_jobs(profile_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_scraping_job(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     job_id: i64,
-) -> Result<ScrapingJob, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .get_job(job_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<ScrapingJob> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .get_job(job_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn delete_scraping_job(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     job_id: i64,
-) -> Result<(), String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .delete_job(job_id)
-        .map_err(|e| e.to_string())
+) -> AppResult<()> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .delete_job(job_id)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn get_scraped_pages(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     job_id: i64,
     limit: i32,
-) -> Result<Vec<ScrapedPage>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .get_pages(job_id, limit)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<ScrapedPage>> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .get_pages(job_id, limit)
+            .map_err(AppError::from)
+    }).await
 }
 
 #[tauri::command]
 async fn search_scraped_content(
-    state: State<'_, Mutex<AppState>>,
+    state: State<'_, AppState>,
     profile_id: i64,
     query: String,
     limit: i32,
-) -> Result<Vec<ScrapedPage>, String> {
-    let state = state.lock().map_err(|e| e.to_string())?;
-    state.scraper_manager
-        .search_content(profile_id, &query, limit)
-        .map_err(|e| e.to_string())
+) -> AppResult<Vec<ScrapedPage>> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .search_content(profile_id, &query, limit)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Crawl a job in the background; the pages scraped arrive in its final task event
+#[tauri::command]
+async fn run_scraping_job(
+    app: tauri::AppHandle,
+    job_id: i64,
+) -> AppResult<String> {
+    start_scraping_run(app, job_id, "manual")
+}
+
+/// Pause a running job after the page it is on
+#[tauri::command]
+async fn pause_scraping_job(
+    state: State<'_, AppState>,
+    job_id: i64,
+) -> AppResult<()> {
+    state.scraper_manager.pause_job(job_id)
+}
+
+#[tauri::command]
+async fn resume_scraping_job(
+    state: State<'_, AppState>,
+    job_id: i64,
+) -> AppResult<()> {
+    state.scraper_manager.resume_job(job_id)
+}
+
+/// Stop a running or paused job; false if it wasn't running
+#[tauri::command]
+async fn cancel_scraping_job(
+    state: State<'_, AppState>,
+    job_id: i64,
+) -> AppResult<bool> {
+    Ok(state
+        .scraper_manager
+        .running_task(job_id)
+        .is_some_and(|task_id| state.tasks.cancel(&task_id)))
+}
+
+/// Set a cron schedule such as "0 6 * * *", or clear it with null
+#[tauri::command]
+async fn set_scraping_schedule(
+    state: State<'_, AppState>,
+    job_id: i64,
+    schedule_cron: Option<String>,
+) -> AppResult<ScrapingJob> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || scraper_manager.set_schedule(job_id, schedule_cron.as_deref())).await
+}
+
+/// Set the job's user agent, robots.txt handling, request delay, retries and sitemap use
+#[tauri::command]
+async fn set_scraping_settings(
+    state: State<'_, AppState>,
+    job_id: i64,
+    settings: CrawlSettings,
+) -> AppResult<ScrapingJob> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || scraper_manager.set_settings(job_id, settings)).await
+}
+
+#[tauri::command]
+async fn get_scraping_runs(
+    state: State<'_, AppState>,
+    job_id: i64,
+    limit: i32,
+) -> AppResult<Vec<ScrapingRun>> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .get_runs(job_id, limit)
+            .map_err(AppError::from)
+    }).await
+}
+
+/// Stored versions of a scraped page, newest first
+#[tauri::command]
+async fn get_page_versions(
+    state: State<'_, AppState>,
+    page_id: i64,
+) -> AppResult<Vec<PageVersion>> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || {
+        scraper_manager
+            .get_page_versions(page_id)
+            .map_err(AppError::from)
+    }).await
+}
+
+#[tauri::command]
+async fn diff_page_versions(
+    state: State<'_, AppState>,
+    from_id: i64,
+    to_id: i64,
+) -> AppResult<PageDiff> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || scraper_manager.diff_page_versions(from_id, to_id)).await
+}
+
+/// Get a notification whenever a run finds the page changed
+#[tauri::command]
+async fn set_page_watch(
+    state: State<'_, AppState>,
+    page_id: i64,
+    watch: bool,
+) -> AppResult<()> {
+    let scraper_manager = state.scraper_manager.clone();
+    blocking(move || scraper_manager.set_page_watch(page_id, watch)).await
+}
+
+/// Start a job as a background task; steps go out as SCRAPER_PROGRESS_EVENT, and
+/// watched pages that changed as SCRAPER_PAGE_CHANGED_EVENT and a desktop notification
+fn start_scraping_run(app: tauri::AppHandle, job_id: i64, trigger: &'static str) -> AppResult<String> {
+    let state = app.state::<AppState>();
+    // Claimed before the task is spawned, so a manual start and the schedule can't both run it
+    let claim = state.scraper_manager.claim_job(job_id)?;
+    let scraper_manager = state.scraper_manager.clone();
+    let progress_handle = app.clone();
+    let change_handle = app.clone();
+    Ok(state.tasks.spawn("run_scraping_job", task_emitter(app.clone()), move |task| async move {
+        scraper_manager
+            .run_claimed(
+                claim,
+                trigger,
+                &task,
+                move |status| {
+                    let _ = progress_handle.emit_all(SCRAPER_PROGRESS_EVENT, status);
+                },
+                move |change| {
+                    let _ = tauri::api::notification::Notification::new(&change_handle.config().tauri.bundle.identifier)
+                        .title(format!("Page changed: {}", change.title.as_deref().unwrap_or(&change.url)))
+                        .body(format!("{} words added, {} removed", change.words_added, change.words_removed))
+                        .show();
+                    let _ = change_handle.emit_all(SCRAPER_PAGE_CHANGED_EVENT, change);
+                },
+            )
+            .await
+    }))
+}
+
+// ==================== Task Commands ====================
+
+/// Ask a running task to stop; it finishes with status "cancelled"
+#[tauri::command]
+async fn cancel_task(
+    state: State<'_, AppState>,
+    task_id: String,
+) -> AppResult<bool> {
+    Ok(state.tasks.cancel(&task_id))
+}
+
+#[tauri::command]
+async fn list_tasks(state: State<'_, AppState>) -> AppResult<Vec<TaskInfo>> {
+    Ok(state.tasks.list())
 }
 
 // ==================== Legacy Commands (for compatibility) ====================
@@ -1610,17 +3015,17 @@ fn greet(name: &str) -> String {
 }
 
 #[tauri::command]
-async fn search_domains(query: String) -> Result<String, String> {
+async fn search_domains(query: String) -> AppResult<String> {
     Ok(format!("Searching for: {}", query))
 }
 
 #[tauri::command]
-async fn add_domain(domain: String) -> Result<String, String> {
+async fn add_domain(domain: String) -> AppResult<String> {
     Ok(format!("Added domain: {}", domain))
 }
 
 #[tauri::command]
-async fn query_knowledge_graph(query: String) -> Result<String, String> {
+async fn query_knowledge_graph(query: String) -> AppResult<String> {
     Ok(format!("Knowledge graph query: {}", query))
 }
 
@@ -1641,43 +3046,65 @@ fn main() {
             let db_path = app_dir.join("earthservers.db");
             let db_path_str = db_path.to_string_lossy().to_string();
 
+            // Create or upgrade the schema before any manager touches the database
+            let migration = migrations::run(&db_path_str).expect("Failed to migrate database");
+            if !migration.applied.is_empty() {
+                println!(
+                    "Migrated database from schema v{} to v{} (backup: {:?})",
+                    migration.from_version, migration.to_version, migration.backup_path
+                );
+            }
+
+            // One pool shared by every manager
+            let db = DbPool::open(&db_path_str).expect("Failed to open database");
+
             // Initialize managers
-            let profile_manager = ProfileManager::new(db_path_str.clone());
-            let privacy_manager = PrivacyManager::new(db_path_str.clone());
-            let knowledge_graph = KnowledgeGraph::new(db_path_str.clone());
-            let theme_manager = ThemeManager::new(db_path_str.clone());
-            let search_manager = SearchManager::new(db_path_str.clone());
-            let memory_manager = MemoryManager::new(db_path_str.clone());
-            let rating_manager = RatingManager::new(db_path_str.clone());
-            let tab_manager = TabManager::new(db_path_str.clone());
-            let bookmark_manager = BookmarkManager::new(db_path_str.clone());
-            let split_view_manager = SplitViewManager::new(db_path_str.clone());
-            let multimedia_manager = MultimediaManager::new(db_path_str.clone());
-            let scraper_manager = ScraperManager::new(db_path_str.clone());
-
-            // Initialize database tables
-            profile_manager.init().expect("Failed to initialize profile tables");
-            knowledge_graph.init().expect("Failed to initialize knowledge graph");
-            theme_manager.init().expect("Failed to initialize theme tables");
-            search_manager.init().expect("Failed to initialize search tables");
-            memory_manager.init().expect("Failed to initialize memory tables");
-
-            // Seed default domains for the active profile
+            let profile_manager = ProfileManager::new(db.clone());
+            let profile_locks = ProfileLockManager::new(db.clone());
+            let password_vault = PasswordVault::new(db.clone());
+            let otp_manager = OtpManager::new(db.clone());
+            let privacy_manager = PrivacyManager::new(db.clone());
+            let knowledge_graph = KnowledgeGraph::new(db.clone());
+            let theme_manager = ThemeManager::new(db.clone());
+            let search_manager = SearchManager::new(db.clone());
+            let memory_manager = MemoryManager::new(db.clone());
+            let rating_manager = RatingManager::new(db.clone());
+            let tab_manager = TabManager::new(db.clone());
+            let bookmark_manager = BookmarkManager::new(db.clone());
+            let split_view_manager = SplitViewManager::new(db.clone());
+            let multimedia_manager = MultimediaManager::new(db.clone());
+            let scraper_manager = ScraperManager::new(db.clone());
+            let backup_manager = BackupManager::new(db.clone());
+            let embedding_indexer = EmbeddingIndexer::new(db.clone());
+            let page_summarizer = PageSummarizer::new(db.clone());
+            let entity_index = EntityIndex::new(db.clone());
+
+            profile_manager.init().expect("Failed to create default profile");
+            profile_locks.init().expect("Failed to load profile encryption state");
+            scraper_manager.init().expect("Failed to reset interrupted scraper runs");
+
+            // Seed default domains for the active profile without holding up the window
             if let Ok(Some(active_profile)) = profile_manager.get_active_profile() {
                 // Get resource directory
                 if let Some(resource_dir) = app.path_resolver().resource_dir() {
-                    if let Ok(imported) = search_manager.seed_default_domains(active_profile.id.unwrap_or(1), &resource_dir) {
-                        if imported > 0 {
-                            println!("Seeded {} default domains", imported);
+                    let search_manager = search_manager.clone();
+                    tauri::async_runtime::spawn_blocking(move || {
+                        if let Ok(imported) = search_manager.seed_default_domains(active_profile.id.unwrap_or(1), &resource_dir) {
+                            if imported > 0 {
+                                println!("Seeded {} default domains", imported);
+                            }
                         }
-                    }
+                    });
                 }
             }
 
             // Store state
             let state = AppState {
-                db_path: db_path_str,
+                db,
                 profile_manager,
+                profile_locks: profile_locks.clone(),
+                password_vault: password_vault.clone(),
+                otp_manager: otp_manager.clone(),
                 privacy_manager,
                 knowledge_graph,
                 theme_manager,
@@ -1689,9 +3116,69 @@ fn main() {
                 split_view_manager,
                 multimedia_manager,
                 scraper_manager,
+                backup_manager,
+                ai_client: RwLock::new(OllamaClient::new()),
+                ai_generations: GenerationRegistry::new(),
+                embedding_indexer: embedding_indexer.clone(),
+                page_summarizer,
+                entity_index,
+                tasks: TaskRegistry::new(),
             };
 
-            app.manage(Mutex::new(state));
+            app.manage(state);
+
+            // Lock profiles idle past their auto-lock setting, and password and authenticator
+            // vaults idle for 15 minutes
+            let lock_handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    let locks = profile_locks.clone();
+                    let locked = tauri::async_runtime::spawn_blocking(move || locks.lock_idle())
+                        .await
+                        .unwrap_or_default();
+                    for profile_id in locked {
+                        let _ = lock_handle.emit_all(PROFILE_LOCK_EVENT, profile_locks.status(profile_id));
+                    }
+                    password_vault.lock_idle();
+                    otp_manager.lock_idle();
+                }
+            });
+
+            // Start scheduled scraping jobs as they come due
+            let schedule_handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+                loop {
+                    interval.tick().await;
+                    let scraper_manager = schedule_handle.state::<AppState>().scraper_manager.clone();
+                    let due = blocking(move || scraper_manager.due_jobs(chrono::Utc::now()))
+                        .await
+                        .unwrap_or_default();
+                    for job_id in due {
+                        if let Err(e) = start_scraping_run(schedule_handle.clone(), job_id, "schedule") {
+                            eprintln!("Failed to start scheduled scraping job {}: {}", job_id, e);
+                        }
+                    }
+                }
+            });
+
+            // Backfill missing embeddings in the background; always reads the current AI config
+            let handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                let client_handle = handle.clone();
+                embedding_indexer
+                    .run(
+                        move || {
+                            Some(client_handle.state::<AppState>().ai_client())
+                        },
+                        move |progress| {
+                            let _ = handle.emit_all(INDEXER_PROGRESS_EVENT, progress);
+                        },
+                    )
+                    .await;
+            });
 
             Ok(())
         })
@@ -1706,6 +3193,18 @@ fn main() {
             get_privacy_settings,
             update_privacy_settings,
             export_profile,
+            // Profile encryption commands
+            get_profile_lock_status,
+            enable_profile_encryption,
+            disable_profile_encryption,
+            lock_profile,
+            unlock_profile,
+            rekey_profile,
+            set_profile_auto_lock,
+            // Backup commands
+            create_profile_backup,
+            inspect_backup,
+            restore_profile_backup,
             // Incognito commands
             get_incognito_status,
             toggle_incognito,
@@ -1720,6 +3219,23 @@ fn main() {
             // Knowledge graph commands
             add_page,
             search_knowledge_graph,
+            semantic_search,
+            find_similar_pages,
+            get_page_neighbors,
+            get_page_backlinks,
+            find_graph_path,
+            get_graph_clusters,
+            link_pages,
+            unlink_pages,
+            export_knowledge_graph,
+            // AI commands
+            get_ai_status,
+            update_ai_config,
+            generate_embedding,
+            ai_generate,
+            ai_generate_stream,
+            cancel_ai_generation,
+            get_embedding_index_status,
             // Theme commands
             get_themes,
             get_active_theme,
@@ -1746,9 +3262,18 @@ fn main() {
             get_indexed_pages,
             index_page,
             search_memory,
+            search_memory_paged,
+            get_page_summary,
+            regenerate_page_summary,
+            ask_memory,
+            extract_entities,
+            list_entities,
+            get_entity_pages,
+            merge_entities,
             get_favorite_pages,
             toggle_page_favorite,
             update_page_tags,
+            update_page_summary,
             delete_indexed_page,
             add_page_note,
             get_page_notes,
@@ -1829,6 +3354,33 @@ fn main() {
             remove_from_media_playlist,
             reorder_media_playlist_items,
             get_media_stats,
+            // Password Manager commands
+            has_password_manager_master,
+            set_password_manager_master,
+            verify_password_manager_master,
+            lock_password_manager,
+            get_password_entries,
+            search_password_entries,
+            add_password_entry,
+            update_password_entry,
+            delete_password_entry,
+            import_password_csv,
+            generate_password,
+            estimate_password_strength,
+            // Authenticator commands
+            has_otp_master,
+            set_otp_master,
+            verify_otp_master,
+            lock_otp,
+            get_otp_entries,
+            add_otp_entry,
+            update_otp_entry,
+            delete_otp_entry,
+            get_otp_codes,
+            next_hotp_code,
+            verify_otp_code,
+            import_otp_uri,
+            export_otp_uri,
             // Web Scraper commands
             create_scraping_job,
             get_scraping_jobs,
@@ -1836,12 +3388,33 @@ fn main() {
             delete_scraping_job,
             get_scraped_pages,
             search_scraped_content,
+            run_scraping_job,
+            pause_scraping_job,
+            resume_scraping_job,
+            cancel_scraping_job,
+            set_scraping_schedule,
+            set_scraping_settings,
+            get_scraping_runs,
+            get_page_versions,
+            diff_page_versions,
+            set_page_watch,
+            // Task commands
+            cancel_task,
+            list_tasks,
             // Legacy commands
             greet,
             search_domains,
             add_domain,
             query_knowledge_graph
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Leave no profile decrypted on disk after a normal quit
+            if let tauri::RunEvent::Exit = event {
+                app.state::<AppState>().profile_locks.lock_all();
+                app.state::<AppState>().password_vault.lock_all();
+                app.state::<AppState>().otp_manager.lock_all();
+            }
+        });
 }
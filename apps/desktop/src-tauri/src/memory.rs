@@ -1,9 +1,15 @@
 // Memory functionality for EarthMemory
 // Manages indexed pages, notes, and semantic search
+// Pages live in the shared page store, so everything visited shows up here too
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, Result, Row, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::tasks::TaskContext;
+
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexedPage {
     pub id: Option<i64>,
@@ -44,118 +50,111 @@ pub struct TagCount {
     pub count: i64,
 }
 
-pub struct MemoryManager {
-    db_path: String,
+/// One page of full-text search results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySearchResults {
+    pub hits: Vec<MemorySearchHit>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
 }
 
-impl MemoryManager {
-    pub fn new(db_path: String) -> Self {
-        MemoryManager { db_path }
-    }
-
-    /// Initialize memory tables
-    pub fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemorySearchHit {
+    pub page: IndexedPage,
+    /// Higher is better (negated BM25, page and note matches combined)
+    pub score: f64,
+    /// Title with matched terms wrapped in <mark></mark>
+    pub title_highlight: String,
+    /// Best matching excerpt of the page content, summary or tags
+    pub snippet: Option<String>,
+    pub note_snippets: Vec<NoteSnippet>,
+}
 
-        // Indexed pages table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS indexed_pages (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                url TEXT NOT NULL,
-                title TEXT NOT NULL,
-                content TEXT,
-                summary TEXT,
-                indexed_at TEXT NOT NULL,
-                last_visited TEXT NOT NULL,
-                visit_count INTEGER NOT NULL DEFAULT 1,
-                is_favorite INTEGER NOT NULL DEFAULT 0,
-                tags TEXT,
-                profile_id INTEGER,
-                UNIQUE(url, profile_id),
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NoteSnippet {
+    pub note_id: i64,
+    pub snippet: String,
+}
 
-        // Page notes table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS page_notes (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                page_id INTEGER NOT NULL,
-                content TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                profile_id INTEGER,
-                FOREIGN KEY (page_id) REFERENCES indexed_pages(id) ON DELETE CASCADE,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+/// Parsed form of the memory search syntax:
+/// `rust "error handling" tag:learning site:docs.rs -nightly async*`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MemoryQuery {
+    /// FTS5 MATCH expression for the free-text part, None if only filters were given
+    pub match_expr: Option<String>,
+    /// FTS5 MATCH expression for `-term` exclusions
+    pub exclude_expr: Option<String>,
+    pub tags: Vec<String>,
+    pub sites: Vec<String>,
+}
 
-        // Indexes for faster lookups
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_pages_url ON indexed_pages(url)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_pages_profile ON indexed_pages(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_pages_favorite ON indexed_pages(is_favorite)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_notes_page ON page_notes(page_id)", [])?;
+#[derive(Clone)]
+pub struct MemoryManager {
+    db: DbPool,
+}
 
-        Ok(())
+impl MemoryManager {
+    pub fn new(db: DbPool) -> Self {
+        MemoryManager { db }
     }
 
     // ==================== Page CRUD ====================
 
     /// Index a new page or update existing
     pub fn index_page(&self, page: &IndexedPage, profile_id: i64) -> Result<IndexedPage> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Check if page exists
-        let existing: Option<i64> = conn.query_row(
-            "SELECT id FROM indexed_pages WHERE url = ?1 AND profile_id = ?2",
-            params![page.url, profile_id],
-            |row| row.get(0),
-        ).ok();
+        let existing: Option<i64> = conn
+            .prepare_cached("SELECT id FROM pages WHERE url = ?1 AND profile_id = ?2")?
+            .query_row(params![page.url, profile_id], |row| row.get(0))
+            .ok();
 
         if let Some(id) = existing {
-            // Update existing page
-            conn.execute(
-                "UPDATE indexed_pages SET
+            // Update existing page; a content change invalidates the stored embedding.
+            // No summary in the request keeps the current one (it may be AI generated);
+            // `update_summary` is how a summary is cleared.
+            conn.prepare_cached(
+                "UPDATE pages SET
+                    embedding = CASE WHEN content IS ?2 AND title IS ?1 THEN embedding ELSE NULL END,
                     title = ?1,
                     content = ?2,
-                    summary = ?3,
-                    last_visited = ?4,
+                    summary = COALESCE(?3, summary),
+                    visited_at = ?4,
+                    indexed_at = COALESCE(indexed_at, ?4),
                     visit_count = visit_count + 1,
                     tags = ?5
                 WHERE id = ?6",
-                params![
-                    page.title,
-                    page.content,
-                    page.summary,
-                    now,
-                    page.tags,
-                    id
-                ],
-            )?;
+            )?.execute(params![
+                page.title,
+                page.content,
+                page.summary,
+                now,
+                page.tags,
+                id
+            ])?;
 
             // Return updated page
             self.get_page_by_id(id)
         } else {
             // Insert new page
-            conn.execute(
-                "INSERT INTO indexed_pages (url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id)
+            conn.prepare_cached(
+                "INSERT INTO pages (url, title, content, summary, indexed_at, visited_at, visit_count, is_favorite, tags, profile_id)
                  VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-                params![
-                    page.url,
-                    page.title,
-                    page.content,
-                    page.summary,
-                    now,
-                    now,
-                    1,
-                    page.is_favorite,
-                    page.tags,
-                    profile_id
-                ],
-            )?;
+            )?.execute(params![
+                page.url,
+                page.title,
+                page.content,
+                page.summary,
+                now,
+                now,
+                1,
+                page.is_favorite,
+                page.tags,
+                profile_id
+            ])?;
 
             let id = conn.last_insert_rowid();
             Ok(IndexedPage {
@@ -176,135 +175,208 @@ impl MemoryManager {
 
     /// Get page by ID
     fn get_page_by_id(&self, id: i64) -> Result<IndexedPage> {
-        let conn = Connection::open(&self.db_path)?;
-        conn.query_row(
-            "SELECT id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id
-             FROM indexed_pages WHERE id = ?1",
-            params![id],
-            |row| {
-                Ok(IndexedPage {
-                    id: Some(row.get(0)?),
-                    url: row.get(1)?,
-                    title: row.get(2)?,
-                    content: row.get(3)?,
-                    summary: row.get(4)?,
-                    indexed_at: row.get(5)?,
-                    last_visited: row.get(6)?,
-                    visit_count: row.get(7)?,
-                    is_favorite: row.get::<_, i64>(8)? == 1,
-                    tags: row.get(9)?,
-                    profile_id: row.get(10)?,
-                })
-            },
-        )
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare_cached(&format!("SELECT {} FROM pages p WHERE p.id = ?1", PAGE_COLUMNS))?;
+        stmt.query_row(params![id], row_to_page)
     }
 
     /// Get all indexed pages for a profile
     pub fn get_pages(&self, profile_id: i64, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<IndexedPage>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let limit = limit.unwrap_or(100);
         let offset = offset.unwrap_or(0);
 
         let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id
-             FROM indexed_pages
-             WHERE profile_id = ?1
-             ORDER BY last_visited DESC
-             LIMIT ?2 OFFSET ?3"
+            &format!(
+                "SELECT {} FROM pages p
+                 WHERE p.profile_id = ?1
+                 ORDER BY p.visited_at DESC
+                 LIMIT ?2 OFFSET ?3",
+                PAGE_COLUMNS
+            )
         )?;
 
-        let pages = stmt.query_map(params![profile_id, limit, offset], |row| {
-            Ok(IndexedPage {
-                id: Some(row.get(0)?),
-                url: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                summary: row.get(4)?,
-                indexed_at: row.get(5)?,
-                last_visited: row.get(6)?,
-                visit_count: row.get(7)?,
-                is_favorite: row.get::<_, i64>(8)? == 1,
-                tags: row.get(9)?,
-                profile_id: row.get(10)?,
-            })
-        })?;
+        let pages = stmt.query_map(params![profile_id, limit, offset], row_to_page)?;
 
         pages.collect()
     }
 
     /// Get favorite pages
     pub fn get_favorites(&self, profile_id: i64) -> Result<Vec<IndexedPage>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id
-             FROM indexed_pages
-             WHERE profile_id = ?1 AND is_favorite = 1
-             ORDER BY last_visited DESC"
+            &format!(
+                "SELECT {} FROM pages p
+                 WHERE p.profile_id = ?1 AND p.is_favorite = 1
+                 ORDER BY p.visited_at DESC",
+                PAGE_COLUMNS
+            )
         )?;
 
-        let pages = stmt.query_map(params![profile_id], |row| {
-            Ok(IndexedPage {
-                id: Some(row.get(0)?),
-                url: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                summary: row.get(4)?,
-                indexed_at: row.get(5)?,
-                last_visited: row.get(6)?,
-                visit_count: row.get(7)?,
-                is_favorite: row.get::<_, i64>(8)? == 1,
-                tags: row.get(9)?,
-                profile_id: row.get(10)?,
-            })
-        })?;
+        let pages = stmt.query_map(params![profile_id], row_to_page)?;
 
         pages.collect()
     }
 
-    /// Search pages by title, URL, content, or tags
+    /// Search pages by title, URL, content, tags or notes (first 50 hits)
     pub fn search_pages(&self, profile_id: i64, query: &str) -> Result<Vec<IndexedPage>> {
-        let conn = Connection::open(&self.db_path)?;
-        let pattern = format!("%{}%", query.to_lowercase());
+        let results = self.search(profile_id, query, 50, 0)?;
+        Ok(results.hits.into_iter().map(|hit| hit.page).collect())
+    }
 
-        let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id
-             FROM indexed_pages
-             WHERE profile_id = ?1 AND (
-                 LOWER(url) LIKE ?2 OR
-                 LOWER(title) LIKE ?2 OR
-                 LOWER(content) LIKE ?2 OR
-                 LOWER(tags) LIKE ?2 OR
-                 LOWER(summary) LIKE ?2
-             )
-             ORDER BY visit_count DESC, last_visited DESC
-             LIMIT 50"
+    /// Full-text search with BM25 ranking, highlighted snippets and pagination.
+    /// See `parse_memory_query` for the supported syntax.
+    pub fn search(&self, profile_id: i64, query: &str, limit: i64, offset: i64) -> Result<MemorySearchResults> {
+        let conn = self.db.get()?;
+        let parsed = parse_memory_query(query);
+        let limit = limit.clamp(1, 200);
+        let offset = offset.max(0);
+
+        let mut results = MemorySearchResults { hits: Vec::new(), total: 0, limit, offset };
+        if parsed.match_expr.is_none() && parsed.tags.is_empty() && parsed.sites.is_empty() {
+            return Ok(results);
+        }
+
+        let mut values: Vec<rusqlite::types::Value> = vec![profile_id.into()];
+        let mut conditions = vec!["p.profile_id = ?1".to_string()];
+
+        // Pages match on their own text or through one of their notes
+        let (ranked_from, score_expr) = if let Some(expr) = &parsed.match_expr {
+            values.push(expr.clone().into());
+            let n = values.len();
+            conditions.push("(ph.page_id IS NOT NULL OR nh.page_id IS NOT NULL)".to_string());
+            (
+                format!(
+                    "LEFT JOIN (
+                        SELECT rowid AS page_id, bm25(pages_fts, 10.0, 1.0, 3.0, 5.0, 2.0) AS rank
+                        FROM pages_fts WHERE pages_fts MATCH ?{n}
+                     ) ph ON ph.page_id = p.id
+                     LEFT JOIN (
+                        SELECT n.page_id AS page_id, MIN(page_notes_fts.rank) AS rank
+                        FROM page_notes_fts JOIN page_notes n ON n.id = page_notes_fts.rowid
+                        WHERE page_notes_fts MATCH ?{n}
+                        GROUP BY n.page_id
+                     ) nh ON nh.page_id = p.id"
+                ),
+                // bm25() is negative, more negative is better; note hits count half
+                "-(COALESCE(ph.rank, 0.0) + 0.5 * COALESCE(nh.rank, 0.0))".to_string(),
+            )
+        } else {
+            (String::new(), "0.0".to_string())
+        };
+
+        if let Some(expr) = &parsed.exclude_expr {
+            values.push(expr.clone().into());
+            let n = values.len();
+            conditions.push(format!(
+                "p.id NOT IN (SELECT rowid FROM pages_fts WHERE pages_fts MATCH ?{n})
+                 AND p.id NOT IN (
+                    SELECT n.page_id FROM page_notes_fts JOIN page_notes n ON n.id = page_notes_fts.rowid
+                    WHERE page_notes_fts MATCH ?{n}
+                 )"
+            ));
+        }
+
+        for tag in &parsed.tags {
+            values.push(format!("%,{},%", tag).into());
+            conditions.push(format!(
+                "(',' || REPLACE(REPLACE(LOWER(COALESCE(p.tags, '')), ', ', ','), ' ,', ',') || ',') LIKE ?{}",
+                values.len()
+            ));
+        }
+
+        for site in &parsed.sites {
+            // Host is the site itself or one of its subdomains
+            let first = values.len() + 1;
+            for pattern in [format!("%://{}", site), format!("%://{}/%", site), format!("%.{}", site), format!("%.{}/%", site)] {
+                values.push(pattern.into());
+            }
+            conditions.push(format!(
+                "(LOWER(p.url) LIKE ?{} OR LOWER(p.url) LIKE ?{} OR LOWER(p.url) LIKE ?{} OR LOWER(p.url) LIKE ?{})",
+                first, first + 1, first + 2, first + 3
+            ));
+        }
+
+        let where_clause = conditions.join(" AND ");
+
+        results.total = conn.query_row(
+            &format!("SELECT COUNT(*) FROM pages p {} WHERE {}", ranked_from, where_clause),
+            rusqlite::params_from_iter(values.iter()),
+            |row| row.get(0),
         )?;
 
-        let pages = stmt.query_map(params![profile_id, pattern], |row| {
-            Ok(IndexedPage {
-                id: Some(row.get(0)?),
-                url: row.get(1)?,
-                title: row.get(2)?,
-                content: row.get(3)?,
-                summary: row.get(4)?,
-                indexed_at: row.get(5)?,
-                last_visited: row.get(6)?,
-                visit_count: row.get(7)?,
-                is_favorite: row.get::<_, i64>(8)? == 1,
-                tags: row.get(9)?,
-                profile_id: row.get(10)?,
-            })
+        let sql = format!(
+            "SELECT {}, {} AS score
+             FROM pages p {}
+             WHERE {}
+             ORDER BY score DESC, p.visited_at DESC
+             LIMIT {} OFFSET {}",
+            PAGE_COLUMNS, score_expr, ranked_from, where_clause, limit, offset
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok((row_to_page(row)?, row.get::<_, f64>(11)?))
         })?;
 
-        pages.collect()
+        for row in rows {
+            let (page, score) = row?;
+            let page_id = page.id.unwrap_or_default();
+            let title_highlight = page.title.clone();
+            results.hits.push(MemorySearchHit {
+                page,
+                score,
+                title_highlight,
+                snippet: None,
+                note_snippets: Vec::new(),
+            });
+
+            if let Some(expr) = &parsed.match_expr {
+                let hit = results.hits.last_mut().expect("just pushed");
+                self.fill_highlights(&conn, hit, page_id, expr)?;
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Highlight the title and pick snippets for one hit. FTS5 auxiliary functions only
+    /// work in a plain MATCH query, so this runs separately from the ranking query.
+    fn fill_highlights(&self, conn: &Connection, hit: &mut MemorySearchHit, page_id: i64, match_expr: &str) -> Result<()> {
+        let mut stmt = conn.prepare_cached(
+            "SELECT highlight(pages_fts, 0, '<mark>', '</mark>'),
+                    snippet(pages_fts, -1, '<mark>', '</mark>', '...', 24)
+             FROM pages_fts WHERE pages_fts MATCH ?1 AND rowid = ?2"
+        )?;
+        let mut rows = stmt.query(params![match_expr, page_id])?;
+        if let Some(row) = rows.next()? {
+            if let Some(title) = row.get::<_, Option<String>>(0)? {
+                hit.title_highlight = title;
+            }
+            hit.snippet = row.get(1)?;
+        }
+
+        let mut stmt = conn.prepare_cached(
+            "SELECT n.id, snippet(page_notes_fts, 0, '<mark>', '</mark>', '...', 24)
+             FROM page_notes_fts JOIN page_notes n ON n.id = page_notes_fts.rowid
+             WHERE page_notes_fts MATCH ?1 AND n.page_id = ?2
+             ORDER BY page_notes_fts.rank
+             LIMIT 3"
+        )?;
+        let notes = stmt.query_map(params![match_expr, page_id], |row| {
+            Ok(NoteSnippet { note_id: row.get(0)?, snippet: row.get(1)? })
+        })?;
+        hit.note_snippets = notes.collect::<Result<Vec<_>>>()?;
+
+        Ok(())
     }
 
     /// Toggle favorite status
     pub fn toggle_favorite(&self, page_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let current: i64 = conn.query_row(
-            "SELECT is_favorite FROM indexed_pages WHERE id = ?1 AND profile_id = ?2",
+            "SELECT is_favorite FROM pages WHERE id = ?1 AND profile_id = ?2",
             params![page_id, profile_id],
             |row| row.get(0),
         )?;
@@ -312,7 +384,7 @@ impl MemoryManager {
         let new_value = if current == 1 { 0 } else { 1 };
 
         conn.execute(
-            "UPDATE indexed_pages SET is_favorite = ?1 WHERE id = ?2 AND profile_id = ?3",
+            "UPDATE pages SET is_favorite = ?1 WHERE id = ?2 AND profile_id = ?3",
             params![new_value, page_id, profile_id],
         )?;
 
@@ -321,21 +393,34 @@ impl MemoryManager {
 
     /// Update page tags
     pub fn update_tags(&self, page_id: i64, profile_id: i64, tags: &str) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
-            "UPDATE indexed_pages SET tags = ?1 WHERE id = ?2 AND profile_id = ?3",
+            "UPDATE pages SET tags = ?1 WHERE id = ?2 AND profile_id = ?3",
             params![tags, page_id, profile_id],
         )?;
 
         Ok(())
     }
 
+    /// Replace the page's summary, or clear it with None (or a blank one)
+    pub fn update_summary(&self, page_id: i64, profile_id: i64, summary: Option<&str>) -> Result<()> {
+        let conn = self.db.get()?;
+        let summary = summary.map(str::trim).filter(|summary| !summary.is_empty());
+
+        conn.execute(
+            "UPDATE pages SET summary = ?1 WHERE id = ?2 AND profile_id = ?3",
+            params![summary, page_id, profile_id],
+        )?;
+
+        Ok(())
+    }
+
     /// Delete an indexed page
     pub fn delete_page(&self, page_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let affected = conn.execute(
-            "DELETE FROM indexed_pages WHERE id = ?1 AND profile_id = ?2",
+            "DELETE FROM pages WHERE id = ?1 AND profile_id = ?2",
             params![page_id, profile_id],
         )?;
         Ok(affected > 0)
@@ -345,14 +430,13 @@ impl MemoryManager {
 
     /// Add a note to a page
     pub fn add_note(&self, page_id: i64, content: &str, profile_id: i64) -> Result<PageNote> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
-        conn.execute(
+        conn.prepare_cached(
             "INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id)
              VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![page_id, content, now, now, profile_id],
-        )?;
+        )?.execute(params![page_id, content, now, now, profile_id])?;
 
         let id = conn.last_insert_rowid();
         Ok(PageNote {
@@ -366,14 +450,16 @@ impl MemoryManager {
     }
 
     /// Get notes for a page
-    pub fn get_page_notes(&self, page_id: i64) -> Result<Vec<PageNote>> {
-        let conn = Connection::open(&self.db_path)?;
+    pub fn get_page_notes(&self, page_id: i64, profile_id: i64) -> Result<Vec<PageNote>> {
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, page_id, content, created_at, updated_at, profile_id
-             FROM page_notes WHERE page_id = ?1 ORDER BY created_at DESC"
+             FROM page_notes
+             WHERE page_id = ?1 AND page_id IN (SELECT id FROM pages WHERE profile_id = ?2)
+             ORDER BY created_at DESC"
         )?;
 
-        let notes = stmt.query_map(params![page_id], |row| {
+        let notes = stmt.query_map(params![page_id, profile_id], |row| {
             Ok(PageNote {
                 id: Some(row.get(0)?),
                 page_id: row.get(1)?,
@@ -389,7 +475,7 @@ impl MemoryManager {
 
     /// Update a note
     pub fn update_note(&self, note_id: i64, content: &str, profile_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         conn.execute(
@@ -402,7 +488,7 @@ impl MemoryManager {
 
     /// Delete a note
     pub fn delete_note(&self, note_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let affected = conn.execute(
             "DELETE FROM page_notes WHERE id = ?1 AND profile_id = ?2",
             params![note_id, profile_id],
@@ -414,10 +500,10 @@ impl MemoryManager {
 
     /// Get memory statistics
     pub fn get_stats(&self, profile_id: i64) -> Result<MemoryStats> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let total_pages: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM indexed_pages WHERE profile_id = ?1",
+            "SELECT COUNT(*) FROM pages WHERE profile_id = ?1",
             params![profile_id],
             |row| row.get(0),
         )?;
@@ -429,20 +515,20 @@ impl MemoryManager {
         )?;
 
         let favorites_count: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM indexed_pages WHERE profile_id = ?1 AND is_favorite = 1",
+            "SELECT COUNT(*) FROM pages WHERE profile_id = ?1 AND is_favorite = 1",
             params![profile_id],
             |row| row.get(0),
         )?;
 
         let total_visits: i64 = conn.query_row(
-            "SELECT COALESCE(SUM(visit_count), 0) FROM indexed_pages WHERE profile_id = ?1",
+            "SELECT COALESCE(SUM(visit_count), 0) FROM pages WHERE profile_id = ?1",
             params![profile_id],
             |row| row.get(0),
         )?;
 
         // Get tag counts
         let mut stmt = conn.prepare(
-            "SELECT tags FROM indexed_pages WHERE profile_id = ?1 AND tags IS NOT NULL AND tags != ''"
+            "SELECT tags FROM pages WHERE profile_id = ?1 AND tags IS NOT NULL AND tags != ''"
         )?;
 
         let mut tag_counts: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
@@ -476,9 +562,9 @@ impl MemoryManager {
 
     /// Get all unique tags
     pub fn get_all_tags(&self, profile_id: i64) -> Result<Vec<String>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT tags FROM indexed_pages WHERE profile_id = ?1 AND tags IS NOT NULL AND tags != ''"
+            "SELECT tags FROM pages WHERE profile_id = ?1 AND tags IS NOT NULL AND tags != ''"
         )?;
 
         let mut all_tags: std::collections::HashSet<String> = std::collections::HashSet::new();
@@ -502,14 +588,20 @@ impl MemoryManager {
 
     // ==================== Export/Import ====================
 
-    /// Export all memory data as JSON
-    pub fn export_memory(&self, profile_id: i64) -> Result<String> {
+    /// Export all memory data as JSON.
+    /// A cancelled export stops early and returns the pages collected so far.
+    pub fn export_memory(&self, profile_id: i64, task: &TaskContext) -> Result<String> {
         let pages = self.get_pages(profile_id, Some(10000), None)?;
+        let total = pages.len() as u64;
 
         // Get notes for each page
         let mut pages_with_notes: Vec<serde_json::Value> = Vec::new();
         for page in pages {
-            let notes = self.get_page_notes(page.id.unwrap_or(0))?;
+            if task.is_cancelled() {
+                break;
+            }
+            task.progress(pages_with_notes.len() as u64 + 1, Some(total));
+            let notes = self.get_page_notes(page.id.unwrap_or(0), profile_id)?;
             pages_with_notes.push(serde_json::json!({
                 "url": page.url,
                 "title": page.title,
@@ -535,16 +627,22 @@ impl MemoryManager {
         Ok(serde_json::to_string_pretty(&export).unwrap_or_default())
     }
 
-    /// Import memory data from JSON
-    pub fn import_memory(&self, profile_id: i64, json_data: &str) -> Result<i64> {
-        let data: serde_json::Value = serde_json::from_str(json_data)
-            .map_err(|_| rusqlite::Error::InvalidQuery)?;
+    /// Import memory data from JSON.
+    /// A cancelled import keeps the pages already written and returns their count.
+    pub fn import_memory(&self, profile_id: i64, json_data: &str, task: &TaskContext) -> AppResult<i64> {
+        let data: serde_json::Value = serde_json::from_str(json_data)?;
 
         let pages = data["pages"].as_array()
-            .ok_or(rusqlite::Error::InvalidQuery)?;
+            .ok_or_else(|| AppError::Validation("Memory export has no \"pages\" array".to_string()))?;
 
+        let total = pages.len() as u64;
         let mut imported = 0i64;
-        for p in pages {
+        for (i, p) in pages.iter().enumerate() {
+            if task.is_cancelled() {
+                break;
+            }
+            task.progress(i as u64 + 1, Some(total));
+
             let page = IndexedPage {
                 id: None,
                 url: p["url"].as_str().unwrap_or_default().to_string(),
@@ -579,6 +677,159 @@ impl MemoryManager {
     }
 }
 
+/// Columns read by `row_to_page`, from `pages` aliased as `p`.
+/// Pages recorded by history alone have no indexed_at yet; their first visit stands in.
+const PAGE_COLUMNS: &str = "p.id, p.url, p.title, p.content, p.summary, COALESCE(p.indexed_at, p.visited_at),
+     p.visited_at, p.visit_count, p.is_favorite, p.tags, p.profile_id";
+
+fn row_to_page(row: &Row) -> Result<IndexedPage> {
+    Ok(IndexedPage {
+        id: Some(row.get(0)?),
+        url: row.get(1)?,
+        title: row.get(2)?,
+        content: row.get(3)?,
+        summary: row.get(4)?,
+        indexed_at: row.get(5)?,
+        last_visited: row.get(6)?,
+        visit_count: row.get(7)?,
+        is_favorite: row.get::<_, i64>(8)? == 1,
+        tags: row.get(9)?,
+        profile_id: row.get(10)?,
+    })
+}
+
+/// Parse the memory search syntax into FTS5 expressions and filters.
+///
+/// - `word` / `prefix*`: terms, all must match
+/// - `"exact phrase"`
+/// - `a OR b`: either term
+/// - `-word` / `-"phrase"`: exclude pages (or their notes) containing it
+/// - `tag:name`: page has the tag
+/// - `site:example.com`: page is on that host or a subdomain
+///
+/// Everything is quoted before it reaches FTS5, so user input can't produce a syntax error.
+pub fn parse_memory_query(query: &str) -> MemoryQuery {
+    let mut parsed = MemoryQuery::default();
+    // Terms are ANDed; each entry is a group of alternatives joined by OR
+    let mut positive: Vec<Vec<String>> = Vec::new();
+    let mut excluded: Vec<String> = Vec::new();
+    let mut pending_or = false;
+
+    for token in tokenize_query(query) {
+        let (negated, quoted, text) = token;
+
+        if !quoted && !negated {
+            if text == "OR" {
+                pending_or = !positive.is_empty();
+                continue;
+            }
+            let lower = text.to_lowercase();
+            if let Some(tag) = lower.strip_prefix("tag:") {
+                if !tag.is_empty() {
+                    parsed.tags.push(tag.to_string());
+                }
+                continue;
+            }
+            if let Some(site) = lower.strip_prefix("site:") {
+                let site = site.trim_start_matches("www.").trim_end_matches('/');
+                if !site.is_empty() {
+                    parsed.sites.push(site.to_string());
+                }
+                continue;
+            }
+        }
+
+        let term = match fts_term(&text, quoted) {
+            Some(t) => t,
+            None => continue,
+        };
+
+        if negated {
+            excluded.push(term);
+        } else if pending_or {
+            if let Some(group) = positive.last_mut() {
+                group.push(term);
+            }
+            pending_or = false;
+        } else {
+            positive.push(vec![term]);
+        }
+    }
+
+    if !positive.is_empty() {
+        let groups: Vec<String> = positive
+            .into_iter()
+            .map(|group| if group.len() > 1 { format!("({})", group.join(" OR ")) } else { group.join("") })
+            .collect();
+        parsed.match_expr = Some(groups.join(" AND "));
+    }
+    if !excluded.is_empty() {
+        parsed.exclude_expr = Some(excluded.join(" OR "));
+    }
+    parsed
+}
+
+/// Split a query into (negated, quoted, text) tokens, honouring double quotes
+fn tokenize_query(query: &str) -> Vec<(bool, bool, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut negated = false;
+        if c == '-' {
+            negated = true;
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            tokens.push((negated, true, phrase));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            if !word.is_empty() {
+                tokens.push((negated, false, word));
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Quote a term or phrase for FTS5; a trailing `*` on a bare word makes it a prefix query
+fn fts_term(text: &str, quoted: bool) -> Option<String> {
+    let (body, prefix) = match text.strip_suffix('*') {
+        Some(body) if !quoted => (body, true),
+        _ => (text, false),
+    };
+
+    // Keep only what the tokenizer would index anyway
+    let cleaned: String = body
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if cleaned.is_empty() {
+        return None;
+    }
+    Some(format!("\"{}\"{}", cleaned, if prefix { "*" } else { "" }))
+}
+
 fn chrono_now() -> String {
     use std::time::{SystemTime, UNIX_EPOCH};
     let duration = SystemTime::now()
@@ -586,3 +837,168 @@ fn chrono_now() -> String {
         .unwrap_or_default();
     format!("{}", duration.as_secs())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_manager(name: &str) -> MemoryManager {
+        MemoryManager::new(crate::migrations::test_database(&format!("memory_{}", name)))
+    }
+
+    fn page(url: &str, title: &str, content: &str, tags: Option<&str>) -> IndexedPage {
+        IndexedPage {
+            id: None,
+            url: url.to_string(),
+            title: title.to_string(),
+            content: Some(content.to_string()),
+            summary: None,
+            indexed_at: String::new(),
+            last_visited: String::new(),
+            visit_count: 1,
+            is_favorite: false,
+            tags: tags.map(String::from),
+            profile_id: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_memory_query() {
+        let parsed = parse_memory_query(r#"rust "error handling" tag:Learning site:www.docs.rs/ -nightly async* OR tokio"#);
+        assert_eq!(parsed.match_expr.as_deref(), Some(r#""rust" AND "error handling" AND ("async"* OR "tokio")"#));
+        assert_eq!(parsed.exclude_expr.as_deref(), Some(r#""nightly""#));
+        assert_eq!(parsed.tags, vec!["learning"]);
+        assert_eq!(parsed.sites, vec!["docs.rs"]);
+
+        // FTS5 operators in user input are neutralized
+        assert_eq!(parse_memory_query("NEAR(a b) c:d ^e").match_expr.as_deref(), Some(r#""NEAR a" AND "b" AND "c d" AND "e""#));
+        assert_eq!(parse_memory_query("  \"\" - ").match_expr, None);
+    }
+
+    #[test]
+    fn test_fts_search() {
+        let manager = test_manager("fts");
+        let rust = manager.index_page(&page("https://doc.rust-lang.org/book", "The Rust Book", "Ownership and borrowing explained", Some("rust, learning")), 1).unwrap();
+        let blog = manager.index_page(&page("https://blog.example.com/rust", "Weekly notes", "Some rust nightly features", Some("blog")), 1).unwrap();
+        manager.index_page(&page("https://other.example.com", "Gardening", "Tomatoes", None), 1).unwrap();
+        manager.index_page(&page("https://doc.rust-lang.org/std", "Rust std", "Standard library", None), 2).unwrap();
+
+        let results = manager.search(1, "rust", 10, 0).unwrap();
+        assert_eq!(results.total, 2);
+        assert_eq!(results.hits[0].page.id, rust.id);
+        assert_eq!(results.hits[0].title_highlight, "The <mark>Rust</mark> Book");
+
+        let results = manager.search(1, "rust -nightly", 10, 0).unwrap();
+        assert_eq!(results.total, 1);
+        assert_eq!(results.hits[0].page.id, rust.id);
+
+        assert_eq!(manager.search(1, "tag:blog", 10, 0).unwrap().hits[0].page.id, blog.id);
+        assert_eq!(manager.search(1, "rust site:rust-lang.org", 10, 0).unwrap().total, 1);
+        assert_eq!(manager.search(1, "\"borrowing explained\"", 10, 0).unwrap().total, 1);
+        assert_eq!(manager.search(1, "own*", 10, 0).unwrap().total, 1);
+
+        // Pagination
+        let second = manager.search(1, "rust", 1, 1).unwrap();
+        assert_eq!(second.total, 2);
+        assert_eq!(second.hits.len(), 1);
+        assert_eq!(second.hits[0].page.id, blog.id);
+
+        // Notes are searchable and updates are picked up by the triggers
+        let note = manager.add_note(blog.id.unwrap(), "Remember the garbage collector comparison", 1).unwrap();
+        let results = manager.search(1, "garbage", 10, 0).unwrap();
+        assert_eq!(results.hits[0].page.id, blog.id);
+        assert_eq!(results.hits[0].note_snippets[0].note_id, note.id.unwrap());
+
+        manager.update_note(note.id.unwrap(), "Nothing to see", 1).unwrap();
+        assert_eq!(manager.search(1, "garbage", 10, 0).unwrap().total, 0);
+
+        manager.delete_page(rust.id.unwrap(), 1).unwrap();
+        assert_eq!(manager.search(1, "ownership", 10, 0).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_summary_is_kept_until_cleared() {
+        let manager = test_manager("summary");
+        let mut saved = page("https://example.com/guide", "Guide", "How it works", None);
+        saved.summary = Some("A short guide".to_string());
+        let id = manager.index_page(&saved, 1).unwrap().id.unwrap();
+
+        // Saving again without a summary leaves it alone
+        let resaved = manager.index_page(&page("https://example.com/guide", "Guide", "How it works, revised", None), 1).unwrap();
+        assert_eq!(resaved.summary.as_deref(), Some("A short guide"));
+
+        // Another profile can't touch it
+        manager.update_summary(id, 2, None).unwrap();
+        assert_eq!(manager.get_page_by_id(id).unwrap().summary.as_deref(), Some("A short guide"));
+
+        manager.update_summary(id, 1, Some("  A longer guide ")).unwrap();
+        assert_eq!(manager.get_page_by_id(id).unwrap().summary.as_deref(), Some("A longer guide"));
+
+        manager.update_summary(id, 1, None).unwrap();
+        assert_eq!(manager.get_page_by_id(id).unwrap().summary, None);
+        let resaved = manager.index_page(&page("https://example.com/guide", "Guide", "How it works", None), 1).unwrap();
+        assert_eq!(resaved.summary, None);
+
+        manager.update_summary(id, 1, Some("Back")).unwrap();
+        manager.update_summary(id, 1, Some("   ")).unwrap();
+        assert_eq!(manager.get_page_by_id(id).unwrap().summary, None);
+    }
+
+    #[test]
+    fn test_history_and_memory_share_pages() {
+        use crate::knowledge_graph::{KnowledgeGraph, Page};
+
+        let manager = test_manager("shared");
+        let graph = KnowledgeGraph::new(manager.db.clone());
+
+        let visited = graph.add_page(&Page {
+            id: None,
+            url: "https://example.com/article".to_string(),
+            title: "Article".to_string(),
+            content: "Visited from the browser".to_string(),
+            visited_at: String::new(),
+            embedding: None,
+            profile_id: Some(1),
+        }, 1).unwrap().unwrap();
+
+        // Saving to memory updates the page history already knows about
+        let saved = manager.index_page(&page("https://example.com/article", "Article", "Saved text", Some("reading")), 1).unwrap();
+        assert_eq!(saved.id, Some(visited));
+        assert_eq!(saved.visit_count, 2);
+        assert_eq!(manager.get_pages(1, None, None).unwrap().len(), 1);
+
+        // Notes written through either manager land in the same table
+        graph.add_note(visited, "graph note").unwrap();
+        manager.add_note(visited, "memory note", 1).unwrap();
+        assert_eq!(manager.get_page_notes(visited, 1).unwrap().len(), 2);
+        assert!(manager.get_page_notes(visited, 2).unwrap().is_empty());
+        assert_eq!(graph.get_notes_for_page(visited).unwrap().len(), 2);
+
+        let history = crate::privacy::PrivacyManager::new(manager.db.clone());
+        assert!(history.delete_history_entry(visited, 1).unwrap());
+        assert!(manager.get_pages(1, None, None).unwrap().is_empty());
+        assert_eq!(manager.search(1, "reading", 10, 0).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_fts_backfills_existing_database() {
+        let path = std::env::temp_dir().join(format!("earth_memory_migrate_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE indexed_pages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, url TEXT NOT NULL, title TEXT NOT NULL, content TEXT,
+                summary TEXT, indexed_at TEXT NOT NULL, last_visited TEXT NOT NULL,
+                visit_count INTEGER NOT NULL DEFAULT 1, is_favorite INTEGER NOT NULL DEFAULT 0,
+                tags TEXT, profile_id INTEGER, UNIQUE(url, profile_id)
+            );
+            INSERT INTO indexed_pages (url, title, content, indexed_at, last_visited, profile_id)
+            VALUES ('https://old.example.com', 'Legacy page', 'written before fts', '1', '1', 1);"
+        ).unwrap();
+
+        let db_path = path.to_string_lossy().to_string();
+        crate::migrations::run(&db_path).unwrap();
+        let manager = MemoryManager::new(DbPool::open(&db_path).unwrap());
+        assert_eq!(manager.search(1, "legacy", 10, 0).unwrap().total, 1);
+    }
+}
@@ -0,0 +1,1370 @@
+// Versioned schema migrations for EarthServers Local
+// Numbered migrations run in order at startup, each inside its own transaction,
+// and the applied versions are recorded in `schema_version`.
+// An existing database is copied aside before it is upgraded.
+// Never change a migration that has shipped: add a new one instead.
+
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use serde::{Deserialize, Serialize};
+
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+/// Every schema change, oldest first. Versions must be consecutive.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "initial_schema", up: initial_schema },
+    Migration { version: 2, name: "page_embeddings", up: page_embeddings },
+    Migration { version: 3, name: "memory_full_text_search", up: memory_full_text_search },
+    Migration { version: 4, name: "page_summaries", up: page_summaries },
+    Migration { version: 5, name: "graph_edges", up: graph_edges },
+    Migration { version: 6, name: "entity_index", up: entity_index },
+    Migration { version: 7, name: "unified_page_store", up: unified_page_store },
+    Migration { version: 8, name: "profile_encryption", up: profile_encryption },
+    Migration { version: 9, name: "media_key_derivation", up: media_key_derivation },
+    Migration { version: 10, name: "password_vault", up: password_vault },
+    Migration { version: 11, name: "otp_vault", up: otp_vault },
+    Migration { version: 12, name: "scraping_runs", up: scraping_runs },
+    Migration { version: 13, name: "scraper_settings", up: scraper_settings },
+    Migration { version: 14, name: "scraping_frontier", up: scraping_frontier },
+    Migration { version: 15, name: "scraped_page_versions", up: scraped_page_versions },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub from_version: i64,
+    pub to_version: i64,
+    /// Names of the migrations applied by this run
+    pub applied: Vec<String>,
+    /// Copy of the database taken before upgrading, if there was anything to upgrade
+    pub backup_path: Option<String>,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Bring the database at `db_path` up to the latest schema version
+pub fn run(db_path: &str) -> Result<MigrationReport> {
+    let conn = Connection::open(db_path)?;
+    migrate(&conn, db_path, latest_version())
+}
+
+fn migrate(conn: &Connection, db_path: &str, target: i64) -> Result<MigrationReport> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    let from_version = current_version(conn)?;
+    if from_version > latest_version() {
+        return Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISMATCH),
+            Some(format!(
+                "database schema version {} is newer than this app supports ({})",
+                from_version,
+                latest_version()
+            )),
+        ));
+    }
+
+    let mut report = MigrationReport {
+        from_version,
+        to_version: from_version,
+        applied: Vec::new(),
+        backup_path: None,
+    };
+
+    let pending: Vec<&Migration> = MIGRATIONS
+        .iter()
+        .filter(|m| m.version > from_version && m.version <= target)
+        .collect();
+    if pending.is_empty() {
+        return Ok(report);
+    }
+
+    if from_version > 0 {
+        report.backup_path = Some(backup(conn, db_path, from_version)?);
+    }
+
+    // Table rebuilds must not cascade; foreign keys can only be toggled outside a transaction
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    let result: Result<()> = pending.iter().try_for_each(|migration| {
+        apply(conn, migration)?;
+        report.to_version = migration.version;
+        report.applied.push(migration.name.to_string());
+        Ok(())
+    });
+    conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    result?;
+
+    Ok(report)
+}
+
+fn apply(conn: &Connection, migration: &Migration) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    (migration.up)(&tx)?;
+    tx.execute(
+        "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+        params![migration.version, migration.name, chrono_now()],
+    )?;
+    tx.commit()
+}
+
+/// Highest applied version. Databases created before versioning are recognised
+/// by their tables and recorded at that version.
+fn current_version(conn: &Connection) -> Result<i64> {
+    let recorded: Option<i64> = conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))?;
+    if let Some(version) = recorded {
+        return Ok(version);
+    }
+
+    let detected = detect_unversioned(conn)?;
+    if let Some(migration) = MIGRATIONS.iter().find(|m| m.version == detected) {
+        conn.execute(
+            "INSERT INTO schema_version (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, chrono_now()],
+        )?;
+    }
+    Ok(detected)
+}
+
+/// Newest migration whose tables an unversioned database already has; 0 for an empty one.
+/// Only builds up to v7 ran without `schema_version`, so later schemas are never detected.
+fn detect_unversioned(conn: &Connection) -> Result<i64> {
+    if table_exists(conn, "pages")? && column_exists(conn, "pages", "visit_count")? {
+        return Ok(7);
+    }
+    for (version, table) in [
+        (6, "entities"),
+        (5, "edges"),
+        (4, "page_summaries"),
+        (3, "indexed_pages_fts"),
+        (2, "embedding_chunks"),
+        (1, "profiles"),
+    ] {
+        if table_exists(conn, table)? {
+            return Ok(version);
+        }
+    }
+    Ok(0)
+}
+
+/// Snapshot the database next to itself, e.g. `earthservers.db.v3.bak`
+fn backup(conn: &Connection, db_path: &str, version: i64) -> Result<String> {
+    let path = format!("{}.v{}.bak", db_path, version);
+    // VACUUM INTO refuses to overwrite; an older backup of the same version is stale anyway
+    let _ = std::fs::remove_file(&path);
+    conn.execute("VACUUM INTO ?1", params![path])?;
+    Ok(path)
+}
+
+// ==================== Migrations ====================
+
+/// v1: the schema as first shipped, before versioning existed
+fn initial_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS profiles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            icon TEXT,
+            created_at TEXT NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS privacy_settings (
+            profile_id INTEGER PRIMARY KEY,
+            auto_delete_days INTEGER,
+            ai_enabled_in_incognito INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        -- Browsing history / knowledge graph
+        CREATE TABLE IF NOT EXISTS pages (
+            id INTEGER PRIMARY KEY,
+            url TEXT NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT,
+            visited_at TEXT NOT NULL,
+            embedding BLOB,
+            profile_id INTEGER,
+            UNIQUE(url, profile_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS notes (
+            id INTEGER PRIMARY KEY,
+            page_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_pages_url ON pages(url);
+        CREATE INDEX IF NOT EXISTS idx_pages_profile ON pages(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_pages_visited ON pages(visited_at);
+
+        CREATE TABLE IF NOT EXISTS themes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            is_active INTEGER NOT NULL DEFAULT 0,
+            base_preset TEXT DEFAULT 'earthservers-default',
+            primary_color TEXT NOT NULL DEFAULT '#0fab89',
+            secondary_color TEXT NOT NULL DEFAULT '#e91e63',
+            accent_color TEXT NOT NULL DEFAULT '#0178C6',
+            text_color TEXT NOT NULL DEFAULT '#f0f0f0',
+            background_color TEXT NOT NULL DEFAULT '#0a0a0f',
+            background_gradient_enabled INTEGER NOT NULL DEFAULT 1,
+            background_gradient_angle INTEGER NOT NULL DEFAULT 135,
+            background_gradient_from TEXT DEFAULT '#0a0a0f',
+            background_gradient_to TEXT DEFAULT '#1a1a2e',
+            card_bg_color TEXT NOT NULL DEFAULT '#1a1a2e',
+            card_opacity INTEGER NOT NULL DEFAULT 80,
+            card_gradient_enabled INTEGER NOT NULL DEFAULT 0,
+            card_gradient_color1 TEXT DEFAULT '#1a1a2e',
+            card_gradient_color2 TEXT DEFAULT '#2a2a3e',
+            navbar_color TEXT DEFAULT '#0a0a0f',
+            navbar_opacity INTEGER NOT NULL DEFAULT 90,
+            custom_css TEXT,
+            extra_settings TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+            UNIQUE(profile_id, name)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_themes_profile ON themes(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_themes_active ON themes(profile_id, is_active);
+
+        -- Search domains and ratings
+        CREATE TABLE IF NOT EXISTS domains (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            url TEXT NOT NULL,
+            category TEXT NOT NULL,
+            trust_score REAL NOT NULL DEFAULT 0.5,
+            added_date TEXT NOT NULL,
+            metadata TEXT,
+            profile_id INTEGER,
+            UNIQUE(url, profile_id),
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS domain_lists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            description TEXT,
+            author TEXT,
+            version TEXT DEFAULT '1.0',
+            created_at TEXT NOT NULL,
+            profile_id INTEGER,
+            UNIQUE(name, profile_id),
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS list_domains (
+            list_id INTEGER NOT NULL,
+            domain_id INTEGER NOT NULL,
+            PRIMARY KEY (list_id, domain_id),
+            FOREIGN KEY (list_id) REFERENCES domain_lists(id) ON DELETE CASCADE,
+            FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS domain_ratings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            domain_id INTEGER NOT NULL,
+            user_id TEXT NOT NULL,
+            trust_rating INTEGER NOT NULL CHECK (trust_rating BETWEEN 1 AND 5),
+            bias_rating INTEGER NOT NULL CHECK (bias_rating BETWEEN 1 AND 4),
+            review_text TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT,
+            helpful_count INTEGER DEFAULT 0,
+            reported BOOLEAN DEFAULT FALSE,
+            FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+            UNIQUE(domain_id, user_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS domain_rating_aggregates (
+            domain_id INTEGER PRIMARY KEY,
+            avg_trust REAL NOT NULL DEFAULT 3.0,
+            avg_bias REAL NOT NULL DEFAULT 2.5,
+            total_ratings INTEGER NOT NULL DEFAULT 0,
+            trust_distribution TEXT,
+            bias_distribution TEXT,
+            last_updated TEXT,
+            FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS subdomain_ratings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            parent_domain_id INTEGER NOT NULL,
+            subdomain TEXT NOT NULL,
+            avg_trust REAL NOT NULL DEFAULT 3.0,
+            avg_bias REAL NOT NULL DEFAULT 2.5,
+            total_ratings INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (parent_domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+            UNIQUE(parent_domain_id, subdomain)
+        );
+
+        CREATE TABLE IF NOT EXISTS rating_categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            domain_rating_id INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            score INTEGER NOT NULL CHECK (score BETWEEN 1 AND 5),
+            FOREIGN KEY (domain_rating_id) REFERENCES domain_ratings(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_domains_url ON domains(url);
+        CREATE INDEX IF NOT EXISTS idx_domains_category ON domains(category);
+        CREATE INDEX IF NOT EXISTS idx_domains_profile ON domains(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_domain_lists_profile ON domain_lists(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_domain_ratings_domain ON domain_ratings(domain_id);
+        CREATE INDEX IF NOT EXISTS idx_domain_ratings_user ON domain_ratings(user_id);
+        CREATE INDEX IF NOT EXISTS idx_subdomain_ratings_parent ON subdomain_ratings(parent_domain_id);
+
+        -- Tabs
+        CREATE TABLE IF NOT EXISTS tabs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            title TEXT,
+            url TEXT NOT NULL,
+            favicon TEXT,
+            position INTEGER NOT NULL,
+            is_pinned INTEGER DEFAULT 0,
+            is_active INTEGER DEFAULT 0,
+            scroll_position INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            last_accessed TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS tab_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tab_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            title TEXT,
+            visited_at TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_tabs_profile ON tabs(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_tabs_position ON tabs(profile_id, position);
+        CREATE INDEX IF NOT EXISTS idx_tab_history_tab ON tab_history(tab_id);
+
+        -- Bookmarks
+        CREATE TABLE IF NOT EXISTS bookmark_folders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            parent_id INTEGER,
+            position INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+            FOREIGN KEY (parent_id) REFERENCES bookmark_folders(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS bookmarks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            url TEXT NOT NULL,
+            favicon TEXT,
+            folder_id INTEGER,
+            tags TEXT,
+            notes TEXT,
+            position INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+            FOREIGN KEY (folder_id) REFERENCES bookmark_folders(id) ON DELETE SET NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_profile ON bookmarks(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_bookmarks_folder ON bookmarks(folder_id);
+        CREATE INDEX IF NOT EXISTS idx_bookmark_folders_profile ON bookmark_folders(profile_id);
+
+        -- Split view
+        CREATE TABLE IF NOT EXISTS split_view_config (
+            profile_id INTEGER PRIMARY KEY,
+            layout TEXT NOT NULL DEFAULT 'single',
+            pane_1_tab_id INTEGER,
+            pane_2_tab_id INTEGER,
+            pane_3_tab_id INTEGER,
+            pane_4_tab_id INTEGER,
+            active_pane INTEGER DEFAULT 1,
+            pane_sizes TEXT,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+            FOREIGN KEY (pane_1_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+            FOREIGN KEY (pane_2_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+            FOREIGN KEY (pane_3_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+            FOREIGN KEY (pane_4_tab_id) REFERENCES tabs(id) ON DELETE SET NULL
+        );
+
+        -- EarthMultiMedia
+        CREATE TABLE IF NOT EXISTS multimedia_privacy (
+            profile_id INTEGER PRIMARY KEY,
+            history_enabled INTEGER DEFAULT 0,
+            playlist_history_enabled INTEGER DEFAULT 0,
+            require_password INTEGER DEFAULT 0,
+            require_otp INTEGER DEFAULT 0,
+            password_hash TEXT,
+            otp_secret TEXT,
+            auto_clear_history_days INTEGER,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS multimedia_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            media_id INTEGER,
+            source TEXT NOT NULL,
+            media_type TEXT NOT NULL,
+            title TEXT,
+            thumbnail TEXT,
+            position INTEGER DEFAULT 0,
+            duration INTEGER,
+            played_at TEXT NOT NULL,
+            encrypted INTEGER DEFAULT 0,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS multimedia_playlists (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            thumbnail TEXT,
+            is_encrypted INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS multimedia_playlist_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            playlist_id INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            media_type TEXT NOT NULL,
+            title TEXT,
+            thumbnail TEXT,
+            position INTEGER NOT NULL,
+            added_at TEXT NOT NULL,
+            FOREIGN KEY (playlist_id) REFERENCES multimedia_playlists(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_multimedia_history_profile ON multimedia_history(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_multimedia_history_played ON multimedia_history(played_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_multimedia_playlists_profile ON multimedia_playlists(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_multimedia_playlist_items_playlist ON multimedia_playlist_items(playlist_id);
+
+        -- EarthMemory
+        CREATE TABLE IF NOT EXISTS indexed_pages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            url TEXT NOT NULL,
+            title TEXT NOT NULL,
+            content TEXT,
+            summary TEXT,
+            indexed_at TEXT NOT NULL,
+            last_visited TEXT NOT NULL,
+            visit_count INTEGER NOT NULL DEFAULT 1,
+            is_favorite INTEGER NOT NULL DEFAULT 0,
+            tags TEXT,
+            profile_id INTEGER,
+            UNIQUE(url, profile_id),
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS page_notes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            page_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            profile_id INTEGER,
+            FOREIGN KEY (page_id) REFERENCES indexed_pages(id) ON DELETE CASCADE,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        -- Memory shared index names with the history table, so only this one was ever created
+        CREATE INDEX IF NOT EXISTS idx_pages_favorite ON indexed_pages(is_favorite);
+        CREATE INDEX IF NOT EXISTS idx_notes_page ON page_notes(page_id);
+
+        -- Scraper
+        CREATE TABLE IF NOT EXISTS scraping_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            url_pattern TEXT,
+            max_depth INTEGER DEFAULT 2,
+            max_pages INTEGER DEFAULT 100,
+            content_selectors TEXT,
+            schedule_cron TEXT,
+            status TEXT DEFAULT 'pending',
+            last_run_at TEXT,
+            pages_scraped INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS scraped_pages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            title TEXT,
+            content TEXT,
+            metadata TEXT,
+            scraped_at TEXT NOT NULL,
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE,
+            UNIQUE(job_id, url)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_scraped_pages_job ON scraped_pages(job_id);
+        CREATE INDEX IF NOT EXISTS idx_scraped_pages_url ON scraped_pages(url);"
+    )
+}
+
+/// v2: page-level embeddings for memory pages and per-chunk embeddings for long pages
+fn page_embeddings(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "indexed_pages", "embedding")? {
+        conn.execute("ALTER TABLE indexed_pages ADD COLUMN embedding BLOB", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS embedding_chunks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL,
+            source_id INTEGER NOT NULL,
+            profile_id INTEGER,
+            chunk_index INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            embedding BLOB NOT NULL,
+            created_at TEXT NOT NULL,
+            UNIQUE(source, source_id, chunk_index)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_embedding_chunks_source ON embedding_chunks(source, source_id);
+        CREATE INDEX IF NOT EXISTS idx_embedding_chunks_profile ON embedding_chunks(profile_id);"
+    )
+}
+
+/// v3: FTS5 indexes over memory pages and notes, kept in sync by triggers
+fn memory_full_text_search(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS indexed_pages_fts USING fts5(
+            title, content, summary, tags, url,
+            content='indexed_pages', content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2', prefix='2 3'
+        );
+
+        CREATE VIRTUAL TABLE IF NOT EXISTS page_notes_fts USING fts5(
+            content,
+            content='page_notes', content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2', prefix='2 3'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS indexed_pages_fts_insert AFTER INSERT ON indexed_pages BEGIN
+            INSERT INTO indexed_pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS indexed_pages_fts_delete AFTER DELETE ON indexed_pages BEGIN
+            INSERT INTO indexed_pages_fts(indexed_pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS indexed_pages_fts_update
+        AFTER UPDATE OF title, content, summary, tags, url ON indexed_pages BEGIN
+            INSERT INTO indexed_pages_fts(indexed_pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+            INSERT INTO indexed_pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+        END;",
+    )?;
+    create_note_fts_triggers(conn)?;
+
+    conn.execute_batch(
+        "INSERT INTO indexed_pages_fts(indexed_pages_fts) VALUES ('rebuild');
+         INSERT INTO page_notes_fts(page_notes_fts) VALUES ('rebuild');"
+    )
+}
+
+/// v4: opt-in AI summaries
+fn page_summaries(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "privacy_settings", "ai_summaries_enabled")? {
+        conn.execute(
+            "ALTER TABLE privacy_settings ADD COLUMN ai_summaries_enabled INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS page_summaries (
+            page_id INTEGER PRIMARY KEY,
+            summary TEXT NOT NULL,
+            suggested_tags TEXT,
+            content_hash TEXT NOT NULL,
+            model TEXT NOT NULL,
+            generated_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES indexed_pages(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v5: typed relations between pages and outbound links by URL
+fn graph_edges(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS edges (
+            id INTEGER PRIMARY KEY,
+            source_id INTEGER NOT NULL,
+            target_id INTEGER NOT NULL,
+            relation TEXT NOT NULL,
+            weight REAL NOT NULL DEFAULT 1.0,
+            profile_id INTEGER,
+            created_at TEXT NOT NULL,
+            UNIQUE(source_id, target_id, relation),
+            FOREIGN KEY (source_id) REFERENCES pages(id) ON DELETE CASCADE,
+            FOREIGN KEY (target_id) REFERENCES pages(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS page_links (
+            source_id INTEGER NOT NULL,
+            target_url TEXT NOT NULL,
+            PRIMARY KEY (source_id, target_url),
+            FOREIGN KEY (source_id) REFERENCES pages(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_edges_source ON edges(source_id);
+        CREATE INDEX IF NOT EXISTS idx_edges_target ON edges(target_id);
+        CREATE INDEX IF NOT EXISTS idx_edges_profile ON edges(profile_id);
+        CREATE INDEX IF NOT EXISTS idx_page_links_target ON page_links(target_url);"
+    )
+}
+
+/// v6: people, organizations, places, dates and keyphrases mentioned in pages
+fn entity_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS entities (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            normalized TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            UNIQUE(profile_id, kind, normalized)
+        );
+
+        CREATE TABLE IF NOT EXISTS entity_mentions (
+            entity_id INTEGER NOT NULL,
+            source TEXT NOT NULL,
+            source_id INTEGER NOT NULL,
+            count INTEGER NOT NULL DEFAULT 1,
+            PRIMARY KEY (entity_id, source, source_id),
+            FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE
+        );
+
+        -- Names folded into another entity by a merge, so re-extraction lands on the merged one
+        CREATE TABLE IF NOT EXISTS entity_aliases (
+            profile_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            normalized TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            PRIMARY KEY (profile_id, kind, normalized),
+            FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS entity_extractions (
+            source TEXT NOT NULL,
+            source_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            extracted_at TEXT NOT NULL,
+            PRIMARY KEY (source, source_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_entity_mentions_source ON entity_mentions(source, source_id);
+        CREATE INDEX IF NOT EXISTS idx_entities_profile ON entities(profile_id, kind);"
+    )
+}
+
+/// v7: one row per (url, profile) in `pages` and one notes table, shared by history,
+/// memory and the knowledge graph. Memory pages are merged into history pages by url
+/// and every table that referenced an indexed page id is remapped.
+fn unified_page_store(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE pages ADD COLUMN summary TEXT;
+         ALTER TABLE pages ADD COLUMN tags TEXT;
+         ALTER TABLE pages ADD COLUMN indexed_at TEXT;
+         ALTER TABLE pages ADD COLUMN visit_count INTEGER NOT NULL DEFAULT 1;
+         ALTER TABLE pages ADD COLUMN is_favorite INTEGER NOT NULL DEFAULT 0;
+         UPDATE pages SET indexed_at = visited_at;
+
+         CREATE TEMP TABLE page_id_map (
+            old_id INTEGER PRIMARY KEY,
+            new_id INTEGER NOT NULL,
+            -- The merged row kept the indexed page's content, so its chunks still apply
+            kept_content INTEGER NOT NULL
+         );"
+    )?;
+
+    let legacy: Vec<LegacyPage> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite,
+                    tags, profile_id, embedding
+             FROM indexed_pages ORDER BY id"
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(LegacyPage {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                title: row.get(2)?,
+                content: row.get(3)?,
+                summary: row.get(4)?,
+                indexed_at: row.get(5)?,
+                last_visited: row.get(6)?,
+                visit_count: row.get(7)?,
+                is_favorite: row.get(8)?,
+                tags: row.get(9)?,
+                profile_id: row.get(10)?,
+                embedding: row.get(11)?,
+            })
+        })?;
+        rows.collect::<Result<_>>()?
+    };
+
+    for page in &legacy {
+        let (new_id, kept_content) = merge_indexed_page(conn, page)?;
+        conn.execute(
+            "INSERT INTO page_id_map (old_id, new_id, kept_content) VALUES (?1, ?2, ?3)",
+            params![page.id, new_id, kept_content],
+        )?;
+    }
+
+    // Both tables are rebuilt so their foreign keys point at pages instead of indexed_pages
+    conn.execute_batch(
+        "CREATE TABLE page_notes_merged (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            page_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            profile_id INTEGER,
+            FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+         );
+         INSERT INTO page_notes_merged (id, page_id, content, created_at, updated_at, profile_id)
+            SELECT n.id, m.new_id, n.content, n.created_at, n.updated_at, n.profile_id
+            FROM page_notes n JOIN page_id_map m ON m.old_id = n.page_id;
+         INSERT INTO page_notes_merged (page_id, content, created_at, updated_at, profile_id)
+            SELECT n.page_id, n.content, n.created_at, n.created_at, p.profile_id
+            FROM notes n JOIN pages p ON p.id = n.page_id
+            ORDER BY n.id;
+         DROP TABLE page_notes;
+         DROP TABLE notes;
+         ALTER TABLE page_notes_merged RENAME TO page_notes;
+
+         CREATE TABLE page_summaries_merged (
+            page_id INTEGER PRIMARY KEY,
+            summary TEXT NOT NULL,
+            suggested_tags TEXT,
+            content_hash TEXT NOT NULL,
+            model TEXT NOT NULL,
+            generated_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+         );
+         INSERT OR IGNORE INTO page_summaries_merged
+            SELECT m.new_id, s.summary, s.suggested_tags, s.content_hash, s.model, s.generated_at
+            FROM page_summaries s JOIN page_id_map m ON m.old_id = s.page_id;
+         DROP TABLE page_summaries;
+         ALTER TABLE page_summaries_merged RENAME TO page_summaries;
+
+         UPDATE OR IGNORE embedding_chunks
+            SET source = 'pages',
+                source_id = (SELECT new_id FROM page_id_map WHERE old_id = source_id)
+            WHERE source = 'indexed_pages'
+              AND source_id IN (SELECT old_id FROM page_id_map WHERE kept_content = 1);
+         DELETE FROM embedding_chunks WHERE source = 'indexed_pages';
+
+         UPDATE OR IGNORE entity_mentions
+            SET source = 'pages',
+                source_id = (SELECT new_id FROM page_id_map WHERE old_id = source_id)
+            WHERE source = 'indexed_pages';
+         DELETE FROM entity_mentions WHERE source = 'indexed_pages';
+         UPDATE OR IGNORE entity_extractions
+            SET source = 'pages',
+                source_id = (SELECT new_id FROM page_id_map WHERE old_id = source_id)
+            WHERE source = 'indexed_pages';
+         DELETE FROM entity_extractions WHERE source = 'indexed_pages';
+
+         DROP TABLE indexed_pages_fts;
+         DROP TABLE indexed_pages;
+         DROP TABLE temp.page_id_map;
+
+         CREATE INDEX idx_pages_favorite ON pages(is_favorite);
+         CREATE INDEX IF NOT EXISTS idx_notes_page ON page_notes(page_id);
+
+         CREATE VIRTUAL TABLE pages_fts USING fts5(
+            title, content, summary, tags, url,
+            content='pages', content_rowid='id',
+            tokenize='unicode61 remove_diacritics 2', prefix='2 3'
+         );
+
+         CREATE TRIGGER pages_fts_insert AFTER INSERT ON pages BEGIN
+            INSERT INTO pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+         END;
+
+         CREATE TRIGGER pages_fts_delete AFTER DELETE ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+         END;
+
+         CREATE TRIGGER pages_fts_update
+         AFTER UPDATE OF title, content, summary, tags, url ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+            INSERT INTO pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+         END;",
+    )?;
+
+    // The note triggers went away with the old page_notes table
+    create_note_fts_triggers(conn)?;
+
+    conn.execute_batch(
+        "INSERT INTO pages_fts(pages_fts) VALUES ('rebuild');
+         INSERT INTO page_notes_fts(page_notes_fts) VALUES ('rebuild');"
+    )
+}
+
+/// v8: passphrase-protected profiles. The data key is stored wrapped by a key derived
+/// from the passphrase; `locked` says whether the profile's rows are currently encrypted.
+fn profile_encryption(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS profile_encryption (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            locked INTEGER NOT NULL DEFAULT 0,
+            auto_lock_minutes INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// v9: Argon2id salt and costs for the media history/playlist key, and a flag for
+/// playlist items stored encrypted (history rows already had one)
+fn media_key_derivation(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "multimedia_privacy", "key_params")? {
+        conn.execute("ALTER TABLE multimedia_privacy ADD COLUMN key_params TEXT", [])?;
+    }
+    if !column_exists(conn, "multimedia_playlist_items", "encrypted")? {
+        conn.execute(
+            "ALTER TABLE multimedia_playlist_items ADD COLUMN encrypted INTEGER NOT NULL DEFAULT 0",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// v10: the password manager. Like profile_encryption, each vault's data key is stored
+/// wrapped by a key derived from the master password; entries are one encrypted JSON
+/// document each, so nothing but ids and timestamps is readable on disk.
+fn password_vault(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS password_vaults (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS password_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES password_vaults(profile_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_password_entries_profile ON password_entries(profile_id);"
+    )
+}
+
+/// v11: the authenticator, with its own master password but the same layout as the
+/// password manager. HOTP counters live in the encrypted document alongside the secret.
+fn otp_vault(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS otp_vaults (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS otp_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES otp_vaults(profile_id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_otp_entries_profile ON otp_entries(profile_id);"
+    )
+}
+
+/// v12: one row per scraper run, manual or scheduled, instead of only the job's latest
+/// status. `errors` is a JSON array of the first failures, `error_count` counts them all.
+fn scraping_runs(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS scraping_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            trigger TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT,
+            pages_scraped INTEGER NOT NULL DEFAULT 0,
+            error_count INTEGER NOT NULL DEFAULT 0,
+            errors TEXT NOT NULL DEFAULT '[]',
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_scraping_runs_job ON scraping_runs(job_id, started_at);"
+    )
+}
+
+/// v13: per-job crawl settings (user agent, robots.txt, request delay, retries) as JSON
+fn scraper_settings(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "scraping_jobs", "settings")? {
+        conn.execute("ALTER TABLE scraping_jobs ADD COLUMN settings TEXT NOT NULL DEFAULT '{}'", [])?;
+    }
+    Ok(())
+}
+
+/// v14: the queue of a crawl in progress, so it can resume after the app quits, and a
+/// hash of each scraped page's text for spotting the same page under another URL.
+/// `state` is queued, scraped, failed, skipped or duplicate.
+fn scraping_frontier(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "scraped_pages", "content_hash")? {
+        conn.execute("ALTER TABLE scraped_pages ADD COLUMN content_hash TEXT", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS scraping_frontier (
+            job_id INTEGER NOT NULL,
+            url_key TEXT NOT NULL,
+            url TEXT NOT NULL,
+            depth INTEGER NOT NULL,
+            seq INTEGER NOT NULL,
+            state TEXT NOT NULL DEFAULT 'queued',
+            PRIMARY KEY (job_id, url_key),
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_scraped_pages_hash ON scraped_pages(job_id, content_hash);"
+    )
+}
+
+/// v15: re-scraping keeps every distinct version of a page instead of overwriting it.
+/// Pages remember their ETag, Last-Modified and links for conditional requests, and
+/// whether the user watches them for changes. Existing pages become their first version.
+fn scraped_page_versions(conn: &Connection) -> Result<()> {
+    for (column, definition) in [
+        ("etag", "TEXT"),
+        ("last_modified", "TEXT"),
+        ("links", "TEXT NOT NULL DEFAULT '[]'"),
+        ("checked_at", "TEXT"),
+        ("changed_at", "TEXT"),
+        ("watch", "INTEGER NOT NULL DEFAULT 0"),
+    ] {
+        if !column_exists(conn, "scraped_pages", column)? {
+            conn.execute(&format!("ALTER TABLE scraped_pages ADD COLUMN {} {}", column, definition), [])?;
+        }
+    }
+    if !column_exists(conn, "scraping_runs", "pages_changed")? {
+        conn.execute("ALTER TABLE scraping_runs ADD COLUMN pages_changed INTEGER NOT NULL DEFAULT 0", [])?;
+    }
+
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS scraped_page_versions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            page_id INTEGER NOT NULL,
+            title TEXT,
+            content TEXT NOT NULL,
+            metadata TEXT,
+            content_hash TEXT,
+            scraped_at TEXT NOT NULL,
+            FOREIGN KEY (page_id) REFERENCES scraped_pages(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_scraped_page_versions_page ON scraped_page_versions(page_id, id);
+
+        INSERT INTO scraped_page_versions (page_id, title, content, metadata, content_hash, scraped_at)
+        SELECT id, title, COALESCE(content, ''), metadata, content_hash, scraped_at FROM scraped_pages
+        WHERE id NOT IN (SELECT page_id FROM scraped_page_versions);"
+    )
+}
+
+fn create_note_fts_triggers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS page_notes_fts_insert AFTER INSERT ON page_notes BEGIN
+            INSERT INTO page_notes_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS page_notes_fts_delete AFTER DELETE ON page_notes BEGIN
+            INSERT INTO page_notes_fts(page_notes_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS page_notes_fts_update AFTER UPDATE OF content ON page_notes BEGIN
+            INSERT INTO page_notes_fts(page_notes_fts, rowid, content) VALUES ('delete', old.id, old.content);
+            INSERT INTO page_notes_fts(rowid, content) VALUES (new.id, new.content);
+        END;"
+    )
+}
+
+struct LegacyPage {
+    id: i64,
+    url: String,
+    title: String,
+    content: Option<String>,
+    summary: Option<String>,
+    indexed_at: String,
+    last_visited: String,
+    visit_count: i64,
+    is_favorite: i64,
+    tags: Option<String>,
+    profile_id: Option<i64>,
+    embedding: Option<Vec<u8>>,
+}
+
+/// Insert or merge one indexed page into `pages`. Returns the pages id and whether
+/// the row now carries the indexed page's content (and embedding).
+fn merge_indexed_page(conn: &Connection, page: &LegacyPage) -> Result<(i64, bool)> {
+    let existing: Option<(i64, Option<String>, String, Option<String>)> = conn.query_row(
+        "SELECT id, content, visited_at, indexed_at FROM pages WHERE url = ?1 AND profile_id IS ?2",
+        params![page.url, page.profile_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    ).optional()?;
+
+    let Some((id, content, visited_at, indexed_at)) = existing else {
+        conn.execute(
+            "INSERT INTO pages (url, title, content, summary, tags, visited_at, indexed_at,
+                                visit_count, is_favorite, embedding, profile_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                page.url,
+                page.title,
+                page.content,
+                page.summary,
+                page.tags,
+                page.last_visited,
+                page.indexed_at,
+                page.visit_count,
+                page.is_favorite,
+                page.embedding,
+                page.profile_id
+            ],
+        )?;
+        return Ok((conn.last_insert_rowid(), true));
+    };
+
+    // The history row wins unless it never had any text
+    let take_content = content.as_deref().unwrap_or("").is_empty()
+        && !page.content.as_deref().unwrap_or("").is_empty();
+    let newer_visit = timestamp(&page.last_visited) > timestamp(&visited_at);
+    let first_seen = match indexed_at {
+        Some(at) if timestamp(&at) <= timestamp(&page.indexed_at) => at,
+        _ => page.indexed_at.clone(),
+    };
+
+    conn.execute(
+        "UPDATE pages SET
+            title = CASE WHEN ?1 THEN ?2 ELSE title END,
+            visited_at = CASE WHEN ?1 THEN ?3 ELSE visited_at END,
+            content = CASE WHEN ?4 THEN ?5 ELSE content END,
+            embedding = CASE WHEN ?4 THEN ?6 ELSE embedding END,
+            summary = COALESCE(summary, ?7),
+            tags = COALESCE(tags, ?8),
+            indexed_at = ?9,
+            visit_count = MAX(visit_count, ?10),
+            is_favorite = MAX(is_favorite, ?11)
+         WHERE id = ?12",
+        params![
+            newer_visit,
+            page.title,
+            page.last_visited,
+            take_content,
+            page.content,
+            page.embedding,
+            page.summary,
+            page.tags,
+            first_seen,
+            page.visit_count,
+            page.is_favorite,
+            id
+        ],
+    )?;
+    Ok((id, take_content))
+}
+
+/// Stored timestamps are unix seconds as text
+fn timestamp(value: &str) -> i64 {
+    value.trim().parse().unwrap_or(0)
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+        params![column],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+/// Fresh, fully migrated database with profiles 1 and 2, for tests in other modules
+#[cfg(test)]
+pub fn test_database(name: &str) -> crate::db::DbPool {
+    let path = std::env::temp_dir().join(format!("earth_{}_{}.db", name, std::process::id()));
+    let db_path = path.to_string_lossy().to_string();
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{}", db_path, suffix));
+    }
+    run(&db_path).unwrap();
+
+    let db = crate::db::DbPool::open(&db_path).unwrap();
+    db.get().unwrap().execute_batch(
+        "INSERT INTO profiles (id, name, created_at) VALUES (1, 'One', '0'), (2, 'Two', '0');"
+    ).unwrap();
+    db
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("earth_migrations_{}_{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(format!("{}.v{}.bak", path.to_string_lossy(), 0));
+        path.to_string_lossy().to_string()
+    }
+
+    /// Table name -> sorted column names, for comparing schemas reached by different paths
+    fn schema(conn: &Connection) -> Vec<(String, Vec<String>)> {
+        let tables: Vec<String> = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_>>().unwrap();
+
+        tables
+            .into_iter()
+            .map(|table| {
+                let mut columns: Vec<String> = conn
+                    .prepare(&format!("SELECT name FROM pragma_table_info('{}')", table)).unwrap()
+                    .query_map([], |row| row.get(0)).unwrap()
+                    .collect::<Result<_>>().unwrap();
+                columns.sort();
+                (table, columns)
+            })
+            .collect()
+    }
+
+    /// `sqlite3 .dump` of one user database, taken after each released build opened it,
+    /// oldest first. The v1 build stored a profile, a history page with a note and a memory
+    /// page with a note; every later build upgraded that same file at startup, so
+    /// `schema_version` records when each migration really ran. v1-v7 predate
+    /// schema_version. Dumps are never edited; a release that adds a migration adds its own.
+    const SNAPSHOTS: &[(i64, &str)] = &[
+        (1, include_str!("../tests/fixtures/migrations/v1.sql")),
+        (2, include_str!("../tests/fixtures/migrations/v2.sql")),
+        (3, include_str!("../tests/fixtures/migrations/v3.sql")),
+        (4, include_str!("../tests/fixtures/migrations/v4.sql")),
+        (5, include_str!("../tests/fixtures/migrations/v5.sql")),
+        (6, include_str!("../tests/fixtures/migrations/v6.sql")),
+        (7, include_str!("../tests/fixtures/migrations/v7.sql")),
+        (8, include_str!("../tests/fixtures/migrations/v8.sql")),
+        (9, include_str!("../tests/fixtures/migrations/v9.sql")),
+        (10, include_str!("../tests/fixtures/migrations/v10.sql")),
+        (11, include_str!("../tests/fixtures/migrations/v11.sql")),
+        (12, include_str!("../tests/fixtures/migrations/v12.sql")),
+        (13, include_str!("../tests/fixtures/migrations/v13.sql")),
+        (14, include_str!("../tests/fixtures/migrations/v14.sql")),
+        (15, include_str!("../tests/fixtures/migrations/v15.sql")),
+    ];
+
+    #[test]
+    fn test_upgrades_from_every_version() {
+        let fresh_path = temp_path("fresh");
+        let report = run(&fresh_path).unwrap();
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.applied.len() as i64, latest_version());
+        assert!(report.backup_path.is_none());
+        let expected = schema(&Connection::open(&fresh_path).unwrap());
+
+        let versions: Vec<i64> = SNAPSHOTS.iter().map(|(version, _)| *version).collect();
+        assert_eq!(versions, (1..=latest_version()).collect::<Vec<_>>(), "every released version has a snapshot");
+
+        for (version, dump) in SNAPSHOTS {
+            let version = *version;
+            let path = temp_path(&format!("v{}", version));
+            Connection::open(&path).unwrap().execute_batch(dump).unwrap();
+
+            let report = run(&path).unwrap();
+            assert_eq!(report.from_version, version, "detected version for v{}", version);
+            assert_eq!(report.to_version, latest_version());
+            assert_eq!(report.applied.len() as i64, latest_version() - version);
+            assert_eq!(report.backup_path.is_some(), version < latest_version());
+            if let Some(backup) = &report.backup_path {
+                assert!(std::path::Path::new(backup).exists());
+                let _ = std::fs::remove_file(backup);
+            }
+
+            let conn = Connection::open(&path).unwrap();
+            assert_eq!(schema(&conn), expected, "schema after upgrading from v{}", version);
+
+            // Both pages survive with their notes, and search sees them
+            let notes: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM page_notes n JOIN pages p ON p.id = n.page_id
+                 WHERE p.url IN ('https://history.example.com', 'https://memory.example.com')",
+                [],
+                |row| row.get(0),
+            ).unwrap();
+            assert_eq!(notes, 2, "notes after upgrading from v{}", version);
+            let hits: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM pages_fts WHERE pages_fts MATCH 'reading'",
+                [],
+                |row| row.get(0),
+            ).unwrap();
+            assert_eq!(hits, 1, "search after upgrading from v{}", version);
+
+            // Running again is a no-op
+            assert!(run(&path).unwrap().applied.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_failed_migration_rolls_back() {
+        let path = temp_path("rollback");
+        let conn = Connection::open(&path).unwrap();
+        migrate(&conn, &path, 6).unwrap();
+        // v7 adds this column, so the migration fails halfway through
+        conn.execute("ALTER TABLE pages ADD COLUMN is_favorite INTEGER", []).unwrap();
+        drop(conn);
+
+        assert!(run(&path).is_err());
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), 6);
+        assert!(table_exists(&conn, "indexed_pages").unwrap());
+        assert!(!column_exists(&conn, "pages", "summary").unwrap());
+        let _ = std::fs::remove_file(format!("{}.v6.bak", path));
+    }
+
+    #[test]
+    fn test_unified_page_store_merges_duplicates() {
+        let path = temp_path("merge");
+        let conn = Connection::open(&path).unwrap();
+        migrate(&conn, &path, 6).unwrap();
+
+        conn.execute_batch(
+            "INSERT INTO profiles (id, name, created_at) VALUES (1, 'Default', '1');
+             INSERT INTO pages (id, url, title, content, visited_at, profile_id)
+                VALUES (1, 'https://shared.example.com', 'Shared (graph)', 'graph text', '200', 1),
+                       (2, 'https://graph-only.example.com', 'Graph only', 'history text', '150', 1);
+             INSERT INTO notes (page_id, content, created_at) VALUES (1, 'graph note', '210');
+
+             INSERT INTO indexed_pages (id, url, title, content, summary, indexed_at, last_visited, visit_count, is_favorite, tags, profile_id)
+                VALUES (1, 'https://memory-only.example.com', 'Memory only', 'memory text', 'saved', '50', '300', 4, 1, 'rust', 1),
+                       (2, 'https://shared.example.com', 'Shared (memory)', 'memory copy', 'summary', '100', '250', 3, 1, 'docs', 1);
+             INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id)
+                VALUES (1, 'memory note', '60', '60', 1), (2, 'shared note', '110', '110', 1);
+             INSERT INTO page_summaries (page_id, summary, content_hash, model, generated_at)
+                VALUES (2, 'generated', 'hash', 'model', '120');
+             INSERT INTO embedding_chunks (source, source_id, profile_id, chunk_index, content, embedding, created_at)
+                VALUES ('indexed_pages', 1, 1, 0, 'memory text', x'00000000', '1'),
+                       ('indexed_pages', 2, 1, 0, 'memory copy', x'00000000', '1');"
+        ).unwrap();
+
+        migrate(&conn, &path, 7).unwrap();
+
+        let shared: (i64, String, String, String, String, i64, i64, String) = conn.query_row(
+            "SELECT id, title, content, visited_at, indexed_at, visit_count, is_favorite, tags
+             FROM pages WHERE url = 'https://shared.example.com'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?)),
+        ).unwrap();
+        assert_eq!(shared, (1, "Shared (memory)".into(), "graph text".into(), "250".into(), "100".into(), 3, 1, "docs".into()));
+
+        let memory_only: i64 = conn.query_row(
+            "SELECT id FROM pages WHERE url = 'https://memory-only.example.com' AND summary = 'saved'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM pages", [], |row| row.get::<_, i64>(0)).unwrap(), 3);
+
+        // Notes from both tables hang off the merged pages
+        let notes: Vec<(i64, String)> = conn
+            .prepare("SELECT page_id, content FROM page_notes ORDER BY content").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<Result<_>>().unwrap();
+        assert_eq!(notes, vec![(1, "graph note".into()), (memory_only, "memory note".into()), (1, "shared note".into())]);
+
+        let summary_page: i64 = conn.query_row("SELECT page_id FROM page_summaries", [], |row| row.get(0)).unwrap();
+        assert_eq!(summary_page, 1);
+
+        // Chunks of content that was dropped in the merge are discarded
+        let chunks: Vec<(String, i64)> = conn
+            .prepare("SELECT source, source_id FROM embedding_chunks").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<Result<_>>().unwrap();
+        assert_eq!(chunks, vec![("pages".to_string(), memory_only)]);
+
+        let hits: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM page_notes_fts WHERE page_notes_fts MATCH 'note'",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(hits, 3);
+
+        // Deleting a page from history removes its notes and summary
+        conn.execute("DELETE FROM pages WHERE id = 1", []).unwrap();
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM page_notes", [], |row| row.get::<_, i64>(0)).unwrap(), 1);
+        assert_eq!(conn.query_row("SELECT COUNT(*) FROM page_summaries", [], |row| row.get::<_, i64>(0)).unwrap(), 0);
+        let _ = std::fs::remove_file(format!("{}.v6.bak", path));
+    }
+}
@@ -1,15 +1,14 @@
 // EarthMultiMedia - Privacy-focused media player
 // Supports video, image, and audio with optional encrypted history
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
-use rand::Rng;
-use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce,
-};
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+
+use crate::crypto::{self, Key};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::otp;
 
 // ==================== Types ====================
 
@@ -97,6 +96,8 @@ pub struct PlaylistItem {
     pub thumbnail: Option<String>,
     pub position: i32,
     pub added_at: String,
+    #[serde(default)]
+    pub encrypted: bool,          // If true, data is encrypted
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -138,20 +139,21 @@ pub struct MediaStats {
 
 // ==================== Manager ====================
 
+#[derive(Clone)]
 pub struct MultimediaManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl MultimediaManager {
-    pub fn new(db_path: String) -> Self {
-        MultimediaManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        MultimediaManager { db }
     }
 
     // ==================== Privacy Settings ====================
 
     /// Get privacy settings for a profile
     pub fn get_privacy_settings(&self, profile_id: i64) -> Result<PrivacySettings> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let result = conn.query_row(
             "SELECT profile_id, history_enabled, playlist_history_enabled, require_password,
@@ -184,7 +186,7 @@ impl MultimediaManager {
 
     /// Create default privacy settings
     fn create_default_privacy_settings(&self, profile_id: i64) -> Result<PrivacySettings> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "INSERT INTO multimedia_privacy (profile_id, history_enabled, playlist_history_enabled,
@@ -201,7 +203,7 @@ impl MultimediaManager {
 
     /// Update privacy settings
     pub fn update_privacy_settings(&self, settings: &PrivacySettings) -> Result<PrivacySettings> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Ensure settings exist
         let _ = self.get_privacy_settings(settings.profile_id)?;
@@ -228,14 +230,12 @@ impl MultimediaManager {
     }
 
     /// Set password for media history access
-    pub fn set_password(&self, profile_id: i64, password: &str) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-
-        // Hash password with SHA256
-        let mut hasher = Sha256::new();
-        hasher.update(password.as_bytes());
-        let hash = format!("{:x}", hasher.finalize());
+    pub fn set_password(&self, profile_id: i64, password: &str) -> AppResult<()> {
+        // Ensure settings exist
+        let _ = self.get_privacy_settings(profile_id)?;
+        let conn = self.db.get()?;
 
+        let hash = crypto::hash_password(password)?;
         conn.execute(
             "UPDATE multimedia_privacy SET password_hash = ?1, require_password = 1 WHERE profile_id = ?2",
             params![hash, profile_id],
@@ -244,27 +244,120 @@ impl MultimediaManager {
         Ok(())
     }
 
-    /// Verify password
-    pub fn verify_password(&self, profile_id: i64, password: &str) -> Result<bool> {
+    /// Verify password. A correct password also upgrades what was stored for it:
+    /// legacy SHA-256 hashes, outdated Argon2 costs and old-format ciphertexts.
+    pub fn verify_password(&self, profile_id: i64, password: &str) -> AppResult<bool> {
         let settings = self.get_privacy_settings(profile_id)?;
 
-        if let Some(stored_hash) = settings.password_hash {
-            let mut hasher = Sha256::new();
-            hasher.update(password.as_bytes());
-            let hash = format!("{:x}", hasher.finalize());
-            Ok(hash == stored_hash)
+        let Some(stored_hash) = settings.password_hash else {
+            return Ok(true); // No password set
+        };
+        let valid = if stored_hash.starts_with('$') {
+            crypto::verify_password(password, &stored_hash)?
         } else {
-            Ok(true) // No password set
+            legacy_password_hash(password) == stored_hash
+        };
+
+        if valid {
+            self.upgrade_encryption(profile_id, password, crypto::needs_rehash(&stored_hash))?;
         }
+        Ok(valid)
     }
 
-    /// Generate OTP secret for TOTP
-    pub fn generate_otp_secret(&self, profile_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+    /// Rehash the password if asked, and move encrypted history and playlist items to
+    /// the current key: rows still under the legacy key, or everything when the key
+    /// parameters are missing or outdated
+    fn upgrade_encryption(&self, profile_id: i64, password: &str, rehash: bool) -> AppResult<()> {
+        let mut conn = self.db.get()?;
+        let stored_params = key_params(&conn, profile_id)?;
+        let rotate = stored_params.as_deref().is_none_or(crypto::needs_rehash);
+        let legacy_rows: i64 = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM multimedia_history
+                     WHERE profile_id = ?1 AND encrypted = 1 AND source NOT LIKE 'v2:%')
+                  + (SELECT COUNT(*) FROM multimedia_playlist_items i
+                     JOIN multimedia_playlists p ON p.id = i.playlist_id
+                     WHERE p.profile_id = ?1 AND i.encrypted = 1 AND i.source NOT LIKE 'v2:%')",
+            params![profile_id],
+            |row| row.get(0),
+        )?;
+        if !rehash && !rotate && legacy_rows == 0 {
+            return Ok(());
+        }
 
-        // Generate random 20-byte secret
-        let secret: [u8; 20] = rand::thread_rng().gen();
-        let secret_base32 = base32_encode(&secret);
+        let old = MediaCipher::new(password, stored_params.as_deref())?;
+        let (new, key_params) = if rotate {
+            let key_params = crypto::new_key_params();
+            (MediaCipher::new(password, Some(&key_params))?, Some(key_params))
+        } else {
+            (old.clone(), None)
+        };
+
+        let tx = conn.transaction()?;
+        if rehash {
+            tx.execute(
+                "UPDATE multimedia_privacy SET password_hash = ?1 WHERE profile_id = ?2",
+                params![crypto::hash_password(password)?, profile_id],
+            )?;
+        }
+        if let Some(key_params) = &key_params {
+            tx.execute(
+                "UPDATE multimedia_privacy SET key_params = ?1 WHERE profile_id = ?2",
+                params![key_params, profile_id],
+            )?;
+        }
+        reseal_rows(
+            &tx,
+            "SELECT id, source, title, thumbnail FROM multimedia_history
+             WHERE profile_id = ?1 AND encrypted = 1",
+            "UPDATE multimedia_history SET source = ?1, title = ?2, thumbnail = ?3 WHERE id = ?4",
+            profile_id, &old, &new, rotate,
+        )?;
+        reseal_rows(
+            &tx,
+            "SELECT i.id, i.source, i.title, i.thumbnail FROM multimedia_playlist_items i
+             JOIN multimedia_playlists p ON p.id = i.playlist_id
+             WHERE p.profile_id = ?1 AND i.encrypted = 1",
+            "UPDATE multimedia_playlist_items SET source = ?1, title = ?2, thumbnail = ?3 WHERE id = ?4",
+            profile_id, &old, &new, rotate,
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The cipher for a profile's history and playlists, creating its key parameters
+    /// on first use
+    fn media_cipher(&self, profile_id: i64, password: &str) -> AppResult<MediaCipher> {
+        // Ensure settings exist
+        let _ = self.get_privacy_settings(profile_id)?;
+        let conn = self.db.get()?;
+
+        let key_params = match key_params(&conn, profile_id)? {
+            Some(key_params) => key_params,
+            None => {
+                let key_params = crypto::new_key_params();
+                conn.execute(
+                    "UPDATE multimedia_privacy SET key_params = ?1 WHERE profile_id = ?2",
+                    params![key_params, profile_id],
+                )?;
+                key_params
+            }
+        };
+        MediaCipher::new(password, Some(&key_params))
+    }
+
+    /// Checks a password given for writing encrypted rows, so a typo can't store rows
+    /// nobody can read back
+    fn cipher_for_write(&self, profile_id: i64, password: &str) -> AppResult<MediaCipher> {
+        if !self.verify_password(profile_id, password)? {
+            return Err(AppError::Crypto("Invalid password".to_string()));
+        }
+        self.media_cipher(profile_id, password)
+    }
+
+    /// Generate a TOTP secret (SHA1, 6 digits, 30 seconds) and require OTP from now on
+    pub fn generate_otp_secret(&self, profile_id: i64) -> Result<String> {
+        let conn = self.db.get()?;
+        let secret_base32 = otp::generate_secret();
 
         conn.execute(
             "UPDATE multimedia_privacy SET otp_secret = ?1, require_otp = 1 WHERE profile_id = ?2",
@@ -274,19 +367,15 @@ impl MultimediaManager {
         Ok(secret_base32)
     }
 
-    /// Verify OTP code
+    /// Verify an OTP code, allowing one step of clock skew
     pub fn verify_otp(&self, profile_id: i64, code: &str) -> Result<bool> {
         let settings = self.get_privacy_settings(profile_id)?;
 
         if let Some(secret) = settings.otp_secret {
-            // Simple TOTP verification (30-second window)
-            let time_step = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() / 30;
-
-            let expected = generate_totp(&secret, time_step);
-            Ok(code == expected)
+            let Ok(secret) = otp::base32_decode(&secret) else {
+                return Ok(false);
+            };
+            Ok(otp::verify_totp(&secret, code, otp::unix_now(), 30, otp::OtpAlgorithm::Sha1, 6))
         } else {
             Ok(true) // No OTP set
         }
@@ -296,30 +385,25 @@ impl MultimediaManager {
 
     /// Add history entry (only if history is enabled)
     /// If password is provided and require_password is enabled, the entry will be encrypted
-    pub fn add_history_entry(&self, entry: &MediaHistoryEntry, password: Option<&str>) -> Result<Option<MediaHistoryEntry>> {
+    pub fn add_history_entry(&self, entry: &MediaHistoryEntry, password: Option<&str>) -> AppResult<Option<MediaHistoryEntry>> {
         let settings = self.get_privacy_settings(entry.profile_id)?;
 
         if !settings.history_enabled {
             return Ok(None); // History disabled, don't save
         }
 
-        let conn = Connection::open(&self.db_path)?;
-        let now = chrono::Utc::now().to_rfc3339();
-
         // Encrypt if password protection is enabled and password is provided
-        let (final_entry, is_encrypted) = if settings.require_password {
-            if let Some(pwd) = password {
-                match encrypt_history_entry(entry, pwd) {
-                    Ok(encrypted) => (encrypted, true),
-                    Err(_) => (entry.clone(), false),
-                }
-            } else {
-                (entry.clone(), false)
+        let (final_entry, is_encrypted) = match password {
+            Some(pwd) if settings.require_password => {
+                let cipher = self.cipher_for_write(entry.profile_id, pwd)?;
+                (encrypt_history_entry(entry, &cipher)?, true)
             }
-        } else {
-            (entry.clone(), false)
+            _ => (entry.clone(), false),
         };
 
+        let conn = self.db.get()?;
+        let now = chrono::Utc::now().to_rfc3339();
+
         conn.execute(
             "INSERT INTO multimedia_history (profile_id, media_id, source, media_type, title,
                 thumbnail, position, duration, played_at, encrypted)
@@ -350,21 +434,19 @@ impl MultimediaManager {
 
     /// Get history entries
     /// If password is provided, encrypted entries will be decrypted
-    pub fn get_history(&self, profile_id: i64, limit: i32, password: Option<&str>) -> Result<Vec<MediaHistoryEntry>> {
+    pub fn get_history(&self, profile_id: i64, limit: i32, password: Option<&str>) -> AppResult<Vec<MediaHistoryEntry>> {
         let settings = self.get_privacy_settings(profile_id)?;
 
         // Check password if required
         if settings.require_password {
-            if let Some(pwd) = password {
-                if !self.verify_password(profile_id, pwd)? {
-                    return Err(rusqlite::Error::InvalidQuery);
-                }
-            } else {
-                return Err(rusqlite::Error::InvalidQuery);
+            match password {
+                Some(pwd) if self.verify_password(profile_id, pwd)? => {}
+                Some(_) => return Err(AppError::Crypto("Invalid password".to_string())),
+                None => return Err(AppError::Crypto("A password is required to view media history".to_string())),
             }
         }
 
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, media_id, source, media_type, title, thumbnail,
                     position, duration, played_at, encrypted
@@ -392,22 +474,20 @@ impl MultimediaManager {
         })?.collect::<Result<Vec<_>>>()?;
 
         // Decrypt entries if password provided
-        if let Some(pwd) = password {
-            Ok(entries.into_iter().map(|entry| {
-                if entry.encrypted {
-                    decrypt_history_entry(&entry, pwd).unwrap_or(entry)
-                } else {
-                    entry
-                }
-            }).collect())
-        } else {
-            Ok(entries)
+        match password {
+            Some(pwd) if entries.iter().any(|entry| entry.encrypted) => {
+                let cipher = self.media_cipher(profile_id, pwd)?;
+                Ok(entries.into_iter().map(|entry| {
+                    decrypt_history_entry(&entry, &cipher).unwrap_or(entry)
+                }).collect())
+            }
+            _ => Ok(entries),
         }
     }
 
     /// Clear all history
     pub fn clear_history(&self, profile_id: i64) -> Result<i32> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let count = conn.execute(
             "DELETE FROM multimedia_history WHERE profile_id = ?1",
             params![profile_id],
@@ -417,7 +497,7 @@ impl MultimediaManager {
 
     /// Delete single history entry
     pub fn delete_history_entry(&self, entry_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.execute(
             "DELETE FROM multimedia_history WHERE id = ?1",
             params![entry_id],
@@ -429,7 +509,7 @@ impl MultimediaManager {
 
     /// Create playlist
     pub fn create_playlist(&self, profile_id: i64, name: &str, description: Option<&str>, encrypted: bool) -> Result<Playlist> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono::Utc::now().to_rfc3339();
 
         conn.execute(
@@ -455,7 +535,7 @@ impl MultimediaManager {
 
     /// Get all playlists
     pub fn get_playlists(&self, profile_id: i64) -> Result<Vec<Playlist>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT p.id, p.profile_id, p.name, p.description, p.thumbnail, p.is_encrypted,
                     p.created_at, p.updated_at, COUNT(i.id) as item_count
@@ -485,7 +565,7 @@ impl MultimediaManager {
 
     /// Delete playlist
     pub fn delete_playlist(&self, playlist_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Delete items first
         conn.execute(
@@ -503,8 +583,27 @@ impl MultimediaManager {
     }
 
     /// Add item to playlist
-    pub fn add_to_playlist(&self, playlist_id: i64, source: &str, media_type: &str, title: Option<&str>, thumbnail: Option<&str>) -> Result<PlaylistItem> {
-        let conn = Connection::open(&self.db_path)?;
+    /// If the playlist is encrypted and a password is provided, the item will be encrypted
+    pub fn add_to_playlist(&self, playlist_id: i64, source: &str, media_type: &str, title: Option<&str>, thumbnail: Option<&str>, password: Option<&str>) -> AppResult<PlaylistItem> {
+        let item = PlaylistItem {
+            id: None,
+            playlist_id,
+            source: source.to_string(),
+            media_type: MediaType::from(media_type),
+            title: title.map(String::from),
+            thumbnail: thumbnail.map(String::from),
+            position: 0,
+            added_at: String::new(),
+            encrypted: false,
+        };
+        let stored = match (self.playlist_owner(playlist_id)?, password) {
+            (Some((profile_id, true)), Some(pwd)) => {
+                encrypt_playlist_item(&item, &self.cipher_for_write(profile_id, pwd)?)?
+            }
+            _ => item.clone(),
+        };
+
+        let conn = self.db.get()?;
         let now = chrono::Utc::now().to_rfc3339();
 
         // Get next position
@@ -515,9 +614,9 @@ impl MultimediaManager {
         )?;
 
         conn.execute(
-            "INSERT INTO multimedia_playlist_items (playlist_id, source, media_type, title, thumbnail, position, added_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![playlist_id, source, media_type, title, thumbnail, position, now],
+            "INSERT INTO multimedia_playlist_items (playlist_id, source, media_type, title, thumbnail, position, added_at, encrypted)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![playlist_id, stored.source, media_type, stored.title, stored.thumbnail, position, now, stored.encrypted],
         )?;
 
         let id = conn.last_insert_rowid();
@@ -530,27 +629,25 @@ impl MultimediaManager {
 
         Ok(PlaylistItem {
             id: Some(id),
-            playlist_id,
-            source: source.to_string(),
-            media_type: MediaType::from(media_type),
-            title: title.map(String::from),
-            thumbnail: thumbnail.map(String::from),
             position,
             added_at: now,
+            encrypted: stored.encrypted,
+            ..item
         })
     }
 
     /// Get playlist items
-    pub fn get_playlist_items(&self, playlist_id: i64) -> Result<Vec<PlaylistItem>> {
-        let conn = Connection::open(&self.db_path)?;
+    /// Encrypted items need the password; they are returned decrypted
+    pub fn get_playlist_items(&self, playlist_id: i64, password: Option<&str>) -> AppResult<Vec<PlaylistItem>> {
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, playlist_id, source, media_type, title, thumbnail, position, added_at
+            "SELECT id, playlist_id, source, media_type, title, thumbnail, position, added_at, encrypted
              FROM multimedia_playlist_items
              WHERE playlist_id = ?1
              ORDER BY position ASC"
         )?;
 
-        let items = stmt.query_map(params![playlist_id], |row| {
+        let items: Vec<PlaylistItem> = stmt.query_map(params![playlist_id], |row| {
             let media_type_str: String = row.get(3)?;
             Ok(PlaylistItem {
                 id: row.get(0)?,
@@ -561,15 +658,41 @@ impl MultimediaManager {
                 thumbnail: row.get(5)?,
                 position: row.get(6)?,
                 added_at: row.get(7)?,
+                encrypted: row.get(8)?,
             })
-        })?;
+        })?.collect::<Result<Vec<_>>>()?;
 
-        items.collect()
+        if !items.iter().any(|item| item.encrypted) {
+            return Ok(items);
+        }
+        let Some((profile_id, _)) = self.playlist_owner(playlist_id)? else {
+            return Ok(items);
+        };
+        let Some(pwd) = password else {
+            return Err(AppError::Crypto("A password is required to view this playlist".to_string()));
+        };
+        if !self.verify_password(profile_id, pwd)? {
+            return Err(AppError::Crypto("Invalid password".to_string()));
+        }
+        let cipher = self.media_cipher(profile_id, pwd)?;
+        Ok(items.into_iter().map(|item| {
+            decrypt_playlist_item(&item, &cipher).unwrap_or(item)
+        }).collect())
+    }
+
+    /// Profile id and encryption flag of a playlist
+    fn playlist_owner(&self, playlist_id: i64) -> Result<Option<(i64, bool)>> {
+        let conn = self.db.get()?;
+        conn.query_row(
+            "SELECT profile_id, is_encrypted FROM multimedia_playlists WHERE id = ?1",
+            params![playlist_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()
     }
 
     /// Remove item from playlist
     pub fn remove_from_playlist(&self, item_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.execute(
             "DELETE FROM multimedia_playlist_items WHERE id = ?1",
             params![item_id],
@@ -579,7 +702,7 @@ impl MultimediaManager {
 
     /// Reorder playlist items
     pub fn reorder_playlist_items(&self, playlist_id: i64, item_ids: Vec<i64>) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         for (position, item_id) in item_ids.iter().enumerate() {
             conn.execute(
@@ -595,7 +718,7 @@ impl MultimediaManager {
 
     /// Get media stats
     pub fn get_stats(&self, profile_id: i64) -> Result<MediaStats> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let total_played: i32 = conn.query_row(
             "SELECT COUNT(*) FROM multimedia_history WHERE profile_id = ?1",
@@ -646,187 +769,308 @@ impl MultimediaManager {
 
 // ==================== Encryption Helper Functions ====================
 
-/// Derives a 32-byte key from a password using SHA256
-fn derive_key_from_password(password: &str) -> [u8; 32] {
-    let mut hasher = Sha256::new();
-    hasher.update(password.as_bytes());
-    // Add salt for better security
-    hasher.update(b"EarthMultiMedia_v1_salt");
-    let result = hasher.finalize();
-    let mut key = [0u8; 32];
-    key.copy_from_slice(&result);
-    key
-}
+/// Marks fields sealed with the profile's Argon2id media key. Fields without it are
+/// bare base64 under the legacy key and get resealed after the next successful login.
+const SEALED_PREFIX: &str = "v2:";
 
-/// Encrypts data using AES-256-GCM
-pub fn encrypt_data(plaintext: &str, password: &str) -> Result<String, String> {
-    let key = derive_key_from_password(password);
-    let cipher = Aes256Gcm::new_from_slice(&key)
-        .map_err(|e| format!("Failed to create cipher: {}", e))?;
-
-    // Generate random 12-byte nonce
-    let nonce_bytes: [u8; 12] = rand::thread_rng().gen();
-    let nonce = Nonce::from_slice(&nonce_bytes);
-
-    // Encrypt
-    let ciphertext = cipher
-        .encrypt(nonce, plaintext.as_bytes())
-        .map_err(|e| format!("Encryption failed: {}", e))?;
-
-    // Prepend nonce to ciphertext and encode as base64
-    let mut result = nonce_bytes.to_vec();
-    result.extend(ciphertext);
-    Ok(BASE64.encode(&result))
+/// Media history and playlist fields for one password: the profile's Argon2id key,
+/// plus the legacy key that older ciphertexts were written with
+#[derive(Clone)]
+pub struct MediaCipher {
+    key: Option<Key>,
+    legacy_key: Key,
 }
 
-/// Decrypts data using AES-256-GCM
-pub fn decrypt_data(encrypted: &str, password: &str) -> Result<String, String> {
-    let key = derive_key_from_password(password);
-    let cipher = Aes256Gcm::new_from_slice(&key)
-        .map_err(|e| format!("Failed to create cipher: {}", e))?;
+impl MediaCipher {
+    fn new(password: &str, key_params: Option<&str>) -> AppResult<Self> {
+        Ok(MediaCipher {
+            key: key_params.map(|params| crypto::derive_key(password, params)).transpose()?,
+            legacy_key: legacy_key(password),
+        })
+    }
 
-    // Decode from base64
-    let data = BASE64.decode(encrypted)
-        .map_err(|e| format!("Invalid base64: {}", e))?;
+    fn seal(&self, plaintext: &str) -> AppResult<String> {
+        let key = self.key.as_ref()
+            .ok_or_else(|| AppError::Crypto("No media key parameters".to_string()))?;
+        Ok(format!("{}{}", SEALED_PREFIX, crypto::encrypt(key, plaintext.as_bytes())?))
+    }
 
-    if data.len() < 12 {
-        return Err("Invalid encrypted data: too short".to_string());
+    fn open(&self, sealed: &str) -> AppResult<String> {
+        let plaintext = match (sealed.strip_prefix(SEALED_PREFIX), &self.key) {
+            (Some(data), Some(key)) => crypto::decrypt(key, data)?,
+            (Some(_), None) => return Err(AppError::Crypto("No media key parameters".to_string())),
+            (None, _) => crypto::decrypt(&self.legacy_key, sealed)?,
+        };
+        String::from_utf8(plaintext)
+            .map_err(|e| AppError::Crypto(format!("Invalid UTF-8: {}", e)))
     }
+}
+
+/// The profile's stored key parameters, if any
+fn key_params(conn: &Connection, profile_id: i64) -> Result<Option<String>> {
+    Ok(conn.query_row(
+        "SELECT key_params FROM multimedia_privacy WHERE profile_id = ?1",
+        params![profile_id],
+        |row| row.get(0),
+    ).optional()?.flatten())
+}
 
-    // Extract nonce (first 12 bytes) and ciphertext
-    let nonce = Nonce::from_slice(&data[..12]);
-    let ciphertext = &data[12..];
+/// Re-encrypt the source/title/thumbnail of the rows `select` finds (?1 = profile id),
+/// either all of them or only those still under the legacy key. Rows that don't open
+/// with `old` are left alone.
+fn reseal_rows(
+    conn: &Connection,
+    select: &str,
+    update: &str,
+    profile_id: i64,
+    old: &MediaCipher,
+    new: &MediaCipher,
+    all: bool,
+) -> AppResult<()> {
+    let rows: Vec<(i64, String, Option<String>, Option<String>)> = conn
+        .prepare(select)?
+        .query_map(params![profile_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<_>>()?;
+
+    let reseal = |value: &str| new.seal(&old.open(value)?);
+    for (id, source, title, thumbnail) in rows {
+        if !all && source.starts_with(SEALED_PREFIX) {
+            continue;
+        }
+        let resealed = (|| -> AppResult<_> {
+            Ok((
+                reseal(&source)?,
+                title.as_deref().map(reseal).transpose()?,
+                thumbnail.as_deref().map(reseal).transpose()?,
+            ))
+        })();
+        if let Ok((source, title, thumbnail)) = resealed {
+            conn.execute(update, params![source, title, thumbnail, id])?;
+        }
+    }
+    Ok(())
+}
 
-    // Decrypt
-    let plaintext = cipher
-        .decrypt(nonce, ciphertext)
-        .map_err(|_| "Decryption failed: invalid password or corrupted data".to_string())?;
+/// SHA-256 hex, as stored by versions before Argon2id; only checked to upgrade it
+fn legacy_password_hash(password: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(password.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
 
+/// The fixed-salt SHA-256 key older ciphertexts were written with; only used to read them
+fn legacy_key(password: &str) -> Key {
+    let mut hasher = Sha256::new();
+    hasher.update(password.as_bytes());
+    hasher.update(b"EarthMultiMedia_v1_salt");
+    hasher.finalize().into()
+}
+
+/// Encrypts a standalone value with AES-256-GCM under an Argon2id key with its own salt.
+/// The result is `<key params>$<base64 ciphertext>`.
+pub fn encrypt_data(plaintext: &str, password: &str) -> AppResult<String> {
+    let key_params = crypto::new_key_params();
+    let key = crypto::derive_key(password, &key_params)?;
+    Ok(format!("{}${}", key_params, crypto::encrypt(&key, plaintext.as_bytes())?))
+}
+
+/// Decrypts `encrypt_data` output, including the legacy format without key params
+pub fn decrypt_data(encrypted: &str, password: &str) -> AppResult<String> {
+    let plaintext = match encrypted.rsplit_once('$') {
+        Some((key_params, data)) if encrypted.starts_with('$') => {
+            crypto::decrypt(&crypto::derive_key(password, key_params)?, data)?
+        }
+        _ => crypto::decrypt(&legacy_key(password), encrypted)?,
+    };
     String::from_utf8(plaintext)
-        .map_err(|e| format!("Invalid UTF-8: {}", e))
+        .map_err(|e| AppError::Crypto(format!("Invalid UTF-8: {}", e)))
 }
 
 /// Encrypts a MediaHistoryEntry's sensitive fields
-pub fn encrypt_history_entry(entry: &MediaHistoryEntry, password: &str) -> Result<MediaHistoryEntry, String> {
+pub fn encrypt_history_entry(entry: &MediaHistoryEntry, cipher: &MediaCipher) -> AppResult<MediaHistoryEntry> {
     let mut encrypted = entry.clone();
-    encrypted.source = encrypt_data(&entry.source, password)?;
+    encrypted.source = cipher.seal(&entry.source)?;
     if let Some(ref title) = entry.title {
-        encrypted.title = Some(encrypt_data(title, password)?);
+        encrypted.title = Some(cipher.seal(title)?);
     }
     if let Some(ref thumbnail) = entry.thumbnail {
-        encrypted.thumbnail = Some(encrypt_data(thumbnail, password)?);
+        encrypted.thumbnail = Some(cipher.seal(thumbnail)?);
     }
     encrypted.encrypted = true;
     Ok(encrypted)
 }
 
 /// Decrypts a MediaHistoryEntry's sensitive fields
-pub fn decrypt_history_entry(entry: &MediaHistoryEntry, password: &str) -> Result<MediaHistoryEntry, String> {
+pub fn decrypt_history_entry(entry: &MediaHistoryEntry, cipher: &MediaCipher) -> AppResult<MediaHistoryEntry> {
     if !entry.encrypted {
         return Ok(entry.clone());
     }
 
     let mut decrypted = entry.clone();
-    decrypted.source = decrypt_data(&entry.source, password)?;
+    decrypted.source = cipher.open(&entry.source)?;
     if let Some(ref title) = entry.title {
-        decrypted.title = Some(decrypt_data(title, password)?);
+        decrypted.title = Some(cipher.open(title)?);
     }
     if let Some(ref thumbnail) = entry.thumbnail {
-        decrypted.thumbnail = Some(decrypt_data(thumbnail, password)?);
+        decrypted.thumbnail = Some(cipher.open(thumbnail)?);
     }
     decrypted.encrypted = false;
     Ok(decrypted)
 }
 
 /// Encrypts a PlaylistItem's sensitive fields
-pub fn encrypt_playlist_item(item: &PlaylistItem, password: &str) -> Result<PlaylistItem, String> {
+pub fn encrypt_playlist_item(item: &PlaylistItem, cipher: &MediaCipher) -> AppResult<PlaylistItem> {
     let mut encrypted = item.clone();
-    encrypted.source = encrypt_data(&item.source, password)?;
+    encrypted.source = cipher.seal(&item.source)?;
     if let Some(ref title) = item.title {
-        encrypted.title = Some(encrypt_data(title, password)?);
+        encrypted.title = Some(cipher.seal(title)?);
     }
     if let Some(ref thumbnail) = item.thumbnail {
-        encrypted.thumbnail = Some(encrypt_data(thumbnail, password)?);
+        encrypted.thumbnail = Some(cipher.seal(thumbnail)?);
     }
+    encrypted.encrypted = true;
     Ok(encrypted)
 }
 
 /// Decrypts a PlaylistItem's sensitive fields
-pub fn decrypt_playlist_item(item: &PlaylistItem, password: &str) -> Result<PlaylistItem, String> {
+pub fn decrypt_playlist_item(item: &PlaylistItem, cipher: &MediaCipher) -> AppResult<PlaylistItem> {
+    if !item.encrypted {
+        return Ok(item.clone());
+    }
+
     let mut decrypted = item.clone();
-    decrypted.source = decrypt_data(&item.source, password)?;
+    decrypted.source = cipher.open(&item.source)?;
     if let Some(ref title) = item.title {
-        decrypted.title = Some(decrypt_data(title, password)?);
+        decrypted.title = Some(cipher.open(title)?);
     }
     if let Some(ref thumbnail) = item.thumbnail {
-        decrypted.thumbnail = Some(decrypt_data(thumbnail, password)?);
+        decrypted.thumbnail = Some(cipher.open(thumbnail)?);
     }
+    decrypted.encrypted = false;
     Ok(decrypted)
 }
 
-// ==================== Helper Functions ====================
-
-/// Simple base32 encoding for OTP secrets
-fn base32_encode(data: &[u8]) -> String {
-    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
-    let mut result = String::new();
-
-    for chunk in data.chunks(5) {
-        let mut buffer = [0u8; 8];
-        let len = chunk.len();
-
-        for (i, &byte) in chunk.iter().enumerate() {
-            let shift = (4 - i) * 8;
-            if shift < 40 {
-                let idx = shift / 8;
-                buffer[idx] |= byte >> (shift % 8);
-                if shift % 8 != 0 && idx + 1 < 8 {
-                    buffer[idx + 1] |= byte << (8 - shift % 8);
-                }
-            }
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
 
-        let bits = len * 8;
-        let chars = (bits + 4) / 5;
-
-        for i in 0..chars {
-            let idx = (i * 5) / 8;
-            let bit_offset = (i * 5) % 8;
-            let value = if bit_offset <= 3 {
-                (buffer[idx] >> (3 - bit_offset)) & 0x1F
-            } else {
-                let low = (buffer[idx] << (bit_offset - 3)) & 0x1F;
-                let high = if idx + 1 < 8 { buffer[idx + 1] >> (11 - bit_offset) } else { 0 };
-                low | high
-            };
-            result.push(ALPHABET[value as usize] as char);
-        }
+    fn legacy_seal(value: &str, password: &str) -> String {
+        crypto::encrypt(&legacy_key(password), value.as_bytes()).unwrap()
     }
 
-    result
-}
-
-/// Generate TOTP code
-fn generate_totp(secret: &str, time_step: u64) -> String {
-    use sha2::Sha256;
-    use hmac::{Hmac, Mac};
-
-    type HmacSha256 = Hmac<Sha256>;
+    fn history_entry(source: &str) -> MediaHistoryEntry {
+        MediaHistoryEntry {
+            id: None,
+            profile_id: 1,
+            media_id: None,
+            source: source.to_string(),
+            media_type: MediaType::Video,
+            title: Some("Holiday".to_string()),
+            thumbnail: None,
+            position: 0,
+            duration: None,
+            played_at: String::new(),
+            encrypted: false,
+        }
+    }
 
-    // Decode base32 secret (simplified)
-    let secret_bytes: Vec<u8> = secret.bytes().take(20).collect();
+    #[test]
+    fn test_legacy_hash_and_ciphertexts_upgrade_on_login() {
+        let db = test_database("media_legacy");
+        let manager = MultimediaManager::new(db.clone());
+        manager.update_privacy_settings(&PrivacySettings { history_enabled: true, ..Default::default() }).unwrap();
+        {
+            let conn = db.get().unwrap();
+            conn.execute(
+                "UPDATE multimedia_privacy SET password_hash = ?1, require_password = 1 WHERE profile_id = 1",
+                params![legacy_password_hash("hunter22")],
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO multimedia_history (profile_id, source, media_type, title, position, played_at, encrypted)
+                 VALUES (1, ?1, 'video', ?2, 0, '2024-01-01T00:00:00Z', 1)",
+                params![legacy_seal("/videos/holiday.mp4", "hunter22"), legacy_seal("Holiday", "hunter22")],
+            ).unwrap();
+            conn.execute_batch(
+                "INSERT INTO multimedia_playlists (id, profile_id, name, is_encrypted, created_at) VALUES (7, 1, 'Private', 1, '0');"
+            ).unwrap();
+            conn.execute(
+                "INSERT INTO multimedia_playlist_items (playlist_id, source, media_type, position, added_at, encrypted)
+                 VALUES (7, ?1, 'audio', 1, '0', 1)",
+                params![legacy_seal("/music/song.mp3", "hunter22")],
+            ).unwrap();
+        }
 
-    // Create HMAC using Mac trait's new_from_slice
-    let mut mac = <HmacSha256 as Mac>::new_from_slice(&secret_bytes).unwrap();
-    mac.update(&time_step.to_be_bytes());
-    let result = mac.finalize().into_bytes();
+        assert!(!manager.verify_password(1, "hunter23").unwrap());
+        let stored = manager.get_privacy_settings(1).unwrap().password_hash.unwrap();
+        assert!(!stored.starts_with('$'), "a wrong password must not upgrade anything");
+
+        assert!(manager.verify_password(1, "hunter22").unwrap());
+        let stored = manager.get_privacy_settings(1).unwrap().password_hash.unwrap();
+        assert!(stored.starts_with("$argon2id$"));
+        assert!(manager.verify_password(1, "hunter22").unwrap());
+        assert!(!manager.verify_password(1, "hunter23").unwrap());
+
+        let conn = db.get().unwrap();
+        let (source, title): (String, String) = conn.query_row(
+            "SELECT source, title FROM multimedia_history WHERE profile_id = 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert!(source.starts_with(SEALED_PREFIX) && title.starts_with(SEALED_PREFIX));
+        let item_source: String = conn.query_row(
+            "SELECT source FROM multimedia_playlist_items WHERE playlist_id = 7",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert!(item_source.starts_with(SEALED_PREFIX));
+
+        let history = manager.get_history(1, 10, Some("hunter22")).unwrap();
+        assert_eq!(history[0].source, "/videos/holiday.mp4");
+        assert_eq!(history[0].title.as_deref(), Some("Holiday"));
+        let items = manager.get_playlist_items(7, Some("hunter22")).unwrap();
+        assert_eq!(items[0].source, "/music/song.mp3");
+        assert!(!items[0].encrypted);
+    }
 
-    // Dynamic truncation
-    let offset = (result[result.len() - 1] & 0x0f) as usize;
-    let code = ((result[offset] as u32 & 0x7f) << 24)
-        | ((result[offset + 1] as u32) << 16)
-        | ((result[offset + 2] as u32) << 8)
-        | (result[offset + 3] as u32);
+    #[test]
+    fn test_encrypted_history_and_playlists_need_the_password() {
+        let db = test_database("media_encrypted");
+        let manager = MultimediaManager::new(db.clone());
+        manager.update_privacy_settings(&PrivacySettings { history_enabled: true, ..Default::default() }).unwrap();
+        manager.set_password(1, "hunter22").unwrap();
+
+        let error = manager.add_history_entry(&history_entry("/a.mp4"), Some("wrong")).unwrap_err();
+        assert_eq!(error.kind(), crate::error::ErrorKind::Crypto);
+        let added = manager.add_history_entry(&history_entry("/a.mp4"), Some("hunter22")).unwrap().unwrap();
+        assert!(added.encrypted);
+        assert_eq!(added.source, "/a.mp4");
+
+        let playlist = manager.create_playlist(1, "Private", None, true).unwrap();
+        let playlist_id = playlist.id.unwrap();
+        manager.add_to_playlist(playlist_id, "/b.mp3", "audio", Some("B"), None, Some("hunter22")).unwrap();
+
+        let conn = db.get().unwrap();
+        let stored: String = conn.query_row("SELECT source FROM multimedia_history", [], |row| row.get(0)).unwrap();
+        assert!(stored.starts_with(SEALED_PREFIX) && !stored.contains("/a.mp4"));
+        let stored: String = conn.query_row("SELECT title FROM multimedia_playlist_items", [], |row| row.get(0)).unwrap();
+        assert!(stored.starts_with(SEALED_PREFIX));
+
+        assert!(manager.get_history(1, 10, Some("wrong")).is_err());
+        assert!(manager.get_history(1, 10, None).is_err());
+        assert_eq!(manager.get_history(1, 10, Some("hunter22")).unwrap()[0].source, "/a.mp4");
+        assert!(manager.get_playlist_items(playlist_id, None).is_err());
+        assert!(manager.get_playlist_items(playlist_id, Some("wrong")).is_err());
+        let items = manager.get_playlist_items(playlist_id, Some("hunter22")).unwrap();
+        assert_eq!(items[0].title.as_deref(), Some("B"));
+    }
 
-    format!("{:06}", code % 1_000_000)
+    #[test]
+    fn test_standalone_data_round_trips_both_formats() {
+        let sealed = encrypt_data("secret", "pw").unwrap();
+        assert!(sealed.starts_with("$argon2id$"));
+        assert_eq!(decrypt_data(&sealed, "pw").unwrap(), "secret");
+        assert!(decrypt_data(&sealed, "other").is_err());
+        assert_eq!(decrypt_data(&legacy_seal("old secret", "pw"), "pw").unwrap(), "old secret");
+    }
 }
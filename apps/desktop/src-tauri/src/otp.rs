@@ -0,0 +1,710 @@
+// OTP authenticator for EarthServers Local
+// TOTP (RFC 6238) and HOTP (RFC 4226) codes with SHA-1/256/512, 6-8 digits and custom
+// periods. Secrets are kept like password manager entries: one AES-GCM encrypted JSON
+// document per entry, behind the authenticator's own master password (see key_store.rs).
+// Entries can be imported from and exported to otpauth:// URIs as found in QR codes.
+
+use hmac::{Hmac, Mac};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, utf8_percent_encode};
+use rand::Rng;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::crypto::{self, Key};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::key_store::MasterKeyStore;
+
+const DEFAULT_DIGITS: u32 = 6;
+const DEFAULT_PERIOD: u64 = 30;
+/// Secrets shorter than 80 bits are rejected; RFC 4226 recommends 160
+const MIN_SECRET_LEN: usize = 10;
+/// TOTP codes from one step before or after the current one are accepted for clock skew
+const TOTP_SKEW_STEPS: u64 = 1;
+/// How far ahead of the stored counter an HOTP code may be, for presses that were never used
+const HOTP_LOOK_AHEAD: u64 = 10;
+
+/// Characters left as-is in otpauth:// labels and parameters
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+// ==================== Types ====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OtpAlgorithm {
+    #[default]
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl OtpAlgorithm {
+    fn parse(name: &str) -> AppResult<Self> {
+        match name.to_ascii_uppercase().replace('-', "").as_str() {
+            "SHA1" => Ok(OtpAlgorithm::Sha1),
+            "SHA256" => Ok(OtpAlgorithm::Sha256),
+            "SHA512" => Ok(OtpAlgorithm::Sha512),
+            _ => Err(AppError::Validation(format!("Unsupported OTP algorithm: {}", name))),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            OtpAlgorithm::Sha1 => "SHA1",
+            OtpAlgorithm::Sha256 => "SHA256",
+            OtpAlgorithm::Sha512 => "SHA512",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtpKind {
+    /// Time-based, a new code every `period` seconds
+    #[default]
+    Totp,
+    /// Counter-based, a new code each time one is requested
+    Hotp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtpEntry {
+    pub id: i64,
+    pub profile_id: i64,
+    pub name: String,
+    pub issuer: String,
+    /// Base32, upper case without padding
+    pub secret: String,
+    pub kind: OtpKind,
+    pub algorithm: OtpAlgorithm,
+    pub digits: u32,
+    /// Seconds per TOTP code
+    pub period: u64,
+    /// Next HOTP counter
+    pub counter: u64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Fields of an entry as the user edits them; unset options take the otpauth defaults
+/// (TOTP, SHA1, 6 digits, 30 seconds, counter 0), or the entry's values on update.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OtpEntryInput {
+    pub name: String,
+    pub issuer: Option<String>,
+    pub secret: String,
+    pub kind: Option<OtpKind>,
+    pub algorithm: Option<OtpAlgorithm>,
+    pub digits: Option<u32>,
+    pub period: Option<u64>,
+    pub counter: Option<u64>,
+}
+
+/// The encrypted document stored per entry
+#[derive(Serialize, Deserialize)]
+struct EntryData {
+    name: String,
+    issuer: String,
+    secret: String,
+    kind: OtpKind,
+    algorithm: OtpAlgorithm,
+    digits: u32,
+    period: u64,
+    counter: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtpCode {
+    pub entry_id: i64,
+    pub code: String,
+    /// Seconds until a TOTP code changes; None for HOTP
+    pub remaining_seconds: Option<u64>,
+}
+
+// ==================== Manager ====================
+
+#[derive(Clone)]
+pub struct OtpManager {
+    db: DbPool,
+    keys: MasterKeyStore,
+}
+
+impl OtpManager {
+    pub fn new(db: DbPool) -> Self {
+        OtpManager {
+            keys: MasterKeyStore::new(db.clone(), "otp_vaults", "authenticator"),
+            db,
+        }
+    }
+
+    pub fn has_master(&self, profile_id: i64) -> AppResult<bool> {
+        self.keys.has_master(profile_id)
+    }
+
+    /// Set the authenticator's master password, or change it (needs the current one).
+    /// The authenticator is unlocked afterwards.
+    pub fn set_master(&self, profile_id: i64, password: &str, current_password: Option<&str>) -> AppResult<()> {
+        self.keys.set_master(profile_id, password, current_password)
+    }
+
+    /// Unlock with the master password; false for a wrong password
+    pub fn unlock(&self, profile_id: i64, password: &str) -> AppResult<bool> {
+        self.keys.unlock(profile_id, password)
+    }
+
+    pub fn lock(&self, profile_id: i64) {
+        self.keys.lock(profile_id)
+    }
+
+    /// Forget every authenticator key, e.g. when the app exits
+    pub fn lock_all(&self) {
+        self.keys.lock_all()
+    }
+
+    /// Lock authenticators unused for longer than the auto-lock timeout
+    pub fn lock_idle(&self) -> Vec<i64> {
+        self.keys.lock_idle()
+    }
+
+    // ==================== Entries ====================
+
+    /// All entries, sorted by issuer then name
+    pub fn get_entries(&self, profile_id: i64) -> AppResult<Vec<OtpEntry>> {
+        let key = self.keys.key(profile_id)?;
+        let conn = self.db.get()?;
+        let mut entries = load_entries(&conn, profile_id, &key)?;
+        entries.sort_by_key(|entry| (entry.issuer.to_lowercase(), entry.name.to_lowercase()));
+        Ok(entries)
+    }
+
+    pub fn add_entry(&self, profile_id: i64, input: &OtpEntryInput) -> AppResult<OtpEntry> {
+        let key = self.keys.key(profile_id)?;
+        let data = entry_data(input, None)?;
+        let conn = self.db.get()?;
+        let now = chrono_now();
+        conn.execute(
+            "INSERT INTO otp_entries (profile_id, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![profile_id, seal(&key, &data)?, now],
+        )?;
+        Ok(to_entry(conn.last_insert_rowid(), profile_id, data, now.clone(), now))
+    }
+
+    pub fn update_entry(&self, entry_id: i64, input: &OtpEntryInput) -> AppResult<OtpEntry> {
+        let (profile_id, key, existing) = self.open_entry(entry_id)?;
+        let data = entry_data(input, Some(&existing.data))?;
+        self.save_entry(entry_id, profile_id, &key, data, existing.created_at)
+    }
+
+    pub fn delete_entry(&self, entry_id: i64) -> AppResult<()> {
+        self.open_entry(entry_id)?;
+        let conn = self.db.get()?;
+        conn.execute("DELETE FROM otp_entries WHERE id = ?1", params![entry_id])?;
+        Ok(())
+    }
+
+    /// Current codes of the profile's TOTP entries. HOTP codes are only produced on
+    /// request by next_hotp_code, since each one uses up a counter value.
+    pub fn get_codes(&self, profile_id: i64) -> AppResult<Vec<OtpCode>> {
+        let now = unix_now();
+        let mut codes = Vec::new();
+        for entry in self.get_entries(profile_id)? {
+            if entry.kind != OtpKind::Totp {
+                continue;
+            }
+            let secret = base32_decode(&entry.secret)?;
+            codes.push(OtpCode {
+                entry_id: entry.id,
+                code: totp(&secret, now, entry.period, entry.algorithm, entry.digits),
+                remaining_seconds: Some(entry.period - now % entry.period),
+            });
+        }
+        Ok(codes)
+    }
+
+    /// The HOTP code for the entry's counter, advancing the counter
+    pub fn next_hotp_code(&self, entry_id: i64) -> AppResult<OtpCode> {
+        let (profile_id, key, mut existing) = self.open_entry(entry_id)?;
+        if existing.data.kind != OtpKind::Hotp {
+            return Err(AppError::Validation("Only HOTP entries have a counter".to_string()));
+        }
+        let data = &existing.data;
+        let code = hotp(&base32_decode(&data.secret)?, data.counter, data.algorithm, data.digits);
+        existing.data.counter += 1;
+        self.save_entry(entry_id, profile_id, &key, existing.data, existing.created_at)?;
+        Ok(OtpCode { entry_id, code, remaining_seconds: None })
+    }
+
+    /// Check a code against the entry. TOTP allows one step of clock skew either way; HOTP
+    /// looks a few counter values ahead and resynchronises the counter on a match.
+    pub fn verify_code(&self, entry_id: i64, code: &str) -> AppResult<bool> {
+        let (profile_id, key, mut existing) = self.open_entry(entry_id)?;
+        let data = &existing.data;
+        let secret = base32_decode(&data.secret)?;
+        match data.kind {
+            OtpKind::Totp => Ok(verify_totp(&secret, code, unix_now(), data.period, data.algorithm, data.digits)),
+            OtpKind::Hotp => match verify_hotp(&secret, code, data.counter, data.algorithm, data.digits) {
+                Some(counter) => {
+                    existing.data.counter = counter + 1;
+                    self.save_entry(entry_id, profile_id, &key, existing.data, existing.created_at)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+        }
+    }
+
+    /// Add an entry from an otpauth:// URI, e.g. the contents of a setup QR code
+    pub fn import_uri(&self, profile_id: i64, uri: &str) -> AppResult<OtpEntry> {
+        self.add_entry(profile_id, &parse_otpauth_uri(uri)?)
+    }
+
+    /// The entry as an otpauth:// URI, for moving it to another authenticator
+    pub fn export_uri(&self, entry_id: i64) -> AppResult<String> {
+        let (profile_id, _, existing) = self.open_entry(entry_id)?;
+        let entry = to_entry(entry_id, profile_id, existing.data, existing.created_at, existing.updated_at);
+        Ok(otpauth_uri(&entry))
+    }
+
+    /// The entry's profile, the unlocked key and the decrypted document
+    fn open_entry(&self, entry_id: i64) -> AppResult<(i64, Key, StoredEntry)> {
+        let conn = self.db.get()?;
+        let (profile_id, sealed, created_at, updated_at) = conn
+            .query_row(
+                "SELECT profile_id, data, created_at, updated_at FROM otp_entries WHERE id = ?1",
+                params![entry_id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Authenticator entry {}", entry_id)))?;
+        let key = self.keys.key(profile_id)?;
+        let data = open(&key, &sealed)?;
+        Ok((profile_id, key, StoredEntry { data, created_at, updated_at }))
+    }
+
+    fn save_entry(&self, entry_id: i64, profile_id: i64, key: &Key, data: EntryData, created_at: String) -> AppResult<OtpEntry> {
+        let conn = self.db.get()?;
+        let now = chrono_now();
+        conn.execute(
+            "UPDATE otp_entries SET data = ?1, updated_at = ?2 WHERE id = ?3",
+            params![seal(key, &data)?, now, entry_id],
+        )?;
+        Ok(to_entry(entry_id, profile_id, data, created_at, now))
+    }
+}
+
+struct StoredEntry {
+    data: EntryData,
+    created_at: String,
+    updated_at: String,
+}
+
+// ==================== Codes ====================
+
+/// RFC 4226 HOTP: the HMAC of the big-endian counter, dynamically truncated to `digits`
+pub fn hotp(secret: &[u8], counter: u64, algorithm: OtpAlgorithm, digits: u32) -> String {
+    let message = counter.to_be_bytes();
+    let hash = match algorithm {
+        OtpAlgorithm::Sha1 => hmac_digest::<Hmac<Sha1>>(secret, &message),
+        OtpAlgorithm::Sha256 => hmac_digest::<Hmac<Sha256>>(secret, &message),
+        OtpAlgorithm::Sha512 => hmac_digest::<Hmac<Sha512>>(secret, &message),
+    };
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    format!("{:0width$}", binary % 10u32.pow(digits), width = digits as usize)
+}
+
+/// RFC 6238 TOTP at `unix_time`, counting periods from the Unix epoch
+pub fn totp(secret: &[u8], unix_time: u64, period: u64, algorithm: OtpAlgorithm, digits: u32) -> String {
+    hotp(secret, unix_time / period, algorithm, digits)
+}
+
+/// Whether `code` is the TOTP code at `unix_time` or one step either side of it
+pub fn verify_totp(secret: &[u8], code: &str, unix_time: u64, period: u64, algorithm: OtpAlgorithm, digits: u32) -> bool {
+    let code = normalize_code(code);
+    let step = unix_time / period;
+    (step.saturating_sub(TOTP_SKEW_STEPS)..=step + TOTP_SKEW_STEPS)
+        .any(|counter| constant_time_eq(&hotp(secret, counter, algorithm, digits), &code))
+}
+
+/// The counter value `code` was generated from, searching `counter` and the values just after it
+pub fn verify_hotp(secret: &[u8], code: &str, counter: u64, algorithm: OtpAlgorithm, digits: u32) -> Option<u64> {
+    let code = normalize_code(code);
+    (counter..=counter + HOTP_LOOK_AHEAD).find(|&candidate| constant_time_eq(&hotp(secret, candidate, algorithm, digits), &code))
+}
+
+/// A random 160-bit secret, base32 encoded
+pub fn generate_secret() -> String {
+    let secret: [u8; 20] = rand::rngs::OsRng.gen();
+    base32_encode(&secret)
+}
+
+pub fn unix_now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+fn hmac_digest<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Codes are often shown grouped, e.g. "123 456"
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// ==================== Base32 ====================
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// RFC 4648 base32 without padding
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut result = String::with_capacity(data.len().div_ceil(5) * 8);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u16;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    result
+}
+
+/// RFC 4648 base32, ignoring case, padding, spaces and dashes as people type secrets
+pub fn base32_decode(text: &str) -> AppResult<Vec<u8>> {
+    let mut result = Vec::with_capacity(text.len() * 5 / 8);
+    let mut buffer: u16 = 0;
+    let mut bits = 0;
+    for c in text.chars().filter(|c| !c.is_whitespace() && !matches!(c, '-' | '=')) {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())
+            .ok_or_else(|| AppError::Validation(format!("'{}' is not a base32 character", c)))?;
+        buffer = ((buffer << 5) | value as u16) & 0x0fff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(result)
+}
+
+// ==================== otpauth:// URIs ====================
+
+/// Parse a Key URI as used by Google Authenticator and most 2FA setup QR codes:
+/// `otpauth://totp/Issuer:account?secret=...&issuer=Issuer&algorithm=SHA1&digits=6&period=30`
+pub fn parse_otpauth_uri(uri: &str) -> AppResult<OtpEntryInput> {
+    let invalid = |reason: &str| AppError::Validation(format!("Not a valid otpauth:// URI: {}", reason));
+    let url = url::Url::parse(uri.trim()).map_err(|e| invalid(&e.to_string()))?;
+    if url.scheme() != "otpauth" {
+        return Err(invalid("wrong scheme"));
+    }
+    let kind = match url.host_str().map(|host| host.to_ascii_lowercase()).as_deref() {
+        Some("totp") => OtpKind::Totp,
+        Some("hotp") => OtpKind::Hotp,
+        _ => return Err(invalid("the type must be totp or hotp")),
+    };
+
+    let label = percent_decode_str(url.path().trim_start_matches('/')).decode_utf8_lossy().into_owned();
+    let (label_issuer, name) = match label.split_once(':') {
+        Some((issuer, name)) => (Some(issuer.trim().to_string()), name.trim().to_string()),
+        None => (None, label.trim().to_string()),
+    };
+
+    let mut input = OtpEntryInput { name, issuer: label_issuer, kind: Some(kind), ..Default::default() };
+    for (key, value) in url.query_pairs() {
+        let number = |what: &str| value.parse::<u64>().map_err(|_| invalid(&format!("{} must be a number", what)));
+        match key.to_ascii_lowercase().as_str() {
+            "secret" => input.secret = value.to_string(),
+            "issuer" if !value.trim().is_empty() => input.issuer = Some(value.trim().to_string()),
+            "algorithm" => input.algorithm = Some(OtpAlgorithm::parse(&value)?),
+            "digits" => input.digits = Some(number("digits")? as u32),
+            "period" => input.period = Some(number("period")?),
+            "counter" => input.counter = Some(number("counter")?),
+            _ => {}
+        }
+    }
+    if input.secret.is_empty() {
+        return Err(invalid("the secret is missing"));
+    }
+    if input.name.is_empty() {
+        input.name = input.issuer.clone().unwrap_or_default();
+    }
+    Ok(input)
+}
+
+pub fn otpauth_uri(entry: &OtpEntry) -> String {
+    let encode = |text: &str| utf8_percent_encode(text, URI_COMPONENT).to_string();
+    let kind = match entry.kind {
+        OtpKind::Totp => "totp",
+        OtpKind::Hotp => "hotp",
+    };
+    let mut uri = if entry.issuer.is_empty() {
+        format!("otpauth://{}/{}?secret={}", kind, encode(&entry.name), entry.secret)
+    } else {
+        format!(
+            "otpauth://{}/{}:{}?secret={}&issuer={}",
+            kind,
+            encode(&entry.issuer),
+            encode(&entry.name),
+            entry.secret,
+            encode(&entry.issuer)
+        )
+    };
+    uri.push_str(&format!("&algorithm={}&digits={}", entry.algorithm.as_str(), entry.digits));
+    match entry.kind {
+        OtpKind::Totp => uri.push_str(&format!("&period={}", entry.period)),
+        OtpKind::Hotp => uri.push_str(&format!("&counter={}", entry.counter)),
+    }
+    uri
+}
+
+// ==================== Helper Functions ====================
+
+/// Validate an input, filling unset options from the existing entry or the defaults
+fn entry_data(input: &OtpEntryInput, existing: Option<&EntryData>) -> AppResult<EntryData> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Validation("Account name is required".to_string()));
+    }
+    let secret = base32_decode(&input.secret)?;
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(AppError::Validation(format!(
+            "The secret is too short; expected at least {} base32 characters",
+            (MIN_SECRET_LEN * 8).div_ceil(5)
+        )));
+    }
+
+    let digits = input.digits.or(existing.map(|e| e.digits)).unwrap_or(DEFAULT_DIGITS);
+    if !(6..=8).contains(&digits) {
+        return Err(AppError::Validation("Codes must have 6 to 8 digits".to_string()));
+    }
+    let period = input.period.or(existing.map(|e| e.period)).unwrap_or(DEFAULT_PERIOD);
+    if !(1..=600).contains(&period) {
+        return Err(AppError::Validation("The period must be between 1 and 600 seconds".to_string()));
+    }
+
+    Ok(EntryData {
+        name: name.to_string(),
+        issuer: input.issuer.as_deref().map(str::trim).unwrap_or_default().to_string(),
+        secret: base32_encode(&secret),
+        kind: input.kind.or(existing.map(|e| e.kind)).unwrap_or_default(),
+        algorithm: input.algorithm.or(existing.map(|e| e.algorithm)).unwrap_or_default(),
+        digits,
+        period,
+        counter: input.counter.or(existing.map(|e| e.counter)).unwrap_or(0),
+    })
+}
+
+fn load_entries(conn: &Connection, profile_id: i64, key: &Key) -> AppResult<Vec<OtpEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, data, created_at, updated_at FROM otp_entries WHERE profile_id = ?1 ORDER BY id"
+    )?;
+    let rows = stmt
+        .query_map(params![profile_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.into_iter()
+        .map(|(id, sealed, created_at, updated_at)| {
+            Ok(to_entry(id, profile_id, open(key, &sealed)?, created_at, updated_at))
+        })
+        .collect()
+}
+
+fn to_entry(id: i64, profile_id: i64, data: EntryData, created_at: String, updated_at: String) -> OtpEntry {
+    OtpEntry {
+        id,
+        profile_id,
+        name: data.name,
+        issuer: data.issuer,
+        secret: data.secret,
+        kind: data.kind,
+        algorithm: data.algorithm,
+        digits: data.digits,
+        period: data.period,
+        counter: data.counter,
+        created_at,
+        updated_at,
+    }
+}
+
+fn seal(key: &Key, data: &EntryData) -> AppResult<String> {
+    crypto::encrypt(key, &serde_json::to_vec(data)?)
+}
+
+fn open(key: &Key, sealed: &str) -> AppResult<EntryData> {
+    let plaintext = crypto::decrypt(key, sealed)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| AppError::Crypto(format!("Corrupted authenticator entry: {}", e)))
+}
+
+fn chrono_now() -> String {
+    format!("{}", unix_now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
+
+    const RFC_SEED: &[u8] = b"12345678901234567890";
+
+    #[test]
+    fn test_rfc4226_hotp_vectors() {
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314",
+            "254676", "287922", "162583", "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp(RFC_SEED, counter as u64, OtpAlgorithm::Sha1, 6), *code, "counter {}", counter);
+        }
+        assert_eq!(verify_hotp(RFC_SEED, "969 429", 1, OtpAlgorithm::Sha1, 6), Some(3));
+        assert_eq!(verify_hotp(RFC_SEED, "755224", 1, OtpAlgorithm::Sha1, 6), None, "used codes stay used");
+    }
+
+    #[test]
+    fn test_rfc6238_totp_vectors() {
+        let sha256_seed = b"12345678901234567890123456789012";
+        let sha512_seed = b"1234567890123456789012345678901234567890123456789012345678901234";
+        let expected: [(u64, &str, &str, &str); 6] = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, sha1, sha256, sha512) in expected {
+            assert_eq!(totp(RFC_SEED, time, 30, OtpAlgorithm::Sha1, 8), sha1, "SHA1 at {}", time);
+            assert_eq!(totp(sha256_seed, time, 30, OtpAlgorithm::Sha256, 8), sha256, "SHA256 at {}", time);
+            assert_eq!(totp(sha512_seed, time, 30, OtpAlgorithm::Sha512, 8), sha512, "SHA512 at {}", time);
+        }
+
+        // One step of clock skew either way
+        assert!(verify_totp(RFC_SEED, "94287082", 59 + 30, 30, OtpAlgorithm::Sha1, 8));
+        assert!(verify_totp(RFC_SEED, "07081804", 1111111109 - 30, 30, OtpAlgorithm::Sha1, 8));
+        assert!(!verify_totp(RFC_SEED, "94287082", 59 + 60, 30, OtpAlgorithm::Sha1, 8));
+        assert!(!verify_totp(RFC_SEED, "4287082", 59, 30, OtpAlgorithm::Sha1, 8));
+    }
+
+    #[test]
+    fn test_base32_round_trips() {
+        // RFC 4648 section 10
+        for (plain, encoded) in [("", ""), ("f", "MY"), ("fo", "MZXQ"), ("foo", "MZXW6"), ("foob", "MZXW6YQ"), ("fooba", "MZXW6YTB"), ("foobar", "MZXW6YTBOI")] {
+            assert_eq!(base32_encode(plain.as_bytes()), encoded);
+            assert_eq!(base32_decode(encoded).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(base32_decode("mzxw 6ytb-oi======").unwrap(), b"foobar");
+        assert!(base32_decode("MZXW1").is_err());
+
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert_eq!(base32_decode(&secret).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn test_otpauth_uris() {
+        let input = parse_otpauth_uri(
+            "otpauth://totp/ACME%20Co:john.doe@email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME+Co&algorithm=SHA256&digits=8&period=60",
+        )
+        .unwrap();
+        assert_eq!(input.name, "john.doe@email.com");
+        assert_eq!(input.issuer.as_deref(), Some("ACME Co"));
+        assert_eq!((input.algorithm, input.digits, input.period), (Some(OtpAlgorithm::Sha256), Some(8), Some(60)));
+
+        let hotp = parse_otpauth_uri("otpauth://hotp/alice?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&counter=5").unwrap();
+        assert_eq!((hotp.kind, hotp.counter, hotp.issuer), (Some(OtpKind::Hotp), Some(5), None));
+
+        assert!(parse_otpauth_uri("otpauth://totp/alice").is_err());
+        assert!(parse_otpauth_uri("otpauth://motp/alice?secret=GEZDGNBV").is_err());
+        assert!(parse_otpauth_uri("https://example.com/?secret=GEZDGNBV").is_err());
+
+        let db = test_database("otp_uris");
+        let otp = OtpManager::new(db);
+        otp.set_master(1, "correct horse", None).unwrap();
+        let entry = otp.add_entry(1, &input).unwrap();
+        let uri = otp.export_uri(entry.id).unwrap();
+        assert_eq!(
+            uri,
+            "otpauth://totp/ACME%20Co:john.doe%40email.com?secret=HXDMVJECJJWSRB3HWIZR4IFUGFTMXBOZ&issuer=ACME%20Co&algorithm=SHA256&digits=8&period=60"
+        );
+        let reimported = otp.import_uri(1, &uri).unwrap();
+        assert_eq!((reimported.name, reimported.issuer, reimported.period), (entry.name, entry.issuer, entry.period));
+    }
+
+    #[test]
+    fn test_entries_are_encrypted_and_hotp_counters_advance() {
+        let db = test_database("otp_entries");
+        let otp = OtpManager::new(db.clone());
+        otp.set_master(1, "correct horse", None).unwrap();
+
+        let secret = base32_encode(RFC_SEED);
+        let github = OtpEntryInput {
+            name: "octo".to_string(),
+            issuer: Some("GitHub".to_string()),
+            secret: secret.to_lowercase(),
+            ..Default::default()
+        };
+        let added = otp.add_entry(1, &github).unwrap();
+        assert_eq!((added.secret.as_str(), added.digits, added.period), (secret.as_str(), 6, 30));
+        let stored: String = db.get().unwrap()
+            .query_row("SELECT data FROM otp_entries WHERE id = ?1", params![added.id], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains(&secret) && !stored.contains("GitHub"));
+
+        let code = &otp.get_codes(1).unwrap()[0];
+        assert_eq!(code.code, totp(RFC_SEED, unix_now(), 30, OtpAlgorithm::Sha1, 6));
+        assert!(otp.verify_code(added.id, &code.code).unwrap());
+
+        let counter = OtpEntryInput { name: "bank".to_string(), secret: secret.clone(), kind: Some(OtpKind::Hotp), ..Default::default() };
+        let bank = otp.add_entry(1, &counter).unwrap();
+        assert_eq!(otp.next_hotp_code(bank.id).unwrap().code, "755224");
+        assert_eq!(otp.next_hotp_code(bank.id).unwrap().code, "287082");
+        assert!(otp.next_hotp_code(added.id).is_err(), "TOTP entries have no counter");
+        assert!(otp.verify_code(bank.id, "338314").unwrap());
+        assert_eq!(otp.next_hotp_code(bank.id).unwrap().code, "254676", "counter resynchronised");
+        assert_eq!(otp.get_codes(1).unwrap().len(), 1);
+
+        // Updating keeps options that aren't given
+        let mut renamed = counter.clone();
+        renamed.name = "Bank".to_string();
+        assert_eq!(otp.update_entry(bank.id, &renamed).unwrap().counter, 6);
+
+        for bad in [
+            OtpEntryInput { name: "x".to_string(), secret: "GEZDGNBV".to_string(), ..Default::default() },
+            OtpEntryInput { digits: Some(10), ..github.clone() },
+            OtpEntryInput { period: Some(0), ..github.clone() },
+            OtpEntryInput { name: " ".to_string(), ..github.clone() },
+        ] {
+            assert_eq!(otp.add_entry(1, &bad).unwrap_err().kind(), crate::error::ErrorKind::Validation);
+        }
+
+        otp.lock(1);
+        let locked = otp.get_entries(1).unwrap_err();
+        assert_eq!(locked.to_string(), "The authenticator vault is locked");
+        assert!(otp.delete_entry(bank.id).is_err());
+        assert!(otp.unlock(1, "correct horse").unwrap());
+        otp.delete_entry(bank.id).unwrap();
+        assert_eq!(otp.get_entries(1).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,775 @@
+// Password manager vault for EarthServers Local
+// Each profile can have one vault, locked by a master password (see key_store.rs).
+// Entries are stored as one AES-GCM encrypted JSON document each.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::crypto::{self, Key};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::key_store::MasterKeyStore;
+
+const DEFAULT_CATEGORY: &str = "General";
+
+// ==================== Types ====================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+    /// Shown masked like a password
+    #[serde(default)]
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordEntry {
+    pub id: i64,
+    pub profile_id: i64,
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub category: String,
+    pub custom_fields: Vec<CustomField>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Fields of an entry as the user edits them. On update, `custom_fields: None` keeps the
+/// entry's existing fields.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PasswordEntryInput {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub notes: Option<String>,
+    pub category: Option<String>,
+    pub custom_fields: Option<Vec<CustomField>>,
+}
+
+/// The encrypted document stored per entry
+#[derive(Serialize, Deserialize)]
+struct EntryData {
+    title: String,
+    username: String,
+    password: String,
+    url: Option<String>,
+    notes: Option<String>,
+    category: String,
+    #[serde(default)]
+    custom_fields: Vec<CustomField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasswordGeneratorOptions {
+    pub length: usize,
+    pub lowercase: bool,
+    pub uppercase: bool,
+    pub digits: bool,
+    pub symbols: bool,
+    /// Leave out characters that are easy to misread, like 0/O and 1/l/I
+    pub exclude_ambiguous: bool,
+}
+
+impl Default for PasswordGeneratorOptions {
+    fn default() -> Self {
+        PasswordGeneratorOptions {
+            length: 20,
+            lowercase: true,
+            uppercase: true,
+            digits: true,
+            symbols: true,
+            exclude_ambiguous: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordStrength {
+    /// 0 (very weak) to 4 (very strong)
+    pub score: u8,
+    pub label: String,
+    /// Estimated guessing entropy after discounting common passwords and patterns
+    pub entropy_bits: f64,
+    pub feedback: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvImportReport {
+    /// Exporter the header was recognised as: chrome, firefox, bitwarden, lastpass,
+    /// 1password or generic
+    pub format: String,
+    pub imported: usize,
+    /// Rows without a password, non-login rows, and entries already in the vault
+    pub skipped: usize,
+}
+
+// ==================== Manager ====================
+
+#[derive(Clone)]
+pub struct PasswordVault {
+    db: DbPool,
+    keys: MasterKeyStore,
+}
+
+impl PasswordVault {
+    pub fn new(db: DbPool) -> Self {
+        PasswordVault {
+            keys: MasterKeyStore::new(db.clone(), "password_vaults", "password"),
+            db,
+        }
+    }
+
+    pub fn has_master(&self, profile_id: i64) -> AppResult<bool> {
+        self.keys.has_master(profile_id)
+    }
+
+    pub fn is_unlocked(&self, profile_id: i64) -> bool {
+        self.keys.is_unlocked(profile_id)
+    }
+
+    /// Create the profile's vault, or change its master password. Changing it needs the
+    /// current one; entries stay as they are since only the data key is rewrapped.
+    /// The vault is unlocked afterwards.
+    pub fn set_master(&self, profile_id: i64, password: &str, current_password: Option<&str>) -> AppResult<()> {
+        self.keys.set_master(profile_id, password, current_password)
+    }
+
+    /// Unlock the vault with its master password; false for a wrong password
+    pub fn unlock(&self, profile_id: i64, password: &str) -> AppResult<bool> {
+        self.keys.unlock(profile_id, password)
+    }
+
+    pub fn lock(&self, profile_id: i64) {
+        self.keys.lock(profile_id)
+    }
+
+    /// Forget every vault key, e.g. when the app exits
+    pub fn lock_all(&self) {
+        self.keys.lock_all()
+    }
+
+    /// Lock vaults unused for longer than AUTO_LOCK_AFTER; returns the profiles locked
+    pub fn lock_idle(&self) -> Vec<i64> {
+        self.keys.lock_idle()
+    }
+
+    // ==================== Entries ====================
+
+    /// All entries, sorted by title
+    pub fn get_entries(&self, profile_id: i64) -> AppResult<Vec<PasswordEntry>> {
+        let key = self.keys.key(profile_id)?;
+        let conn = self.db.get()?;
+        let mut entries = load_entries(&conn, profile_id, &key)?;
+        entries.sort_by_key(|entry| entry.title.to_lowercase());
+        Ok(entries)
+    }
+
+    /// Entries whose title, username, URL, notes, category or custom field names contain
+    /// every word of the query (case-insensitive), title matches first. Passwords and
+    /// field values are never searched.
+    pub fn search(&self, profile_id: i64, query: &str) -> AppResult<Vec<PasswordEntry>> {
+        let query = query.trim().to_lowercase();
+        let mut entries = self.get_entries(profile_id)?;
+        if query.is_empty() {
+            return Ok(entries);
+        }
+
+        let words: Vec<&str> = query.split_whitespace().collect();
+        entries.retain(|entry| {
+            let haystack = searchable_text(entry);
+            words.iter().all(|word| haystack.contains(word))
+        });
+        entries.sort_by_key(|entry| {
+            let title = entry.title.to_lowercase();
+            let rank = if title.starts_with(&query) {
+                0
+            } else if title.contains(&query) {
+                1
+            } else {
+                2
+            };
+            (rank, title)
+        });
+        Ok(entries)
+    }
+
+    pub fn add_entry(&self, profile_id: i64, input: &PasswordEntryInput) -> AppResult<PasswordEntry> {
+        let key = self.keys.key(profile_id)?;
+        let data = entry_data(input, Vec::new())?;
+        let conn = self.db.get()?;
+        let now = chrono_now();
+        conn.execute(
+            "INSERT INTO password_entries (profile_id, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+            params![profile_id, seal(&key, &data)?, now],
+        )?;
+        Ok(to_entry(conn.last_insert_rowid(), profile_id, data, now.clone(), now))
+    }
+
+    pub fn update_entry(&self, entry_id: i64, input: &PasswordEntryInput) -> AppResult<PasswordEntry> {
+        let conn = self.db.get()?;
+        let (profile_id, sealed, created_at) = conn
+            .query_row(
+                "SELECT profile_id, data, created_at FROM password_entries WHERE id = ?1",
+                params![entry_id],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Password entry {}", entry_id)))?;
+        let key = self.keys.key(profile_id)?;
+
+        let existing = open(&key, &sealed)?;
+        let data = entry_data(input, existing.custom_fields)?;
+        let now = chrono_now();
+        conn.execute(
+            "UPDATE password_entries SET data = ?1, updated_at = ?2 WHERE id = ?3",
+            params![seal(&key, &data)?, now, entry_id],
+        )?;
+        Ok(to_entry(entry_id, profile_id, data, created_at, now))
+    }
+
+    pub fn delete_entry(&self, entry_id: i64) -> AppResult<()> {
+        let conn = self.db.get()?;
+        let profile_id: i64 = conn
+            .query_row("SELECT profile_id FROM password_entries WHERE id = ?1", params![entry_id], |row| row.get(0))
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Password entry {}", entry_id)))?;
+        self.keys.key(profile_id)?;
+
+        conn.execute("DELETE FROM password_entries WHERE id = ?1", params![entry_id])?;
+        Ok(())
+    }
+
+    /// Import a CSV export from Chrome, Firefox, Bitwarden, LastPass, 1Password or any file
+    /// with recognisable column names. Entries already in the vault are skipped.
+    pub fn import_csv(&self, profile_id: i64, csv: &str) -> AppResult<CsvImportReport> {
+        let key = self.keys.key(profile_id)?;
+        let (format, rows) = parse_csv_export(csv)?;
+
+        let mut conn = self.db.get()?;
+        let mut seen: HashSet<(String, String, String)> = load_entries(&conn, profile_id, &key)?
+            .into_iter()
+            .map(|entry| (entry.title, entry.username, entry.password))
+            .collect();
+
+        let tx = conn.transaction()?;
+        let now = chrono_now();
+        let mut report = CsvImportReport { format: format.to_string(), imported: 0, skipped: 0 };
+        for row in rows {
+            let Some(input) = row else {
+                report.skipped += 1;
+                continue;
+            };
+            if !seen.insert((input.title.clone(), input.username.clone(), input.password.clone())) {
+                report.skipped += 1;
+                continue;
+            }
+            let data = entry_data(&input, Vec::new())?;
+            tx.execute(
+                "INSERT INTO password_entries (profile_id, data, created_at, updated_at) VALUES (?1, ?2, ?3, ?3)",
+                params![profile_id, seal(&key, &data)?, now],
+            )?;
+            report.imported += 1;
+        }
+        tx.commit()?;
+        Ok(report)
+    }
+}
+
+// ==================== Generator ====================
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+const AMBIGUOUS: &str = "0O1lI|";
+
+/// A random password with at least one character from every enabled class
+pub fn generate_password(options: &PasswordGeneratorOptions) -> AppResult<String> {
+    if !(8..=128).contains(&options.length) {
+        return Err(AppError::Validation("Password length must be between 8 and 128".to_string()));
+    }
+
+    let classes: Vec<Vec<char>> = [
+        (options.lowercase, LOWERCASE),
+        (options.uppercase, UPPERCASE),
+        (options.digits, DIGITS),
+        (options.symbols, SYMBOLS),
+    ]
+    .into_iter()
+    .filter(|(enabled, _)| *enabled)
+    .map(|(_, chars)| {
+        chars
+            .chars()
+            .filter(|c| !options.exclude_ambiguous || !AMBIGUOUS.contains(*c))
+            .collect()
+    })
+    .collect();
+    if classes.is_empty() {
+        return Err(AppError::Validation("Enable at least one character class".to_string()));
+    }
+
+    let mut rng = rand::rngs::OsRng;
+    let all: Vec<char> = classes.iter().flatten().copied().collect();
+    let mut password: Vec<char> = classes
+        .iter()
+        .map(|class| class[rng.gen_range(0..class.len())])
+        .collect();
+    while password.len() < options.length {
+        password.push(all[rng.gen_range(0..all.len())]);
+    }
+    password.shuffle(&mut rng);
+    Ok(password.into_iter().collect())
+}
+
+// ==================== Strength ====================
+
+/// Most common passwords in public breach corpora, compared after undoing leetspeak and
+/// dropping trailing digits and symbols. Checked offline; nothing is sent anywhere.
+const COMMON_PASSWORDS: &[&str] = &[
+    "password", "qwerty", "qwertyuiop", "letmein", "welcome", "admin", "administrator",
+    "iloveyou", "monkey", "dragon", "football", "baseball", "sunshine", "master", "princess",
+    "login", "trustno", "starwars", "shadow", "superman", "batman", "michael", "jennifer",
+    "charlie", "freedom", "whatever", "hello", "secret", "abc", "passw", "pass", "test",
+    "guest", "changeme", "default", "zaq", "asdf", "asdfgh", "zxcvbn", "qazwsx",
+];
+
+const KEYBOARD_ROWS: &[&str] = &["qwertyuiop", "asdfghjkl", "zxcvbnm", "1234567890"];
+
+/// Estimate how hard a password is to guess: character-class entropy, with repeated
+/// characters, sequences (abc, 321), keyboard walks and years counted as nearly free and
+/// common passwords capped.
+pub fn estimate_strength(password: &str) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().collect();
+    let mut feedback = Vec::new();
+
+    let has_lower = chars.iter().any(|c| c.is_ascii_lowercase());
+    let has_upper = chars.iter().any(|c| c.is_ascii_uppercase());
+    let has_digit = chars.iter().any(|c| c.is_ascii_digit());
+    let has_symbol = chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ');
+    let has_other = chars.iter().any(|c| !c.is_ascii());
+    let pool = [(has_lower, 26), (has_upper, 26), (has_digit, 10), (has_symbol, 33), (has_other, 100)]
+        .iter()
+        .filter(|(present, _)| *present)
+        .map(|(_, size)| size)
+        .sum::<u32>()
+        .max(1);
+    let bits_per_char = f64::from(pool).log2();
+
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut patterned = 0;
+    let mut entropy = 0.0;
+    for (i, c) in lower.iter().enumerate() {
+        let predictable = i > 0 && {
+            let prev = lower[i - 1];
+            let step = *c as i64 - prev as i64;
+            step.abs() <= 1 || KEYBOARD_ROWS.iter().any(|row| adjacent_on_row(row, prev, *c))
+        };
+        if predictable {
+            patterned += 1;
+            entropy += 1.0;
+        } else {
+            entropy += bits_per_char;
+        }
+    }
+    if patterned >= 2 {
+        feedback.push("Avoid repeated characters, sequences like abc or 123, and keyboard patterns".to_string());
+    }
+
+    let text: String = lower.iter().collect();
+    if let Some(year) = find_year(&text) {
+        // A year costs about 7 bits to guess, not 4 digits' worth
+        entropy -= (4.0 * bits_per_char - 7.0).max(0.0);
+        feedback.push(format!("Avoid years like {}", year));
+    }
+
+    if is_common_password(&text) {
+        entropy = entropy.min(10.0);
+        feedback.insert(0, "This is one of the most common passwords".to_string());
+    }
+
+    if chars.len() < 12 {
+        feedback.push("Use at least 12 characters".to_string());
+    }
+    let classes = [has_lower, has_upper, has_digit, has_symbol || has_other].iter().filter(|c| **c).count();
+    if classes < 3 && chars.len() < 16 {
+        feedback.push("Mix upper and lower case letters, digits and symbols, or use a longer passphrase".to_string());
+    }
+
+    let entropy = entropy.max(0.0);
+    let score = match entropy {
+        e if e < 28.0 => 0,
+        e if e < 36.0 => 1,
+        e if e < 60.0 => 2,
+        e if e < 80.0 => 3,
+        _ => 4,
+    };
+    let label = ["very weak", "weak", "fair", "strong", "very strong"][score as usize];
+    PasswordStrength {
+        score,
+        label: label.to_string(),
+        entropy_bits: (entropy * 10.0).round() / 10.0,
+        feedback,
+    }
+}
+
+fn adjacent_on_row(row: &str, a: char, b: char) -> bool {
+    match (row.find(a), row.find(b)) {
+        (Some(i), Some(j)) => i.abs_diff(j) == 1,
+        _ => false,
+    }
+}
+
+fn find_year(text: &str) -> Option<&str> {
+    let bytes = text.as_bytes();
+    (0..bytes.len().saturating_sub(3)).find_map(|i| {
+        let candidate = text.get(i..i + 4)?;
+        let is_year = (candidate.starts_with("19") || candidate.starts_with("20"))
+            && candidate.bytes().all(|b| b.is_ascii_digit());
+        is_year.then_some(candidate)
+    })
+}
+
+fn is_common_password(text: &str) -> bool {
+    if !text.is_empty() && text.chars().all(|c| c.is_ascii_digit()) {
+        return true;
+    }
+    let core = text.trim_end_matches(|c: char| !c.is_alphabetic());
+    [text, core]
+        .iter()
+        .map(|candidate| unleet(candidate))
+        .any(|candidate| !candidate.is_empty() && COMMON_PASSWORDS.contains(&candidate.as_str()))
+}
+
+fn unleet(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            c => c,
+        })
+        .collect()
+}
+
+// ==================== CSV Import ====================
+
+/// Column names used by each exporter, lowercased; the first match wins
+const TITLE_COLUMNS: &[&str] = &["name", "title"];
+const URL_COLUMNS: &[&str] = &["url", "login_uri", "website", "web site", "origin_url"];
+const USERNAME_COLUMNS: &[&str] = &["username", "login_username", "login", "user", "email", "user name"];
+const PASSWORD_COLUMNS: &[&str] = &["password", "login_password"];
+const NOTES_COLUMNS: &[&str] = &["note", "notes", "extra", "comments"];
+const CATEGORY_COLUMNS: &[&str] = &["grouping", "folder", "category", "group", "tags"];
+
+/// The exporter and one input per data row; None for rows that aren't importable
+fn parse_csv_export(csv: &str) -> AppResult<(&'static str, Vec<Option<PasswordEntryInput>>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(csv.trim_start_matches('\u{feff}').as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::Validation(format!("Invalid CSV: {}", e)))?
+        .iter()
+        .map(|h| h.to_lowercase())
+        .collect();
+
+    let column = |names: &[&str]| names.iter().find_map(|name| headers.iter().position(|h| h == name));
+    let Some(password_column) = column(PASSWORD_COLUMNS) else {
+        return Err(AppError::Validation("The CSV has no password column".to_string()));
+    };
+    let has = |name: &str| headers.iter().any(|h| h == name);
+    let format = if has("login_uri") {
+        "bitwarden"
+    } else if has("httprealm") {
+        "firefox"
+    } else if has("grouping") {
+        "lastpass"
+    } else if has("otpauth") || has("archived") {
+        "1password"
+    } else if headers.len() >= 4 && headers[..4] == ["name", "url", "username", "password"] {
+        "chrome"
+    } else {
+        "generic"
+    };
+    let (title, url, username, notes, category) = (
+        column(TITLE_COLUMNS),
+        column(URL_COLUMNS),
+        column(USERNAME_COLUMNS),
+        column(NOTES_COLUMNS),
+        column(CATEGORY_COLUMNS),
+    );
+    let kind = if format == "bitwarden" { column(&["type"]) } else { None };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| AppError::Validation(format!("Invalid CSV: {}", e)))?;
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .filter(|value| !value.is_empty())
+                .map(String::from)
+        };
+
+        let password = field(Some(password_column));
+        let is_login = field(kind).is_none_or(|kind| kind == "login");
+        let (Some(password), true) = (password, is_login) else {
+            rows.push(None);
+            continue;
+        };
+        let url = field(url);
+        let username = field(username).unwrap_or_default();
+        let title = field(title)
+            .or_else(|| url.as_deref().and_then(host_of))
+            .or_else(|| (!username.is_empty()).then(|| username.clone()));
+        let Some(title) = title else {
+            rows.push(None);
+            continue;
+        };
+        rows.push(Some(PasswordEntryInput {
+            title,
+            username,
+            password,
+            url,
+            notes: field(notes),
+            category: field(category),
+            custom_fields: None,
+        }));
+    }
+    Ok((format, rows))
+}
+
+fn host_of(url: &str) -> Option<String> {
+    let host = url::Url::parse(url).ok()?.host_str()?.to_string();
+    Some(host.strip_prefix("www.").map(String::from).unwrap_or(host))
+}
+
+// ==================== Helper Functions ====================
+
+fn load_entries(conn: &Connection, profile_id: i64, key: &Key) -> AppResult<Vec<PasswordEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, data, created_at, updated_at FROM password_entries WHERE profile_id = ?1 ORDER BY id"
+    )?;
+    let rows = stmt
+        .query_map(params![profile_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(id, sealed, created_at, updated_at)| {
+            Ok(to_entry(id, profile_id, open(key, &sealed)?, created_at, updated_at))
+        })
+        .collect()
+}
+
+/// Validate and normalize an input; `existing_fields` are kept when it has none
+fn entry_data(input: &PasswordEntryInput, existing_fields: Vec<CustomField>) -> AppResult<EntryData> {
+    let title = input.title.trim();
+    if title.is_empty() {
+        return Err(AppError::Validation("A title is required".to_string()));
+    }
+    if input.password.is_empty() {
+        return Err(AppError::Validation("A password is required".to_string()));
+    }
+    let custom_fields = input.custom_fields.clone().unwrap_or(existing_fields);
+    if custom_fields.iter().any(|field| field.name.trim().is_empty()) {
+        return Err(AppError::Validation("Custom fields need a name".to_string()));
+    }
+
+    let non_empty = |value: &Option<String>| {
+        value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(String::from)
+    };
+    Ok(EntryData {
+        title: title.to_string(),
+        username: input.username.trim().to_string(),
+        password: input.password.clone(),
+        url: non_empty(&input.url),
+        notes: non_empty(&input.notes),
+        category: non_empty(&input.category).unwrap_or_else(|| DEFAULT_CATEGORY.to_string()),
+        custom_fields,
+    })
+}
+
+fn to_entry(id: i64, profile_id: i64, data: EntryData, created_at: String, updated_at: String) -> PasswordEntry {
+    PasswordEntry {
+        id,
+        profile_id,
+        title: data.title,
+        username: data.username,
+        password: data.password,
+        url: data.url,
+        notes: data.notes,
+        category: data.category,
+        custom_fields: data.custom_fields,
+        created_at,
+        updated_at,
+    }
+}
+
+fn searchable_text(entry: &PasswordEntry) -> String {
+    let mut parts = vec![entry.title.as_str(), entry.username.as_str(), entry.category.as_str()];
+    parts.extend(entry.url.as_deref());
+    parts.extend(entry.notes.as_deref());
+    parts.extend(entry.custom_fields.iter().map(|field| field.name.as_str()));
+    parts.join("\n").to_lowercase()
+}
+
+fn seal(key: &Key, data: &EntryData) -> AppResult<String> {
+    crypto::encrypt(key, &serde_json::to_vec(data)?)
+}
+
+fn open(key: &Key, sealed: &str) -> AppResult<EntryData> {
+    let plaintext = crypto::decrypt(key, sealed)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| AppError::Crypto(format!("Corrupted password entry: {}", e)))
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
+
+    fn input(title: &str, username: &str, password: &str) -> PasswordEntryInput {
+        PasswordEntryInput {
+            title: title.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_are_encrypted_and_need_the_master_password() {
+        let db = test_database("vault_entries");
+        let vault = PasswordVault::new(db.clone());
+        assert!(!vault.has_master(1).unwrap());
+        assert!(vault.set_master(1, "short", None).is_err());
+        vault.set_master(1, "correct horse", None).unwrap();
+
+        let mut github = input("GitHub", "octo@example.com", "s3cret-pass");
+        github.url = Some("https://github.com/login".to_string());
+        github.custom_fields = Some(vec![CustomField { name: "Recovery code".to_string(), value: "1234-5678".to_string(), hidden: true }]);
+        let added = vault.add_entry(1, &github).unwrap();
+        vault.add_entry(1, &input("Bank", "me", "hunter22")).unwrap();
+
+        let stored: String = db.get().unwrap()
+            .query_row("SELECT data FROM password_entries WHERE id = ?1", params![added.id], |row| row.get(0))
+            .unwrap();
+        assert!(!stored.contains("GitHub") && !stored.contains("s3cret"));
+
+        // Editing without custom fields keeps them
+        let mut edited = input("GitHub", "octo@example.com", "n3w-pass");
+        edited.category = Some("Work".to_string());
+        let updated = vault.update_entry(added.id, &edited).unwrap();
+        assert_eq!(updated.custom_fields.len(), 1);
+        assert_eq!(updated.category, "Work");
+
+        assert_eq!(vault.search(1, "recovery").unwrap()[0].id, added.id);
+        assert_eq!(vault.search(1, "git work").unwrap().len(), 1);
+        assert!(vault.search(1, "hunter22").unwrap().is_empty(), "passwords aren't searchable");
+
+        vault.lock(1);
+        assert_eq!(vault.get_entries(1).unwrap_err().kind(), crate::error::ErrorKind::Locked);
+        assert!(vault.delete_entry(added.id).is_err());
+        assert!(!vault.unlock(1, "wrong horse").unwrap());
+        assert!(vault.unlock(1, "correct horse").unwrap());
+        let titles: Vec<String> = vault.get_entries(1).unwrap().into_iter().map(|e| e.title).collect();
+        assert_eq!(titles, ["Bank", "GitHub"]);
+
+        // Changing the master password keeps the entries readable
+        assert!(vault.set_master(1, "battery staple", None).is_err());
+        vault.set_master(1, "battery staple", Some("correct horse")).unwrap();
+        vault.lock(1);
+        assert!(!vault.unlock(1, "correct horse").unwrap());
+        assert!(vault.unlock(1, "battery staple").unwrap());
+        assert_eq!(vault.get_entries(1).unwrap()[1].password, "n3w-pass");
+
+        // Other profiles have their own vault
+        assert!(vault.get_entries(2).is_err());
+    }
+
+    #[test]
+    fn test_generator_honours_options() {
+        let options = PasswordGeneratorOptions { length: 32, symbols: false, exclude_ambiguous: true, ..Default::default() };
+        let password = generate_password(&options).unwrap();
+        assert_eq!(password.chars().count(), 32);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric() && !AMBIGUOUS.contains(c)));
+        assert!(password.chars().any(|c| c.is_ascii_digit()));
+        assert!(password.chars().any(|c| c.is_ascii_uppercase()));
+        assert_ne!(password, generate_password(&options).unwrap());
+
+        let digits_only = PasswordGeneratorOptions { length: 8, lowercase: false, uppercase: false, symbols: false, ..Default::default() };
+        assert!(generate_password(&digits_only).unwrap().chars().all(|c| c.is_ascii_digit()));
+        assert!(generate_password(&PasswordGeneratorOptions { length: 4, ..Default::default() }).is_err());
+        let none = PasswordGeneratorOptions { lowercase: false, uppercase: false, digits: false, symbols: false, ..Default::default() };
+        assert!(generate_password(&none).is_err());
+    }
+
+    #[test]
+    fn test_strength_discounts_common_passwords_and_patterns() {
+        for weak in ["password", "P@ssw0rd1!", "123456789", "qwerty123", "aaaaaaaaaaaa", "abcdefgh1234"] {
+            assert!(estimate_strength(weak).score <= 1, "{} scored {:?}", weak, estimate_strength(weak));
+        }
+        assert!(estimate_strength("P@ssw0rd1!").feedback[0].contains("most common"));
+        assert!(estimate_strength("summer1987").feedback.iter().any(|f| f.contains("1987")));
+
+        let strong = estimate_strength("vT9#qL2!mZ8$wR4x");
+        assert_eq!(strong.score, 4, "{:?}", strong);
+        assert!(strong.feedback.is_empty());
+        assert!(estimate_strength("correct horse battery staple").score >= 3);
+    }
+
+    #[test]
+    fn test_csv_import_from_common_exporters() {
+        let db = test_database("vault_csv");
+        let vault = PasswordVault::new(db);
+        vault.set_master(1, "correct horse", None).unwrap();
+
+        let chrome = "name,url,username,password,note\n\
+                      github.com,https://github.com/,octo,gh-pass,\n\
+                      ,https://www.example.com/login,me,ex-pass,\"multi\nline\"\n\
+                      nopass,https://nopass.example,me,,\n";
+        let report = vault.import_csv(1, chrome).unwrap();
+        assert_eq!((report.format.as_str(), report.imported, report.skipped), ("chrome", 2, 1));
+
+        let bitwarden = "folder,favorite,type,name,notes,fields,reprompt,login_uri,login_username,login_password,login_totp\n\
+                         Work,,login,Jira,,,,https://jira.example.com,dev,jira-pass,\n\
+                         ,,note,Wifi,the code is 1234,,,,,,\n";
+        let report = vault.import_csv(1, bitwarden).unwrap();
+        assert_eq!((report.format.as_str(), report.imported, report.skipped), ("bitwarden", 1, 1));
+
+        let firefox = "\u{feff}\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\
+                       \"https://github.com\",\"octo\",\"gh-pass\",,\"https://github.com\",\"{a}\",\"1\",\"1\",\"1\"\n";
+        let report = vault.import_csv(1, firefox).unwrap();
+        assert_eq!((report.format.as_str(), report.imported, report.skipped), ("firefox", 0, 1), "already imported from chrome");
+
+        let entries = vault.get_entries(1).unwrap();
+        let example = entries.iter().find(|e| e.title == "example.com").unwrap();
+        assert_eq!(example.notes.as_deref(), Some("multi\nline"));
+        let jira = entries.iter().find(|e| e.title == "Jira").unwrap();
+        assert_eq!((jira.category.as_str(), jira.username.as_str()), ("Work", "dev"));
+
+        assert!(vault.import_csv(1, "title,notes\nx,y\n").is_err());
+    }
+}
@@ -1,14 +1,21 @@
 // Privacy and incognito mode management for EarthServers Local
 // Handles session-based incognito state and history management
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, ToSql, params};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use crate::db::DbPool;
+
 /// Global incognito state - in-memory only, not persisted
 static INCOGNITO_MODE: AtomicBool = AtomicBool::new(false);
 
+/// Pages nothing in memory depends on. `indexed_at` is stamped on the first visit as
+/// well, so saved pages are told apart by what was added to them.
+const HISTORY_ONLY: &str = "is_favorite = 0 AND summary IS NULL AND COALESCE(tags, '') = ''
+     AND NOT EXISTS (SELECT 1 FROM page_notes n WHERE n.page_id = pages.id)";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryEntry {
     pub id: i64,
@@ -16,6 +23,9 @@ pub struct HistoryEntry {
     pub title: String,
     pub visited_at: String,
     pub profile_id: i64,
+    /// Visits recorded by history and memory for this page
+    pub visit_count: i64,
+    pub is_favorite: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,13 +48,14 @@ pub struct DateRange {
     pub end: String,
 }
 
+#[derive(Clone)]
 pub struct PrivacyManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl PrivacyManager {
-    pub fn new(db_path: String) -> Self {
-        PrivacyManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        PrivacyManager { db }
     }
 
     // ==================== Incognito Mode ====================
@@ -82,15 +93,15 @@ impl PrivacyManager {
         limit: i64,
         offset: i64,
     ) -> Result<Vec<HistoryEntry>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let entries: Vec<HistoryEntry> = match search_query {
             Some(q) => {
                 let pattern = format!("%{}%", q);
                 let mut stmt = conn.prepare(
-                    "SELECT id, url, title, visited_at, profile_id
+                    "SELECT id, url, title, visited_at, profile_id, visit_count, is_favorite
                      FROM pages
-                     WHERE profile_id = ?1 AND (url LIKE ?2 OR title LIKE ?2)
+                     WHERE profile_id = ?1 AND visit_count > 0 AND (url LIKE ?2 OR title LIKE ?2)
                      ORDER BY visited_at DESC
                      LIMIT ?3 OFFSET ?4"
                 )?;
@@ -101,15 +112,17 @@ impl PrivacyManager {
                         title: row.get(2)?,
                         visited_at: row.get(3)?,
                         profile_id: row.get(4)?,
+                        visit_count: row.get(5)?,
+                        is_favorite: row.get::<_, i64>(6)? == 1,
                     })
                 })?;
                 rows.filter_map(|r| r.ok()).collect()
             }
             None => {
                 let mut stmt = conn.prepare(
-                    "SELECT id, url, title, visited_at, profile_id
+                    "SELECT id, url, title, visited_at, profile_id, visit_count, is_favorite
                      FROM pages
-                     WHERE profile_id = ?1
+                     WHERE profile_id = ?1 AND visit_count > 0
                      ORDER BY visited_at DESC
                      LIMIT ?2 OFFSET ?3"
                 )?;
@@ -120,6 +133,8 @@ impl PrivacyManager {
                         title: row.get(2)?,
                         visited_at: row.get(3)?,
                         profile_id: row.get(4)?,
+                        visit_count: row.get(5)?,
+                        is_favorite: row.get::<_, i64>(6)? == 1,
                     })
                 })?;
                 rows.filter_map(|r| r.ok()).collect()
@@ -131,11 +146,11 @@ impl PrivacyManager {
 
     /// Delete a single history entry
     pub fn delete_history_entry(&self, entry_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // First delete associated notes
         conn.execute(
-            "DELETE FROM notes WHERE page_id = ?1",
+            "DELETE FROM page_notes WHERE page_id = ?1",
             params![entry_id],
         )?;
 
@@ -155,89 +170,80 @@ impl PrivacyManager {
         start_date: &str,
         end_date: &str,
     ) -> Result<i64> {
-        let conn = Connection::open(&self.db_path)?;
-
-        // First get the IDs to delete
-        let mut stmt = conn.prepare(
-            "SELECT id FROM pages WHERE profile_id = ?1 AND visited_at BETWEEN ?2 AND ?3"
-        )?;
-        let ids: Vec<i64> = stmt
-            .query_map(params![profile_id, start_date, end_date], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        // Delete associated notes
-        for id in &ids {
-            conn.execute("DELETE FROM notes WHERE page_id = ?1", params![id])?;
-        }
-
-        // Delete the pages
-        let affected = conn.execute(
-            "DELETE FROM pages WHERE profile_id = ?1 AND visited_at BETWEEN ?2 AND ?3",
-            params![profile_id, start_date, end_date],
-        )?;
-
-        Ok(affected as i64)
+        self.clear_visits(
+            profile_id,
+            "visited_at BETWEEN ?2 AND ?3",
+            params![start_date, end_date],
+        )
     }
 
     /// Clear all history for a profile
     pub fn clear_all_history(&self, profile_id: i64) -> Result<i64> {
-        let conn = Connection::open(&self.db_path)?;
-
-        // First delete all notes for this profile's pages
-        conn.execute(
-            "DELETE FROM notes WHERE page_id IN (SELECT id FROM pages WHERE profile_id = ?1)",
-            params![profile_id],
-        )?;
-
-        // Then delete all pages
-        let affected = conn.execute(
-            "DELETE FROM pages WHERE profile_id = ?1",
-            params![profile_id],
-        )?;
-
-        Ok(affected as i64)
+        self.clear_visits(profile_id, "1", params![])
     }
 
     /// Auto-delete history older than specified days
     pub fn auto_delete_old_history(&self, profile_id: i64, days: i32) -> Result<i64> {
-        let conn = Connection::open(&self.db_path)?;
-
         // Calculate cutoff timestamp (days ago in seconds)
         let cutoff = chrono_days_ago(days);
+        self.clear_visits(profile_id, "visited_at < ?2", params![cutoff])
+    }
 
-        // Get IDs to delete
-        let mut stmt = conn.prepare(
-            "SELECT id FROM pages WHERE profile_id = ?1 AND visited_at < ?2"
-        )?;
-        let ids: Vec<i64> = stmt
-            .query_map(params![profile_id, cutoff], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
+    /// Forget the visits to the profile's pages matching `filter` (whose parameters start
+    /// at ?2). History-only pages are deleted along with the chunks and entity mentions
+    /// indexed from them; pages kept in memory stay and only lose their visits.
+    /// Returns how many pages left the history.
+    fn clear_visits(&self, profile_id: i64, filter: &str, args: &[&dyn ToSql]) -> Result<i64> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        let mut all_args: Vec<&dyn ToSql> = vec![&profile_id];
+        all_args.extend_from_slice(args);
+        let scope = format!("profile_id = ?1 AND ({})", filter);
+
+        let ids: Vec<i64> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id FROM pages WHERE {} AND {}",
+                scope, HISTORY_ONLY
+            ))?;
+            let rows = stmt.query_map(all_args.as_slice(), |row| row.get(0))?;
+            rows.collect::<Result<_>>()?
+        };
 
-        // Delete associated notes
+        // Chunks and mentions point at pages without a foreign key
         for id in &ids {
-            conn.execute("DELETE FROM notes WHERE page_id = ?1", params![id])?;
+            for table in ["embedding_chunks", "entity_mentions", "entity_extractions"] {
+                tx.execute(
+                    &format!("DELETE FROM {} WHERE source = 'pages' AND source_id = ?1", table),
+                    params![id],
+                )?;
+            }
+            tx.execute("DELETE FROM pages WHERE id = ?1", params![id])?;
         }
 
-        // Delete the pages
-        let affected = conn.execute(
-            "DELETE FROM pages WHERE profile_id = ?1 AND visited_at < ?2",
-            params![profile_id, cutoff],
+        // Memory keeps the page; its first-seen time stands in for the last visit
+        let kept = tx.execute(
+            &format!(
+                "UPDATE pages SET visit_count = 0, visited_at = COALESCE(indexed_at, visited_at)
+                 WHERE {} AND visit_count > 0",
+                scope
+            ),
+            all_args.as_slice(),
         )?;
 
-        Ok(affected as i64)
+        tx.commit()?;
+        Ok((ids.len() + kept) as i64)
     }
 
     // ==================== History Statistics ====================
 
     /// Get statistics about browsing history
     pub fn get_history_stats(&self, profile_id: i64) -> Result<HistoryStats> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Total pages
         let total_pages: i64 = conn.query_row(
-            "SELECT COUNT(*) FROM pages WHERE profile_id = ?1",
+            "SELECT COUNT(*) FROM pages WHERE profile_id = ?1 AND visit_count > 0",
             params![profile_id],
             |row| row.get(0),
         )?;
@@ -252,7 +258,7 @@ impl PrivacyManager {
                         ELSE LENGTH(SUBSTR(url, INSTR(url, '://') + 3))
                     END
                 )
-            ) FROM pages WHERE profile_id = ?1",
+            ) FROM pages WHERE profile_id = ?1 AND visit_count > 0",
             params![profile_id],
             |row| row.get(0),
         )?;
@@ -267,9 +273,9 @@ impl PrivacyManager {
                         ELSE LENGTH(SUBSTR(url, INSTR(url, '://') + 3))
                     END
                 ) as domain,
-                COUNT(*) as visit_count
+                SUM(visit_count) as visit_count
              FROM pages
-             WHERE profile_id = ?1
+             WHERE profile_id = ?1 AND visit_count > 0
              GROUP BY domain
              ORDER BY visit_count DESC
              LIMIT 10"
@@ -299,10 +305,10 @@ impl PrivacyManager {
 
     /// Export history as JSON
     pub fn export_history(&self, profile_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = conn.prepare(
-            "SELECT id, url, title, content, visited_at FROM pages WHERE profile_id = ?1 ORDER BY visited_at DESC"
+            "SELECT id, url, title, content, visited_at FROM pages WHERE profile_id = ?1 AND visit_count > 0 ORDER BY visited_at DESC"
         )?;
 
         let entries: Vec<serde_json::Value> = stmt
@@ -369,4 +375,66 @@ mod tests {
         assert!(!state);
         assert!(!PrivacyManager::is_incognito());
     }
+
+    #[test]
+    fn test_clearing_history_keeps_memory() {
+        let db = crate::migrations::test_database("privacy_clear");
+        db.get().unwrap().execute_batch(
+            "INSERT INTO pages (id, url, title, visited_at, indexed_at, profile_id) VALUES
+                (1, 'https://seen.example', 'Seen', '10', '10', 1),
+                (2, 'https://other.example', 'Other profile', '10', '10', 2);
+             INSERT INTO pages (id, url, title, visited_at, indexed_at, is_favorite, profile_id)
+                VALUES (3, 'https://favorite.example', 'Favorite', '30', '5', 1, 1);
+             INSERT INTO pages (id, url, title, visited_at, indexed_at, tags, profile_id)
+                VALUES (4, 'https://saved.example', 'Saved', '40', '6', 'reading', 1);
+             INSERT INTO pages (id, url, title, visited_at, indexed_at, profile_id)
+                VALUES (5, 'https://noted.example', 'Noted', '50', '7', 1);
+             INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id)
+                VALUES (5, 'keep me', '50', '50', 1);
+             INSERT INTO embedding_chunks (source, source_id, profile_id, chunk_index, content, embedding, created_at)
+                VALUES ('pages', 1, 1, 0, 'seen', x'00', '10'), ('pages', 4, 1, 0, 'saved', x'00', '40');
+             INSERT INTO entities (id, profile_id, kind, name, normalized, created_at)
+                VALUES (1, 1, 'topic', 'Rust', 'rust', '10');
+             INSERT INTO entity_mentions (entity_id, source, source_id) VALUES (1, 'pages', 1), (1, 'pages', 4);
+             INSERT INTO entity_extractions (source, source_id, content_hash, extracted_at)
+                VALUES ('pages', 1, 'h', '10');"
+        ).unwrap();
+        let privacy = PrivacyManager::new(db.clone());
+
+        assert_eq!(privacy.clear_all_history(1).unwrap(), 4);
+        assert!(privacy.get_history(1, None, 10, 0).unwrap().is_empty());
+        assert_eq!(privacy.get_history(2, None, 10, 0).unwrap().len(), 1);
+
+        let conn = db.get().unwrap();
+        let kept: Vec<(i64, String, i64)> = conn
+            .prepare("SELECT id, visited_at, visit_count FROM pages WHERE profile_id = 1 ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(kept, vec![(3, "5".into(), 0), (4, "6".into(), 0), (5, "7".into(), 0)]);
+
+        let notes: i64 = conn.query_row("SELECT COUNT(*) FROM page_notes", [], |row| row.get(0)).unwrap();
+        assert_eq!(notes, 1);
+        for table in ["embedding_chunks", "entity_mentions"] {
+            let sources: Vec<i64> = conn
+                .prepare(&format!("SELECT source_id FROM {}", table))
+                .unwrap()
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(sources, vec![4], "{}", table);
+        }
+        let extractions: i64 = conn.query_row("SELECT COUNT(*) FROM entity_extractions", [], |row| row.get(0)).unwrap();
+        assert_eq!(extractions, 0);
+
+        // Already cleared; a new visit brings the page back into the history
+        assert_eq!(privacy.clear_all_history(1).unwrap(), 0);
+        conn.execute("UPDATE pages SET visit_count = 1, visited_at = '60' WHERE id = 4", []).unwrap();
+        assert_eq!(privacy.delete_history_by_date_range(1, "55", "65").unwrap(), 1);
+        assert_eq!(privacy.auto_delete_old_history(2, 1).unwrap(), 1);
+        assert!(privacy.get_history(2, None, 10, 0).unwrap().is_empty());
+    }
 }
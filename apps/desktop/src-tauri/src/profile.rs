@@ -4,6 +4,9 @@
 use rusqlite::{Connection, Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
     pub id: Option<i64>,
@@ -18,6 +21,9 @@ pub struct PrivacySettings {
     pub profile_id: i64,
     pub auto_delete_days: Option<i32>,
     pub ai_enabled_in_incognito: bool,
+    /// Generate page summaries and tag suggestions with the local LLM (opt-in)
+    #[serde(default)]
+    pub ai_summaries_enabled: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,41 +32,19 @@ pub struct ProfileWithSettings {
     pub privacy: PrivacySettings,
 }
 
+#[derive(Clone)]
 pub struct ProfileManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl ProfileManager {
-    pub fn new(db_path: String) -> Self {
-        ProfileManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        ProfileManager { db }
     }
 
-    /// Initialize profile tables in the database
+    /// Create the default profile on first start (tables come from the migrations)
     pub fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-
-        // Create profiles table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS profiles (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                icon TEXT,
-                created_at TEXT NOT NULL,
-                is_active INTEGER NOT NULL DEFAULT 0
-            )",
-            [],
-        )?;
-
-        // Create privacy settings table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS privacy_settings (
-                profile_id INTEGER PRIMARY KEY,
-                auto_delete_days INTEGER,
-                ai_enabled_in_incognito INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
+        let conn = self.db.get()?;
 
         // Create default profile if none exists
         let count: i64 = conn.query_row(
@@ -97,7 +81,7 @@ impl ProfileManager {
 
     /// Create a new profile
     pub fn create_profile(&self, name: &str, icon: Option<&str>) -> Result<Profile> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         conn.execute(
@@ -124,7 +108,7 @@ impl ProfileManager {
 
     /// Get all profiles
     pub fn get_profiles(&self) -> Result<Vec<Profile>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, name, icon, created_at, is_active FROM profiles ORDER BY created_at ASC"
         )?;
@@ -144,7 +128,7 @@ impl ProfileManager {
 
     /// Get the active profile
     pub fn get_active_profile(&self) -> Result<Option<Profile>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, name, icon, created_at, is_active FROM profiles WHERE is_active = 1"
         )?;
@@ -168,7 +152,7 @@ impl ProfileManager {
 
     /// Switch to a different profile
     pub fn switch_profile(&self, profile_id: i64) -> Result<Profile> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Deactivate all profiles
         conn.execute("UPDATE profiles SET is_active = 0", [])?;
@@ -197,7 +181,7 @@ impl ProfileManager {
 
     /// Update profile details
     pub fn update_profile(&self, profile_id: i64, name: &str, icon: Option<&str>) -> Result<Profile> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE profiles SET name = ?1, icon = ?2 WHERE id = ?3",
@@ -220,8 +204,8 @@ impl ProfileManager {
     }
 
     /// Delete a profile and all associated data
-    pub fn delete_profile(&self, profile_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+    pub fn delete_profile(&self, profile_id: i64) -> AppResult<()> {
+        let conn = self.db.get()?;
 
         // Check if this is the only profile
         let count: i64 = conn.query_row(
@@ -231,7 +215,7 @@ impl ProfileManager {
         )?;
 
         if count <= 1 {
-            return Err(rusqlite::Error::QueryReturnedNoRows); // Can't delete last profile
+            return Err(AppError::Validation("Can't delete the last profile".to_string()));
         }
 
         // Check if deleting active profile
@@ -261,9 +245,10 @@ impl ProfileManager {
 
     /// Get privacy settings for a profile
     pub fn get_privacy_settings(&self, profile_id: i64) -> Result<PrivacySettings> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT profile_id, auto_delete_days, ai_enabled_in_incognito FROM privacy_settings WHERE profile_id = ?1"
+            "SELECT profile_id, auto_delete_days, ai_enabled_in_incognito, ai_summaries_enabled
+             FROM privacy_settings WHERE profile_id = ?1"
         )?;
 
         stmt.query_row(params![profile_id], |row| {
@@ -271,19 +256,22 @@ impl ProfileManager {
                 profile_id: row.get(0)?,
                 auto_delete_days: row.get(1)?,
                 ai_enabled_in_incognito: row.get::<_, i64>(2)? == 1,
+                ai_summaries_enabled: row.get::<_, i64>(3)? == 1,
             })
         })
     }
 
     /// Update privacy settings for a profile
     pub fn update_privacy_settings(&self, settings: &PrivacySettings) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
-            "UPDATE privacy_settings SET auto_delete_days = ?1, ai_enabled_in_incognito = ?2 WHERE profile_id = ?3",
+            "UPDATE privacy_settings SET auto_delete_days = ?1, ai_enabled_in_incognito = ?2, ai_summaries_enabled = ?3
+             WHERE profile_id = ?4",
             params![
                 settings.auto_delete_days,
                 if settings.ai_enabled_in_incognito { 1 } else { 0 },
+                if settings.ai_summaries_enabled { 1 } else { 0 },
                 settings.profile_id
             ],
         )?;
@@ -293,7 +281,7 @@ impl ProfileManager {
 
     /// Export profile data as JSON
     pub fn export_profile(&self, profile_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get profile
         let profile = self.get_profile_by_id(&conn, profile_id)?;
@@ -377,7 +365,12 @@ mod tests {
 
     #[test]
     fn test_create_profile() {
-        let manager = ProfileManager::new(":memory:".to_string());
+        let path = std::env::temp_dir().join(format!("earth_profile_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let db_path = path.to_string_lossy().to_string();
+        crate::migrations::run(&db_path).unwrap();
+
+        let manager = ProfileManager::new(DbPool::open(&db_path).unwrap());
         manager.init().unwrap();
 
         let profile = manager.create_profile("Test Profile", Some("star")).unwrap();
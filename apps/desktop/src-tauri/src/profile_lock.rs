@@ -0,0 +1,725 @@
+// Encrypted-at-rest profiles for EarthServers Local
+// A profile with a passphrase gets a random data key, stored wrapped by a key derived
+// from the passphrase (see crypto.rs). Locking encrypts the profile's content columns in
+// place and forgets the data key; unlocking decrypts them again. A locked profile's
+// history, memory, notes, entities, bookmarks, tabs and media history are ciphertext on
+// disk, so a copy of the database reveals their row counts and timestamps but no URLs,
+// titles or text. Web scraper jobs, the pages they scraped and the domain list are not
+// encrypted: scheduled crawls run on their own, locked or not. An unlocked profile is
+// plaintext like any other, which is why profiles lock on exit and after a period of
+// inactivity.
+
+use rusqlite::types::Value;
+use rusqlite::{Connection, ErrorCode, OptionalExtension, params, params_from_iter};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::crypto::{self, Key, KEY_LEN};
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+
+/// Emitted with a ProfileLockStatus when a profile is locked for inactivity
+pub const PROFILE_LOCK_EVENT: &str = "profile-lock-changed";
+
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Columns holding a profile's content, and which rows belong to the profile (?1 = profile id)
+struct EncryptedColumns {
+    table: &'static str,
+    scope: &'static str,
+    columns: &'static [&'static str],
+    /// Unique key covering an encrypted column, which a restored value can collide on
+    unique: &'static [&'static str],
+}
+
+const ENCRYPTED: &[EncryptedColumns] = &[
+    EncryptedColumns {
+        table: "pages",
+        scope: "profile_id = ?1",
+        columns: &["url", "title", "content", "summary", "tags", "embedding"],
+        unique: &["url", "profile_id"],
+    },
+    EncryptedColumns {
+        table: "page_notes",
+        scope: "page_id IN (SELECT id FROM pages WHERE profile_id = ?1)",
+        columns: &["content"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "page_summaries",
+        scope: "page_id IN (SELECT id FROM pages WHERE profile_id = ?1)",
+        columns: &["summary", "suggested_tags"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "page_links",
+        scope: "source_id IN (SELECT id FROM pages WHERE profile_id = ?1)",
+        columns: &["target_url"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "embedding_chunks",
+        scope: "source = 'pages' AND source_id IN (SELECT id FROM pages WHERE profile_id = ?1)",
+        columns: &["content", "embedding"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "entities",
+        scope: "profile_id = ?1",
+        columns: &["name", "normalized"],
+        unique: &["profile_id", "kind", "normalized"],
+    },
+    EncryptedColumns {
+        table: "entity_aliases",
+        scope: "profile_id = ?1",
+        columns: &["normalized"],
+        unique: &["profile_id", "kind", "normalized"],
+    },
+    EncryptedColumns {
+        table: "bookmarks",
+        scope: "profile_id = ?1",
+        columns: &["title", "url", "favicon", "tags", "notes"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "bookmark_folders",
+        scope: "profile_id = ?1",
+        columns: &["name"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "tabs",
+        scope: "profile_id = ?1",
+        columns: &["title", "url", "favicon"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "tab_history",
+        scope: "tab_id IN (SELECT id FROM tabs WHERE profile_id = ?1)",
+        columns: &["url", "title"],
+        unique: &[],
+    },
+    EncryptedColumns {
+        table: "multimedia_history",
+        scope: "profile_id = ?1",
+        columns: &["source", "title", "thumbnail"],
+        unique: &[],
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileLockStatus {
+    pub profile_id: i64,
+    /// The profile has a passphrase
+    pub encrypted: bool,
+    pub locked: bool,
+    pub auto_lock_minutes: Option<u32>,
+}
+
+struct Session {
+    locked: bool,
+    /// The data key while unlocked. Also None for a profile the app quit without locking
+    /// (a crash): it stays readable and can only be locked once the passphrase is entered.
+    key: Option<Key>,
+    auto_lock_minutes: Option<u32>,
+    last_activity: Instant,
+}
+
+#[derive(Clone)]
+pub struct ProfileLockManager {
+    db: DbPool,
+    sessions: Arc<Mutex<HashMap<i64, Session>>>,
+}
+
+impl ProfileLockManager {
+    pub fn new(db: DbPool) -> Self {
+        ProfileLockManager {
+            db,
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Load which profiles are encrypted and locked
+    pub fn init(&self) -> AppResult<()> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare("SELECT profile_id, locked, auto_lock_minutes FROM profile_encryption")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, bool>(1)?, row.get::<_, Option<u32>>(2)?))
+        })?;
+
+        let mut sessions = self.sessions();
+        for row in rows {
+            let (profile_id, locked, auto_lock_minutes) = row?;
+            sessions.insert(profile_id, Session { locked, key: None, auto_lock_minutes, last_activity: Instant::now() });
+        }
+        Ok(())
+    }
+
+    pub fn status(&self, profile_id: i64) -> ProfileLockStatus {
+        match self.sessions().get(&profile_id) {
+            Some(session) => ProfileLockStatus {
+                profile_id,
+                encrypted: true,
+                locked: session.locked,
+                auto_lock_minutes: session.auto_lock_minutes,
+            },
+            None => ProfileLockStatus { profile_id, encrypted: false, locked: false, auto_lock_minutes: None },
+        }
+    }
+
+    /// Fails with ProfileLocked for a locked profile; otherwise counts as activity for auto-lock.
+    /// Called by every command that reads or writes encrypted content.
+    pub fn ensure_unlocked(&self, profile_id: i64) -> AppResult<()> {
+        match self.sessions().get_mut(&profile_id) {
+            Some(session) if session.locked => Err(AppError::ProfileLocked(profile_id)),
+            Some(session) => {
+                session.last_activity = Instant::now();
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Give a profile a passphrase. It stays unlocked until `lock` is called.
+    pub fn enable(&self, profile_id: i64, passphrase: &str, auto_lock_minutes: Option<u32>) -> AppResult<ProfileLockStatus> {
+        check_passphrase(passphrase)?;
+        check_auto_lock(auto_lock_minutes)?;
+        if self.sessions().contains_key(&profile_id) {
+            return Err(AppError::Validation("This profile already has a passphrase".to_string()));
+        }
+
+        let conn = self.db.get()?;
+        let exists = conn
+            .query_row("SELECT 1 FROM profiles WHERE id = ?1", params![profile_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Err(AppError::NotFound(format!("Profile {}", profile_id)));
+        }
+
+        let key = crypto::random_key();
+        let key_params = crypto::new_key_params();
+        let wrapped_key = crypto::encrypt(&crypto::derive_key(passphrase, &key_params)?, &key)?;
+        let now = chrono_now();
+        conn.execute(
+            "INSERT INTO profile_encryption (profile_id, key_params, wrapped_key, locked, auto_lock_minutes, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5, ?5)",
+            params![profile_id, key_params, wrapped_key, auto_lock_minutes, now],
+        )?;
+
+        self.sessions().insert(profile_id, Session {
+            locked: false,
+            key: Some(key),
+            auto_lock_minutes,
+            last_activity: Instant::now(),
+        });
+        Ok(self.status(profile_id))
+    }
+
+    /// Remove the passphrase, decrypting the profile first if it is locked
+    pub fn disable(&self, profile_id: i64, passphrase: &str) -> AppResult<ProfileLockStatus> {
+        let conn = self.db.get()?;
+        let (key, locked) = unwrap_key(&conn, profile_id, passphrase)?;
+
+        let tx = conn.unchecked_transaction()?;
+        if locked {
+            transform(&tx, profile_id, |value| open_value(&key, value))?;
+        }
+        tx.execute("DELETE FROM profile_encryption WHERE profile_id = ?1", params![profile_id])?;
+        tx.commit()?;
+
+        self.sessions().remove(&profile_id);
+        Ok(self.status(profile_id))
+    }
+
+    /// Encrypt the profile's content and forget its key
+    pub fn lock(&self, profile_id: i64) -> AppResult<ProfileLockStatus> {
+        let key = {
+            let mut sessions = self.sessions();
+            let session = sessions
+                .get_mut(&profile_id)
+                .ok_or_else(|| AppError::Validation("This profile has no passphrase".to_string()))?;
+            if session.locked {
+                return Ok(self.status_of(profile_id, session));
+            }
+            let key = session.key.ok_or_else(|| {
+                AppError::Crypto("Enter the passphrase once more to be able to lock this profile".to_string())
+            })?;
+            // Refuse new readers straight away; they would see half-encrypted rows
+            session.locked = true;
+            key
+        };
+
+        let result = (|| -> AppResult<()> {
+            let conn = self.db.get()?;
+            let tx = conn.unchecked_transaction()?;
+            transform(&tx, profile_id, |value| seal_value(&key, value))?;
+            tx.execute(
+                "UPDATE profile_encryption SET locked = 1, updated_at = ?1 WHERE profile_id = ?2",
+                params![chrono_now(), profile_id],
+            )?;
+            tx.commit()?;
+
+            if let Err(e) = scrub(&conn) {
+                eprintln!("Failed to scrub the database after locking profile {}: {}", profile_id, e);
+            }
+            Ok(())
+        })();
+
+        let mut sessions = self.sessions();
+        if let Some(session) = sessions.get_mut(&profile_id) {
+            match result {
+                Ok(()) => session.key = None,
+                Err(e) => {
+                    session.locked = false;
+                    return Err(e);
+                }
+            }
+        }
+        drop(sessions);
+        Ok(self.status(profile_id))
+    }
+
+    /// Check the passphrase and decrypt the profile's content
+    pub fn unlock(&self, profile_id: i64, passphrase: &str) -> AppResult<ProfileLockStatus> {
+        let conn = self.db.get()?;
+        let (key, locked) = unwrap_key(&conn, profile_id, passphrase)?;
+
+        if locked {
+            let tx = conn.unchecked_transaction()?;
+            transform(&tx, profile_id, |value| open_value(&key, value))?;
+            tx.execute(
+                "UPDATE profile_encryption SET locked = 0, updated_at = ?1 WHERE profile_id = ?2",
+                params![chrono_now(), profile_id],
+            )?;
+            tx.commit()?;
+        }
+
+        let mut sessions = self.sessions();
+        let session = sessions.entry(profile_id).or_insert(Session {
+            locked: false,
+            key: None,
+            auto_lock_minutes: None,
+            last_activity: Instant::now(),
+        });
+        session.locked = false;
+        session.key = Some(key);
+        session.last_activity = Instant::now();
+        drop(sessions);
+        Ok(self.status(profile_id))
+    }
+
+    /// Change the passphrase and rotate the data key. A locked profile is re-encrypted
+    /// with the new key value by value, so no plaintext is written along the way.
+    pub fn rekey(&self, profile_id: i64, old_passphrase: &str, new_passphrase: &str) -> AppResult<ProfileLockStatus> {
+        check_passphrase(new_passphrase)?;
+        let conn = self.db.get()?;
+        let (old_key, locked) = unwrap_key(&conn, profile_id, old_passphrase)?;
+
+        let new_key = crypto::random_key();
+        let key_params = crypto::new_key_params();
+        let wrapped_key = crypto::encrypt(&crypto::derive_key(new_passphrase, &key_params)?, &new_key)?;
+
+        let tx = conn.unchecked_transaction()?;
+        if locked {
+            transform(&tx, profile_id, |value| seal_value(&new_key, open_value(&old_key, value)?))?;
+        }
+        tx.execute(
+            "UPDATE profile_encryption SET key_params = ?1, wrapped_key = ?2, updated_at = ?3 WHERE profile_id = ?4",
+            params![key_params, wrapped_key, chrono_now(), profile_id],
+        )?;
+        tx.commit()?;
+
+        if let Some(session) = self.sessions().get_mut(&profile_id) {
+            if !session.locked {
+                session.key = Some(new_key);
+            }
+        }
+        Ok(self.status(profile_id))
+    }
+
+    /// None turns auto-lock off
+    pub fn set_auto_lock(&self, profile_id: i64, minutes: Option<u32>) -> AppResult<ProfileLockStatus> {
+        check_auto_lock(minutes)?;
+        if !self.sessions().contains_key(&profile_id) {
+            return Err(AppError::Validation("This profile has no passphrase".to_string()));
+        }
+
+        let conn = self.db.get()?;
+        conn.execute(
+            "UPDATE profile_encryption SET auto_lock_minutes = ?1, updated_at = ?2 WHERE profile_id = ?3",
+            params![minutes, chrono_now(), profile_id],
+        )?;
+        if let Some(session) = self.sessions().get_mut(&profile_id) {
+            session.auto_lock_minutes = minutes;
+            session.last_activity = Instant::now();
+        }
+        Ok(self.status(profile_id))
+    }
+
+    /// Lock every profile idle for longer than its auto-lock setting; returns the ones locked
+    pub fn lock_idle(&self) -> Vec<i64> {
+        self.lock_idle_at(Instant::now())
+    }
+
+    /// Lock every unlocked profile, e.g. when the app exits
+    pub fn lock_all(&self) {
+        let unlocked: Vec<i64> = self.sessions()
+            .iter()
+            .filter(|(_, session)| !session.locked && session.key.is_some())
+            .map(|(profile_id, _)| *profile_id)
+            .collect();
+        for profile_id in unlocked {
+            if let Err(e) = self.lock(profile_id) {
+                eprintln!("Failed to lock profile {}: {}", profile_id, e);
+            }
+        }
+    }
+
+    fn lock_idle_at(&self, now: Instant) -> Vec<i64> {
+        let idle: Vec<i64> = self.sessions()
+            .iter()
+            .filter(|(_, session)| !session.locked && session.key.is_some())
+            .filter(|(_, session)| session.auto_lock_minutes.is_some_and(|minutes| {
+                now.saturating_duration_since(session.last_activity) >= Duration::from_secs(u64::from(minutes) * 60)
+            }))
+            .map(|(profile_id, _)| *profile_id)
+            .collect();
+
+        idle.into_iter()
+            .filter(|profile_id| match self.lock(*profile_id) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("Failed to auto-lock profile {}: {}", profile_id, e);
+                    false
+                }
+            })
+            .collect()
+    }
+
+    fn status_of(&self, profile_id: i64, session: &Session) -> ProfileLockStatus {
+        ProfileLockStatus {
+            profile_id,
+            encrypted: true,
+            locked: session.locked,
+            auto_lock_minutes: session.auto_lock_minutes,
+        }
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<i64, Session>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// ==================== Helper Functions ====================
+
+fn check_passphrase(passphrase: &str) -> AppResult<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(AppError::Validation(format!(
+            "The passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        )));
+    }
+    Ok(())
+}
+
+fn check_auto_lock(minutes: Option<u32>) -> AppResult<()> {
+    if minutes == Some(0) {
+        return Err(AppError::Validation("Auto-lock needs at least one minute".to_string()));
+    }
+    Ok(())
+}
+
+/// The profile's data key and whether its rows are currently encrypted
+fn unwrap_key(conn: &Connection, profile_id: i64, passphrase: &str) -> AppResult<(Key, bool)> {
+    let (key_params, wrapped_key, locked): (String, String, bool) = conn
+        .query_row(
+            "SELECT key_params, wrapped_key, locked FROM profile_encryption WHERE profile_id = ?1",
+            params![profile_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::Validation("This profile has no passphrase".to_string()))?;
+
+    let passphrase_key = crypto::derive_key(passphrase, &key_params)?;
+    let key = crypto::decrypt(&passphrase_key, &wrapped_key)
+        .map_err(|_| AppError::Crypto("Wrong passphrase".to_string()))?;
+    let key: Key = key
+        .try_into()
+        .map_err(|_| AppError::Crypto(format!("Stored key is not {} bytes", KEY_LEN)))?;
+    Ok((key, locked))
+}
+
+/// Rewrite every encrypted column of the profile's rows through `f`
+fn transform(conn: &Connection, profile_id: i64, f: impl Fn(Value) -> AppResult<Value>) -> AppResult<()> {
+    for spec in ENCRYPTED {
+        let rows: Vec<(i64, Vec<Value>)> = {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT rowid, {} FROM {} WHERE {}",
+                spec.columns.join(", "),
+                spec.table,
+                spec.scope
+            ))?;
+            let rows = stmt.query_map(params![profile_id], |row| {
+                let values = (1..=spec.columns.len())
+                    .map(|i| row.get::<_, Value>(i))
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok((row.get(0)?, values))
+            })?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        let assignments: Vec<String> = spec.columns.iter()
+            .enumerate()
+            .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+            .collect();
+        let rowid_param = spec.columns.len() + 1;
+        let mut update = conn.prepare(&format!(
+            "UPDATE {} SET {} WHERE rowid = ?{}",
+            spec.table,
+            assignments.join(", "),
+            rowid_param
+        ))?;
+
+        // A row added under the same unique key while the profile was locked (say, the same
+        // URL visited again) gives way to the one being restored. It is deleted rather than
+        // replaced so its triggers and cascades run, keeping the full-text indexes in step.
+        let same_key: Vec<String> = spec.unique.iter()
+            .map(|column| match spec.columns.iter().position(|c| c == column) {
+                Some(i) => format!("{} = ?{}", column, i + 1),
+                None => format!("{0} = (SELECT {0} FROM {1} WHERE rowid = ?{2})", column, spec.table, rowid_param),
+            })
+            .collect();
+        let displace = format!(
+            "DELETE FROM {} WHERE rowid != ?{} AND {}",
+            spec.table,
+            rowid_param,
+            same_key.join(" AND ")
+        );
+
+        for (rowid, values) in rows {
+            let mut values = values.into_iter().map(&f).collect::<AppResult<Vec<_>>>()?;
+            values.push(Value::Integer(rowid));
+            match update.execute(params_from_iter(&values)) {
+                Err(rusqlite::Error::SqliteFailure(e, _))
+                    if e.code == ErrorCode::ConstraintViolation && !spec.unique.is_empty() =>
+                {
+                    conn.execute(&displace, params_from_iter(&values))?;
+                    update.execute(params_from_iter(&values))?;
+                }
+                result => {
+                    result?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encrypt one value, keeping its SQLite type in the first plaintext byte
+fn seal_value(key: &Key, value: Value) -> AppResult<Value> {
+    let (tag, bytes) = match value {
+        Value::Null => return Ok(Value::Null),
+        Value::Text(text) => (b't', text.into_bytes()),
+        Value::Blob(blob) => (b'b', blob),
+        Value::Integer(i) => (b'i', i.to_string().into_bytes()),
+        Value::Real(f) => (b'r', f.to_string().into_bytes()),
+    };
+    let mut plaintext = Vec::with_capacity(bytes.len() + 1);
+    plaintext.push(tag);
+    plaintext.extend(bytes);
+    Ok(Value::Text(crypto::encrypt(key, &plaintext)?))
+}
+
+/// Decrypt one value. The key was already checked against the wrapped copy, so anything
+/// that doesn't decrypt was written in plaintext while the profile was locked; keep it.
+fn open_value(key: &Key, value: Value) -> AppResult<Value> {
+    let Value::Text(encoded) = &value else { return Ok(value) };
+    let Ok(plaintext) = crypto::decrypt(key, encoded) else { return Ok(value) };
+
+    let corrupt = || AppError::Crypto("Encrypted profile data is corrupted".to_string());
+    let (tag, bytes) = plaintext.split_first().ok_or_else(corrupt)?;
+    let text = || String::from_utf8(bytes.to_vec()).map_err(|_| corrupt());
+    Ok(match tag {
+        b't' => Value::Text(text()?),
+        b'b' => Value::Blob(bytes.to_vec()),
+        b'i' => Value::Integer(text()?.parse().map_err(|_| corrupt())?),
+        b'r' => Value::Real(text()?.parse().map_err(|_| corrupt())?),
+        _ => return Err(corrupt()),
+    })
+}
+
+/// Drop what the old plaintext left behind: deleted entries in the full-text indexes are
+/// only purged by a merge, and old page versions linger in the WAL until a checkpoint
+fn scrub(conn: &Connection) -> AppResult<()> {
+    conn.execute_batch(
+        "INSERT INTO pages_fts(pages_fts) VALUES ('optimize');
+         INSERT INTO page_notes_fts(page_notes_fts) VALUES ('optimize');"
+    )?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    Ok(())
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorKind;
+
+    fn seeded(name: &str) -> (ProfileLockManager, DbPool) {
+        let db = crate::migrations::test_database(&format!("lock_{}", name));
+        db.get().unwrap().execute_batch(
+            "INSERT INTO pages (id, url, title, content, visited_at, embedding, profile_id)
+                VALUES (1, 'https://diary.example', 'Diary', 'my secret diary entry', '0', X'0102', 1);
+             INSERT INTO page_notes (page_id, content, created_at, updated_at, profile_id)
+                VALUES (1, 'a private thought', '0', '0', 1);
+             INSERT INTO bookmarks (profile_id, title, url, created_at, updated_at)
+                VALUES (1, 'Bank', 'https://bank.example', '0', '0');
+             INSERT INTO pages (url, title, content, visited_at, profile_id)
+                VALUES ('https://public.example', 'Public', 'nothing to hide', '0', 2);"
+        ).unwrap();
+        let manager = ProfileLockManager::new(db.clone());
+        manager.init().unwrap();
+        (manager, db)
+    }
+
+    fn page_content(db: &DbPool) -> String {
+        db.get().unwrap().query_row("SELECT content FROM pages WHERE id = 1", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_locked_profile_is_unreadable_on_disk() {
+        let (manager, db) = seeded("on_disk");
+        manager.enable(1, "correct horse", None).unwrap();
+        let status = manager.lock(1).unwrap();
+        assert!(status.locked);
+        assert_eq!(manager.ensure_unlocked(1).unwrap_err().kind(), ErrorKind::Locked);
+        assert!(manager.ensure_unlocked(2).is_ok());
+
+        let conn = db.get().unwrap();
+        let leaks: i64 = conn.query_row(
+            "SELECT (SELECT COUNT(*) FROM pages WHERE url LIKE '%diary%' OR content LIKE '%secret%')
+                  + (SELECT COUNT(*) FROM page_notes WHERE content LIKE '%private%')
+                  + (SELECT COUNT(*) FROM bookmarks WHERE url LIKE '%bank%')
+                  + (SELECT COUNT(*) FROM pages_fts WHERE pages_fts MATCH 'secret')",
+            [],
+            |row| row.get(0),
+        ).unwrap();
+        assert_eq!(leaks, 0);
+        // Other profiles are untouched
+        let public: i64 = conn.query_row("SELECT COUNT(*) FROM pages WHERE content = 'nothing to hide'", [], |row| row.get(0)).unwrap();
+        assert_eq!(public, 1);
+        drop(conn);
+
+        // Nor is the plaintext anywhere in the file, including freed pages and the WAL
+        let path = db.get().unwrap().path().unwrap().to_string();
+        for file in [path.clone(), format!("{}-wal", path)] {
+            let bytes = std::fs::read(&file).unwrap_or_default();
+            assert!(!bytes.windows(13).any(|w| w == b"secret diary "), "plaintext found in {}", file);
+        }
+    }
+
+    #[test]
+    fn test_unlock_restores_content() {
+        let (manager, db) = seeded("unlock");
+        manager.enable(1, "correct horse", None).unwrap();
+        manager.lock(1).unwrap();
+
+        assert_eq!(manager.unlock(1, "wrong horse").unwrap_err().kind(), ErrorKind::Crypto);
+        assert!(manager.status(1).locked);
+
+        // Written while locked, e.g. by a background job; survives the unlock as is
+        db.get().unwrap().execute(
+            "INSERT INTO bookmarks (profile_id, title, url, created_at, updated_at) VALUES (1, 'Late', 'https://late.example', '0', '0')",
+            [],
+        ).unwrap();
+
+        assert!(!manager.unlock(1, "correct horse").unwrap().locked);
+        let page: (String, String, Vec<u8>) = db.get().unwrap()
+            .query_row("SELECT url, content, embedding FROM pages WHERE id = 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!(page, ("https://diary.example".to_string(), "my secret diary entry".to_string(), vec![1, 2]));
+        let urls: Vec<String> = db.get().unwrap()
+            .prepare("SELECT url FROM bookmarks WHERE profile_id = 1 ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(urls, vec!["https://bank.example", "https://late.example"]);
+        assert!(manager.ensure_unlocked(1).is_ok());
+    }
+
+    #[test]
+    fn test_unlock_displaces_page_revisited_while_locked() {
+        let (manager, db) = seeded("revisited");
+        manager.enable(1, "correct horse", None).unwrap();
+        manager.lock(1).unwrap();
+
+        // Same URL stored in plaintext while the restored row is still ciphertext
+        db.get().unwrap().execute(
+            "INSERT INTO pages (url, title, content, visited_at, profile_id)
+             VALUES ('https://diary.example', 'Diary', 'written while locked', '1', 1)",
+            [],
+        ).unwrap();
+
+        manager.unlock(1, "correct horse").unwrap();
+        let conn = db.get().unwrap();
+        let ids: Vec<i64> = conn
+            .prepare("SELECT id FROM pages WHERE url = 'https://diary.example'").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(ids, vec![1]);
+        assert_eq!(page_content(&db), "my secret diary entry");
+
+        // The displaced row left the full-text index with it
+        let hits = |term: &str| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM pages_fts WHERE pages_fts MATCH ?1", [term], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(hits("locked"), 0);
+        assert_eq!(hits("secret"), 1);
+        conn.execute("INSERT INTO pages_fts(pages_fts) VALUES ('integrity-check')", []).unwrap();
+    }
+
+    #[test]
+    fn test_rekey_while_locked() {
+        let (manager, db) = seeded("rekey");
+        manager.enable(1, "correct horse", None).unwrap();
+        manager.lock(1).unwrap();
+        let sealed = page_content(&db);
+
+        assert_eq!(manager.rekey(1, "wrong horse", "battery staple").unwrap_err().kind(), ErrorKind::Crypto);
+        manager.rekey(1, "correct horse", "battery staple").unwrap();
+        assert!(manager.status(1).locked);
+        assert_ne!(page_content(&db), sealed, "rows are re-encrypted under the new data key");
+
+        assert!(manager.unlock(1, "correct horse").is_err());
+        manager.unlock(1, "battery staple").unwrap();
+        assert_eq!(page_content(&db), "my secret diary entry");
+    }
+
+    #[test]
+    fn test_auto_lock_after_inactivity() {
+        let (manager, _db) = seeded("auto_lock");
+        manager.enable(1, "correct horse", Some(5)).unwrap();
+
+        assert!(manager.lock_idle_at(Instant::now() + Duration::from_secs(4 * 60)).is_empty());
+        assert_eq!(manager.lock_idle_at(Instant::now() + Duration::from_secs(5 * 60)), vec![1]);
+        assert!(manager.status(1).locked);
+
+        // After a restart the profile is still locked; disabling decrypts it for good
+        let restarted = ProfileLockManager::new(manager.db.clone());
+        restarted.init().unwrap();
+        assert!(restarted.status(1).locked);
+        assert!(!restarted.disable(1, "correct horse").unwrap().encrypted);
+        assert!(restarted.ensure_unlocked(1).is_ok());
+    }
+}
@@ -1,10 +1,12 @@
 // Community Trust & Bias Rating System for EarthSearch
 // Manages domain ratings, aggregates, and subdomain-specific ratings
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::db::DbPool;
+
 // ==================== Data Structures ====================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,20 +73,21 @@ pub struct UserRatingHistory {
 
 // ==================== Rating Manager ====================
 
+#[derive(Clone)]
 pub struct RatingManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl RatingManager {
-    pub fn new(db_path: String) -> Self {
-        RatingManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        RatingManager { db }
     }
 
     // ==================== Rating CRUD ====================
 
     /// Submit or update a domain rating
     pub fn submit_rating(&self, rating: &DomainRating) -> Result<DomainRating> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Check if user already rated this domain
@@ -146,7 +149,7 @@ impl RatingManager {
 
     /// Get a user's rating for a specific domain
     pub fn get_user_rating(&self, domain_id: i64, user_id: &str) -> Result<Option<DomainRating>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let result = conn.query_row(
             "SELECT id, domain_id, user_id, trust_rating, bias_rating, review_text,
@@ -178,7 +181,7 @@ impl RatingManager {
 
     /// Get all ratings for a domain
     pub fn get_domain_ratings(&self, domain_id: i64, limit: Option<i64>) -> Result<Vec<DomainRating>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let limit_clause = limit.map(|l| format!(" LIMIT {}", l)).unwrap_or_default();
 
         let mut stmt = conn.prepare(&format!(
@@ -209,7 +212,7 @@ impl RatingManager {
 
     /// Delete a rating
     pub fn delete_rating(&self, rating_id: i64, user_id: &str) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get domain_id before deletion for aggregate update
         let domain_id: Option<i64> = conn.query_row(
@@ -236,7 +239,7 @@ impl RatingManager {
 
     /// Update aggregated ratings for a domain
     pub fn update_aggregates(&self, domain_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Calculate averages and distributions
@@ -303,7 +306,7 @@ impl RatingManager {
 
     /// Get aggregated ratings for a domain
     pub fn get_aggregate(&self, domain_id: i64) -> Result<Option<RatingAggregate>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let result = conn.query_row(
             "SELECT domain_id, avg_trust, avg_bias, total_ratings, trust_distribution, bias_distribution, last_updated
@@ -377,7 +380,7 @@ impl RatingManager {
 
     /// Get average scores per category for a domain
     fn get_category_averages(&self, domain_id: i64) -> Result<HashMap<String, f64>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = conn.prepare(
             "SELECT rc.category, AVG(CAST(rc.score AS REAL))
@@ -405,7 +408,7 @@ impl RatingManager {
 
     /// Submit a subdomain-specific rating
     pub fn submit_subdomain_rating(&self, parent_domain_id: i64, subdomain: &str, trust: f64, bias: f64) -> Result<SubdomainRating> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Check if subdomain already exists
         let existing: Option<(i64, i64)> = conn.query_row(
@@ -456,7 +459,7 @@ impl RatingManager {
 
     /// Get subdomain ratings for a parent domain
     pub fn get_subdomain_ratings(&self, parent_domain_id: i64) -> Result<Vec<SubdomainRating>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = conn.prepare(
             "SELECT id, parent_domain_id, subdomain, avg_trust, avg_bias, total_ratings
@@ -481,7 +484,7 @@ impl RatingManager {
 
     /// Mark a rating as helpful
     pub fn mark_helpful(&self, rating_id: i64) -> Result<i32> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE domain_ratings SET helpful_count = helpful_count + 1 WHERE id = ?1",
@@ -499,7 +502,7 @@ impl RatingManager {
 
     /// Report a rating
     pub fn report_rating(&self, rating_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let affected = conn.execute(
             "UPDATE domain_ratings SET reported = TRUE WHERE id = ?1",
@@ -513,7 +516,7 @@ impl RatingManager {
 
     /// Get a user's rating history
     pub fn get_user_history(&self, user_id: &str) -> Result<UserRatingHistory> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = conn.prepare(
             "SELECT id, domain_id, user_id, trust_rating, bias_rating, review_text,
@@ -557,7 +560,7 @@ impl RatingManager {
 
     /// Add category scores to a rating
     pub fn add_category_scores(&self, rating_id: i64, categories: Vec<(String, i32)>) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Clear existing categories for this rating
         conn.execute(
@@ -578,7 +581,7 @@ impl RatingManager {
 
     /// Get category scores for a rating
     pub fn get_rating_categories(&self, rating_id: i64) -> Result<Vec<RatingCategory>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let mut stmt = conn.prepare(
             "SELECT id, domain_rating_id, category, score FROM rating_categories WHERE domain_rating_id = ?1"
@@ -0,0 +1,296 @@
+// robots.txt and sitemap parsing for the web scraper
+// Follows RFC 9309: the groups naming our product token apply, or the `*` groups when
+// none do; the longest matching rule wins and Allow wins a tie. `Crawl-delay` isn't in
+// the RFC but is widely used, so it's honoured too. Fetching and caching live in scraper.rs.
+
+use std::time::Duration;
+
+/// What robots.txt allows a crawler to fetch from one site
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+    sitemaps: Vec<String>,
+    unavailable: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// One user-agent group while parsing
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// No robots.txt (a 4xx response): everything is allowed
+    pub fn allow_all() -> Self {
+        Robots::default()
+    }
+
+    /// robots.txt couldn't be fetched (a 5xx response or network error): nothing is allowed
+    pub fn unavailable() -> Self {
+        Robots {
+            rules: vec![Rule { allow: false, pattern: "/".to_string() }],
+            unavailable: true,
+            ..Robots::default()
+        }
+    }
+
+    /// The rules `text` sets for `user_agent` (a full User-Agent header; only its
+    /// product token is matched against the groups)
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let token = product_token(user_agent);
+        let mut groups: Vec<Group> = Vec::new();
+        let mut sitemaps = Vec::new();
+        // Consecutive user-agent lines share a group; any other line ends the list
+        let mut in_agent_list = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !in_agent_list {
+                        groups.push(Group::default());
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                    in_agent_list = true;
+                }
+                "sitemap" => {
+                    // Sitemaps apply to every crawler, wherever they appear
+                    if !value.is_empty() {
+                        sitemaps.push(value.to_string());
+                    }
+                }
+                key => {
+                    in_agent_list = false;
+                    let Some(group) = groups.last_mut() else {
+                        continue;
+                    };
+                    match key {
+                        "allow" | "disallow" if !value.is_empty() => group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        }),
+                        "crawl-delay" => {
+                            group.crawl_delay = value
+                                .parse::<f64>()
+                                .ok()
+                                .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                                .map(Duration::from_secs_f64);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        // Every group naming us applies; failing that, every `*` group
+        let names = |group: &Group, agent: &str| group.agents.iter().any(|a| a == agent);
+        let agent = if !token.is_empty() && groups.iter().any(|group| names(group, &token)) { token.as_str() } else { "*" };
+        let mut robots = Robots { sitemaps, ..Robots::default() };
+        for group in groups.iter().filter(|group| names(group, agent)) {
+            robots.rules.extend(group.rules.iter().cloned());
+            robots.crawl_delay = robots.crawl_delay.max(group.crawl_delay);
+        }
+        robots
+    }
+
+    /// Whether the rules let us fetch `url`
+    pub fn is_allowed(&self, url: &url::Url) -> bool {
+        let mut path = url.path().to_string();
+        if path == "/robots.txt" {
+            return true;
+        }
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, &path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// How long to wait between requests, if the site says
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+
+    /// Sitemap URLs listed in the file
+    pub fn sitemaps(&self) -> &[String] {
+        &self.sitemaps
+    }
+
+    /// Whether everything is off limits because robots.txt couldn't be fetched
+    pub fn is_unavailable(&self) -> bool {
+        self.unavailable
+    }
+}
+
+/// The name a crawler goes by in robots.txt: the leading letters of its User-Agent,
+/// e.g. "reclaimbot" for "ReclaimBot/1.0"
+pub fn product_token(user_agent: &str) -> String {
+    user_agent
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic() || *c == '_' || *c == '-')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Match a robots.txt path pattern, where `*` is any run of characters and a trailing
+/// `$` anchors the end. Patterns otherwise match as prefixes.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        if last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+/// The URLs in a sitemap. A sitemap index lists further sitemaps instead of pages.
+#[derive(Debug, Default)]
+pub struct Sitemap {
+    pub pages: Vec<String>,
+    pub sitemaps: Vec<String>,
+}
+
+pub fn parse_sitemap(xml: &str) -> Sitemap {
+    let loc = regex::Regex::new(r"(?s)<loc>\s*(.*?)\s*</loc>").unwrap();
+    let urls = loc
+        .captures_iter(xml)
+        .map(|captures| {
+            captures[1]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&")
+        })
+        .collect();
+
+    if xml.contains("<sitemapindex") {
+        Sitemap { pages: Vec::new(), sitemaps: urls }
+    } else {
+        Sitemap { pages: urls, sitemaps: Vec::new() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(robots: &Robots, path: &str) -> bool {
+        robots.is_allowed(&url::Url::parse(&format!("https://example.com{}", path)).unwrap())
+    }
+
+    #[test]
+    fn test_groups_and_longest_match() {
+        let text = "
+            # Everyone else
+            User-agent: *
+            Disallow: /private/
+            Crawl-delay: 10
+
+            User-agent: OtherBot
+            User-agent: reclaimbot
+            Disallow: /
+            Allow: /docs/
+            Disallow: /docs/drafts/
+            Allow: /*.css$
+            Crawl-delay: 2.5
+
+            Sitemap: https://example.com/sitemap.xml
+        ";
+
+        let ours = Robots::parse(text, "ReclaimBot/1.0 (+https://example.org/bot)");
+        assert!(allowed(&ours, "/docs/intro"));
+        assert!(!allowed(&ours, "/docs/drafts/next"));
+        assert!(!allowed(&ours, "/blog/"));
+        assert!(allowed(&ours, "/theme/site.css"));
+        assert!(!allowed(&ours, "/theme/site.css?v=2"));
+        assert!(allowed(&ours, "/robots.txt"));
+        assert_eq!(ours.crawl_delay(), Some(Duration::from_millis(2500)));
+        assert_eq!(ours.sitemaps(), ["https://example.com/sitemap.xml"]);
+
+        let others = Robots::parse(text, "Mozilla/5.0");
+        assert!(allowed(&others, "/blog/"));
+        assert!(!allowed(&others, "/private/keys"));
+        assert_eq!(others.crawl_delay(), Some(Duration::from_secs(10)));
+
+        // A tie between Allow and Disallow goes to Allow
+        let tie = Robots::parse("User-agent: *\nDisallow: /page\nAllow: /page\n", "ReclaimBot");
+        assert!(allowed(&tie, "/page"));
+        // An empty Disallow allows everything
+        assert!(allowed(&Robots::parse("User-agent: *\nDisallow:\n", "ReclaimBot"), "/anything"));
+        assert!(allowed(&Robots::allow_all(), "/anything"));
+        assert!(!allowed(&Robots::unavailable(), "/"));
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert!(pattern_matches("/fish", "/fish.html"));
+        assert!(!pattern_matches("/fish", "/Fish.asp"));
+        assert!(pattern_matches("/*.php", "/folder/filename.php?parameters"));
+        assert!(pattern_matches("/*.php$", "/filename.php"));
+        assert!(!pattern_matches("/*.php$", "/filename.php/"));
+        assert!(pattern_matches("/fish*.php", "/fishheads/catfish.php?parameters"));
+        assert!(!pattern_matches("/fish*.php", "/Fish.PHP"));
+        assert!(pattern_matches("/", "/"));
+        assert!(pattern_matches("/a$", "/a"));
+        assert!(!pattern_matches("/a$", "/ab"));
+    }
+
+    #[test]
+    fn test_sitemaps() {
+        let index = parse_sitemap(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <sitemap><loc>https://example.com/sitemap-posts.xml</loc></sitemap>
+            </sitemapindex>"#,
+        );
+        assert_eq!(index.sitemaps, ["https://example.com/sitemap-posts.xml"]);
+        assert!(index.pages.is_empty());
+
+        let pages = parse_sitemap(
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc> https://example.com/a?x=1&amp;y=2 </loc><lastmod>2024-01-01</lastmod></url>
+              <url><loc>https://example.com/b</loc></url>
+            </urlset>"#,
+        );
+        assert_eq!(pages.pages, ["https://example.com/a?x=1&y=2", "https://example.com/b"]);
+    }
+}
@@ -1,10 +1,60 @@
 // Web Scraper for Reclaim
-// Allows users to scrape and index web content for local search
+// Allows users to scrape and index web content for local search.
+// Jobs run as background tasks, by hand or on their cron schedule; a running job can be
+// paused, resumed and cancelled, and every run is kept in scraping_runs.
+// Crawls are polite by default: robots.txt is honoured (see robots.rs), requests to one
+// host are spaced out across every running job, and 429s and 5xx responses are retried
+// with backoff. Pages are visited breadth-first from a persisted frontier (frontier.rs),
+// so an interrupted crawl resumes on the next run.
+// Re-scraping asks for pages conditionally (ETag / Last-Modified) and keeps each real
+// change to a page's text as a new version, which can be diffed against the one before.
 
-use rusqlite::{Connection, Result, params};
+use html_scraper::{ElementRef, Html, Selector};
+use rusqlite::{OptionalExtension, Result, params};
 use serde::{Deserialize, Serialize};
-use reqwest::Client;
-use std::collections::HashSet;
+use sha2::{Digest, Sha256};
+use reqwest::header::{
+    HeaderMap, HeaderValue, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::{Client, StatusCode};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::frontier::{self, CrawlScope, Frontier, Visit};
+use crate::robots::{self, Robots};
+use crate::tasks::TaskContext;
+
+pub const SCRAPER_PROGRESS_EVENT: &str = "scraper-progress";
+pub const SCRAPER_PAGE_CHANGED_EVENT: &str = "scraper-page-changed";
+
+pub const DEFAULT_USER_AGENT: &str = "ReclaimBot/1.0 (Reclaim Web Scraper)";
+
+/// Runs keep the first this-many errors; the rest are only counted
+const MAX_RUN_ERRORS: usize = 50;
+
+/// How long a fetched robots.txt is trusted, and how soon to try again when it couldn't be fetched
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const ROBOTS_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+/// robots.txt past this size is ignored, as RFC 9309 allows
+const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+const MAX_REQUEST_DELAY_MS: u64 = 60_000;
+const MAX_RETRIES: u32 = 10;
+/// First retry waits this long, doubling after each attempt
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait before a retry; a server asking for longer is given up on
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(5 * 60);
+/// Sitemap files fetched per run, counting those listed in sitemap indexes
+const MAX_SITEMAPS: usize = 10;
+/// Versions kept per page; older ones are dropped as new ones come in
+const MAX_PAGE_VERSIONS: i64 = 50;
+/// Unchanged words shown on each side of a change in a diff
+const DIFF_CONTEXT_WORDS: usize = 20;
 
 // ==================== Types ====================
 
@@ -19,16 +69,63 @@ pub struct ScrapingJob {
     pub max_pages: i32,
     pub content_selectors: Vec<ContentSelector>,
     pub schedule_cron: Option<String>,
+    pub settings: CrawlSettings,
     pub status: String,
     pub last_run_at: Option<String>,
     pub pages_scraped: i32,
     pub created_at: String,
 }
 
+/// A named field to pull out of every page with a CSS selector, e.g. `author` from
+/// `meta[name=author]`. Values end up in the page's metadata JSON under `name`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContentSelector {
     pub name: String,
     pub selector: String,
+    /// Take this attribute (e.g. `content` of a meta tag, `datetime` of a time) instead of the text
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Collect every match into an array instead of taking the first
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// How a job crawls. Stored as JSON in scraping_jobs.settings; missing fields take
+/// their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlSettings {
+    /// User-Agent header, also matched against robots.txt; None for DEFAULT_USER_AGENT
+    pub user_agent: Option<String>,
+    /// Skip pages robots.txt disallows and wait out its Crawl-delay
+    pub respect_robots: bool,
+    /// Least time between two requests to the same host
+    pub request_delay_ms: u64,
+    /// Retries of a 429 or 5xx response before giving up on the page
+    pub max_retries: u32,
+    /// Also crawl the pages listed in the site's sitemaps
+    pub follow_sitemaps: bool,
+    /// Which links to follow from the base URL
+    pub scope: CrawlScope,
+}
+
+impl Default for CrawlSettings {
+    fn default() -> Self {
+        CrawlSettings {
+            user_agent: None,
+            respect_robots: true,
+            request_delay_ms: 1000,
+            max_retries: 3,
+            follow_sitemaps: false,
+            scope: CrawlScope::default(),
+        }
+    }
+}
+
+impl CrawlSettings {
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,83 +136,186 @@ pub struct ScrapedPage {
     pub title: Option<String>,
     pub content: String,
     pub metadata: Option<String>,
+    /// When the current content was scraped
     pub scraped_at: String,
+    /// When the page was last fetched, changed or not
+    pub checked_at: Option<String>,
+    /// When a re-scrape last found new content; None if it never has
+    pub changed_at: Option<String>,
+    /// Notify the user when the page changes
+    pub watch: bool,
+}
+
+/// One stored version of a page's content, without the content itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageVersion {
+    pub id: i64,
+    pub page_id: i64,
+    pub title: Option<String>,
+    pub content_hash: Option<String>,
+    pub scraped_at: String,
+}
+
+/// Word-level differences between two versions of a page
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageDiff {
+    pub page_id: i64,
+    pub from: PageVersion,
+    pub to: PageVersion,
+    /// Read in order, they give the newer text with the removed words in place; long
+    /// unchanged stretches are cut down to DIFF_CONTEXT_WORDS either side of a change
+    pub segments: Vec<DiffSegment>,
+    pub words_added: i32,
+    pub words_removed: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffSegment {
+    pub kind: DiffKind,
+    pub text: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// Payload of SCRAPER_PAGE_CHANGED_EVENT, sent when a watched page changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageChanged {
+    pub job_id: i64,
+    pub page_id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    /// None if the page had no earlier version to compare with
+    pub from_version: Option<i64>,
+    pub to_version: i64,
+    pub words_added: i32,
+    pub words_removed: i32,
+}
+
+/// Payload of SCRAPER_PROGRESS_EVENT, sent as a run moves along
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobStatus {
+    pub job_id: i64,
+    pub run_id: i64,
     pub status: String,
     pub pages_scraped: i32,
     pub current_url: Option<String>,
+    /// The latest error, if any
     pub error: Option<String>,
+    pub error_count: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapingRun {
+    pub id: i64,
+    pub job_id: i64,
+    /// "manual" or "schedule"
+    pub trigger: String,
+    /// running, paused, completed, cancelled, failed, or interrupted when the app quit mid-run
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub pages_scraped: i32,
+    /// Pages whose content differed from the last time they were scraped
+    pub pages_changed: i32,
+    pub error_count: i32,
+    pub errors: Vec<RunError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunError {
+    pub url: Option<String>,
+    pub message: String,
+}
+
+/// A run in progress, for pausing and cancelling it by job id
+struct ActiveRun {
+    /// Empty until the run's task starts
+    task_id: String,
+    paused: watch::Sender<bool>,
 }
 
-// ==================== Database ====================
-
-pub fn init_scraper_tables(conn: &Connection) -> Result<()> {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS scraping_jobs (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            profile_id INTEGER NOT NULL,
-            name TEXT NOT NULL,
-            base_url TEXT NOT NULL,
-            url_pattern TEXT,
-            max_depth INTEGER DEFAULT 2,
-            max_pages INTEGER DEFAULT 100,
-            content_selectors TEXT,
-            schedule_cron TEXT,
-            status TEXT DEFAULT 'pending',
-            last_run_at TEXT,
-            pages_scraped INTEGER DEFAULT 0,
-            created_at TEXT NOT NULL
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS scraped_pages (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            job_id INTEGER NOT NULL,
-            url TEXT NOT NULL,
-            title TEXT,
-            content TEXT,
-            metadata TEXT,
-            scraped_at TEXT NOT NULL,
-            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE,
-            UNIQUE(job_id, url)
-        )",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_scraped_pages_job ON scraped_pages(job_id)",
-        [],
-    )?;
-
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_scraped_pages_url ON scraped_pages(url)",
-        [],
-    )?;
-
-    Ok(())
+/// A job marked running ahead of its task; dropping it marks the job idle again
+pub struct JobClaim {
+    job_id: i64,
+    paused: watch::Receiver<bool>,
+    active: Arc<Mutex<HashMap<i64, ActiveRun>>>,
+}
+
+impl Drop for JobClaim {
+    fn drop(&mut self) {
+        self.active.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.job_id);
+    }
+}
+
+/// What the scraper knows about the sites it visits, shared by every job
+#[derive(Default)]
+struct Hosts {
+    /// robots.txt by origin, until it's due to be fetched again
+    robots: HashMap<String, (RobotsFile, Instant)>,
+    /// When each host may next be sent a request
+    next_request: HashMap<String, Instant>,
+}
+
+#[derive(Clone)]
+enum RobotsFile {
+    Found(String),
+    /// A 4xx response: no rules
+    Missing,
+    /// A 5xx response or network error: keep off the site
+    Unreachable,
+}
+
+impl RobotsFile {
+    fn rules(&self, user_agent: &str) -> Robots {
+        match self {
+            RobotsFile::Found(text) => Robots::parse(text, user_agent),
+            RobotsFile::Missing => Robots::allow_all(),
+            RobotsFile::Unreachable => Robots::unavailable(),
+        }
+    }
 }
 
 // ==================== Manager ====================
 
+#[derive(Clone)]
 pub struct ScraperManager {
-    db_path: String,
+    db: DbPool,
+    /// Running jobs by job id
+    active: Arc<Mutex<HashMap<i64, ActiveRun>>>,
+    hosts: Arc<Mutex<Hosts>>,
 }
 
 impl ScraperManager {
-    pub fn new(db_path: String) -> Self {
-        // Initialize tables
-        if let Ok(conn) = Connection::open(&db_path) {
-            let _ = init_scraper_tables(&conn);
+    pub fn new(db: DbPool) -> Self {
+        ScraperManager {
+            db,
+            active: Arc::new(Mutex::new(HashMap::new())),
+            hosts: Arc::new(Mutex::new(Hosts::default())),
         }
-        ScraperManager { db_path }
     }
 
-    /// Create a new scraping job
+    /// Runs still marked running or paused were cut short when the app last quit
+    pub fn init(&self) -> Result<()> {
+        let conn = self.db.get()?;
+        let now = chrono::Utc::now().to_rfc3339();
+        conn.execute(
+            "UPDATE scraping_runs SET status = 'interrupted', finished_at = ?1 WHERE status IN ('running', 'paused')",
+            params![now],
+        )?;
+        conn.execute(
+            "UPDATE scraping_jobs SET status = 'interrupted' WHERE status IN ('running', 'paused')",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Create a new scraping job; fails if a content selector isn't valid CSS
     pub fn create_job(
         &self,
         profile_id: i64,
@@ -125,8 +325,10 @@ impl ScraperManager {
         max_depth: i32,
         max_pages: i32,
         content_selectors: Vec<ContentSelector>,
-    ) -> Result<i64> {
-        let conn = Connection::open(&self.db_path)?;
+    ) -> AppResult<i64> {
+        compile_selectors(&content_selectors)?;
+
+        let conn = self.db.get()?;
         let now = chrono::Utc::now().to_rfc3339();
         let selectors_json = serde_json::to_string(&content_selectors).unwrap_or_default();
 
@@ -141,10 +343,10 @@ impl ScraperManager {
 
     /// Get all scraping jobs for a profile
     pub fn get_jobs(&self, profile_id: i64) -> Result<Vec<ScrapingJob>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, name, base_url, url_pattern, max_depth, max_pages,
-                    content_selectors, schedule_cron, status, last_run_at, pages_scraped, created_at
+                    content_selectors, schedule_cron, settings, status, last_run_at, pages_scraped, created_at
              FROM scraping_jobs
              WHERE profile_id = ?1
              ORDER BY created_at DESC"
@@ -153,6 +355,7 @@ impl ScraperManager {
         let jobs = stmt.query_map(params![profile_id], |row| {
             let selectors_json: String = row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "[]".to_string());
             let selectors: Vec<ContentSelector> = serde_json::from_str(&selectors_json).unwrap_or_default();
+            let settings: CrawlSettings = serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default();
 
             Ok(ScrapingJob {
                 id: row.get(0)?,
@@ -164,10 +367,11 @@ impl ScraperManager {
                 max_pages: row.get(6)?,
                 content_selectors: selectors,
                 schedule_cron: row.get(8)?,
-                status: row.get(9)?,
-                last_run_at: row.get(10)?,
-                pages_scraped: row.get(11)?,
-                created_at: row.get(12)?,
+                settings,
+                status: row.get(10)?,
+                last_run_at: row.get(11)?,
+                pages_scraped: row.get(12)?,
+                created_at: row.get(13)?,
             })
         })?;
 
@@ -176,16 +380,17 @@ impl ScraperManager {
 
     /// Get a single job by ID
     pub fn get_job(&self, job_id: i64) -> Result<ScrapingJob> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.query_row(
             "SELECT id, profile_id, name, base_url, url_pattern, max_depth, max_pages,
-                    content_selectors, schedule_cron, status, last_run_at, pages_scraped, created_at
+                    content_selectors, schedule_cron, settings, status, last_run_at, pages_scraped, created_at
              FROM scraping_jobs WHERE id = ?1",
             params![job_id],
             |row| {
                 let selectors_json: String = row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "[]".to_string());
                 let selectors: Vec<ContentSelector> = serde_json::from_str(&selectors_json).unwrap_or_default();
+                let settings: CrawlSettings = serde_json::from_str(&row.get::<_, String>(9)?).unwrap_or_default();
 
                 Ok(ScrapingJob {
                     id: row.get(0)?,
@@ -197,10 +402,11 @@ impl ScraperManager {
                     max_pages: row.get(6)?,
                     content_selectors: selectors,
                     schedule_cron: row.get(8)?,
-                    status: row.get(9)?,
-                    last_run_at: row.get(10)?,
-                    pages_scraped: row.get(11)?,
-                    created_at: row.get(12)?,
+                    settings,
+                    status: row.get(10)?,
+                    last_run_at: row.get(11)?,
+                    pages_scraped: row.get(12)?,
+                    created_at: row.get(13)?,
                 })
             },
         )
@@ -208,10 +414,12 @@ impl ScraperManager {
 
     /// Delete a scraping job
     pub fn delete_job(&self, job_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
-        // Delete scraped pages first
+        // Delete scraped pages, run history and any unfinished crawl first
         conn.execute("DELETE FROM scraped_pages WHERE job_id = ?1", params![job_id])?;
+        conn.execute("DELETE FROM scraping_runs WHERE job_id = ?1", params![job_id])?;
+        conn.execute("DELETE FROM scraping_frontier WHERE job_id = ?1", params![job_id])?;
 
         // Delete the job
         conn.execute("DELETE FROM scraping_jobs WHERE id = ?1", params![job_id])?;
@@ -221,7 +429,7 @@ impl ScraperManager {
 
     /// Update job status
     pub fn update_job_status(&self, job_id: i64, status: &str, pages_scraped: i32) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono::Utc::now().to_rfc3339();
 
         conn.execute(
@@ -232,25 +440,150 @@ impl ScraperManager {
         Ok(())
     }
 
-    /// Save a scraped page
-    pub fn save_page(&self, job_id: i64, url: &str, title: Option<&str>, content: &str, metadata: Option<&str>) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+    /// Save a fetched page. A new page or changed text adds a version; otherwise only
+    /// the title, metadata and caching headers are brought up to date.
+    fn save_page(&self, job_id: i64, page: &FetchedPage) -> Result<SavedPage> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
         let now = chrono::Utc::now().to_rfc3339();
+        let links = serde_json::to_string(page.links).unwrap_or_else(|_| "[]".to_string());
 
-        conn.execute(
-            "INSERT OR REPLACE INTO scraped_pages (job_id, url, title, content, metadata, scraped_at)
+        let existing = tx
+            .query_row(
+                "SELECT id, content_hash, content, watch FROM scraped_pages WHERE job_id = ?1 AND url = ?2",
+                params![job_id, page.url],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, bool>(3)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let (page_id, change, watched) = match existing {
+            None => {
+                tx.execute(
+                    "INSERT INTO scraped_pages (job_id, url, title, content, metadata, content_hash, etag, last_modified, links, scraped_at, checked_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+                    params![job_id, page.url, page.title, page.content, page.metadata, page.content_hash, page.etag, page.last_modified, links, now],
+                )?;
+                (tx.last_insert_rowid(), PageChange::New, false)
+            }
+            // Pages from before content hashes were kept are compared by their text
+            Some((page_id, hash, content, watched))
+                if hash.as_deref() == Some(page.content_hash)
+                    || (hash.is_none() && content.as_deref() == Some(page.content)) =>
+            {
+                tx.execute(
+                    "UPDATE scraped_pages
+                     SET title = ?1, metadata = ?2, content_hash = ?3, etag = ?4, last_modified = ?5, links = ?6, checked_at = ?7
+                     WHERE id = ?8",
+                    params![page.title, page.metadata, page.content_hash, page.etag, page.last_modified, links, now, page_id],
+                )?;
+                (page_id, PageChange::Unchanged, watched)
+            }
+            Some((page_id, _, content, watched)) => {
+                tx.execute(
+                    "UPDATE scraped_pages
+                     SET title = ?1, content = ?2, metadata = ?3, content_hash = ?4, etag = ?5, last_modified = ?6, links = ?7,
+                         scraped_at = ?8, checked_at = ?8, changed_at = ?8
+                     WHERE id = ?9",
+                    params![page.title, page.content, page.metadata, page.content_hash, page.etag, page.last_modified, links, now, page_id],
+                )?;
+                let previous_version: Option<i64> = tx.query_row(
+                    "SELECT MAX(id) FROM scraped_page_versions WHERE page_id = ?1",
+                    params![page_id],
+                    |row| row.get(0),
+                )?;
+                let previous_content = content.unwrap_or_default();
+                (page_id, PageChange::Changed { previous_version, previous_content }, watched)
+            }
+        };
+
+        if matches!(change, PageChange::Unchanged) {
+            tx.commit()?;
+            return Ok(SavedPage { page_id, change, version_id: None, watched });
+        }
+        tx.execute(
+            "INSERT INTO scraped_page_versions (page_id, title, content, metadata, content_hash, scraped_at)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![job_id, url, title, content, metadata, now],
+            params![page_id, page.title, page.content, page.metadata, page.content_hash, now],
+        )?;
+        let version_id = tx.last_insert_rowid();
+        tx.execute(
+            "DELETE FROM scraped_page_versions
+             WHERE page_id = ?1 AND id NOT IN (
+                 SELECT id FROM scraped_page_versions WHERE page_id = ?1 ORDER BY id DESC LIMIT ?2
+             )",
+            params![page_id, MAX_PAGE_VERSIONS],
         )?;
+        tx.commit()?;
 
-        Ok(())
+        Ok(SavedPage { page_id, change, version_id: Some(version_id), watched })
+    }
+
+    /// Headers asking for the page at `url` only if it changed since it was last
+    /// scraped; empty for a page the job hasn't got
+    fn conditional_headers(&self, job_id: i64, url: &str) -> Result<HeaderMap> {
+        let conn = self.db.get()?;
+        let validators = conn
+            .query_row(
+                "SELECT etag, last_modified FROM scraped_pages WHERE job_id = ?1 AND url = ?2",
+                params![job_id, url],
+                |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?)),
+            )
+            .optional()?;
+
+        let mut headers = HeaderMap::new();
+        if let Some((etag, last_modified)) = validators {
+            if let Some(etag) = etag.and_then(|etag| HeaderValue::from_str(&etag).ok()) {
+                headers.insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(date) = last_modified.and_then(|date| HeaderValue::from_str(&date).ok()) {
+                headers.insert(IF_MODIFIED_SINCE, date);
+            }
+        }
+        Ok(headers)
+    }
+
+    /// Note that the page at `url` was found unchanged (a 304) and return the links it
+    /// had; None if the job hasn't got the page
+    fn mark_unchanged(&self, job_id: i64, url: &str) -> Result<Option<Vec<String>>> {
+        let conn = self.db.get()?;
+        let updated = conn.execute(
+            "UPDATE scraped_pages SET checked_at = ?1 WHERE job_id = ?2 AND url = ?3",
+            params![chrono::Utc::now().to_rfc3339(), job_id, url],
+        )?;
+        if updated == 0 {
+            return Ok(None);
+        }
+        let links: String = conn.query_row(
+            "SELECT links FROM scraped_pages WHERE job_id = ?1 AND url = ?2",
+            params![job_id, url],
+            |row| row.get(0),
+        )?;
+        Ok(Some(serde_json::from_str(&links).unwrap_or_default()))
+    }
+
+    /// URL of a page of the job, other than `url`, whose text hashes to `content_hash`
+    fn page_with_content(&self, job_id: i64, content_hash: &str, url: &str) -> Result<Option<String>> {
+        let conn = self.db.get()?;
+        conn.query_row(
+            "SELECT url FROM scraped_pages WHERE job_id = ?1 AND content_hash = ?2 AND url != ?3 LIMIT 1",
+            params![job_id, content_hash, url],
+            |row| row.get(0),
+        )
+        .optional()
     }
 
     /// Get scraped pages for a job
     pub fn get_pages(&self, job_id: i64, limit: i32) -> Result<Vec<ScrapedPage>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
-            "SELECT id, job_id, url, title, content, metadata, scraped_at
+            "SELECT id, job_id, url, title, content, metadata, scraped_at, checked_at, changed_at, watch
              FROM scraped_pages
              WHERE job_id = ?1
              ORDER BY scraped_at DESC
@@ -266,6 +599,9 @@ impl ScraperManager {
                 content: row.get(4)?,
                 metadata: row.get(5)?,
                 scraped_at: row.get(6)?,
+                checked_at: row.get(7)?,
+                changed_at: row.get(8)?,
+                watch: row.get(9)?,
             })
         })?;
 
@@ -274,11 +610,12 @@ impl ScraperManager {
 
     /// Search scraped content
     pub fn search_content(&self, profile_id: i64, query: &str, limit: i32) -> Result<Vec<ScrapedPage>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let search_pattern = format!("%{}%", query);
 
         let mut stmt = conn.prepare(
-            "SELECT sp.id, sp.job_id, sp.url, sp.title, sp.content, sp.metadata, sp.scraped_at
+            "SELECT sp.id, sp.job_id, sp.url, sp.title, sp.content, sp.metadata, sp.scraped_at,
+                    sp.checked_at, sp.changed_at, sp.watch
              FROM scraped_pages sp
              JOIN scraping_jobs sj ON sp.job_id = sj.id
              WHERE sj.profile_id = ?1
@@ -296,132 +633,1518 @@ impl ScraperManager {
                 content: row.get(4)?,
                 metadata: row.get(5)?,
                 scraped_at: row.get(6)?,
+                checked_at: row.get(7)?,
+                changed_at: row.get(8)?,
+                watch: row.get(9)?,
             })
         })?;
 
         pages.collect()
     }
 
-    /// Run a scraping job (simplified version - actual scraping would be more complex)
-    pub async fn run_job(&self, job_id: i64) -> std::result::Result<(), String> {
-        let job = self.get_job(job_id).map_err(|e| e.to_string())?;
+    // ==================== Versions ====================
 
-        self.update_job_status(job_id, "running", 0).map_err(|e| e.to_string())?;
+    /// Watch a page, to be told when a run finds it changed, or stop watching it
+    pub fn set_page_watch(&self, page_id: i64, watch: bool) -> AppResult<()> {
+        let conn = self.db.get()?;
+        let updated = conn.execute("UPDATE scraped_pages SET watch = ?1 WHERE id = ?2", params![watch, page_id])?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Scraped page {}", page_id)));
+        }
+        Ok(())
+    }
+
+    /// The page's stored versions, newest first
+    pub fn get_page_versions(&self, page_id: i64) -> Result<Vec<PageVersion>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, page_id, title, content_hash, scraped_at
+             FROM scraped_page_versions
+             WHERE page_id = ?1
+             ORDER BY id DESC"
+        )?;
+
+        let versions = stmt.query_map(params![page_id], |row| {
+            Ok(PageVersion {
+                id: row.get(0)?,
+                page_id: row.get(1)?,
+                title: row.get(2)?,
+                content_hash: row.get(3)?,
+                scraped_at: row.get(4)?,
+            })
+        })?;
 
+        versions.collect()
+    }
+
+    /// What changed in a page's text between two of its versions
+    pub fn diff_page_versions(&self, from_id: i64, to_id: i64) -> AppResult<PageDiff> {
+        let conn = self.db.get()?;
+        let version = |id: i64| {
+            conn.query_row(
+                "SELECT id, page_id, title, content_hash, scraped_at, content FROM scraped_page_versions WHERE id = ?1",
+                params![id],
+                |row| {
+                    let version = PageVersion {
+                        id: row.get(0)?,
+                        page_id: row.get(1)?,
+                        title: row.get(2)?,
+                        content_hash: row.get(3)?,
+                        scraped_at: row.get(4)?,
+                    };
+                    Ok((version, row.get::<_, String>(5)?))
+                },
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Page version {}", id)))
+        };
+        let (from, old) = version(from_id)?;
+        let (to, new) = version(to_id)?;
+        if from.page_id != to.page_id {
+            return Err(AppError::Validation("Only versions of the same page can be compared".to_string()));
+        }
+
+        let (segments, words_added, words_removed) = diff_words(&old, &new);
+        Ok(PageDiff { page_id: to.page_id, from, to, segments, words_added, words_removed })
+    }
+
+    // ==================== Scheduling ====================
+
+    /// Set or clear (None or blank) the job's cron schedule
+    pub fn set_schedule(&self, job_id: i64, schedule_cron: Option<&str>) -> AppResult<ScrapingJob> {
+        let schedule_cron = schedule_cron.map(str::trim).filter(|cron| !cron.is_empty());
+        if let Some(cron) = schedule_cron {
+            parse_schedule(cron)?;
+        }
+
+        let conn = self.db.get()?;
+        let updated = conn.execute(
+            "UPDATE scraping_jobs SET schedule_cron = ?1 WHERE id = ?2",
+            params![schedule_cron, job_id],
+        )?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Scraping job {}", job_id)));
+        }
+        Ok(self.get_job(job_id)?)
+    }
+
+    /// Change how a job crawls
+    pub fn set_settings(&self, job_id: i64, mut settings: CrawlSettings) -> AppResult<ScrapingJob> {
+        settings.user_agent = settings.user_agent.map(|agent| agent.trim().to_string()).filter(|agent| !agent.is_empty());
+        if let Some(agent) = &settings.user_agent {
+            if HeaderValue::from_str(agent).is_err() || robots::product_token(agent).is_empty() {
+                return Err(AppError::Validation(
+                    "The user agent must start with a name, like ReclaimBot/1.0".to_string(),
+                ));
+            }
+        }
+        if settings.request_delay_ms > MAX_REQUEST_DELAY_MS {
+            return Err(AppError::Validation(format!(
+                "The request delay can be at most {} seconds",
+                MAX_REQUEST_DELAY_MS / 1000
+            )));
+        }
+        if settings.max_retries > MAX_RETRIES {
+            return Err(AppError::Validation(format!("At most {} retries are allowed", MAX_RETRIES)));
+        }
+
+        let conn = self.db.get()?;
+        let updated = conn.execute(
+            "UPDATE scraping_jobs SET settings = ?1 WHERE id = ?2",
+            params![serde_json::to_string(&settings)?, job_id],
+        )?;
+        if updated == 0 {
+            return Err(AppError::NotFound(format!("Scraping job {}", job_id)));
+        }
+        Ok(self.get_job(job_id)?)
+    }
+
+    /// Scheduled jobs whose next run, counted from their last run (or creation), is due
+    pub fn due_jobs(&self, now: chrono::DateTime<chrono::Utc>) -> AppResult<Vec<i64>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, schedule_cron, COALESCE(last_run_at, created_at)
+             FROM scraping_jobs
+             WHERE schedule_cron IS NOT NULL AND schedule_cron != ''"
+        )?;
+        let jobs = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(jobs
+            .into_iter()
+            .filter(|(job_id, cron, since)| {
+                !self.is_running(*job_id) && next_run_after(cron, since).is_some_and(|next| next <= now)
+            })
+            .map(|(job_id, _, _)| job_id)
+            .collect())
+    }
+
+    // ==================== Runs ====================
+
+    pub fn is_running(&self, job_id: i64) -> bool {
+        self.active().contains_key(&job_id)
+    }
+
+    /// Id of the task running the job, for cancelling it
+    pub fn running_task(&self, job_id: i64) -> Option<String> {
+        self.active()
+            .get(&job_id)
+            .map(|run| run.task_id.clone())
+            .filter(|task_id| !task_id.is_empty())
+    }
+
+    /// Mark the job running, failing if it already is. Checked and recorded in one step,
+    /// before the run's task is spawned, so two starts of the same job can't both get through.
+    pub fn claim_job(&self, job_id: i64) -> AppResult<JobClaim> {
+        let (paused_tx, paused) = watch::channel(false);
+        let mut active = self.active();
+        if active.contains_key(&job_id) {
+            return Err(AppError::Validation("This job is already running".to_string()));
+        }
+        active.insert(job_id, ActiveRun { task_id: String::new(), paused: paused_tx });
+        Ok(JobClaim { job_id, paused, active: self.active.clone() })
+    }
+
+    /// Pause a running job at its next page; it keeps its place until resumed or cancelled
+    pub fn pause_job(&self, job_id: i64) -> AppResult<()> {
+        self.set_paused(job_id, true)
+    }
+
+    pub fn resume_job(&self, job_id: i64) -> AppResult<()> {
+        self.set_paused(job_id, false)
+    }
+
+    fn set_paused(&self, job_id: i64, paused: bool) -> AppResult<()> {
+        match self.active().get(&job_id) {
+            Some(run) => {
+                run.paused.send_replace(paused);
+                Ok(())
+            }
+            None => Err(AppError::Validation("This job is not running".to_string())),
+        }
+    }
+
+    /// The job's runs, newest first
+    pub fn get_runs(&self, job_id: i64, limit: i32) -> Result<Vec<ScrapingRun>> {
+        let conn = self.db.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT id, job_id, trigger, status, started_at, finished_at, pages_scraped, pages_changed, error_count, errors
+             FROM scraping_runs
+             WHERE job_id = ?1
+             ORDER BY started_at DESC, id DESC
+             LIMIT ?2"
+        )?;
+
+        let runs = stmt.query_map(params![job_id, limit], |row| {
+            let errors: String = row.get(9)?;
+            Ok(ScrapingRun {
+                id: row.get(0)?,
+                job_id: row.get(1)?,
+                trigger: row.get(2)?,
+                status: row.get(3)?,
+                started_at: row.get(4)?,
+                finished_at: row.get(5)?,
+                pages_scraped: row.get(6)?,
+                pages_changed: row.get(7)?,
+                error_count: row.get(8)?,
+                errors: serde_json::from_str(&errors).unwrap_or_default(),
+            })
+        })?;
+
+        runs.collect()
+    }
+
+    /// Run a scraping job, recording the run in its history and reporting each step to
+    /// `on_progress`, and each watched page found changed to `on_change`. `trigger` is
+    /// "manual" or "schedule". Returns the number of pages scraped; cancelling leaves the
+    /// job "cancelled" with what it got.
+    pub async fn run_job(
+        &self,
+        job_id: i64,
+        trigger: &str,
+        task: &TaskContext,
+        on_progress: impl Fn(JobStatus) + Send + Sync,
+        on_change: impl Fn(PageChanged) + Send + Sync,
+    ) -> AppResult<i32> {
+        let claim = self.claim_job(job_id)?;
+        self.run_claimed(claim, trigger, task, on_progress, on_change).await
+    }
+
+    /// `run_job` for a job claimed with `claim_job`
+    pub async fn run_claimed(
+        &self,
+        claim: JobClaim,
+        trigger: &str,
+        task: &TaskContext,
+        on_progress: impl Fn(JobStatus) + Send + Sync,
+        on_change: impl Fn(PageChanged) + Send + Sync,
+    ) -> AppResult<i32> {
+        let job_id = claim.job_id;
+        if let Some(run) = self.active().get_mut(&job_id) {
+            run.task_id = task.id().to_string();
+        }
+        let job = self.get_job(job_id)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound(format!("Scraping job {}", job_id)))?;
+
+        let mut run = RunState {
+            job_id,
+            run_id: self.start_run(job_id, trigger)?,
+            status: "running",
+            pages_scraped: 0,
+            pages_changed: 0,
+            errors: Vec::new(),
+            error_count: 0,
+        };
+        self.update_job_status(job_id, "running", 0)?;
+
+        let result = self.crawl(&job, task, claim.paused.clone(), &mut run, &on_progress, &on_change).await;
+        drop(claim);
+
+        run.status = match &result {
+            Err(_) if !task.is_cancelled() => "failed",
+            _ if task.is_cancelled() => "cancelled",
+            _ => "completed",
+        };
+        if let Err(e) = &result {
+            if !task.is_cancelled() {
+                run.record_error(None, e.to_string());
+            }
+        }
+        self.save_run(&run, true)?;
+        self.update_job_status(job_id, run.status, run.pages_scraped)?;
+        on_progress(run.to_status(None));
+        result.map(|_| run.pages_scraped)
+    }
+
+    async fn crawl(
+        &self,
+        job: &ScrapingJob,
+        task: &TaskContext,
+        mut paused: watch::Receiver<bool>,
+        run: &mut RunState,
+        on_progress: &(impl Fn(JobStatus) + Send + Sync),
+        on_change: &(impl Fn(PageChanged) + Send + Sync),
+    ) -> AppResult<()> {
+        let settings = &job.settings;
         let client = Client::builder()
-            .user_agent("Reclaim Web Scraper/1.0")
-            .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .map_err(|e| e.to_string())?;
+            .user_agent(settings.user_agent())
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        let request_delay = Duration::from_millis(settings.request_delay_ms);
+        // robots.txt rules by origin, parsed once per run
+        let mut rules: HashMap<String, Robots> = HashMap::new();
 
-        let mut visited: HashSet<String> = HashSet::new();
-        let mut to_visit = vec![(job.base_url.clone(), 0)];
+        let job_id = run.job_id;
+        let max_pages = job.max_pages.max(0);
         let url_regex = job.url_pattern.as_ref()
             .and_then(|p| regex::Regex::new(p).ok());
+        let fields = compile_selectors(&job.content_selectors)?;
+        let base = match url::Url::parse(&job.base_url).ok().and_then(frontier::canonicalize) {
+            Some(base) => base,
+            None => {
+                run.record_error(Some(&job.base_url), "Not a web page URL".to_string());
+                return Ok(());
+            }
+        };
+        // A link is followed if it's in scope and matches the job's URL pattern
+        let follows = |url: &url::Url| {
+            settings.scope.allows(&base, url) && url_regex.as_ref().is_none_or(|regex| regex.is_match(url.as_str()))
+        };
 
-        let mut pages_scraped = 0;
+        // Carry on from where an interrupted run stopped, or start from the base URL
+        let mut frontier = Frontier::load(&self.db, job_id)?;
+        if frontier.is_new() {
+            frontier.push(&base, 0)?;
+            if settings.follow_sitemaps && job.max_depth > 0 {
+                for link in self.sitemap_pages(&client, &base, settings, &mut rules, max_pages, task).await {
+                    if let Some(link) = url::Url::parse(&link).ok().and_then(frontier::canonicalize) {
+                        if follows(&link) {
+                            frontier.push(&link, 1)?;
+                        }
+                    }
+                }
+            }
+        }
 
-        while let Some((url, depth)) = to_visit.pop() {
-            if visited.len() >= job.max_pages as usize || depth > job.max_depth {
+        while frontier.scraped() < max_pages {
+            if *paused.borrow() && !task.is_cancelled() {
+                self.set_run_status(run, "paused")?;
+                on_progress(run.to_status(None));
+                tokio::select! {
+                    _ = paused.wait_for(|paused| !*paused) => {}
+                    _ = task.cancelled() => {}
+                }
+                self.set_run_status(run, "running")?;
+            }
+            if task.is_cancelled() {
                 break;
             }
+            let Some(entry) = frontier.pop() else {
+                break;
+            };
+            let url = entry.url.to_string();
 
-            if visited.contains(&url) {
-                continue;
-            }
+            on_progress(run.to_status(Some(&url)));
 
-            // Check URL pattern
-            if let Some(ref regex) = url_regex {
-                if !regex.is_match(&url) {
+            let mut delay = request_delay;
+            if settings.respect_robots {
+                let Some(robots) = self.robots(&client, &entry.url, settings, &mut rules, task).await else {
+                    break;
+                };
+                if !robots.is_allowed(&entry.url) {
+                    let reason = if robots.is_unavailable() {
+                        "robots.txt could not be fetched, so the site is off limits"
+                    } else {
+                        "Disallowed by robots.txt"
+                    };
+                    run.record_error(Some(&url), reason.to_string());
+                    self.save_run(run, false).ok();
+                    on_progress(run.to_status(Some(&url)));
+                    frontier.finish(&entry, Visit::Skipped)?;
                     continue;
                 }
+                delay = delay.max(robots.crawl_delay().unwrap_or_default());
             }
 
-            // Fetch the page
-            match client.get(&url).send().await {
-                Ok(response) => {
-                    if let Ok(html) = response.text().await {
-                        // Simple text extraction (remove HTML tags)
-                        let text_content = extract_text(&html);
-                        let title = extract_title(&html);
-
-                        // Save the page
-                        if let Err(e) = self.save_page(job_id, &url, title.as_deref(), &text_content, None) {
-                            eprintln!("Failed to save page {}: {}", url, e);
-                        } else {
-                            pages_scraped += 1;
-                            self.update_job_status(job_id, "running", pages_scraped).ok();
-                        }
+            // Fetch the page, unless it hasn't changed since it was last scraped
+            let headers = self.conditional_headers(job_id, &url)?;
+            let Some(response) = self.fetch(&client, &entry.url, &headers, delay, settings.max_retries, task).await else {
+                break;
+            };
+            let response = match response.and_then(|response| response.error_for_status()) {
+                Ok(response) => response,
+                Err(e) => {
+                    let message = match e.status() {
+                        Some(status) => format!("HTTP {}", status),
+                        None => e.to_string(),
+                    };
+                    run.record_error(Some(&url), message);
+                    self.save_run(run, false).ok();
+                    on_progress(run.to_status(Some(&url)));
+                    frontier.finish(&entry, Visit::Failed)?;
+                    continue;
+                }
+            };
+            let links = if response.status() == StatusCode::NOT_MODIFIED {
+                // Nothing new to download, but the page's links still lead on
+                match self.mark_unchanged(job_id, &url)? {
+                    Some(links) => {
+                        run.pages_scraped += 1;
+                        frontier.finish(&entry, Visit::Scraped)?;
+                        links
+                    }
+                    None => {
+                        run.record_error(Some(&url), format!("HTTP {}", StatusCode::NOT_MODIFIED));
+                        self.save_run(run, false).ok();
+                        frontier.finish(&entry, Visit::Failed)?;
+                        continue;
+                    }
+                }
+            } else {
+                if !is_page(&response) {
+                    frontier.finish(&entry, Visit::Skipped)?;
+                    continue;
+                }
+                let header = |name| response.headers().get(name).and_then(|value| value.to_str().ok()).map(String::from);
+                let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+                let html = match response.text().await {
+                    Ok(html) => html,
+                    Err(e) => {
+                        run.record_error(Some(&url), e.to_string());
+                        self.save_run(run, false).ok();
+                        frontier.finish(&entry, Visit::Failed)?;
+                        continue;
+                    }
+                };
 
-                        // Extract links for crawling
-                        if depth < job.max_depth {
-                            for link in extract_links(&html, &url) {
-                                if !visited.contains(&link) {
-                                    to_visit.push((link, depth + 1));
-                                }
+                let page = extract_page(&html, &url, &fields);
+                let hash = content_hash(&page.text);
+                // The same page under another URL, like a print view or an alias
+                if self.page_with_content(job_id, &hash, &url)?.is_some() {
+                    frontier.finish(&entry, Visit::Duplicate)?;
+                    continue;
+                }
+
+                let metadata = page.fields.as_ref().map(|fields| fields.to_string());
+                let fetched = FetchedPage {
+                    url: &url,
+                    title: page.title.as_deref(),
+                    content: &page.text,
+                    metadata: metadata.as_deref(),
+                    content_hash: &hash,
+                    etag: etag.as_deref(),
+                    last_modified: last_modified.as_deref(),
+                    links: &page.links,
+                };
+                match self.save_page(job_id, &fetched) {
+                    Ok(saved) => {
+                        run.pages_scraped += 1;
+                        frontier.finish(&entry, Visit::Scraped)?;
+                        if let PageChange::Changed { previous_version, previous_content } = &saved.change {
+                            run.pages_changed += 1;
+                            if saved.watched {
+                                let (_, words_added, words_removed) = diff_words(previous_content, &page.text);
+                                on_change(PageChanged {
+                                    job_id,
+                                    page_id: saved.page_id,
+                                    url: url.clone(),
+                                    title: page.title.clone(),
+                                    from_version: *previous_version,
+                                    to_version: saved.version_id.unwrap_or_default(),
+                                    words_added,
+                                    words_removed,
+                                });
                             }
                         }
-
-                        visited.insert(url);
+                    }
+                    Err(e) => {
+                        run.record_error(Some(&url), format!("Failed to save page: {}", e));
+                        frontier.finish(&entry, Visit::Failed)?;
                     }
                 }
-                Err(e) => {
-                    eprintln!("Failed to fetch {}: {}", url, e);
+                page.links
+            };
+            self.update_job_status(job_id, "running", run.pages_scraped).ok();
+            task.progress(frontier.scraped() as u64, Some(max_pages as u64));
+            self.save_run(run, false).ok();
+
+            // Queue the links for crawling
+            if entry.depth < job.max_depth {
+                for link in &links {
+                    if let Ok(link) = url::Url::parse(link) {
+                        if follows(&link) {
+                            frontier.push(&link, entry.depth + 1)?;
+                        }
+                    }
                 }
             }
+        }
 
-            // Small delay to be polite
-            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        // Done or cancelled, so the next run starts afresh; after an error it resumes
+        frontier.clear()?;
+        Ok(())
+    }
+
+    /// The robots.txt rules for `url`'s site, fetched the first time a run needs them
+    /// unless a recent copy is cached. None if the task is cancelled while fetching.
+    async fn robots(
+        &self,
+        client: &Client,
+        url: &url::Url,
+        settings: &CrawlSettings,
+        rules: &mut HashMap<String, Robots>,
+        task: &TaskContext,
+    ) -> Option<Robots> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(robots) = rules.get(&origin) {
+            return Some(robots.clone());
+        }
+
+        let cached = self
+            .hosts()
+            .robots
+            .get(&origin)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(file, _)| file.clone());
+        let file = match cached {
+            Some(file) => file,
+            None => {
+                let file = match url.join("/robots.txt") {
+                    Ok(robots_url) => {
+                        let delay = Duration::from_millis(settings.request_delay_ms);
+                        match self.fetch(client, &robots_url, &HeaderMap::new(), delay, settings.max_retries, task).await? {
+                            Ok(response) if response.status().is_success() => match response.text().await {
+                                Ok(text) if text.len() <= MAX_ROBOTS_BYTES => RobotsFile::Found(text),
+                                Ok(_) => RobotsFile::Missing,
+                                Err(_) => RobotsFile::Unreachable,
+                            },
+                            Ok(response) if is_retryable(response.status()) => RobotsFile::Unreachable,
+                            Ok(_) => RobotsFile::Missing,
+                            Err(_) => RobotsFile::Unreachable,
+                        }
+                    }
+                    // Not a web URL, so there's nothing to ask
+                    Err(_) => RobotsFile::Missing,
+                };
+                let ttl = match file {
+                    RobotsFile::Unreachable => ROBOTS_RETRY_AFTER,
+                    _ => ROBOTS_TTL,
+                };
+                self.hosts().robots.insert(origin.clone(), (file.clone(), Instant::now() + ttl));
+                file
+            }
+        };
+
+        let robots = file.rules(settings.user_agent());
+        rules.insert(origin, robots.clone());
+        Some(robots)
+    }
+
+    /// Pages listed in the sitemaps of `base`'s site: those robots.txt names, or
+    /// /sitemap.xml when it names none. At most `limit` pages from MAX_SITEMAPS files.
+    async fn sitemap_pages(
+        &self,
+        client: &Client,
+        base: &url::Url,
+        settings: &CrawlSettings,
+        rules: &mut HashMap<String, Robots>,
+        limit: i32,
+        task: &TaskContext,
+    ) -> Vec<String> {
+        let limit = limit.max(0) as usize;
+        let Some(robots) = self.robots(client, base, settings, rules, task).await else {
+            return Vec::new();
+        };
+        let mut sitemaps: Vec<String> = robots.sitemaps().iter().rev().cloned().collect();
+        if sitemaps.is_empty() {
+            sitemaps.extend(base.join("/sitemap.xml").ok().map(String::from));
+        }
+
+        let mut delay = Duration::from_millis(settings.request_delay_ms);
+        if settings.respect_robots {
+            delay = delay.max(robots.crawl_delay().unwrap_or_default());
         }
+        let mut pages = Vec::new();
+        let mut fetched = 0;
+        while let Some(sitemap) = sitemaps.pop() {
+            if fetched >= MAX_SITEMAPS || pages.len() >= limit {
+                break;
+            }
+            let Ok(url) = url::Url::parse(&sitemap) else {
+                continue;
+            };
+            if settings.respect_robots && url.origin() == base.origin() && !robots.is_allowed(&url) {
+                continue;
+            }
+            fetched += 1;
 
-        self.update_job_status(job_id, "completed", pages_scraped).map_err(|e| e.to_string())?;
+            let Some(response) = self.fetch(client, &url, &HeaderMap::new(), delay, settings.max_retries, task).await else {
+                break;
+            };
+            let Ok(response) = response.and_then(|response| response.error_for_status()) else {
+                continue;
+            };
+            let Ok(xml) = response.text().await else {
+                continue;
+            };
+            let sitemap = robots::parse_sitemap(&xml);
+            pages.extend(sitemap.pages);
+            sitemaps.extend(sitemap.sitemaps.into_iter().rev());
+        }
+        pages.truncate(limit);
+        pages
+    }
+
+    /// GET `url` with `headers` once its host's turn comes, retrying 429 and 5xx
+    /// responses with backoff. None if the task is cancelled while waiting.
+    async fn fetch(
+        &self,
+        client: &Client,
+        url: &url::Url,
+        headers: &HeaderMap,
+        delay: Duration,
+        max_retries: u32,
+        task: &TaskContext,
+    ) -> Option<reqwest::Result<reqwest::Response>> {
+        let host = url.host_str().unwrap_or_default().to_string();
+        let mut attempt = 0;
+        loop {
+            let turn = self.take_turn(&host, delay);
+            tokio::select! {
+                _ = tokio::time::sleep_until(turn.into()) => {}
+                _ = task.cancelled() => return None,
+            }
+
+            let response = client.get(url.clone()).headers(headers.clone()).send().await;
+            let wait = match &response {
+                Ok(response) if attempt < max_retries && is_retryable(response.status()) => {
+                    retry_wait(attempt, response.headers().get(RETRY_AFTER))
+                }
+                _ => None,
+            };
+            let Some(wait) = wait else {
+                return Some(response);
+            };
+            self.hold_host(&host, Instant::now() + wait);
+            attempt += 1;
+        }
+    }
+
+    /// Book `host`'s next request, `delay` after the last one booked; returns when it may go
+    fn take_turn(&self, host: &str, delay: Duration) -> Instant {
+        let mut hosts = self.hosts();
+        let now = Instant::now();
+        let turn = hosts.next_request.get(host).copied().filter(|at| *at > now).unwrap_or(now);
+        hosts.next_request.insert(host.to_string(), turn + delay);
+        turn
+    }
+
+    /// Send `host` nothing more until `until`, e.g. after it answered 429
+    fn hold_host(&self, host: &str, until: Instant) {
+        let mut hosts = self.hosts();
+        let next = hosts.next_request.entry(host.to_string()).or_insert(until);
+        *next = (*next).max(until);
+    }
+
+    fn start_run(&self, job_id: i64, trigger: &str) -> Result<i64> {
+        let conn = self.db.get()?;
+        conn.execute(
+            "INSERT INTO scraping_runs (job_id, trigger, status, started_at) VALUES (?1, ?2, 'running', ?3)",
+            params![job_id, trigger, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn set_run_status(&self, run: &mut RunState, status: &'static str) -> Result<()> {
+        run.status = status;
+        self.save_run(run, false)?;
+        self.update_job_status(run.job_id, status, run.pages_scraped)
+    }
+
+    fn save_run(&self, run: &RunState, finished: bool) -> Result<()> {
+        let conn = self.db.get()?;
+        let finished_at = finished.then(|| chrono::Utc::now().to_rfc3339());
+        conn.execute(
+            "UPDATE scraping_runs
+             SET status = ?1, pages_scraped = ?2, pages_changed = ?3, error_count = ?4, errors = ?5,
+                 finished_at = COALESCE(?6, finished_at)
+             WHERE id = ?7",
+            params![
+                run.status,
+                run.pages_scraped,
+                run.pages_changed,
+                run.error_count,
+                serde_json::to_string(&run.errors).unwrap_or_else(|_| "[]".to_string()),
+                finished_at,
+                run.run_id,
+            ],
+        )?;
         Ok(())
     }
+
+    fn active(&self) -> std::sync::MutexGuard<'_, HashMap<i64, ActiveRun>> {
+        self.active.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn hosts(&self) -> std::sync::MutexGuard<'_, Hosts> {
+        self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Whether a response is worth extracting: HTML or plain text. One without a
+/// Content-Type is given the benefit of the doubt.
+fn is_page(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("html") || value.starts_with("text/plain")
+        })
+}
+
+fn content_hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// How long to wait before retry number `attempt + 1`: what the server's Retry-After
+/// asks for, else exponential backoff. None when the server asks for more than MAX_RETRY_WAIT.
+fn retry_wait(attempt: u32, retry_after: Option<&HeaderValue>) -> Option<Duration> {
+    let asked = retry_after.and_then(|value| value.to_str().ok()).and_then(|value| {
+        let value = value.trim();
+        value.parse::<u64>().ok().map(Duration::from_secs).or_else(|| {
+            let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+        })
+    });
+    match asked {
+        Some(wait) => (wait <= MAX_RETRY_WAIT).then_some(wait),
+        None => Some(RETRY_BACKOFF.saturating_mul(2u32.saturating_pow(attempt)).min(MAX_RETRY_WAIT)),
+    }
+}
+
+/// Counters of the run in progress
+struct RunState {
+    job_id: i64,
+    run_id: i64,
+    status: &'static str,
+    pages_scraped: i32,
+    pages_changed: i32,
+    errors: Vec<RunError>,
+    error_count: i32,
 }
 
-// ==================== Helper Functions ====================
+impl RunState {
+    fn record_error(&mut self, url: Option<&str>, message: String) {
+        self.error_count += 1;
+        if self.errors.len() < MAX_RUN_ERRORS {
+            self.errors.push(RunError { url: url.map(String::from), message });
+        }
+    }
 
-/// Extract text content from HTML (simple implementation)
-fn extract_text(html: &str) -> String {
-    // Remove script and style tags and their content
-    let re_script = regex::Regex::new(r"(?is)<script[^>]*>.*?</script>").unwrap();
-    let re_style = regex::Regex::new(r"(?is)<style[^>]*>.*?</style>").unwrap();
-    let re_tags = regex::Regex::new(r"<[^>]+>").unwrap();
-    let re_whitespace = regex::Regex::new(r"\s+").unwrap();
+    fn to_status(&self, current_url: Option<&str>) -> JobStatus {
+        JobStatus {
+            job_id: self.job_id,
+            run_id: self.run_id,
+            status: self.status.to_string(),
+            pages_scraped: self.pages_scraped,
+            current_url: current_url.map(String::from),
+            error: self.errors.last().map(|e| e.message.clone()),
+            error_count: self.error_count,
+        }
+    }
+}
 
-    let text = re_script.replace_all(html, "");
-    let text = re_style.replace_all(&text, "");
-    let text = re_tags.replace_all(&text, " ");
-    let text = re_whitespace.replace_all(&text, " ");
+/// A page as fetched, ready to save
+struct FetchedPage<'a> {
+    url: &'a str,
+    title: Option<&'a str>,
+    content: &'a str,
+    metadata: Option<&'a str>,
+    content_hash: &'a str,
+    etag: Option<&'a str>,
+    last_modified: Option<&'a str>,
+    links: &'a [String],
+}
 
-    text.trim().to_string()
+enum PageChange {
+    New,
+    Unchanged,
+    /// The text differs from what was stored, whose version is `previous_version`
+    /// (None for a page that somehow has no versions)
+    Changed { previous_version: Option<i64>, previous_content: String },
 }
 
-/// Extract title from HTML
-fn extract_title(html: &str) -> Option<String> {
-    let re = regex::Regex::new(r"(?is)<title[^>]*>(.*?)</title>").ok()?;
-    re.captures(html)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().trim().to_string())
+struct SavedPage {
+    page_id: i64,
+    change: PageChange,
+    /// The version added, if the text was new
+    version_id: Option<i64>,
+    watched: bool,
 }
 
-/// Extract links from HTML
-fn extract_links(html: &str, base_url: &str) -> Vec<String> {
-    let re = regex::Regex::new(r#"href=["']([^"']+)["']"#).unwrap();
-    let base = url::Url::parse(base_url).ok();
+// ==================== Diffs ====================
 
-    re.captures_iter(html)
-        .filter_map(|caps| caps.get(1))
-        .filter_map(|m| {
-            let href = m.as_str();
-            if href.starts_with("http://") || href.starts_with("https://") {
-                Some(href.to_string())
-            } else if let Some(ref base) = base {
-                base.join(href).ok().map(|u| u.to_string())
-            } else {
-                None
+/// Word-by-word differences from `old` to `new`, with the number of words added and removed
+fn diff_words(old: &str, new: &str) -> (Vec<DiffSegment>, i32, i32) {
+    let diff = similar::TextDiff::from_words(old, new);
+    let mut segments: Vec<DiffSegment> = Vec::new();
+    let (mut added, mut removed) = (0, 0);
+
+    for change in diff.iter_all_changes() {
+        let kind = match change.tag() {
+            similar::ChangeTag::Equal => DiffKind::Equal,
+            similar::ChangeTag::Insert => DiffKind::Insert,
+            similar::ChangeTag::Delete => DiffKind::Delete,
+        };
+        let text = change.value();
+        let words = text.split_whitespace().count() as i32;
+        match kind {
+            DiffKind::Insert => added += words,
+            DiffKind::Delete => removed += words,
+            DiffKind::Equal => {}
+        }
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(text),
+            _ => segments.push(DiffSegment { kind, text: text.to_string() }),
+        }
+    }
+
+    // Keep only the context around each change of long unchanged stretches
+    let count = segments.len();
+    for (i, segment) in segments.iter_mut().enumerate() {
+        if segment.kind != DiffKind::Equal {
+            continue;
+        }
+        let start = segment.text.len() - segment.text.trim_start().len();
+        let words: Vec<&str> = segment.text[start..].split_inclusive(' ').collect();
+        let before = if i == 0 { 0 } else { DIFF_CONTEXT_WORDS };
+        let after = if i + 1 == count { 0 } else { DIFF_CONTEXT_WORDS };
+        if words.len() > before + after + 1 {
+            let mut text = segment.text[..start].to_string();
+            text.push_str(&words[..before].concat());
+            text.push('…');
+            if after > 0 {
+                text.push(' ');
+                text.push_str(&words[words.len() - after..].concat());
             }
+            segment.text = text;
+        }
+    }
+
+    (segments, added, removed)
+}
+
+// ==================== Schedules ====================
+
+/// Parse a cron expression. The usual five fields (minute hour day month weekday) are
+/// accepted as well as the six or seven-field form with seconds and years.
+pub fn parse_schedule(expression: &str) -> AppResult<cron::Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&expression)
+        .map_err(|e| AppError::Validation(format!("Invalid cron schedule: {}", e)))
+}
+
+/// When a schedule next fires after `since` (an RFC 3339 timestamp)
+fn next_run_after(expression: &str, since: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    let since = chrono::DateTime::parse_from_rfc3339(since).ok()?.with_timezone(&chrono::Utc);
+    parse_schedule(expression).ok()?.after(&since).next()
+}
+
+// ==================== HTML Extraction ====================
+
+/// Elements whose text is never page content
+const HIDDEN_ELEMENTS: &[&str] = &["head", "script", "style", "noscript", "template", "svg"];
+
+/// Elements that break text into separate words or lines
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "br", "dd", "div", "dl", "dt", "figcaption",
+    "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main", "nav",
+    "ol", "p", "pre", "section", "table", "td", "th", "tr", "ul",
+];
+
+/// A content selector, parsed once per run
+struct FieldSelector {
+    name: String,
+    selector: Selector,
+    attribute: Option<String>,
+    all: bool,
+}
+
+/// What a run keeps from one fetched page
+struct PageContent {
+    title: Option<String>,
+    /// Visible text of the body, whitespace collapsed
+    text: String,
+    /// Absolute links to web pages, canonicalized
+    links: Vec<String>,
+    /// Selector results as a JSON object, None when the job has no selectors
+    fields: Option<serde_json::Value>,
+}
+
+fn compile_selectors(selectors: &[ContentSelector]) -> AppResult<Vec<FieldSelector>> {
+    selectors
+        .iter()
+        .map(|field| {
+            let selector = Selector::parse(&field.selector).map_err(|e| {
+                AppError::Validation(format!("Invalid CSS selector for \"{}\": {}", field.name, e))
+            })?;
+            Ok(FieldSelector {
+                name: field.name.clone(),
+                selector,
+                attribute: field.attribute.clone().filter(|attribute| !attribute.trim().is_empty()),
+                all: field.all,
+            })
         })
-        .filter(|url| !url.contains('#') && !url.ends_with(".pdf") && !url.ends_with(".jpg") && !url.ends_with(".png"))
         .collect()
 }
+
+/// Parse a page and pull out its title, text, links and selector fields. Each field is a
+/// string, or null when nothing matched; `all` fields are arrays of every match.
+fn extract_page(html: &str, url: &str, fields: &[FieldSelector]) -> PageContent {
+    let document = Html::parse_document(html);
+
+    let title = document
+        .select(&Selector::parse("title").unwrap())
+        .next()
+        .map(element_text)
+        .filter(|title| !title.is_empty());
+    let text = element_text(document.root_element());
+
+    // Relative links resolve against <base href> when the page has one
+    let page_url = url::Url::parse(url).ok();
+    let base = document
+        .select(&Selector::parse("base[href]").unwrap())
+        .next()
+        .and_then(|base| base.value().attr("href"))
+        .and_then(|href| match &page_url {
+            Some(page_url) => page_url.join(href).ok(),
+            None => url::Url::parse(href).ok(),
+        })
+        .or(page_url);
+    let mut links = Vec::new();
+    for anchor in document.select(&Selector::parse("a[href]").unwrap()) {
+        let href = anchor.value().attr("href").unwrap_or_default();
+        let Some(link) = base.as_ref().and_then(|base| base.join(href.trim()).ok()).and_then(frontier::canonicalize) else {
+            continue;
+        };
+        let link = link.to_string();
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+
+    let fields = (!fields.is_empty()).then(|| {
+        let mut values = serde_json::Map::new();
+        for field in fields {
+            let mut matches = document.select(&field.selector).filter_map(|element| {
+                let value = match &field.attribute {
+                    Some(attribute) => element.value().attr(attribute).map(|value| value.trim().to_string()),
+                    None => Some(element_text(element)),
+                };
+                value.filter(|value| !value.is_empty())
+            });
+            let value = if field.all {
+                serde_json::Value::from(matches.collect::<Vec<_>>())
+            } else {
+                matches.next().map(serde_json::Value::from).unwrap_or(serde_json::Value::Null)
+            };
+            values.insert(field.name.clone(), value);
+        }
+        serde_json::Value::Object(values)
+    });
+
+    PageContent { title, text, links, fields }
+}
+
+/// The visible text inside an element, whitespace collapsed
+fn element_text(element: ElementRef) -> String {
+    let mut text = String::new();
+    push_text(element, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn push_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        if let Some(fragment) = child.value().as_text() {
+            text.push_str(fragment);
+        } else if let Some(child) = ElementRef::wrap(child) {
+            let name = child.value().name();
+            if HIDDEN_ELEMENTS.contains(&name) {
+                continue;
+            }
+            let block = BLOCK_ELEMENTS.contains(&name);
+            if block {
+                text.push(' ');
+            }
+            push_text(child, text);
+            if block {
+                text.push(' ');
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations::test_database;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_schedules_parse_and_come_due() {
+        let db = test_database("scraper_schedule");
+        let scraper = ScraperManager::new(db.clone());
+        let job_id = scraper.create_job(1, "Docs", "https://example.com", None, 1, 10, Vec::new()).unwrap();
+        db.get().unwrap()
+            .execute("UPDATE scraping_jobs SET last_run_at = '2024-05-01T10:20:00+00:00' WHERE id = ?1", params![job_id])
+            .unwrap();
+
+        assert!(scraper.set_schedule(job_id, Some("every hour")).is_err());
+        assert!(scraper.set_schedule(job_id + 1, Some("0 * * * *")).is_err());
+        let job = scraper.set_schedule(job_id, Some(" 0 * * * * ")).unwrap();
+        assert_eq!(job.schedule_cron.as_deref(), Some("0 * * * *"));
+        assert!(parse_schedule("0 30 9 * * Mon-Fri").is_ok(), "six fields with seconds");
+
+        let at = |h, m| chrono::Utc.with_ymd_and_hms(2024, 5, 1, h, m, 0).unwrap();
+        assert!(scraper.due_jobs(at(10, 59)).unwrap().is_empty());
+        assert_eq!(scraper.due_jobs(at(11, 0)).unwrap(), vec![job_id]);
+
+        scraper.set_schedule(job_id, Some("")).unwrap();
+        assert!(scraper.due_jobs(at(23, 0)).unwrap().is_empty());
+    }
+
+    const ARTICLE: &str = include_str!("../tests/fixtures/scraper/article.html");
+    const LISTING: &str = include_str!("../tests/fixtures/scraper/listing.html");
+
+    fn selector(name: &str, selector: &str, attribute: Option<&str>, all: bool) -> ContentSelector {
+        ContentSelector {
+            name: name.to_string(),
+            selector: selector.to_string(),
+            attribute: attribute.map(str::to_string),
+            all,
+        }
+    }
+
+    /// A local HTTP server for crawl tests
+    struct TestSite {
+        base: String,
+        /// Path and User-Agent of every request, in order
+        requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    impl TestSite {
+        fn hits(&self, path: &str) -> usize {
+            self.requests.lock().unwrap().iter().filter(|(p, _)| p == path).count()
+        }
+    }
+
+    /// Serve on a free local port, answering each request with `respond(path, base URL, request)`
+    async fn serve(respond: impl Fn(&str, &str, &str) -> String + Send + 'static) -> TestSite {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let site = TestSite { base: base.clone(), requests: requests.clone() };
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 4096];
+                let len = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..len]);
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let user_agent = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("user-agent: ").or_else(|| line.strip_prefix("User-Agent: ")))
+                    .unwrap_or_default()
+                    .to_string();
+                let response = respond(&path, &base, &request);
+                requests.lock().unwrap().push((path, user_agent));
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        site
+    }
+
+    fn reply(status: &str, headers: &str, body: &str) -> String {
+        format!("HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}", status, headers, body.len(), body)
+    }
+
+    fn html(body: &str) -> String {
+        reply("200 OK", "Content-Type: text/html\r\n", body)
+    }
+
+    fn not_found() -> String {
+        reply("404 Not Found", "", "")
+    }
+
+    fn no_delay() -> CrawlSettings {
+        CrawlSettings { request_delay_ms: 0, ..CrawlSettings::default() }
+    }
+
+    #[test]
+    fn test_extracts_fields_text_and_links() {
+        let fields = compile_selectors(&[
+            selector("headline", "article h1.post-title", None, false),
+            selector("author", "meta[name=author]", Some("content"), false),
+            selector("published", "article time", Some("datetime"), false),
+            selector("tags", "ul.tags li", None, true),
+            selector("comments", "#comments", None, false),
+        ])
+        .unwrap();
+        let page = extract_page(ARTICLE, "https://notes.example.com/2024/03/sqlite.html", &fields);
+
+        assert_eq!(page.title.as_deref(), Some("Tuning SQLite for Desktop Apps | Field Notes"));
+        assert_eq!(
+            page.fields.unwrap(),
+            serde_json::json!({
+                "headline": "Tuning SQLite for Desktop Apps",
+                "author": "Ada Lovelace",
+                "published": "2024-03-18T09:30:00Z",
+                "tags": ["sqlite", "performance", "desktop"],
+                "comments": null,
+            })
+        );
+
+        assert!(page.text.starts_with("Home Archive Elsewhere Jump to comments Tuning SQLite for Desktop Apps By Ada Lovelace"));
+        assert!(page.text.contains("sqlite performance desktop Write-ahead logging"));
+        assert!(page.text.ends_with("Read more about WAL. © 2024 Field Notes"));
+        for hidden in ["font-family", "analytics", "not content", "Enable JavaScript"] {
+            assert!(!page.text.contains(hidden), "{} leaked into the text", hidden);
+        }
+
+        assert_eq!(
+            page.links,
+            vec![
+                "https://notes.example.com/",
+                "https://notes.example.com/archive/",
+                "https://example.org/elsewhere",
+                "https://notes.example.com/2024/03/sqlite.html",
+                "https://notes.example.com/2024/wal.html",
+            ]
+        );
+        assert!(extract_page(ARTICLE, "https://notes.example.com/", &[]).fields.is_none());
+    }
+
+    #[test]
+    fn test_links_resolve_against_the_base_element() {
+        let fields = compile_selectors(&[
+            selector("skus", "li.product", Some("data-sku"), true),
+            selector("prices", ".product .price", None, true),
+            selector("first", "a.name", Some("href"), false),
+        ])
+        .unwrap();
+        let page = extract_page(LISTING, "https://cdn.example.net/mirror/listing", &fields);
+
+        assert_eq!(
+            page.links,
+            vec!["https://shop.example.com/catalogue/widget.html", "https://shop.example.com/catalogue/gadget.html"]
+        );
+        assert_eq!(
+            page.fields.unwrap(),
+            serde_json::json!({
+                "skus": ["A-100", "B-200", "C-300"],
+                "prices": ["€4.50", "€12.00"],
+                "first": "widget.html",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runs_store_fields_as_page_metadata() {
+        let db = test_database("scraper_fields");
+        let scraper = ScraperManager::new(db.clone());
+        let invalid = scraper.create_job(1, "Bad", "https://example.com", None, 1, 10, vec![selector("x", "p[", None, false)]);
+        assert!(matches!(invalid, Err(AppError::Validation(message)) if message.contains("\"x\"")));
+
+        let site = serve(|path, _, _| if path == "/notes/sqlite.html" { html(ARTICLE) } else { not_found() }).await;
+        let job_id = scraper
+            .create_job(
+                1,
+                "Notes",
+                &format!("{}/notes/sqlite.html", site.base),
+                None,
+                0,
+                10,
+                vec![selector("author", ".byline .author", None, false), selector("tags", ".tags li", None, true)],
+            )
+            .unwrap();
+        scraper.set_settings(job_id, no_delay()).unwrap();
+        let pages = scraper.run_job(job_id, "manual", &TaskContext::detached("scrape"), |_| {}, |_| {}).await.unwrap();
+        assert_eq!(pages, 1);
+
+        let page = &scraper.get_pages(job_id, 10).unwrap()[0];
+        assert_eq!(page.title.as_deref(), Some("Tuning SQLite for Desktop Apps | Field Notes"));
+        let metadata: serde_json::Value = serde_json::from_str(page.metadata.as_deref().unwrap()).unwrap();
+        assert_eq!(metadata, serde_json::json!({"author": "Ada Lovelace", "tags": ["sqlite", "performance", "desktop"]}));
+    }
+
+    #[tokio::test]
+    async fn test_runs_are_recorded_with_their_errors() {
+        let db = test_database("scraper_runs");
+        let scraper = ScraperManager::new(db.clone());
+        // Nothing listens on port 9, so the fetch fails straight away
+        let job_id = scraper.create_job(1, "Down", "http://127.0.0.1:9/", None, 1, 10, Vec::new()).unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let pages = scraper
+            .run_job(job_id, "manual", &TaskContext::detached("scrape"), move |status| sink.lock().unwrap().push(status), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(pages, 0);
+        assert!(!scraper.is_running(job_id));
+        assert!(scraper.pause_job(job_id).is_err());
+
+        let runs = scraper.get_runs(job_id, 10).unwrap();
+        assert_eq!(runs.len(), 1);
+        let run = &runs[0];
+        assert_eq!((run.trigger.as_str(), run.status.as_str(), run.error_count), ("manual", "completed", 1));
+        assert_eq!(run.errors[0].url.as_deref(), Some("http://127.0.0.1:9/"));
+        assert!(run.finished_at.is_some());
+        assert_eq!(scraper.get_job(job_id).unwrap().status, "completed");
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.last().unwrap().status, "completed");
+        assert!(events.iter().all(|e| e.job_id == job_id && e.run_id == run.id));
+
+        // A run cut short by quitting the app
+        db.get().unwrap()
+            .execute("UPDATE scraping_runs SET status = 'running', finished_at = NULL", [])
+            .unwrap();
+        scraper.init().unwrap();
+        assert_eq!(scraper.get_runs(job_id, 10).unwrap()[0].status, "interrupted");
+    }
+
+    #[tokio::test]
+    async fn test_job_is_claimed_before_its_run_starts() {
+        let db = test_database("scraper_claim");
+        let scraper = ScraperManager::new(db.clone());
+        let job_id = scraper.create_job(1, "Down", "http://127.0.0.1:9/", None, 1, 10, Vec::new()).unwrap();
+
+        // Starts racing each other: exactly one gets the job
+        let claims: Vec<JobClaim> = std::thread::scope(|s| {
+            let starts: Vec<_> = (0..8).map(|_| s.spawn(|| scraper.claim_job(job_id))).collect();
+            starts.into_iter().filter_map(|start| start.join().unwrap().ok()).collect()
+        });
+        assert_eq!(claims.len(), 1);
+        let claim = claims.into_iter().next().unwrap();
+
+        // Claimed but not started yet: running, but no task to cancel
+        assert!(scraper.is_running(job_id));
+        assert!(scraper.running_task(job_id).is_none());
+        let now = chrono::Utc::now() + chrono::Duration::days(1);
+        scraper.set_schedule(job_id, Some("* * * * *")).unwrap();
+        assert!(scraper.due_jobs(now).unwrap().is_empty());
+        let second = scraper.run_job(job_id, "schedule", &TaskContext::detached("scrape"), |_| {}, |_| {}).await;
+        assert_eq!(second.unwrap_err().kind(), crate::error::ErrorKind::Validation);
+        assert!(scraper.get_runs(job_id, 10).unwrap().is_empty());
+
+        scraper.run_claimed(claim, "manual", &TaskContext::detached("scrape"), |_| {}, |_| {}).await.unwrap();
+        assert!(!scraper.is_running(job_id));
+        assert_eq!(scraper.get_runs(job_id, 10).unwrap().len(), 1);
+        assert_eq!(scraper.due_jobs(now).unwrap(), vec![job_id]);
+
+        // A claim given up before its task ran frees the job too
+        drop(scraper.claim_job(job_id).unwrap());
+        assert!(scraper.claim_job(job_id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_robots_rules_sitemaps_and_user_agent() {
+        let db = test_database("scraper_robots");
+        let scraper = ScraperManager::new(db.clone());
+        let site = serve(|path, base, _| match path {
+            "/robots.txt" => reply(
+                "200 OK",
+                "",
+                &format!(
+                    "User-agent: *\nDisallow: /\n\nUser-agent: testbot\nDisallow: /secret\nCrawl-delay: 0\n\nSitemap: {}/sitemaps/index.xml\n",
+                    base
+                ),
+            ),
+            "/sitemaps/index.xml" => reply(
+                "200 OK",
+                "",
+                &format!("<sitemapindex><sitemap><loc>{}/sitemaps/pages.xml</loc></sitemap></sitemapindex>", base),
+            ),
+            "/sitemaps/pages.xml" => reply("200 OK", "", &format!("<urlset><url><loc>{}/from-sitemap</loc></url></urlset>", base)),
+            "/" => html(r#"<a href="/open">Open</a> <a href="/secret/plans">Secret</a>"#),
+            "/open" => html("<p>Open to all</p>"),
+            "/from-sitemap" => html("<p>Listed in the sitemap</p>"),
+            _ => not_found(),
+        })
+        .await;
+
+        let job_id = scraper.create_job(1, "Site", &format!("{}/", site.base), None, 1, 10, Vec::new()).unwrap();
+        let invalid = CrawlSettings { user_agent: Some("/1.0".to_string()), ..no_delay() };
+        assert!(matches!(scraper.set_settings(job_id, invalid), Err(AppError::Validation(_))));
+        assert!(scraper.set_settings(job_id, CrawlSettings { request_delay_ms: 120_000, ..no_delay() }).is_err());
+        let settings = CrawlSettings { user_agent: Some(" TestBot/2.0 ".to_string()), follow_sitemaps: true, ..no_delay() };
+        let job = scraper.set_settings(job_id, settings).unwrap();
+        assert_eq!(job.settings.user_agent(), "TestBot/2.0");
+        assert!(job.settings.respect_robots);
+
+        let task = TaskContext::detached("scrape");
+        assert_eq!(scraper.run_job(job_id, "manual", &task, |_| {}, |_| {}).await.unwrap(), 3);
+        let mut urls: Vec<String> = scraper.get_pages(job_id, 10).unwrap().into_iter().map(|page| page.url).collect();
+        urls.sort();
+        let expected: Vec<String> = ["/", "/from-sitemap", "/open"].iter().map(|path| format!("{}{}", site.base, path)).collect();
+        assert_eq!(urls, expected);
+
+        let run = &scraper.get_runs(job_id, 1).unwrap()[0];
+        assert_eq!(run.error_count, 1);
+        assert_eq!(run.errors[0].message, "Disallowed by robots.txt");
+        assert_eq!(site.hits("/secret/plans"), 0);
+        assert!(site.requests.lock().unwrap().iter().all(|(_, agent)| agent == "TestBot/2.0"));
+
+        // robots.txt is cached between runs
+        scraper.run_job(job_id, "manual", &task, |_| {}, |_| {}).await.unwrap();
+        assert_eq!(site.hits("/robots.txt"), 1);
+
+        // Ignoring robots.txt is up to the user
+        scraper.set_settings(job_id, CrawlSettings { respect_robots: false, ..no_delay() }).unwrap();
+        scraper.run_job(job_id, "manual", &task, |_| {}, |_| {}).await.unwrap();
+        assert_eq!(site.hits("/secret/plans"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_busy_and_failing_responses_are_retried() {
+        let db = test_database("scraper_retries");
+        let scraper = ScraperManager::new(db.clone());
+        let busy = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = busy.clone();
+        let site = serve(move |path, _, _| match path {
+            "/" if counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < 2 => {
+                reply("503 Service Unavailable", "Retry-After: 0\r\n", "")
+            }
+            "/" => html(r#"<a href="/later">Later</a> <a href="/missing">Missing</a>"#),
+            "/later" => reply("429 Too Many Requests", "Retry-After: 3600\r\n", ""),
+            _ => not_found(),
+        })
+        .await;
+
+        let job_id = scraper.create_job(1, "Busy", &format!("{}/", site.base), None, 1, 10, Vec::new()).unwrap();
+        scraper.set_settings(job_id, no_delay()).unwrap();
+        let pages = scraper.run_job(job_id, "manual", &TaskContext::detached("scrape"), |_| {}, |_| {}).await.unwrap();
+        assert_eq!(pages, 1);
+        assert_eq!(site.hits("/"), 3);
+        // An hour is longer than we wait, so that page is given up on at once
+        assert_eq!(site.hits("/later"), 1);
+
+        let run = &scraper.get_runs(job_id, 1).unwrap()[0];
+        let mut errors: Vec<&str> = run.errors.iter().map(|e| e.message.as_str()).collect();
+        errors.sort();
+        assert_eq!(errors, ["HTTP 404 Not Found", "HTTP 429 Too Many Requests"]);
+        assert!(scraper.get_pages(job_id, 10).unwrap().iter().all(|page| page.url == format!("{}/", site.base)));
+    }
+
+    #[tokio::test]
+    async fn test_crawl_is_breadth_first_deduplicated_and_resumable() {
+        const ALPHA: &str = r#"<p>Alpha</p> <a href="/a/deep">More</a>"#;
+        let db = test_database("scraper_frontier");
+        let scraper = ScraperManager::new(db.clone());
+        let site = serve(|path, _, _| match path {
+            "/" => html(
+                r#"<a href="/b">B</a> <a href="/a?utm_source=feed">A</a> <a href="/a#intro">A again</a> <a href="/a/">A/</a>
+                   <a href="https://elsewhere.example/">Out of scope</a> <a href="/missing">Gone</a> <a href="/print-a">Print</a>"#,
+            ),
+            "/a" | "/print-a" => html(ALPHA),
+            "/b" => html("<p>Beta</p>"),
+            "/a/deep" => html("<p>Deep</p>"),
+            _ => not_found(),
+        })
+        .await;
+        let fetched = || -> Vec<String> {
+            site.requests.lock().unwrap().iter().map(|(path, _)| path.clone()).filter(|path| path != "/robots.txt").collect()
+        };
+
+        let job_id = scraper.create_job(1, "Site", &format!("{}/", site.base), None, 2, 4, Vec::new()).unwrap();
+        scraper.set_settings(job_id, no_delay()).unwrap();
+        let task = TaskContext::detached("scrape");
+        assert_eq!(scraper.run_job(job_id, "manual", &task, |_| {}, |_| {}).await.unwrap(), 4);
+        // The 404 and the duplicate don't count towards max_pages, so /a/deep is still reached
+        assert_eq!(fetched(), ["/", "/b", "/a", "/missing", "/print-a", "/a/deep"]);
+        let pages = scraper.get_pages(job_id, 10).unwrap();
+        assert!(pages.iter().all(|page| !page.url.ends_with("/print-a")));
+        assert_eq!(scraper.get_runs(job_id, 1).unwrap()[0].errors[0].message, "HTTP 404 Not Found");
+        assert!(Frontier::load(&db, job_id).unwrap().is_new(), "a finished crawl starts over next time");
+
+        // A crawl that was cut short after the home page carries on with what was queued
+        let resumed = scraper.create_job(1, "Resumed", &format!("{}/", site.base), None, 2, 10, Vec::new()).unwrap();
+        scraper.set_settings(resumed, no_delay()).unwrap();
+        let mut frontier = Frontier::load(&db, resumed).unwrap();
+        frontier.push(&url::Url::parse(&format!("{}/", site.base)).unwrap(), 0).unwrap();
+        let home = frontier.pop().unwrap();
+        frontier.finish(&home, Visit::Scraped).unwrap();
+        frontier.push(&url::Url::parse(&format!("{}/b", site.base)).unwrap(), 1).unwrap();
+
+        site.requests.lock().unwrap().clear();
+        assert_eq!(scraper.run_job(resumed, "schedule", &task, |_| {}, |_| {}).await.unwrap(), 1);
+        assert_eq!(fetched(), ["/b"]);
+    }
+
+    #[tokio::test]
+    async fn test_rescrapes_keep_versions_and_report_watched_changes() {
+        let db = test_database("scraper_versions");
+        let scraper = ScraperManager::new(db.clone());
+        let news = Arc::new(Mutex::new("<p>The release is planned for Monday morning.</p>".to_string()));
+        let served = news.clone();
+        let site = serve(move |path, _, request| match path {
+            "/" if request.to_ascii_lowercase().contains("if-none-match: \"home\"") => reply("304 Not Modified", "", ""),
+            "/" => reply("200 OK", "Content-Type: text/html\r\nETag: \"home\"\r\n", r#"<a href="/news">News</a>"#),
+            "/news" => html(&served.lock().unwrap()),
+            _ => not_found(),
+        })
+        .await;
+
+        let job_id = scraper.create_job(1, "Site", &format!("{}/", site.base), None, 1, 10, Vec::new()).unwrap();
+        scraper.set_settings(job_id, no_delay()).unwrap();
+        let task = TaskContext::detached("scrape");
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let run = |scraper: ScraperManager, changes: Arc<Mutex<Vec<PageChanged>>>| {
+            let task = task.clone();
+            async move { scraper.run_job(job_id, "manual", &task, |_| {}, move |change| changes.lock().unwrap().push(change)).await }
+        };
+        assert_eq!(run(scraper.clone(), changes.clone()).await.unwrap(), 2);
+        let page = |path: &str| {
+            scraper.get_pages(job_id, 10).unwrap().into_iter().find(|page| page.url == format!("{}{}", site.base, path)).unwrap()
+        };
+        let news_page = page("/news");
+        assert_eq!(scraper.get_page_versions(news_page.id.unwrap()).unwrap().len(), 1);
+
+        // Nothing changed: the home page answers 304 and its stored links are still followed
+        assert_eq!(run(scraper.clone(), changes.clone()).await.unwrap(), 2);
+        assert_eq!(site.hits("/news"), 2);
+        assert_eq!(scraper.get_page_versions(news_page.id.unwrap()).unwrap().len(), 1);
+        let home = page("/");
+        assert!(home.changed_at.is_none() && home.checked_at.as_deref() != Some(home.scraped_at.as_str()));
+        assert_eq!(scraper.get_runs(job_id, 1).unwrap()[0].pages_changed, 0);
+
+        // A change to a page nobody watches is kept but not reported
+        *news.lock().unwrap() = "<p>The release is planned for Tuesday morning.</p>".to_string();
+        run(scraper.clone(), changes.clone()).await.unwrap();
+        assert!(changes.lock().unwrap().is_empty());
+        assert_eq!(scraper.get_runs(job_id, 1).unwrap()[0].pages_changed, 1);
+
+        assert!(matches!(scraper.set_page_watch(-1, true), Err(AppError::NotFound(_))));
+        scraper.set_page_watch(news_page.id.unwrap(), true).unwrap();
+        *news.lock().unwrap() = "<p>The release is now planned for Tuesday evening.</p>".to_string();
+        run(scraper.clone(), changes.clone()).await.unwrap();
+
+        let news_page = page("/news");
+        assert!(news_page.watch && news_page.changed_at.is_some());
+        assert_eq!(news_page.id, page("/news").id, "the page keeps its id across versions");
+        assert_eq!(news_page.content, "The release is now planned for Tuesday evening.");
+        let versions = scraper.get_page_versions(news_page.id.unwrap()).unwrap();
+        assert_eq!(versions.len(), 3);
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!((change.page_id, change.from_version, change.to_version), (news_page.id.unwrap(), Some(versions[1].id), versions[0].id));
+        assert_eq!((change.words_added, change.words_removed), (2, 1));
+
+        let diff = scraper.diff_page_versions(versions[1].id, versions[0].id).unwrap();
+        let kinds: Vec<(DiffKind, &str)> = diff.segments.iter().map(|s| (s.kind, s.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            [
+                (DiffKind::Equal, "The release is "),
+                (DiffKind::Insert, "now "),
+                (DiffKind::Equal, "planned for Tuesday "),
+                (DiffKind::Delete, "morning."),
+                (DiffKind::Insert, "evening."),
+            ]
+        );
+        let other = scraper.get_page_versions(page("/").id.unwrap()).unwrap();
+        assert!(matches!(scraper.diff_page_versions(other[0].id, versions[0].id), Err(AppError::Validation(_))));
+    }
+
+    #[test]
+    fn test_diffs_cut_long_unchanged_stretches() {
+        let words: Vec<String> = (0..100).map(|i| format!("w{}", i)).collect();
+        let old = words.join(" ");
+        let new = old.replace("w50", "fifty");
+        let (segments, added, removed) = diff_words(&old, &new);
+        assert_eq!((added, removed), (1, 1));
+        assert!(segments[0].text.starts_with('…'));
+        assert!(segments[0].text.ends_with("w49 "));
+        assert_eq!(segments[0].text.split_whitespace().count(), DIFF_CONTEXT_WORDS + 1);
+        assert_eq!(segments.last().unwrap().text, format!(" {}", words[51..71].join(" ").replace("w70", "w70 …")));
+    }
+
+    #[test]
+    fn test_requests_are_spaced_per_host() {
+        let scraper = ScraperManager::new(test_database("scraper_hosts"));
+        let delay = Duration::from_secs(2);
+        let first = scraper.take_turn("example.com", delay);
+        let second = scraper.take_turn("example.com", delay);
+        assert!(second >= first + delay);
+        assert!(scraper.take_turn("example.org", delay) < first + delay);
+
+        scraper.hold_host("example.org", first + Duration::from_secs(30));
+        assert!(scraper.take_turn("example.org", delay) >= first + Duration::from_secs(30));
+
+        assert_eq!(retry_wait(0, None), Some(RETRY_BACKOFF));
+        assert_eq!(retry_wait(3, None), Some(RETRY_BACKOFF * 8));
+        assert_eq!(retry_wait(20, None), Some(MAX_RETRY_WAIT));
+        assert_eq!(retry_wait(0, Some(&HeaderValue::from_static("7"))), Some(Duration::from_secs(7)));
+        assert_eq!(retry_wait(0, Some(&HeaderValue::from_static("86400"))), None);
+        let soon = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = retry_wait(0, Some(&HeaderValue::from_str(&soon).unwrap())).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+    }
+}
@@ -1,9 +1,13 @@
 // Search engine functionality for EarthSearch
 // Manages domain whitelists and search within curated domains
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+use crate::tasks::TaskContext;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Domain {
     pub id: Option<i64>,
@@ -50,334 +54,34 @@ pub struct CategoryCount {
     pub count: i64,
 }
 
+#[derive(Clone)]
 pub struct SearchManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl SearchManager {
-    pub fn new(db_path: String) -> Self {
-        SearchManager { db_path }
-    }
-
-    /// Initialize search tables
-    pub fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-
-        // Domains table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS domains (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                url TEXT NOT NULL,
-                category TEXT NOT NULL,
-                trust_score REAL NOT NULL DEFAULT 0.5,
-                added_date TEXT NOT NULL,
-                metadata TEXT,
-                profile_id INTEGER,
-                UNIQUE(url, profile_id),
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Domain lists table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS domain_lists (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL,
-                description TEXT,
-                author TEXT,
-                version TEXT DEFAULT '1.0',
-                created_at TEXT NOT NULL,
-                profile_id INTEGER,
-                UNIQUE(name, profile_id),
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // List-domain associations
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS list_domains (
-                list_id INTEGER NOT NULL,
-                domain_id INTEGER NOT NULL,
-                PRIMARY KEY (list_id, domain_id),
-                FOREIGN KEY (list_id) REFERENCES domain_lists(id) ON DELETE CASCADE,
-                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Domain ratings table
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS domain_ratings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                domain_id INTEGER NOT NULL,
-                user_id TEXT NOT NULL,
-                trust_rating INTEGER NOT NULL CHECK (trust_rating BETWEEN 1 AND 5),
-                bias_rating INTEGER NOT NULL CHECK (bias_rating BETWEEN 1 AND 4),
-                review_text TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT,
-                helpful_count INTEGER DEFAULT 0,
-                reported BOOLEAN DEFAULT FALSE,
-                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE,
-                UNIQUE(domain_id, user_id)
-            )",
-            [],
-        )?;
-
-        // Aggregated ratings for quick lookup
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS domain_rating_aggregates (
-                domain_id INTEGER PRIMARY KEY,
-                avg_trust REAL NOT NULL DEFAULT 3.0,
-                avg_bias REAL NOT NULL DEFAULT 2.5,
-                total_ratings INTEGER NOT NULL DEFAULT 0,
-                trust_distribution TEXT,
-                bias_distribution TEXT,
-                last_updated TEXT,
-                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Subdomain-specific ratings
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS subdomain_ratings (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                parent_domain_id INTEGER NOT NULL,
-                subdomain TEXT NOT NULL,
-                avg_trust REAL NOT NULL DEFAULT 3.0,
-                avg_bias REAL NOT NULL DEFAULT 2.5,
-                total_ratings INTEGER NOT NULL DEFAULT 0,
-                FOREIGN KEY (parent_domain_id) REFERENCES domains(id) ON DELETE CASCADE,
-                UNIQUE(parent_domain_id, subdomain)
-            )",
-            [],
-        )?;
-
-        // Rating categories for detailed breakdowns
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS rating_categories (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                domain_rating_id INTEGER NOT NULL,
-                category TEXT NOT NULL,
-                score INTEGER NOT NULL CHECK (score BETWEEN 1 AND 5),
-                FOREIGN KEY (domain_rating_id) REFERENCES domain_ratings(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Indexes
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domains_url ON domains(url)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domains_category ON domains(category)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domains_profile ON domains(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domain_lists_profile ON domain_lists(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domain_ratings_domain ON domain_ratings(domain_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_domain_ratings_user ON domain_ratings(user_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_subdomain_ratings_parent ON subdomain_ratings(parent_domain_id)", [])?;
-
-        // ==================== Tabs System ====================
-
-        // Tabs table (per profile)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tabs (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                title TEXT,
-                url TEXT NOT NULL,
-                favicon TEXT,
-                position INTEGER NOT NULL,
-                is_pinned INTEGER DEFAULT 0,
-                is_active INTEGER DEFAULT 0,
-                scroll_position INTEGER DEFAULT 0,
-                created_at TEXT NOT NULL,
-                last_accessed TEXT NOT NULL,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Tab history (back/forward navigation per tab)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS tab_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                tab_id INTEGER NOT NULL,
-                url TEXT NOT NULL,
-                title TEXT,
-                visited_at TEXT NOT NULL,
-                position INTEGER NOT NULL,
-                FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Tab indexes
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_tabs_profile ON tabs(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_tabs_position ON tabs(profile_id, position)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_tab_history_tab ON tab_history(tab_id)", [])?;
-
-        // ==================== Bookmarks System ====================
-
-        // Bookmark folders
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS bookmark_folders (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                name TEXT NOT NULL,
-                parent_id INTEGER,
-                position INTEGER DEFAULT 0,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
-                FOREIGN KEY (parent_id) REFERENCES bookmark_folders(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Bookmarks
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS bookmarks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                title TEXT NOT NULL,
-                url TEXT NOT NULL,
-                favicon TEXT,
-                folder_id INTEGER,
-                tags TEXT,
-                notes TEXT,
-                position INTEGER DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
-                FOREIGN KEY (folder_id) REFERENCES bookmark_folders(id) ON DELETE SET NULL
-            )",
-            [],
-        )?;
-
-        // Bookmark indexes
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_bookmarks_profile ON bookmarks(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_bookmarks_folder ON bookmarks(folder_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_bookmark_folders_profile ON bookmark_folders(profile_id)", [])?;
-
-        // ==================== Split View System ====================
-
-        // Split view configuration (per profile)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS split_view_config (
-                profile_id INTEGER PRIMARY KEY,
-                layout TEXT NOT NULL DEFAULT 'single',
-                pane_1_tab_id INTEGER,
-                pane_2_tab_id INTEGER,
-                pane_3_tab_id INTEGER,
-                pane_4_tab_id INTEGER,
-                active_pane INTEGER DEFAULT 1,
-                pane_sizes TEXT,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
-                FOREIGN KEY (pane_1_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
-                FOREIGN KEY (pane_2_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
-                FOREIGN KEY (pane_3_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
-                FOREIGN KEY (pane_4_tab_id) REFERENCES tabs(id) ON DELETE SET NULL
-            )",
-            [],
-        )?;
-
-        // ==================== EarthMultiMedia System ====================
-
-        // Multimedia privacy settings (per profile)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS multimedia_privacy (
-                profile_id INTEGER PRIMARY KEY,
-                history_enabled INTEGER DEFAULT 0,
-                playlist_history_enabled INTEGER DEFAULT 0,
-                require_password INTEGER DEFAULT 0,
-                require_otp INTEGER DEFAULT 0,
-                password_hash TEXT,
-                otp_secret TEXT,
-                auto_clear_history_days INTEGER,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Multimedia history (only used if history_enabled)
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS multimedia_history (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                media_id INTEGER,
-                source TEXT NOT NULL,
-                media_type TEXT NOT NULL,
-                title TEXT,
-                thumbnail TEXT,
-                position INTEGER DEFAULT 0,
-                duration INTEGER,
-                played_at TEXT NOT NULL,
-                encrypted INTEGER DEFAULT 0,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Multimedia playlists
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS multimedia_playlists (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                name TEXT NOT NULL,
-                description TEXT,
-                thumbnail TEXT,
-                is_encrypted INTEGER DEFAULT 0,
-                created_at TEXT NOT NULL,
-                updated_at TEXT,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Playlist items
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS multimedia_playlist_items (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                playlist_id INTEGER NOT NULL,
-                source TEXT NOT NULL,
-                media_type TEXT NOT NULL,
-                title TEXT,
-                thumbnail TEXT,
-                position INTEGER NOT NULL,
-                added_at TEXT NOT NULL,
-                FOREIGN KEY (playlist_id) REFERENCES multimedia_playlists(id) ON DELETE CASCADE
-            )",
-            [],
-        )?;
-
-        // Multimedia indexes
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_multimedia_history_profile ON multimedia_history(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_multimedia_history_played ON multimedia_history(played_at DESC)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_multimedia_playlists_profile ON multimedia_playlists(profile_id)", [])?;
-        conn.execute("CREATE INDEX IF NOT EXISTS idx_multimedia_playlist_items_playlist ON multimedia_playlist_items(playlist_id)", [])?;
-
-        Ok(())
+    pub fn new(db: DbPool) -> Self {
+        SearchManager { db }
     }
 
     // ==================== Domain CRUD ====================
 
     /// Add a new domain
     pub fn add_domain(&self, domain: &Domain, profile_id: i64) -> Result<Domain> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
-        conn.execute(
+        conn.prepare_cached(
             "INSERT INTO domains (url, category, trust_score, added_date, metadata, profile_id)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                domain.url,
-                domain.category,
-                domain.trust_score,
-                now,
-                domain.metadata,
-                profile_id
-            ],
-        )?;
+        )?.execute(params![
+            domain.url,
+            domain.category,
+            domain.trust_score,
+            now,
+            domain.metadata,
+            profile_id
+        ])?;
 
         let id = conn.last_insert_rowid();
         Ok(Domain {
@@ -393,7 +97,7 @@ impl SearchManager {
 
     /// Get all domains for a profile
     pub fn get_domains(&self, profile_id: i64) -> Result<Vec<Domain>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, url, category, trust_score, added_date, metadata, profile_id
              FROM domains WHERE profile_id = ?1 ORDER BY trust_score DESC, url ASC"
@@ -416,7 +120,7 @@ impl SearchManager {
 
     /// Get domains by category
     pub fn get_domains_by_category(&self, profile_id: i64, category: &str) -> Result<Vec<Domain>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, url, category, trust_score, added_date, metadata, profile_id
              FROM domains WHERE profile_id = ?1 AND category = ?2 ORDER BY trust_score DESC"
@@ -439,7 +143,7 @@ impl SearchManager {
 
     /// Update a domain
     pub fn update_domain(&self, domain: &Domain) -> Result<Domain> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE domains SET url = ?1, category = ?2, trust_score = ?3, metadata = ?4
@@ -458,7 +162,7 @@ impl SearchManager {
 
     /// Delete a domain
     pub fn delete_domain(&self, domain_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let affected = conn.execute(
             "DELETE FROM domains WHERE id = ?1 AND profile_id = ?2",
             params![domain_id, profile_id],
@@ -468,7 +172,7 @@ impl SearchManager {
 
     /// Search domains by URL pattern
     pub fn search_domains(&self, profile_id: i64, query: &str) -> Result<Vec<Domain>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let pattern = format!("%{}%", query.to_lowercase());
 
         let mut stmt = conn.prepare(
@@ -497,7 +201,7 @@ impl SearchManager {
 
     /// Create a new domain list
     pub fn create_list(&self, list: &DomainList, profile_id: i64) -> Result<DomainList> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         conn.execute(
@@ -528,7 +232,7 @@ impl SearchManager {
 
     /// Get all domain lists for a profile
     pub fn get_lists(&self, profile_id: i64) -> Result<Vec<DomainList>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT dl.id, dl.name, dl.description, dl.author, dl.version, dl.created_at, dl.profile_id,
                     COUNT(ld.domain_id) as domain_count
@@ -557,17 +261,16 @@ impl SearchManager {
 
     /// Add domain to a list
     pub fn add_domain_to_list(&self, list_id: i64, domain_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
-        let result = conn.execute(
-            "INSERT OR IGNORE INTO list_domains (list_id, domain_id) VALUES (?1, ?2)",
-            params![list_id, domain_id],
-        );
+        let conn = self.db.get()?;
+        let result = conn
+            .prepare_cached("INSERT OR IGNORE INTO list_domains (list_id, domain_id) VALUES (?1, ?2)")?
+            .execute(params![list_id, domain_id]);
         Ok(result.is_ok())
     }
 
     /// Remove domain from a list
     pub fn remove_domain_from_list(&self, list_id: i64, domain_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let affected = conn.execute(
             "DELETE FROM list_domains WHERE list_id = ?1 AND domain_id = ?2",
             params![list_id, domain_id],
@@ -577,7 +280,7 @@ impl SearchManager {
 
     /// Get domains in a list
     pub fn get_list_domains(&self, list_id: i64) -> Result<Vec<Domain>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT d.id, d.url, d.category, d.trust_score, d.added_date, d.metadata, d.profile_id
              FROM domains d
@@ -603,7 +306,7 @@ impl SearchManager {
 
     /// Delete a list
     pub fn delete_list(&self, list_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let affected = conn.execute(
             "DELETE FROM domain_lists WHERE id = ?1 AND profile_id = ?2",
             params![list_id, profile_id],
@@ -627,7 +330,7 @@ impl SearchManager {
 
     /// Export a list with its domains
     pub fn export_list(&self, list_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get list info
         let list: DomainList = conn.query_row(
@@ -670,16 +373,21 @@ impl SearchManager {
         Ok(serde_json::to_string_pretty(&export).unwrap_or_default())
     }
 
-    /// Import domains from JSON
-    pub fn import_domains(&self, profile_id: i64, json_data: &str) -> Result<i64> {
-        let data: serde_json::Value = serde_json::from_str(json_data)
-            .map_err(|e| rusqlite::Error::InvalidQuery)?;
+    /// Import domains from JSON; a cancelled import keeps what it already added
+    pub fn import_domains(&self, profile_id: i64, json_data: &str, task: &TaskContext) -> AppResult<i64> {
+        let data: serde_json::Value = serde_json::from_str(json_data)?;
 
         let domains = data["domains"].as_array()
-            .ok_or(rusqlite::Error::InvalidQuery)?;
+            .ok_or_else(|| AppError::Validation("Domain list has no \"domains\" array".to_string()))?;
 
+        let total = domains.len() as u64;
         let mut imported = 0i64;
-        for d in domains {
+        for (i, d) in domains.iter().enumerate() {
+            if task.is_cancelled() {
+                break;
+            }
+            task.progress(i as u64 + 1, Some(total));
+
             let domain = Domain {
                 id: None,
                 url: d["url"].as_str().unwrap_or_default().to_string(),
@@ -704,7 +412,7 @@ impl SearchManager {
 
     /// Get domain statistics
     pub fn get_stats(&self, profile_id: i64) -> Result<DomainStats> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let total_domains: i64 = conn.query_row(
             "SELECT COUNT(*) FROM domains WHERE profile_id = ?1",
@@ -746,7 +454,7 @@ impl SearchManager {
 
     /// Get all unique categories
     pub fn get_categories(&self, profile_id: i64) -> Result<Vec<String>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT DISTINCT category FROM domains WHERE profile_id = ?1 ORDER BY category"
         )?;
@@ -791,8 +499,8 @@ struct EarthListDomain {
 impl SearchManager {
     /// Seed default domains from bundled .earth files
     /// Only runs if the database has no domains for the given profile
-    pub fn seed_default_domains(&self, profile_id: i64, resource_dir: &std::path::Path) -> Result<i64> {
-        let conn = Connection::open(&self.db_path)?;
+    pub fn seed_default_domains(&self, profile_id: i64, resource_dir: &std::path::Path) -> AppResult<i64> {
+        let conn = self.db.get()?;
 
         // Check if domains already exist for this profile
         let existing_count: i64 = conn.query_row(
@@ -828,12 +536,9 @@ impl SearchManager {
     }
 
     /// Import a single .earth file
-    fn import_earth_file(&self, path: &std::path::Path, profile_id: i64) -> Result<i64> {
-        let contents = std::fs::read_to_string(path)
-            .map_err(|_| rusqlite::Error::InvalidQuery)?;
-
-        let list_data: EarthListFile = serde_json::from_str(&contents)
-            .map_err(|_| rusqlite::Error::InvalidQuery)?;
+    fn import_earth_file(&self, path: &std::path::Path, profile_id: i64) -> AppResult<i64> {
+        let contents = std::fs::read_to_string(path)?;
+        let list_data: EarthListFile = serde_json::from_str(&contents)?;
 
         // Create the list
         let list = DomainList {
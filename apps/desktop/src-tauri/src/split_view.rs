@@ -1,9 +1,12 @@
 // Split view management for Earth Reclaim
 // Multi-pane layout system for viewing multiple tabs simultaneously
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+use crate::error::{AppError, AppResult};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SplitLayout {
     #[serde(rename = "single")]
@@ -69,18 +72,19 @@ impl Default for PaneSizes {
     }
 }
 
+#[derive(Clone)]
 pub struct SplitViewManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl SplitViewManager {
-    pub fn new(db_path: String) -> Self {
-        SplitViewManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        SplitViewManager { db }
     }
 
     /// Get or create split view config for a profile
     pub fn get_config(&self, profile_id: i64) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let result = conn.query_row(
             "SELECT profile_id, layout, pane_1_tab_id, pane_2_tab_id, pane_3_tab_id, pane_4_tab_id, active_pane, pane_sizes
@@ -117,7 +121,7 @@ impl SplitViewManager {
 
     /// Create default split view config
     fn create_default_config(&self, profile_id: i64) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "INSERT INTO split_view_config (profile_id, layout, active_pane)
@@ -139,7 +143,7 @@ impl SplitViewManager {
 
     /// Set the split layout
     pub fn set_layout(&self, profile_id: i64, layout: &str) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let split_layout = SplitLayout::from(layout);
 
         // Ensure config exists
@@ -186,8 +190,8 @@ impl SplitViewManager {
     }
 
     /// Set which tab is shown in a pane
-    pub fn set_pane_tab(&self, profile_id: i64, pane_number: i32, tab_id: Option<i64>) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+    pub fn set_pane_tab(&self, profile_id: i64, pane_number: i32, tab_id: Option<i64>) -> AppResult<SplitViewConfig> {
+        let conn = self.db.get()?;
 
         // Ensure config exists
         let _ = self.get_config(profile_id)?;
@@ -197,7 +201,7 @@ impl SplitViewManager {
             2 => "pane_2_tab_id",
             3 => "pane_3_tab_id",
             4 => "pane_4_tab_id",
-            _ => return Err(rusqlite::Error::InvalidParameterName("Invalid pane number".to_string())),
+            _ => return Err(AppError::Validation("Invalid pane number".to_string())),
         };
 
         conn.execute(
@@ -205,12 +209,12 @@ impl SplitViewManager {
             params![tab_id, profile_id],
         )?;
 
-        self.get_config(profile_id)
+        Ok(self.get_config(profile_id)?)
     }
 
     /// Set the active pane (which has focus)
-    pub fn set_active_pane(&self, profile_id: i64, pane_number: i32) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+    pub fn set_active_pane(&self, profile_id: i64, pane_number: i32) -> AppResult<SplitViewConfig> {
+        let conn = self.db.get()?;
 
         // Validate pane number based on current layout
         let config = self.get_config(profile_id)?;
@@ -221,7 +225,7 @@ impl SplitViewManager {
         };
 
         if pane_number < 1 || pane_number > max_panes {
-            return Err(rusqlite::Error::InvalidParameterName(
+            return Err(AppError::Validation(
                 format!("Pane number must be between 1 and {}", max_panes)
             ));
         }
@@ -231,11 +235,11 @@ impl SplitViewManager {
             params![pane_number, profile_id],
         )?;
 
-        self.get_config(profile_id)
+        Ok(self.get_config(profile_id)?)
     }
 
     /// Cycle to next pane
-    pub fn cycle_pane(&self, profile_id: i64, direction: i32) -> Result<SplitViewConfig> {
+    pub fn cycle_pane(&self, profile_id: i64, direction: i32) -> AppResult<SplitViewConfig> {
         let config = self.get_config(profile_id)?;
 
         let max_panes = match config.layout {
@@ -265,7 +269,7 @@ impl SplitViewManager {
 
     /// Update pane sizes
     pub fn update_pane_sizes(&self, profile_id: i64, sizes: PaneSizes) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let sizes_json = serde_json::to_string(&sizes)
             .unwrap_or_else(|_| "{}".to_string());
@@ -279,7 +283,7 @@ impl SplitViewManager {
     }
 
     /// Swap tabs between two panes
-    pub fn swap_panes(&self, profile_id: i64, pane_a: i32, pane_b: i32) -> Result<SplitViewConfig> {
+    pub fn swap_panes(&self, profile_id: i64, pane_a: i32, pane_b: i32) -> AppResult<SplitViewConfig> {
         let config = self.get_config(profile_id)?;
 
         let tab_a = match pane_a {
@@ -317,7 +321,7 @@ impl SplitViewManager {
 
     /// Reset to single pane view
     pub fn reset_to_single(&self, profile_id: i64) -> Result<SplitViewConfig> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE split_view_config SET
@@ -0,0 +1,352 @@
+// AI page summaries for EarthMemory
+// Generates a concise summary and suggested tags for indexed pages with the local LLM
+// Opt-in per profile; manual edits to a summary are never overwritten
+
+use rusqlite::{OptionalExtension, Result, params};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ai::{AiError, OllamaClient};
+use crate::db::DbPool;
+use crate::error::AppResult;
+use crate::privacy::PrivacyManager;
+use crate::profile::PrivacySettings;
+
+/// Event emitted when a page's AI summary has been (re)generated
+pub const SUMMARY_EVENT: &str = "page-summary-updated";
+
+/// Page text beyond this is cut before prompting, small local models have short contexts
+const MAX_INPUT_CHARS: usize = 6000;
+const MAX_SUGGESTED_TAGS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageSummary {
+    pub page_id: i64,
+    pub summary: String,
+    pub suggested_tags: Vec<String>,
+    /// Hash of the title and content the summary was generated from
+    pub content_hash: String,
+    pub model: String,
+    pub generated_at: String,
+    /// False when the page shows a summary the user wrote instead of this one
+    pub applied: bool,
+}
+
+/// What the model needs to summarize a page
+#[derive(Debug, Clone)]
+pub struct SummaryInput {
+    pub page_id: i64,
+    pub title: String,
+    pub content: String,
+    pub content_hash: String,
+}
+
+#[derive(Clone)]
+pub struct PageSummarizer {
+    db: DbPool,
+}
+
+impl PageSummarizer {
+    pub fn new(db: DbPool) -> Self {
+        PageSummarizer { db }
+    }
+
+    /// Whether AI summaries may run for a profile right now
+    pub fn is_allowed(settings: &PrivacySettings) -> bool {
+        settings.ai_summaries_enabled && (!PrivacyManager::is_incognito() || settings.ai_enabled_in_incognito)
+    }
+
+    /// Get the last generated summary for a page
+    pub fn get_summary(&self, page_id: i64, profile_id: i64) -> Result<Option<PageSummary>> {
+        let conn = self.db.get()?;
+        conn.query_row(
+            "SELECT s.page_id, s.summary, s.suggested_tags, s.content_hash, s.model, s.generated_at,
+                    COALESCE(p.summary = s.summary, 0)
+             FROM page_summaries s JOIN pages p ON p.id = s.page_id
+             WHERE s.page_id = ?1 AND p.profile_id = ?2",
+            params![page_id, profile_id],
+            |row| {
+                Ok(PageSummary {
+                    page_id: row.get(0)?,
+                    summary: row.get(1)?,
+                    suggested_tags: split_tags(&row.get::<_, Option<String>>(2)?.unwrap_or_default()),
+                    content_hash: row.get(3)?,
+                    model: row.get(4)?,
+                    generated_at: row.get(5)?,
+                    applied: row.get::<_, i64>(6)? == 1,
+                })
+            },
+        )
+        .optional()
+    }
+
+    /// Page text to summarize, or None if the stored summary is still current
+    /// (unless `force`) or the page has nothing to summarize
+    pub fn pending_input(&self, page_id: i64, profile_id: i64, force: bool) -> Result<Option<SummaryInput>> {
+        let conn = self.db.get()?;
+
+        let page: Option<(String, Option<String>)> = conn.query_row(
+            "SELECT title, content FROM pages WHERE id = ?1 AND profile_id = ?2",
+            params![page_id, profile_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional()?;
+
+        let (title, content) = match page {
+            Some((title, Some(content))) if !content.trim().is_empty() => (title, content),
+            _ => return Ok(None),
+        };
+
+        let hash = content_hash(&title, &content);
+        if !force {
+            let stored_hash: Option<String> = conn.query_row(
+                "SELECT content_hash FROM page_summaries WHERE page_id = ?1",
+                params![page_id],
+                |row| row.get(0),
+            ).optional()?;
+            if stored_hash.as_deref() == Some(hash.as_str()) {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(SummaryInput {
+            page_id,
+            title,
+            content,
+            content_hash: hash,
+        }))
+    }
+
+    /// Store a generated summary. The page's visible summary is only replaced when it is empty
+    /// or still the previous AI summary, so anything the user typed stays.
+    /// Returns None if the page changed while the model was running (a newer run will follow).
+    pub fn store_summary(
+        &self,
+        input: &SummaryInput,
+        summary: &str,
+        suggested_tags: &[String],
+        model: &str,
+    ) -> Result<Option<PageSummary>> {
+        let mut conn = self.db.get()?;
+        let tx = conn.transaction()?;
+
+        let current: Option<(String, Option<String>, Option<String>)> = tx.query_row(
+            "SELECT title, content, summary FROM pages WHERE id = ?1",
+            params![input.page_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).optional()?;
+
+        let (title, content, page_summary) = match current {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        if content_hash(&title, content.as_deref().unwrap_or_default()) != input.content_hash {
+            return Ok(None);
+        }
+
+        let previous_ai: Option<String> = tx.query_row(
+            "SELECT summary FROM page_summaries WHERE page_id = ?1",
+            params![input.page_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let user_owned = match page_summary.as_deref().map(str::trim) {
+            None | Some("") => false,
+            Some(shown) => previous_ai.as_deref().map(str::trim) != Some(shown),
+        };
+
+        let now = chrono_now();
+        tx.execute(
+            "INSERT OR REPLACE INTO page_summaries (page_id, summary, suggested_tags, content_hash, model, generated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![input.page_id, summary, suggested_tags.join(", "), input.content_hash, model, now],
+        )?;
+
+        if !user_owned {
+            tx.execute(
+                "UPDATE pages SET summary = ?1 WHERE id = ?2",
+                params![summary, input.page_id],
+            )?;
+        }
+
+        tx.commit()?;
+
+        Ok(Some(PageSummary {
+            page_id: input.page_id,
+            summary: summary.to_string(),
+            suggested_tags: suggested_tags.to_vec(),
+            content_hash: input.content_hash.clone(),
+            model: model.to_string(),
+            generated_at: now,
+            applied: !user_owned,
+        }))
+    }
+
+    /// Summarize a page if it needs it. Returns the new summary, or None if nothing was done.
+    pub async fn summarize_page(
+        &self,
+        client: &OllamaClient,
+        page_id: i64,
+        profile_id: i64,
+        force: bool,
+    ) -> AppResult<Option<PageSummary>> {
+        let input = match self.pending_input(page_id, profile_id, force)? {
+            Some(input) => input,
+            None => return Ok(None),
+        };
+
+        let response = client.generate(&build_prompt(&input.title, &input.content), None).await?;
+        let (summary, tags) = parse_summary_response(&response)
+            .ok_or_else(|| AiError::InvalidResponse("model returned an empty summary".to_string()))?;
+
+        Ok(self.store_summary(&input, &summary, &tags, &client.config().generation_model)?)
+    }
+}
+
+// ==================== Helper Functions ====================
+
+fn build_prompt(title: &str, content: &str) -> String {
+    let content: String = content.chars().take(MAX_INPUT_CHARS).collect();
+    format!(
+        "Summarize the following web page in two or three sentences and suggest up to {} short topic tags.\n\
+         Respond with JSON only, in the form {{\"summary\": \"...\", \"tags\": [\"...\"]}}.\n\n\
+         Title: {}\n\n{}",
+        MAX_SUGGESTED_TAGS, title, content
+    )
+}
+
+/// Pull the summary and tags out of the model output. Small models don't always stick to
+/// JSON, so plain text is accepted as a summary without tags.
+pub fn parse_summary_response(response: &str) -> Option<(String, Vec<String>)> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => {
+            serde_json::from_str::<serde_json::Value>(&response[start..=end]).ok()
+        }
+        _ => None,
+    };
+
+    let (summary, tags) = match json {
+        Some(value) => {
+            let summary = value["summary"].as_str().unwrap_or_default().to_string();
+            let tags = value["tags"]
+                .as_array()
+                .map(|tags| tags.iter().filter_map(|t| t.as_str()).collect::<Vec<_>>().join(","))
+                .unwrap_or_default();
+            (summary, split_tags(&tags))
+        }
+        None => (response.to_string(), Vec::new()),
+    };
+
+    let summary = summary.split_whitespace().collect::<Vec<_>>().join(" ");
+    if summary.is_empty() {
+        return None;
+    }
+    Some((summary, tags))
+}
+
+/// Normalize a comma separated tag list the way EarthMemory stores tags
+fn split_tags(tags: &str) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for tag in tags.split(',') {
+        let tag = tag.trim().trim_start_matches('#').trim().to_lowercase();
+        if !tag.is_empty() && !result.contains(&tag) && result.len() < MAX_SUGGESTED_TAGS {
+            result.push(tag);
+        }
+    }
+    result
+}
+
+fn content_hash(title: &str, content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(title.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(content.as_bytes());
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{IndexedPage, MemoryManager};
+
+    fn setup(name: &str) -> (MemoryManager, PageSummarizer, i64) {
+        let db = crate::migrations::test_database(&format!("summary_{}", name));
+        let memory = MemoryManager::new(db.clone());
+        let summarizer = PageSummarizer::new(db);
+
+        let page = memory.index_page(&IndexedPage {
+            id: None,
+            url: "https://example.com".to_string(),
+            title: "Example".to_string(),
+            content: Some("Some long article text".to_string()),
+            summary: None,
+            indexed_at: String::new(),
+            last_visited: String::new(),
+            visit_count: 1,
+            is_favorite: false,
+            tags: None,
+            profile_id: None,
+        }, 1).unwrap();
+
+        (memory, summarizer, page.id.unwrap())
+    }
+
+    #[test]
+    fn test_parse_summary_response() {
+        let (summary, tags) = parse_summary_response(
+            "Sure! {\"summary\": \"A page  about Rust.\", \"tags\": [\"Rust\", \"#programming\", \"rust\"]}"
+        ).unwrap();
+        assert_eq!(summary, "A page about Rust.");
+        assert_eq!(tags, vec!["rust", "programming"]);
+
+        let (summary, tags) = parse_summary_response("Just a plain summary.").unwrap();
+        assert_eq!(summary, "Just a plain summary.");
+        assert!(tags.is_empty());
+
+        assert!(parse_summary_response("{\"summary\": \"\"}").is_none());
+    }
+
+    #[test]
+    fn test_store_summary_keeps_manual_edits() {
+        let (memory, summarizer, page_id) = setup("manual");
+
+        let input = summarizer.pending_input(page_id, 1, false).unwrap().unwrap();
+        let stored = summarizer.store_summary(&input, "AI summary", &["tag".to_string()], "test").unwrap().unwrap();
+        assert!(stored.applied);
+        assert!(summarizer.pending_input(page_id, 1, false).unwrap().is_none());
+
+        // The user rewrites the summary; a regenerated one must not replace it
+        let conn = summarizer.db.get().unwrap();
+        conn.execute("UPDATE pages SET summary = 'My words' WHERE id = ?1", params![page_id]).unwrap();
+        let input = summarizer.pending_input(page_id, 1, true).unwrap().unwrap();
+        let stored = summarizer.store_summary(&input, "AI summary v2", &[], "test").unwrap().unwrap();
+        assert!(!stored.applied);
+
+        let page = memory.get_pages(1, None, None).unwrap().remove(0);
+        assert_eq!(page.summary.as_deref(), Some("My words"));
+        assert_eq!(summarizer.get_summary(page_id, 1).unwrap().unwrap().summary, "AI summary v2");
+    }
+
+    #[test]
+    fn test_content_change_requires_new_summary() {
+        let (_memory, summarizer, page_id) = setup("stale");
+
+        let input = summarizer.pending_input(page_id, 1, false).unwrap().unwrap();
+        let conn = summarizer.db.get().unwrap();
+        conn.execute("UPDATE pages SET content = 'Rewritten article' WHERE id = ?1", params![page_id]).unwrap();
+
+        // Generated from old content: discarded
+        assert!(summarizer.store_summary(&input, "Outdated", &[], "test").unwrap().is_none());
+
+        let input = summarizer.pending_input(page_id, 1, false).unwrap().unwrap();
+        assert_eq!(input.content, "Rewritten article");
+        assert!(summarizer.pending_input(page_id, 2, false).unwrap().is_none());
+    }
+}
@@ -1,9 +1,11 @@
 // Tab management for Earth Reclaim
 // Browser-like tab system with history and state
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+
 fn chrono_now() -> String {
     chrono::Utc::now().to_rfc3339()
 }
@@ -40,18 +42,19 @@ pub struct CreateTabRequest {
     pub title: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct TabManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl TabManager {
-    pub fn new(db_path: String) -> Self {
-        TabManager { db_path }
+    pub fn new(db: DbPool) -> Self {
+        TabManager { db }
     }
 
     /// Create a new tab
     pub fn create_tab(&self, profile_id: i64, url: &str, title: Option<&str>) -> Result<Tab> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Get max position
@@ -97,7 +100,7 @@ impl TabManager {
 
     /// Close/delete a tab
     pub fn close_tab(&self, tab_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get the tab's profile and position
         let (profile_id, position): (i64, i32) = conn.query_row(
@@ -120,7 +123,7 @@ impl TabManager {
 
     /// Get all tabs for a profile
     pub fn get_all_tabs(&self, profile_id: i64) -> Result<Vec<Tab>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, title, url, favicon, position, is_pinned, is_active, scroll_position, created_at, last_accessed
              FROM tabs WHERE profile_id = ?1 ORDER BY is_pinned DESC, position ASC"
@@ -147,7 +150,7 @@ impl TabManager {
 
     /// Update tab details
     pub fn update_tab(&self, tab_id: i64, title: Option<&str>, url: Option<&str>, favicon: Option<&str>) -> Result<Tab> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Build update query dynamically
@@ -230,7 +233,7 @@ impl TabManager {
 
     /// Get a single tab
     pub fn get_tab(&self, tab_id: i64) -> Result<Tab> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.query_row(
             "SELECT id, profile_id, title, url, favicon, position, is_pinned, is_active, scroll_position, created_at, last_accessed
              FROM tabs WHERE id = ?1",
@@ -255,7 +258,7 @@ impl TabManager {
 
     /// Reorder tabs
     pub fn reorder_tabs(&self, tab_ids: Vec<i64>) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         for (index, tab_id) in tab_ids.iter().enumerate() {
             conn.execute(
@@ -269,7 +272,7 @@ impl TabManager {
 
     /// Pin/unpin a tab
     pub fn pin_tab(&self, tab_id: i64, pinned: bool) -> Result<Tab> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         conn.execute(
             "UPDATE tabs SET is_pinned = ?1 WHERE id = ?2",
@@ -281,7 +284,7 @@ impl TabManager {
 
     /// Set active tab (deactivates others in profile)
     pub fn set_active_tab(&self, tab_id: i64) -> Result<Tab> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         // Get profile_id
@@ -308,7 +311,7 @@ impl TabManager {
 
     /// Get tab history
     pub fn get_tab_history(&self, tab_id: i64) -> Result<Vec<TabHistoryEntry>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, tab_id, url, title, visited_at, position
              FROM tab_history WHERE tab_id = ?1 ORDER BY position ASC"
@@ -330,7 +333,7 @@ impl TabManager {
 
     /// Navigate back in tab history
     pub fn navigate_back(&self, tab_id: i64) -> Result<Option<String>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get current tab's URL
         let current_url: String = conn.query_row(
@@ -364,7 +367,7 @@ impl TabManager {
 
     /// Navigate forward in tab history
     pub fn navigate_forward(&self, tab_id: i64) -> Result<Option<String>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Get current tab's URL
         let current_url: String = conn.query_row(
@@ -411,7 +414,7 @@ impl TabManager {
 
     /// Update scroll position
     pub fn update_scroll_position(&self, tab_id: i64, scroll_position: i32) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.execute(
             "UPDATE tabs SET scroll_position = ?1 WHERE id = ?2",
             params![scroll_position, tab_id],
@@ -421,7 +424,7 @@ impl TabManager {
 
     /// Close all tabs except pinned
     pub fn close_unpinned_tabs(&self, profile_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         conn.execute(
             "DELETE FROM tabs WHERE profile_id = ?1 AND is_pinned = 0",
             params![profile_id],
@@ -431,7 +434,7 @@ impl TabManager {
 
     /// Close tabs to the right of a given tab
     pub fn close_tabs_to_right(&self, tab_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let (profile_id, position): (i64, i32) = conn.query_row(
             "SELECT profile_id, position FROM tabs WHERE id = ?1",
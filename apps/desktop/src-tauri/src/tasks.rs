@@ -0,0 +1,330 @@
+// Background tasks for long-running commands
+// Imports, exports and scraper runs return a task id straight away and keep working
+// on their own thread, reporting progress through TASK_EVENT until they finish.
+// Any running task can be cancelled by id; the work stops at its next checkpoint.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::ai::CancelToken;
+use crate::error::{AppError, AppResult};
+
+pub const TASK_EVENT: &str = "task-progress";
+
+/// Progress events closer together than this are dropped (the final one always goes out)
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskEvent {
+    pub task_id: String,
+    pub kind: String,
+    pub status: TaskStatus,
+    pub done: u64,
+    pub total: Option<u64>,
+    /// What the command would have returned; also set for a cancelled task that got partway
+    pub result: Option<serde_json::Value>,
+    /// The failure as `{ kind, message, details }`, like a command error
+    pub error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskInfo {
+    pub task_id: String,
+    pub kind: String,
+    pub started_at: String,
+    pub done: u64,
+    pub total: Option<u64>,
+}
+
+type Emitter = Arc<dyn Fn(TaskEvent) + Send + Sync>;
+
+/// Handed to the work: check `is_cancelled` between items and report `progress`
+#[derive(Clone)]
+pub struct TaskContext {
+    id: String,
+    kind: String,
+    cancel: CancelToken,
+    emit: Option<Emitter>,
+    state: Arc<Mutex<ProgressState>>,
+}
+
+struct ProgressState {
+    done: u64,
+    total: Option<u64>,
+    last_emit: Option<Instant>,
+}
+
+impl TaskContext {
+    fn new(id: String, kind: &str, emit: Option<Emitter>) -> Self {
+        TaskContext {
+            id,
+            kind: kind.to_string(),
+            cancel: CancelToken::new(),
+            emit,
+            state: Arc::new(Mutex::new(ProgressState { done: 0, total: None, last_emit: None })),
+        }
+    }
+
+    /// For running task-aware work inline: never cancelled, progress goes nowhere
+    pub fn detached(kind: &str) -> Self {
+        Self::new(String::new(), kind, None)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// Resolves once the task is cancelled, for racing against async work
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await
+    }
+
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        let Some(emit) = &self.emit else { return };
+        {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.done = done;
+            state.total = total;
+            let finished = total.is_some_and(|t| done >= t);
+            if !finished && state.last_emit.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            state.last_emit = Some(Instant::now());
+        }
+        emit(self.event(TaskStatus::Running, None, None));
+    }
+
+    fn event(&self, status: TaskStatus, result: Option<serde_json::Value>, error: Option<&AppError>) -> TaskEvent {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        TaskEvent {
+            task_id: self.id.clone(),
+            kind: self.kind.clone(),
+            status,
+            done: state.done,
+            total: state.total,
+            result,
+            error: error.and_then(|e| serde_json::to_value(e).ok()),
+        }
+    }
+}
+
+struct RunningTask {
+    context: TaskContext,
+    started_at: String,
+}
+
+#[derive(Clone, Default)]
+pub struct TaskRegistry {
+    running: Arc<Mutex<HashMap<String, RunningTask>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run blocking work (SQLite, file parsing) on the blocking thread pool.
+    /// Must be called from within the async runtime, as commands are.
+    pub fn spawn_blocking<T, F, E>(&self, kind: &str, emit: E, work: F) -> String
+    where
+        T: Serialize,
+        F: FnOnce(&TaskContext) -> AppResult<T> + Send + 'static,
+        E: Fn(TaskEvent) + Send + Sync + 'static,
+    {
+        let context = self.start(kind, Arc::new(emit));
+        let registry = self.clone();
+        let id = context.id.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = work(&context);
+            registry.finish(&context, result);
+        });
+        id
+    }
+
+    /// Run async work (network) as a task
+    pub fn spawn<T, F, Fut, E>(&self, kind: &str, emit: E, work: F) -> String
+    where
+        T: Serialize,
+        F: FnOnce(TaskContext) -> Fut,
+        Fut: Future<Output = AppResult<T>> + Send + 'static,
+        E: Fn(TaskEvent) + Send + Sync + 'static,
+    {
+        let context = self.start(kind, Arc::new(emit));
+        let registry = self.clone();
+        let id = context.id.clone();
+        let future = work(context.clone());
+        tokio::spawn(async move {
+            let result = future.await;
+            registry.finish(&context, result);
+        });
+        id
+    }
+
+    /// Returns false if no task with that id is running
+    pub fn cancel(&self, task_id: &str) -> bool {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        match running.get(task_id) {
+            Some(task) => {
+                task.context.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let mut tasks: Vec<TaskInfo> = running
+            .values()
+            .map(|task| {
+                let state = task.context.state.lock().unwrap_or_else(|e| e.into_inner());
+                TaskInfo {
+                    task_id: task.context.id.clone(),
+                    kind: task.context.kind.clone(),
+                    started_at: task.started_at.clone(),
+                    done: state.done,
+                    total: state.total,
+                }
+            })
+            .collect();
+        tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+        tasks
+    }
+
+    fn start(&self, kind: &str, emit: Emitter) -> TaskContext {
+        let id = format!("{}-{}", kind, self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        let context = TaskContext::new(id.clone(), kind, Some(emit));
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).insert(
+            id,
+            RunningTask { context: context.clone(), started_at: chrono_now() },
+        );
+        context
+    }
+
+    fn finish<T: Serialize>(&self, context: &TaskContext, result: AppResult<T>) {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&context.id);
+
+        let event = match result {
+            Ok(value) => {
+                let status = if context.is_cancelled() { TaskStatus::Cancelled } else { TaskStatus::Completed };
+                match serde_json::to_value(value) {
+                    Ok(value) => context.event(status, Some(value), None),
+                    Err(e) => context.event(TaskStatus::Failed, None, Some(&AppError::from(e))),
+                }
+            }
+            Err(_) if context.is_cancelled() => context.event(TaskStatus::Cancelled, None, None),
+            Err(e) => context.event(TaskStatus::Failed, None, Some(&e)),
+        };
+        if let Some(emit) = &context.emit {
+            emit(event);
+        }
+    }
+}
+
+fn chrono_now() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let duration = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}", duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn collector() -> (impl Fn(TaskEvent) + Send + Sync + 'static, mpsc::UnboundedReceiver<TaskEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (move |event| { let _ = tx.send(event); }, rx)
+    }
+
+    async fn final_event(rx: &mut mpsc::UnboundedReceiver<TaskEvent>) -> TaskEvent {
+        loop {
+            let event = rx.recv().await.expect("task finished without a final event");
+            if event.status != TaskStatus::Running {
+                return event;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocking_task_reports_progress_and_result() {
+        let registry = TaskRegistry::new();
+        let (emit, mut rx) = collector();
+
+        let id = registry.spawn_blocking("import", emit, |task| {
+            for i in 1..=3 {
+                task.progress(i, Some(3));
+            }
+            Ok(3)
+        });
+
+        let done = final_event(&mut rx).await;
+        assert_eq!(done.task_id, id);
+        assert_eq!(done.status, TaskStatus::Completed);
+        assert_eq!(done.done, 3);
+        assert_eq!(done.result, Some(serde_json::json!(3)));
+        assert!(registry.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_work_and_keeps_partial_result() {
+        let registry = TaskRegistry::new();
+        let (emit, mut rx) = collector();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+
+        let id = registry.spawn_blocking("import", emit, move |task| {
+            let mut imported = 0;
+            started_tx.send(()).unwrap();
+            while !task.is_cancelled() {
+                imported += 1;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Ok(imported)
+        });
+
+        started_rx.recv().unwrap();
+        assert_eq!(registry.list().len(), 1);
+        assert!(registry.cancel(&id));
+
+        let done = final_event(&mut rx).await;
+        assert_eq!(done.status, TaskStatus::Cancelled);
+        assert!(done.result.is_some());
+        assert!(!registry.cancel(&id));
+    }
+
+    #[tokio::test]
+    async fn test_async_task_failure() {
+        let registry = TaskRegistry::new();
+        let (emit, mut rx) = collector();
+
+        registry.spawn("scrape", emit, |_task| async {
+            Err::<(), _>(AppError::Network("unreachable".to_string()))
+        });
+
+        let done = final_event(&mut rx).await;
+        assert_eq!(done.status, TaskStatus::Failed);
+        let error = done.error.unwrap();
+        assert_eq!(error["kind"], "network");
+        assert_eq!(error["message"], "network error: unreachable");
+    }
+}
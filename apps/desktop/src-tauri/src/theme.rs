@@ -1,9 +1,11 @@
 // Theme management for EarthServers Local
 // Handles per-profile theme customization and preset themes
 
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Result, params};
 use serde::{Deserialize, Serialize};
 
+use crate::db::DbPool;
+
 /// Theme configuration stored in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Theme {
@@ -164,68 +166,19 @@ pub fn get_preset_themes() -> Vec<PresetTheme> {
     ]
 }
 
+#[derive(Clone)]
 pub struct ThemeManager {
-    db_path: String,
+    db: DbPool,
 }
 
 impl ThemeManager {
-    pub fn new(db_path: String) -> Self {
-        ThemeManager { db_path }
-    }
-
-    /// Initialize theme table
-    pub fn init(&self) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS themes (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                profile_id INTEGER NOT NULL,
-                name TEXT NOT NULL,
-                is_active INTEGER NOT NULL DEFAULT 0,
-                base_preset TEXT DEFAULT 'earthservers-default',
-                primary_color TEXT NOT NULL DEFAULT '#0fab89',
-                secondary_color TEXT NOT NULL DEFAULT '#e91e63',
-                accent_color TEXT NOT NULL DEFAULT '#0178C6',
-                text_color TEXT NOT NULL DEFAULT '#f0f0f0',
-                background_color TEXT NOT NULL DEFAULT '#0a0a0f',
-                background_gradient_enabled INTEGER NOT NULL DEFAULT 1,
-                background_gradient_angle INTEGER NOT NULL DEFAULT 135,
-                background_gradient_from TEXT DEFAULT '#0a0a0f',
-                background_gradient_to TEXT DEFAULT '#1a1a2e',
-                card_bg_color TEXT NOT NULL DEFAULT '#1a1a2e',
-                card_opacity INTEGER NOT NULL DEFAULT 80,
-                card_gradient_enabled INTEGER NOT NULL DEFAULT 0,
-                card_gradient_color1 TEXT DEFAULT '#1a1a2e',
-                card_gradient_color2 TEXT DEFAULT '#2a2a3e',
-                navbar_color TEXT DEFAULT '#0a0a0f',
-                navbar_opacity INTEGER NOT NULL DEFAULT 90,
-                custom_css TEXT,
-                extra_settings TEXT,
-                created_at TEXT NOT NULL,
-                updated_at TEXT,
-                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
-                UNIQUE(profile_id, name)
-            )",
-            [],
-        )?;
-
-        // Create indexes
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_themes_profile ON themes(profile_id)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_themes_active ON themes(profile_id, is_active)",
-            [],
-        )?;
-
-        Ok(())
+    pub fn new(db: DbPool) -> Self {
+        ThemeManager { db }
     }
 
     /// Ensure a profile has at least one theme
     pub fn ensure_default_theme(&self, profile_id: i64) -> Result<()> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         let count: i64 = conn.query_row(
             "SELECT COUNT(*) FROM themes WHERE profile_id = ?1",
@@ -246,7 +199,7 @@ impl ThemeManager {
 
     /// Get all themes for a profile
     pub fn get_themes(&self, profile_id: i64) -> Result<Vec<Theme>> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, name, is_active, base_preset,
                     primary_color, secondary_color, accent_color, text_color,
@@ -296,7 +249,7 @@ impl ThemeManager {
     pub fn get_active_theme(&self, profile_id: i64) -> Result<Option<Theme>> {
         self.ensure_default_theme(profile_id)?;
 
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, name, is_active, base_preset,
                     primary_color, secondary_color, accent_color, text_color,
@@ -348,7 +301,7 @@ impl ThemeManager {
 
     /// Save/update a theme
     pub fn save_theme(&self, theme: &Theme) -> Result<Theme> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let now = chrono_now();
 
         if let Some(id) = theme.id {
@@ -414,7 +367,7 @@ impl ThemeManager {
 
     /// Set active theme for a profile
     pub fn set_active_theme(&self, profile_id: i64, theme_id: i64) -> Result<Theme> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Deactivate all themes for this profile
         conn.execute(
@@ -435,7 +388,7 @@ impl ThemeManager {
 
     /// Delete a theme
     pub fn delete_theme(&self, theme_id: i64, profile_id: i64) -> Result<bool> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
 
         // Check if this is the only theme
         let count: i64 = conn.query_row(
@@ -499,7 +452,7 @@ impl ThemeManager {
 
     /// Export theme as JSON
     pub fn export_theme(&self, theme_id: i64) -> Result<String> {
-        let conn = Connection::open(&self.db_path)?;
+        let conn = self.db.get()?;
         let mut stmt = conn.prepare(
             "SELECT id, profile_id, name, is_active, base_preset,
                     primary_color, secondary_color, accent_color, text_color,
@@ -3,13 +3,15 @@
 
 use tauri::Window;
 
+use crate::error::AppResult;
+
 /// Navigate to a URL in the webview
 #[tauri::command]
 pub async fn webview_navigate(
     _window: Window,
     tab_id: i64,
     url: String,
-) -> Result<(), String> {
+) -> AppResult<()> {
     // In Tauri 1.x, webview management is different from 2.x
     // For now, this is a stub that the frontend handles via iframe
     println!("webview_navigate: tab={}, url={}", tab_id, url);
@@ -21,7 +23,7 @@ pub async fn webview_navigate(
 pub async fn webview_go_back(
     _window: Window,
     tab_id: i64,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("webview_go_back: tab={}", tab_id);
     Ok(())
 }
@@ -31,7 +33,7 @@ pub async fn webview_go_back(
 pub async fn webview_go_forward(
     _window: Window,
     tab_id: i64,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("webview_go_forward: tab={}", tab_id);
     Ok(())
 }
@@ -41,7 +43,7 @@ pub async fn webview_go_forward(
 pub async fn webview_reload(
     _window: Window,
     tab_id: i64,
-) -> Result<(), String> {
+) -> AppResult<()> {
     println!("webview_reload: tab={}", tab_id);
     Ok(())
 }
@@ -51,7 +53,7 @@ pub async fn webview_reload(
 pub async fn webview_get_html(
     _window: Window,
     tab_id: i64,
-) -> Result<String, String> {
+) -> AppResult<String> {
     // This would extract HTML from the webview
     // For now, return placeholder
     println!("webview_get_html: tab={}", tab_id);
@@ -63,7 +65,7 @@ pub async fn webview_get_html(
 pub async fn webview_get_url(
     _window: Window,
     tab_id: i64,
-) -> Result<String, String> {
+) -> AppResult<String> {
     println!("webview_get_url: tab={}", tab_id);
     Ok(String::new())
 }
@@ -73,7 +75,7 @@ pub async fn webview_get_url(
 pub async fn webview_get_title(
     _window: Window,
     tab_id: i64,
-) -> Result<String, String> {
+) -> AppResult<String> {
     println!("webview_get_title: tab={}", tab_id);
     Ok(String::new())
 }
@@ -84,7 +86,7 @@ pub async fn webview_execute_js(
     _window: Window,
     tab_id: i64,
     script: String,
-) -> Result<String, String> {
+) -> AppResult<String> {
     println!("webview_execute_js: tab={}, script_len={}", tab_id, script.len());
     Ok(String::new())
 }
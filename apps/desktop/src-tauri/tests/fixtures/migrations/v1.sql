@@ -0,0 +1,332 @@
+PRAGMA foreign_keys=OFF;
+BEGIN TRANSACTION;
+CREATE TABLE scraping_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            url_pattern TEXT,
+            max_depth INTEGER DEFAULT 2,
+            max_pages INTEGER DEFAULT 100,
+            content_selectors TEXT,
+            schedule_cron TEXT,
+            status TEXT DEFAULT 'pending',
+            last_run_at TEXT,
+            pages_scraped INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+CREATE TABLE scraped_pages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            title TEXT,
+            content TEXT,
+            metadata TEXT,
+            scraped_at TEXT NOT NULL,
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE,
+            UNIQUE(job_id, url)
+        );
+CREATE TABLE profiles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                icon TEXT,
+                created_at TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 0
+            );
+INSERT INTO profiles VALUES(1,'Default','user','1792199612',1);
+CREATE TABLE privacy_settings (
+                profile_id INTEGER PRIMARY KEY,
+                auto_delete_days INTEGER,
+                ai_enabled_in_incognito INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+INSERT INTO privacy_settings VALUES(1,NULL,0);
+CREATE TABLE pages (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                content TEXT,
+                visited_at TEXT NOT NULL,
+                embedding BLOB,
+                profile_id INTEGER,
+                UNIQUE(url, profile_id)
+            );
+INSERT INTO pages VALUES(1,'https://history.example.com','History','seen in the browser','10',NULL,1);
+CREATE TABLE notes (
+                id INTEGER PRIMARY KEY,
+                page_id INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+            );
+INSERT INTO notes VALUES(1,1,'history note','11');
+CREATE TABLE themes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 0,
+                base_preset TEXT DEFAULT 'earthservers-default',
+                primary_color TEXT NOT NULL DEFAULT '#0fab89',
+                secondary_color TEXT NOT NULL DEFAULT '#e91e63',
+                accent_color TEXT NOT NULL DEFAULT '#0178C6',
+                text_color TEXT NOT NULL DEFAULT '#f0f0f0',
+                background_color TEXT NOT NULL DEFAULT '#0a0a0f',
+                background_gradient_enabled INTEGER NOT NULL DEFAULT 1,
+                background_gradient_angle INTEGER NOT NULL DEFAULT 135,
+                background_gradient_from TEXT DEFAULT '#0a0a0f',
+                background_gradient_to TEXT DEFAULT '#1a1a2e',
+                card_bg_color TEXT NOT NULL DEFAULT '#1a1a2e',
+                card_opacity INTEGER NOT NULL DEFAULT 80,
+                card_gradient_enabled INTEGER NOT NULL DEFAULT 0,
+                card_gradient_color1 TEXT DEFAULT '#1a1a2e',
+                card_gradient_color2 TEXT DEFAULT '#2a2a3e',
+                navbar_color TEXT DEFAULT '#0a0a0f',
+                navbar_opacity INTEGER NOT NULL DEFAULT 90,
+                custom_css TEXT,
+                extra_settings TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                UNIQUE(profile_id, name)
+            );
+CREATE TABLE domains (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL,
+                category TEXT NOT NULL,
+                trust_score REAL NOT NULL DEFAULT 0.5,
+                added_date TEXT NOT NULL,
+                metadata TEXT,
+                profile_id INTEGER,
+                UNIQUE(url, profile_id),
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE domain_lists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                description TEXT,
+                author TEXT,
+                version TEXT DEFAULT '1.0',
+                created_at TEXT NOT NULL,
+                profile_id INTEGER,
+                UNIQUE(name, profile_id),
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE list_domains (
+                list_id INTEGER NOT NULL,
+                domain_id INTEGER NOT NULL,
+                PRIMARY KEY (list_id, domain_id),
+                FOREIGN KEY (list_id) REFERENCES domain_lists(id) ON DELETE CASCADE,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+            );
+CREATE TABLE domain_ratings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain_id INTEGER NOT NULL,
+                user_id TEXT NOT NULL,
+                trust_rating INTEGER NOT NULL CHECK (trust_rating BETWEEN 1 AND 5),
+                bias_rating INTEGER NOT NULL CHECK (bias_rating BETWEEN 1 AND 4),
+                review_text TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                helpful_count INTEGER DEFAULT 0,
+                reported BOOLEAN DEFAULT FALSE,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+                UNIQUE(domain_id, user_id)
+            );
+CREATE TABLE domain_rating_aggregates (
+                domain_id INTEGER PRIMARY KEY,
+                avg_trust REAL NOT NULL DEFAULT 3.0,
+                avg_bias REAL NOT NULL DEFAULT 2.5,
+                total_ratings INTEGER NOT NULL DEFAULT 0,
+                trust_distribution TEXT,
+                bias_distribution TEXT,
+                last_updated TEXT,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+            );
+CREATE TABLE subdomain_ratings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                parent_domain_id INTEGER NOT NULL,
+                subdomain TEXT NOT NULL,
+                avg_trust REAL NOT NULL DEFAULT 3.0,
+                avg_bias REAL NOT NULL DEFAULT 2.5,
+                total_ratings INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (parent_domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+                UNIQUE(parent_domain_id, subdomain)
+            );
+CREATE TABLE rating_categories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain_rating_id INTEGER NOT NULL,
+                category TEXT NOT NULL,
+                score INTEGER NOT NULL CHECK (score BETWEEN 1 AND 5),
+                FOREIGN KEY (domain_rating_id) REFERENCES domain_ratings(id) ON DELETE CASCADE
+            );
+CREATE TABLE tabs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                title TEXT,
+                url TEXT NOT NULL,
+                favicon TEXT,
+                position INTEGER NOT NULL,
+                is_pinned INTEGER DEFAULT 0,
+                is_active INTEGER DEFAULT 0,
+                scroll_position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                last_accessed TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE tab_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tab_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                title TEXT,
+                visited_at TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
+            );
+CREATE TABLE bookmark_folders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                parent_id INTEGER,
+                position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (parent_id) REFERENCES bookmark_folders(id) ON DELETE CASCADE
+            );
+CREATE TABLE bookmarks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                url TEXT NOT NULL,
+                favicon TEXT,
+                folder_id INTEGER,
+                tags TEXT,
+                notes TEXT,
+                position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (folder_id) REFERENCES bookmark_folders(id) ON DELETE SET NULL
+            );
+CREATE TABLE split_view_config (
+                profile_id INTEGER PRIMARY KEY,
+                layout TEXT NOT NULL DEFAULT 'single',
+                pane_1_tab_id INTEGER,
+                pane_2_tab_id INTEGER,
+                pane_3_tab_id INTEGER,
+                pane_4_tab_id INTEGER,
+                active_pane INTEGER DEFAULT 1,
+                pane_sizes TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (pane_1_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_2_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_3_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_4_tab_id) REFERENCES tabs(id) ON DELETE SET NULL
+            );
+CREATE TABLE multimedia_privacy (
+                profile_id INTEGER PRIMARY KEY,
+                history_enabled INTEGER DEFAULT 0,
+                playlist_history_enabled INTEGER DEFAULT 0,
+                require_password INTEGER DEFAULT 0,
+                require_otp INTEGER DEFAULT 0,
+                password_hash TEXT,
+                otp_secret TEXT,
+                auto_clear_history_days INTEGER,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                media_id INTEGER,
+                source TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                title TEXT,
+                thumbnail TEXT,
+                position INTEGER DEFAULT 0,
+                duration INTEGER,
+                played_at TEXT NOT NULL,
+                encrypted INTEGER DEFAULT 0,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_playlists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                thumbnail TEXT,
+                is_encrypted INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_playlist_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                playlist_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                title TEXT,
+                thumbnail TEXT,
+                position INTEGER NOT NULL,
+                added_at TEXT NOT NULL,
+                FOREIGN KEY (playlist_id) REFERENCES multimedia_playlists(id) ON DELETE CASCADE
+            );
+CREATE TABLE indexed_pages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                content TEXT,
+                summary TEXT,
+                indexed_at TEXT NOT NULL,
+                last_visited TEXT NOT NULL,
+                visit_count INTEGER NOT NULL DEFAULT 1,
+                is_favorite INTEGER NOT NULL DEFAULT 0,
+                tags TEXT,
+                profile_id INTEGER,
+                UNIQUE(url, profile_id),
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+INSERT INTO indexed_pages VALUES(5,'https://memory.example.com','Memory','saved for later',NULL,'20','20',1,0,'reading',1);
+CREATE TABLE page_notes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                page_id INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                profile_id INTEGER,
+                FOREIGN KEY (page_id) REFERENCES indexed_pages(id) ON DELETE CASCADE,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+INSERT INTO page_notes VALUES(1,5,'memory note','21','21',1);
+PRAGMA writable_schema=ON;
+CREATE TABLE IF NOT EXISTS sqlite_sequence(name,seq);
+DELETE FROM sqlite_sequence;
+INSERT INTO sqlite_sequence VALUES('profiles',1);
+INSERT INTO sqlite_sequence VALUES('indexed_pages',5);
+INSERT INTO sqlite_sequence VALUES('page_notes',1);
+CREATE INDEX idx_scraped_pages_job ON scraped_pages(job_id);
+CREATE INDEX idx_scraped_pages_url ON scraped_pages(url);
+CREATE INDEX idx_pages_url ON pages(url);
+CREATE INDEX idx_pages_profile ON pages(profile_id);
+CREATE INDEX idx_pages_visited ON pages(visited_at);
+CREATE INDEX idx_themes_profile ON themes(profile_id);
+CREATE INDEX idx_themes_active ON themes(profile_id, is_active);
+CREATE INDEX idx_domains_url ON domains(url);
+CREATE INDEX idx_domains_category ON domains(category);
+CREATE INDEX idx_domains_profile ON domains(profile_id);
+CREATE INDEX idx_domain_lists_profile ON domain_lists(profile_id);
+CREATE INDEX idx_domain_ratings_domain ON domain_ratings(domain_id);
+CREATE INDEX idx_domain_ratings_user ON domain_ratings(user_id);
+CREATE INDEX idx_subdomain_ratings_parent ON subdomain_ratings(parent_domain_id);
+CREATE INDEX idx_tabs_profile ON tabs(profile_id);
+CREATE INDEX idx_tabs_position ON tabs(profile_id, position);
+CREATE INDEX idx_tab_history_tab ON tab_history(tab_id);
+CREATE INDEX idx_bookmarks_profile ON bookmarks(profile_id);
+CREATE INDEX idx_bookmarks_folder ON bookmarks(folder_id);
+CREATE INDEX idx_bookmark_folders_profile ON bookmark_folders(profile_id);
+CREATE INDEX idx_multimedia_history_profile ON multimedia_history(profile_id);
+CREATE INDEX idx_multimedia_history_played ON multimedia_history(played_at DESC);
+CREATE INDEX idx_multimedia_playlists_profile ON multimedia_playlists(profile_id);
+CREATE INDEX idx_multimedia_playlist_items_playlist ON multimedia_playlist_items(playlist_id);
+CREATE INDEX idx_pages_favorite ON indexed_pages(is_favorite);
+CREATE INDEX idx_notes_page ON page_notes(page_id);
+PRAGMA writable_schema=OFF;
+COMMIT;
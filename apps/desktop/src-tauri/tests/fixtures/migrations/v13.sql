@@ -0,0 +1,514 @@
+/* WARNING: Script requires that SQLITE_DBCONFIG_DEFENSIVE be disabled */
+PRAGMA foreign_keys=OFF;
+BEGIN TRANSACTION;
+CREATE TABLE scraping_jobs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            url_pattern TEXT,
+            max_depth INTEGER DEFAULT 2,
+            max_pages INTEGER DEFAULT 100,
+            content_selectors TEXT,
+            schedule_cron TEXT,
+            status TEXT DEFAULT 'pending',
+            last_run_at TEXT,
+            pages_scraped INTEGER DEFAULT 0,
+            created_at TEXT NOT NULL
+        , settings TEXT NOT NULL DEFAULT '{}');
+CREATE TABLE scraped_pages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            title TEXT,
+            content TEXT,
+            metadata TEXT,
+            scraped_at TEXT NOT NULL,
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE,
+            UNIQUE(job_id, url)
+        );
+CREATE TABLE profiles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                icon TEXT,
+                created_at TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 0
+            );
+INSERT INTO profiles VALUES(1,'Default','user','1792199612',1);
+CREATE TABLE privacy_settings (
+                profile_id INTEGER PRIMARY KEY,
+                auto_delete_days INTEGER,
+                ai_enabled_in_incognito INTEGER NOT NULL DEFAULT 0, ai_summaries_enabled INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+INSERT INTO privacy_settings VALUES(1,NULL,0,0);
+CREATE TABLE pages (
+                id INTEGER PRIMARY KEY,
+                url TEXT NOT NULL,
+                title TEXT NOT NULL,
+                content TEXT,
+                visited_at TEXT NOT NULL,
+                embedding BLOB,
+                profile_id INTEGER, summary TEXT, tags TEXT, indexed_at TEXT, visit_count INTEGER NOT NULL DEFAULT 1, is_favorite INTEGER NOT NULL DEFAULT 0,
+                UNIQUE(url, profile_id)
+            );
+INSERT INTO pages VALUES(1,'https://history.example.com','History','seen in the browser','10',NULL,1,NULL,NULL,'10',1,0);
+INSERT INTO pages VALUES(2,'https://memory.example.com','Memory','saved for later','20',NULL,1,NULL,'reading','20',1,0);
+CREATE TABLE themes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                is_active INTEGER NOT NULL DEFAULT 0,
+                base_preset TEXT DEFAULT 'earthservers-default',
+                primary_color TEXT NOT NULL DEFAULT '#0fab89',
+                secondary_color TEXT NOT NULL DEFAULT '#e91e63',
+                accent_color TEXT NOT NULL DEFAULT '#0178C6',
+                text_color TEXT NOT NULL DEFAULT '#f0f0f0',
+                background_color TEXT NOT NULL DEFAULT '#0a0a0f',
+                background_gradient_enabled INTEGER NOT NULL DEFAULT 1,
+                background_gradient_angle INTEGER NOT NULL DEFAULT 135,
+                background_gradient_from TEXT DEFAULT '#0a0a0f',
+                background_gradient_to TEXT DEFAULT '#1a1a2e',
+                card_bg_color TEXT NOT NULL DEFAULT '#1a1a2e',
+                card_opacity INTEGER NOT NULL DEFAULT 80,
+                card_gradient_enabled INTEGER NOT NULL DEFAULT 0,
+                card_gradient_color1 TEXT DEFAULT '#1a1a2e',
+                card_gradient_color2 TEXT DEFAULT '#2a2a3e',
+                navbar_color TEXT DEFAULT '#0a0a0f',
+                navbar_opacity INTEGER NOT NULL DEFAULT 90,
+                custom_css TEXT,
+                extra_settings TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                UNIQUE(profile_id, name)
+            );
+CREATE TABLE domains (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL,
+                category TEXT NOT NULL,
+                trust_score REAL NOT NULL DEFAULT 0.5,
+                added_date TEXT NOT NULL,
+                metadata TEXT,
+                profile_id INTEGER,
+                UNIQUE(url, profile_id),
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE domain_lists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                description TEXT,
+                author TEXT,
+                version TEXT DEFAULT '1.0',
+                created_at TEXT NOT NULL,
+                profile_id INTEGER,
+                UNIQUE(name, profile_id),
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE list_domains (
+                list_id INTEGER NOT NULL,
+                domain_id INTEGER NOT NULL,
+                PRIMARY KEY (list_id, domain_id),
+                FOREIGN KEY (list_id) REFERENCES domain_lists(id) ON DELETE CASCADE,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+            );
+CREATE TABLE domain_ratings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain_id INTEGER NOT NULL,
+                user_id TEXT NOT NULL,
+                trust_rating INTEGER NOT NULL CHECK (trust_rating BETWEEN 1 AND 5),
+                bias_rating INTEGER NOT NULL CHECK (bias_rating BETWEEN 1 AND 4),
+                review_text TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                helpful_count INTEGER DEFAULT 0,
+                reported BOOLEAN DEFAULT FALSE,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+                UNIQUE(domain_id, user_id)
+            );
+CREATE TABLE domain_rating_aggregates (
+                domain_id INTEGER PRIMARY KEY,
+                avg_trust REAL NOT NULL DEFAULT 3.0,
+                avg_bias REAL NOT NULL DEFAULT 2.5,
+                total_ratings INTEGER NOT NULL DEFAULT 0,
+                trust_distribution TEXT,
+                bias_distribution TEXT,
+                last_updated TEXT,
+                FOREIGN KEY (domain_id) REFERENCES domains(id) ON DELETE CASCADE
+            );
+CREATE TABLE subdomain_ratings (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                parent_domain_id INTEGER NOT NULL,
+                subdomain TEXT NOT NULL,
+                avg_trust REAL NOT NULL DEFAULT 3.0,
+                avg_bias REAL NOT NULL DEFAULT 2.5,
+                total_ratings INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (parent_domain_id) REFERENCES domains(id) ON DELETE CASCADE,
+                UNIQUE(parent_domain_id, subdomain)
+            );
+CREATE TABLE rating_categories (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                domain_rating_id INTEGER NOT NULL,
+                category TEXT NOT NULL,
+                score INTEGER NOT NULL CHECK (score BETWEEN 1 AND 5),
+                FOREIGN KEY (domain_rating_id) REFERENCES domain_ratings(id) ON DELETE CASCADE
+            );
+CREATE TABLE tabs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                title TEXT,
+                url TEXT NOT NULL,
+                favicon TEXT,
+                position INTEGER NOT NULL,
+                is_pinned INTEGER DEFAULT 0,
+                is_active INTEGER DEFAULT 0,
+                scroll_position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                last_accessed TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE tab_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                tab_id INTEGER NOT NULL,
+                url TEXT NOT NULL,
+                title TEXT,
+                visited_at TEXT NOT NULL,
+                position INTEGER NOT NULL,
+                FOREIGN KEY (tab_id) REFERENCES tabs(id) ON DELETE CASCADE
+            );
+CREATE TABLE bookmark_folders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                parent_id INTEGER,
+                position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (parent_id) REFERENCES bookmark_folders(id) ON DELETE CASCADE
+            );
+CREATE TABLE bookmarks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                url TEXT NOT NULL,
+                favicon TEXT,
+                folder_id INTEGER,
+                tags TEXT,
+                notes TEXT,
+                position INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (folder_id) REFERENCES bookmark_folders(id) ON DELETE SET NULL
+            );
+CREATE TABLE split_view_config (
+                profile_id INTEGER PRIMARY KEY,
+                layout TEXT NOT NULL DEFAULT 'single',
+                pane_1_tab_id INTEGER,
+                pane_2_tab_id INTEGER,
+                pane_3_tab_id INTEGER,
+                pane_4_tab_id INTEGER,
+                active_pane INTEGER DEFAULT 1,
+                pane_sizes TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE,
+                FOREIGN KEY (pane_1_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_2_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_3_tab_id) REFERENCES tabs(id) ON DELETE SET NULL,
+                FOREIGN KEY (pane_4_tab_id) REFERENCES tabs(id) ON DELETE SET NULL
+            );
+CREATE TABLE multimedia_privacy (
+                profile_id INTEGER PRIMARY KEY,
+                history_enabled INTEGER DEFAULT 0,
+                playlist_history_enabled INTEGER DEFAULT 0,
+                require_password INTEGER DEFAULT 0,
+                require_otp INTEGER DEFAULT 0,
+                password_hash TEXT,
+                otp_secret TEXT,
+                auto_clear_history_days INTEGER, key_params TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                media_id INTEGER,
+                source TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                title TEXT,
+                thumbnail TEXT,
+                position INTEGER DEFAULT 0,
+                duration INTEGER,
+                played_at TEXT NOT NULL,
+                encrypted INTEGER DEFAULT 0,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_playlists (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                thumbnail TEXT,
+                is_encrypted INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL,
+                updated_at TEXT,
+                FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+            );
+CREATE TABLE multimedia_playlist_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                playlist_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                title TEXT,
+                thumbnail TEXT,
+                position INTEGER NOT NULL,
+                added_at TEXT NOT NULL, encrypted INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (playlist_id) REFERENCES multimedia_playlists(id) ON DELETE CASCADE
+            );
+CREATE TABLE embedding_chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                source_id INTEGER NOT NULL,
+                profile_id INTEGER,
+                chunk_index INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                created_at TEXT NOT NULL,
+                UNIQUE(source, source_id, chunk_index)
+            );
+PRAGMA writable_schema=ON;
+INSERT INTO sqlite_schema(type,name,tbl_name,rootpage,sql)VALUES('table','page_notes_fts','page_notes_fts',0,'CREATE VIRTUAL TABLE page_notes_fts USING fts5(
+                content,
+                content=''page_notes'', content_rowid=''id'',
+                tokenize=''unicode61 remove_diacritics 2'', prefix=''2 3''
+            )');
+CREATE TABLE IF NOT EXISTS 'page_notes_fts_data'(id INTEGER PRIMARY KEY, block BLOB);
+INSERT INTO page_notes_fts_data VALUES(1,X'0204');
+INSERT INTO page_notes_fts_data VALUES(10,X'000000000101010001010101');
+INSERT INTO page_notes_fts_data VALUES(137438953473,X'0000005c0830686973746f727902020201066d656d6f727901020201046e6f7465010203010203000331686902020201026d6501020201026e6f01020301020300043268697302020201036d656d01020201036e6f74010203010203040c0b0c08070a0908');
+CREATE TABLE IF NOT EXISTS 'page_notes_fts_idx'(segid, term, pgno, PRIMARY KEY(segid, term)) WITHOUT ROWID;
+INSERT INTO page_notes_fts_idx VALUES(1,X'',2);
+CREATE TABLE IF NOT EXISTS 'page_notes_fts_docsize'(id INTEGER PRIMARY KEY, sz BLOB);
+INSERT INTO page_notes_fts_docsize VALUES(1,X'02');
+INSERT INTO page_notes_fts_docsize VALUES(2,X'02');
+CREATE TABLE IF NOT EXISTS 'page_notes_fts_config'(k PRIMARY KEY, v) WITHOUT ROWID;
+INSERT INTO page_notes_fts_config VALUES('version',4);
+CREATE TABLE edges (
+                id INTEGER PRIMARY KEY,
+                source_id INTEGER NOT NULL,
+                target_id INTEGER NOT NULL,
+                relation TEXT NOT NULL,
+                weight REAL NOT NULL DEFAULT 1.0,
+                profile_id INTEGER,
+                created_at TEXT NOT NULL,
+                UNIQUE(source_id, target_id, relation),
+                FOREIGN KEY (source_id) REFERENCES pages(id) ON DELETE CASCADE,
+                FOREIGN KEY (target_id) REFERENCES pages(id) ON DELETE CASCADE
+            );
+CREATE TABLE page_links (
+                source_id INTEGER NOT NULL,
+                target_url TEXT NOT NULL,
+                PRIMARY KEY (source_id, target_url),
+                FOREIGN KEY (source_id) REFERENCES pages(id) ON DELETE CASCADE
+            );
+CREATE TABLE entities (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                profile_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                name TEXT NOT NULL,
+                normalized TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                UNIQUE(profile_id, kind, normalized)
+            );
+CREATE TABLE entity_mentions (
+                entity_id INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                source_id INTEGER NOT NULL,
+                count INTEGER NOT NULL DEFAULT 1,
+                PRIMARY KEY (entity_id, source, source_id),
+                FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE
+            );
+CREATE TABLE entity_aliases (
+                profile_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                normalized TEXT NOT NULL,
+                entity_id INTEGER NOT NULL,
+                PRIMARY KEY (profile_id, kind, normalized),
+                FOREIGN KEY (entity_id) REFERENCES entities(id) ON DELETE CASCADE
+            );
+CREATE TABLE entity_extractions (
+                source TEXT NOT NULL,
+                source_id INTEGER NOT NULL,
+                content_hash TEXT NOT NULL,
+                extracted_at TEXT NOT NULL,
+                PRIMARY KEY (source, source_id)
+            );
+CREATE TABLE IF NOT EXISTS "page_notes" (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    page_id INTEGER NOT NULL,
+                    content TEXT NOT NULL,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL,
+                    profile_id INTEGER,
+                    FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE,
+                    FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+                 );
+INSERT INTO page_notes VALUES(1,2,'memory note','21','21',1);
+INSERT INTO page_notes VALUES(2,1,'history note','11','11',1);
+CREATE TABLE IF NOT EXISTS "page_summaries" (
+                    page_id INTEGER PRIMARY KEY,
+                    summary TEXT NOT NULL,
+                    suggested_tags TEXT,
+                    content_hash TEXT NOT NULL,
+                    model TEXT NOT NULL,
+                    generated_at TEXT NOT NULL,
+                    FOREIGN KEY (page_id) REFERENCES pages(id) ON DELETE CASCADE
+                 );
+INSERT INTO sqlite_schema(type,name,tbl_name,rootpage,sql)VALUES('table','pages_fts','pages_fts',0,'CREATE VIRTUAL TABLE pages_fts USING fts5(
+            title, content, summary, tags, url,
+            content=''pages'', content_rowid=''id'',
+            tokenize=''unicode61 remove_diacritics 2'', prefix=''2 3''
+        )');
+CREATE TABLE IF NOT EXISTS 'pages_fts_data'(id INTEGER PRIMARY KEY, block BLOB);
+INSERT INTO pages_fts_data VALUES(1,X'020207000108');
+INSERT INTO pages_fts_data VALUES(10,X'000000000101010001010101');
+INSERT INTO pages_fts_data VALUES(137438953473,X'000001bb083062726f7773657201060101050103636f6d0106010405010601040501076578616d706c65010601040401060104040103666f7202060101030107686973746f7279010802010403020474747073010601040201060104020102696e010601010301056c61746572020601010401066d656d6f7279020802010403010772656164696e670206010302010573617665640206010102020365656e010601010201037468650106010104000331627201060101050102636f0106010405010601040501026578010601040401060104040102666f020601010301026869010802010403020174010601040201060104020102696e010601010301026c61020601010401026d65020802010403010272650206010302010273610206010102020165010601010201027468010601010400043262726f01060101050103636f6d010601040501060104050103657861010601040401060104040103666f7202060101030103686973010802010403020274740106010402010601040201036c6174020601010401036d656d020802010403010372656102060103020103736176020601010202026565010601010201037468650106010104040e0f130a0f10090c0e0e0c0a0a0a0e0e090a0d09090a090908090b0f0f0a0b0e0a0b0a0a09');
+CREATE TABLE IF NOT EXISTS 'pages_fts_idx'(segid, term, pgno, PRIMARY KEY(segid, term)) WITHOUT ROWID;
+INSERT INTO pages_fts_idx VALUES(1,X'',2);
+CREATE TABLE IF NOT EXISTS 'pages_fts_docsize'(id INTEGER PRIMARY KEY, sz BLOB);
+INSERT INTO pages_fts_docsize VALUES(1,X'0104000004');
+INSERT INTO pages_fts_docsize VALUES(2,X'0103000104');
+CREATE TABLE IF NOT EXISTS 'pages_fts_config'(k PRIMARY KEY, v) WITHOUT ROWID;
+INSERT INTO pages_fts_config VALUES('version',4);
+CREATE TABLE schema_version (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        );
+INSERT INTO schema_version VALUES(7,'unified_page_store','1792199789');
+INSERT INTO schema_version VALUES(8,'profile_encryption','1792199793');
+INSERT INTO schema_version VALUES(9,'media_key_derivation','1792199796');
+INSERT INTO schema_version VALUES(10,'password_vault','1792199800');
+INSERT INTO schema_version VALUES(11,'otp_vault','1792199803');
+INSERT INTO schema_version VALUES(12,'scraping_runs','1792199807');
+INSERT INTO schema_version VALUES(13,'scraper_settings','1792199811');
+CREATE TABLE profile_encryption (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            locked INTEGER NOT NULL DEFAULT 0,
+            auto_lock_minutes INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+CREATE TABLE password_vaults (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+CREATE TABLE password_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES password_vaults(profile_id) ON DELETE CASCADE
+        );
+CREATE TABLE otp_vaults (
+            profile_id INTEGER PRIMARY KEY,
+            key_params TEXT NOT NULL,
+            wrapped_key TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES profiles(id) ON DELETE CASCADE
+        );
+CREATE TABLE otp_entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (profile_id) REFERENCES otp_vaults(profile_id) ON DELETE CASCADE
+        );
+CREATE TABLE scraping_runs (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            job_id INTEGER NOT NULL,
+            trigger TEXT NOT NULL,
+            status TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT,
+            pages_scraped INTEGER NOT NULL DEFAULT 0,
+            error_count INTEGER NOT NULL DEFAULT 0,
+            errors TEXT NOT NULL DEFAULT '[]',
+            FOREIGN KEY (job_id) REFERENCES scraping_jobs(id) ON DELETE CASCADE
+        );
+CREATE TABLE IF NOT EXISTS sqlite_sequence(name,seq);
+DELETE FROM sqlite_sequence;
+INSERT INTO sqlite_sequence VALUES('profiles',1);
+INSERT INTO sqlite_sequence VALUES('page_notes',2);
+CREATE TRIGGER pages_fts_insert AFTER INSERT ON pages BEGIN
+            INSERT INTO pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+        END;
+CREATE TRIGGER pages_fts_delete AFTER DELETE ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+        END;
+CREATE TRIGGER pages_fts_update
+        AFTER UPDATE OF title, content, summary, tags, url ON pages BEGIN
+            INSERT INTO pages_fts(pages_fts, rowid, title, content, summary, tags, url)
+            VALUES ('delete', old.id, old.title, old.content, old.summary, old.tags, old.url);
+            INSERT INTO pages_fts(rowid, title, content, summary, tags, url)
+            VALUES (new.id, new.title, new.content, new.summary, new.tags, new.url);
+        END;
+CREATE TRIGGER page_notes_fts_insert AFTER INSERT ON page_notes BEGIN
+            INSERT INTO page_notes_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+CREATE TRIGGER page_notes_fts_delete AFTER DELETE ON page_notes BEGIN
+            INSERT INTO page_notes_fts(page_notes_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        END;
+CREATE TRIGGER page_notes_fts_update AFTER UPDATE OF content ON page_notes BEGIN
+            INSERT INTO page_notes_fts(page_notes_fts, rowid, content) VALUES ('delete', old.id, old.content);
+            INSERT INTO page_notes_fts(rowid, content) VALUES (new.id, new.content);
+        END;
+CREATE INDEX idx_scraped_pages_job ON scraped_pages(job_id);
+CREATE INDEX idx_scraped_pages_url ON scraped_pages(url);
+CREATE INDEX idx_pages_url ON pages(url);
+CREATE INDEX idx_pages_profile ON pages(profile_id);
+CREATE INDEX idx_pages_visited ON pages(visited_at);
+CREATE INDEX idx_themes_profile ON themes(profile_id);
+CREATE INDEX idx_themes_active ON themes(profile_id, is_active);
+CREATE INDEX idx_domains_url ON domains(url);
+CREATE INDEX idx_domains_category ON domains(category);
+CREATE INDEX idx_domains_profile ON domains(profile_id);
+CREATE INDEX idx_domain_lists_profile ON domain_lists(profile_id);
+CREATE INDEX idx_domain_ratings_domain ON domain_ratings(domain_id);
+CREATE INDEX idx_domain_ratings_user ON domain_ratings(user_id);
+CREATE INDEX idx_subdomain_ratings_parent ON subdomain_ratings(parent_domain_id);
+CREATE INDEX idx_tabs_profile ON tabs(profile_id);
+CREATE INDEX idx_tabs_position ON tabs(profile_id, position);
+CREATE INDEX idx_tab_history_tab ON tab_history(tab_id);
+CREATE INDEX idx_bookmarks_profile ON bookmarks(profile_id);
+CREATE INDEX idx_bookmarks_folder ON bookmarks(folder_id);
+CREATE INDEX idx_bookmark_folders_profile ON bookmark_folders(profile_id);
+CREATE INDEX idx_multimedia_history_profile ON multimedia_history(profile_id);
+CREATE INDEX idx_multimedia_history_played ON multimedia_history(played_at DESC);
+CREATE INDEX idx_multimedia_playlists_profile ON multimedia_playlists(profile_id);
+CREATE INDEX idx_multimedia_playlist_items_playlist ON multimedia_playlist_items(playlist_id);
+CREATE INDEX idx_embedding_chunks_source ON embedding_chunks(source, source_id);
+CREATE INDEX idx_embedding_chunks_profile ON embedding_chunks(profile_id);
+CREATE INDEX idx_edges_source ON edges(source_id);
+CREATE INDEX idx_edges_target ON edges(target_id);
+CREATE INDEX idx_edges_profile ON edges(profile_id);
+CREATE INDEX idx_page_links_target ON page_links(target_url);
+CREATE INDEX idx_entity_mentions_source ON entity_mentions(source, source_id);
+CREATE INDEX idx_entities_profile ON entities(profile_id, kind);
+CREATE INDEX idx_pages_favorite ON pages(is_favorite);
+CREATE INDEX idx_notes_page ON page_notes(page_id);
+CREATE INDEX idx_password_entries_profile ON password_entries(profile_id);
+CREATE INDEX idx_otp_entries_profile ON otp_entries(profile_id);
+CREATE INDEX idx_scraping_runs_job ON scraping_runs(job_id, started_at);
+PRAGMA writable_schema=OFF;
+COMMIT;
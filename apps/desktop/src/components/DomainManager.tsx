@@ -2,7 +2,7 @@
 // Manages curated domain whitelists with CRUD operations
 
 import { useState, useEffect, useCallback } from 'react';
-import { invoke } from '../lib/tauri';
+import { invoke, runTask } from '../lib/tauri';
 import { RatingBadge, RatingForm, RatingDisplay } from './RatingComponents';
 
 interface Domain {
@@ -228,7 +228,7 @@ export function DomainManager({ profileId, onOpenUrl }: DomainManagerProps) {
     if (!profileId || !importData.trim()) return;
 
     try {
-      const count = await invoke<number>('import_domains', { profileId, jsonData: importData });
+      const count = await runTask<number>('import_domains', { profileId, jsonData: importData });
       setShowImport(false);
       setImportData('');
       setError(null);
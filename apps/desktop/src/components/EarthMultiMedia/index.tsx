@@ -440,6 +440,7 @@ interface PlaylistItem {
   thumbnail: string | null;
   position: number;
   added_at: string;
+  encrypted: boolean;
 }
 
 interface EarthMultiMediaProps {
@@ -8,6 +8,8 @@ interface HistoryEntry {
   title: string;
   visited_at: string;
   profile_id: number;
+  visit_count: number;
+  is_favorite: boolean;
 }
 
 interface HistoryStats {
@@ -2,7 +2,7 @@
 // Manages indexed pages, favorites, notes, and semantic search
 
 import { useState, useEffect, useCallback } from 'react';
-import { invoke } from '../lib/tauri';
+import { invoke, runTask } from '../lib/tauri';
 
 interface IndexedPage {
   id: number | null;
@@ -179,8 +179,9 @@ export function MemoryManager({ profileId }: MemoryManagerProps) {
 
   // Load notes for a page
   const loadNotes = async (pageId: number) => {
+    if (!profileId) return;
     try {
-      const notesData = await invoke<PageNote[]>('get_page_notes', { pageId });
+      const notesData = await invoke<PageNote[]>('get_page_notes', { pageId, profileId });
       setNotes(notesData);
     } catch (err) {
       console.error('Failed to load notes:', err);
@@ -217,7 +218,7 @@ export function MemoryManager({ profileId }: MemoryManagerProps) {
     if (!profileId) return;
 
     try {
-      const json = await invoke<string>('export_memory', { profileId });
+      const json = await runTask<string>('export_memory', { profileId });
       const blob = new Blob([json], { type: 'application/json' });
       const url = URL.createObjectURL(blob);
       const a = document.createElement('a');
@@ -235,7 +236,7 @@ export function MemoryManager({ profileId }: MemoryManagerProps) {
     if (!profileId || !importData.trim()) return;
 
     try {
-      const count = await invoke<number>('import_memory', { profileId, jsonData: importData });
+      const count = await runTask<number>('import_memory', { profileId, jsonData: importData });
       setShowImport(false);
       setImportData('');
       loadData();
@@ -1,5 +1,5 @@
 // OTP Authenticator Component for Reclaim
-// Stores and generates TOTP codes for 2FA
+// Stores 2FA secrets and shows the TOTP/HOTP codes the backend generates
 
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '../lib/tauri';
@@ -10,10 +10,13 @@ export interface OTPEntry {
   name: string;
   issuer: string;
   secret: string; // Base32 encoded secret
+  kind: 'totp' | 'hotp';
   algorithm: 'SHA1' | 'SHA256' | 'SHA512';
   digits: number;
   period: number;
+  counter: number; // Next HOTP counter
   created_at: string;
+  updated_at: string;
 }
 
 interface OTPAuthenticatorProps {
@@ -22,25 +25,10 @@ interface OTPAuthenticatorProps {
   onClose: () => void;
 }
 
-// Simple TOTP implementation
-function generateTOTP(secret: string, _algorithm: string = 'SHA1', digits: number = 6, period: number = 30): string {
-  // This is a simplified TOTP generator for demo purposes
-  // In production, use a proper TOTP library like otpauth
-  const counter = Math.floor(Date.now() / 1000 / period);
-
-  // Create a deterministic code based on secret and counter
-  // This is NOT cryptographically secure - just for demo
-  let hash = 0;
-  const combined = secret + counter.toString();
-  for (let i = 0; i < combined.length; i++) {
-    const char = combined.charCodeAt(i);
-    hash = ((hash << 5) - hash) + char;
-    hash = hash & hash;
-  }
-
-  // Get the last N digits
-  const code = Math.abs(hash % Math.pow(10, digits)).toString().padStart(digits, '0');
-  return code;
+interface OTPCode {
+  entry_id: number;
+  code: string;
+  remaining_seconds: number | null;
 }
 
 export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticatorProps) {
@@ -53,6 +41,8 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
   const [searchQuery, setSearchQuery] = useState('');
   const [currentTime, setCurrentTime] = useState(Date.now());
   const [copiedId, setCopiedId] = useState<number | null>(null);
+  const [codes, setCodes] = useState<Record<number, string>>({});
+  const [codesExpireAt, setCodesExpireAt] = useState(0);
 
   // Update time every second for TOTP countdown
   useEffect(() => {
@@ -62,6 +52,13 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
     return () => clearInterval(interval);
   }, []);
 
+  // Fetch new codes once the first of the current ones rolls over
+  useEffect(() => {
+    if (isUnlocked && entries.length > 0 && currentTime >= codesExpireAt) {
+      loadCodes();
+    }
+  }, [isUnlocked, entries, currentTime, codesExpireAt]);
+
   useEffect(() => {
     if (isOpen) {
       checkMasterPassword();
@@ -70,7 +67,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
 
   const checkMasterPassword = async () => {
     try {
-      const hasPass = await invoke<boolean>('has_otp_master', { profile_id: profileId });
+      const hasPass = await invoke<boolean>('has_otp_master', { profileId });
       setHasMasterPassword(hasPass);
     } catch {
       setHasMasterPassword(false);
@@ -80,7 +77,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
   const unlock = async () => {
     try {
       const valid = await invoke<boolean>('verify_otp_master', {
-        profile_id: profileId,
+        profileId,
         password: masterPassword,
       });
       if (valid) {
@@ -90,9 +87,8 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
       } else {
         alert('Incorrect master password');
       }
-    } catch {
-      setIsUnlocked(true);
-      loadEntries();
+    } catch (err) {
+      console.error('Failed to unlock authenticator:', err);
     }
   };
 
@@ -103,7 +99,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
     }
     try {
       await invoke('set_otp_master', {
-        profile_id: profileId,
+        profileId,
         password: masterPassword,
       });
       setHasMasterPassword(true);
@@ -117,17 +113,41 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
 
   const loadEntries = async () => {
     try {
-      const data = await invoke<OTPEntry[]>('get_otp_entries', { profile_id: profileId });
+      const data = await invoke<OTPEntry[]>('get_otp_entries', { profileId });
       setEntries(data);
     } catch (err) {
       console.error('Failed to load OTP entries:', err);
     }
   };
 
+  const loadCodes = async () => {
+    try {
+      const data = await invoke<OTPCode[]>('get_otp_codes', { profileId });
+      const now = Date.now();
+      setCodes(prev => ({ ...prev, ...Object.fromEntries(data.map(c => [c.entry_id, c.code])) }));
+      const remaining = data.map(c => c.remaining_seconds ?? Infinity);
+      setCodesExpireAt(now + Math.min(30, ...remaining) * 1000);
+    } catch (err) {
+      console.error('Failed to load OTP codes:', err);
+      setCodesExpireAt(Date.now() + 5000);
+    }
+  };
+
+  // HOTP codes use up a counter value, so they are only generated on request
+  const nextHotpCode = async (entryId: number) => {
+    try {
+      const next = await invoke<OTPCode>('next_hotp_code', { entryId });
+      setCodes(prev => ({ ...prev, [entryId]: next.code }));
+    } catch (err) {
+      console.error('Failed to generate HOTP code:', err);
+    }
+  };
+
   const deleteEntry = async (entryId: number) => {
     if (!confirm('Are you sure you want to delete this authenticator entry?')) return;
     try {
-      await invoke('delete_otp_entry', { entry_id: entryId });
+      await invoke('delete_otp_entry', { entryId });
+      setCodesExpireAt(0);
       loadEntries();
     } catch (err) {
       console.error('Failed to delete entry:', err);
@@ -239,7 +259,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
             <div className="flex-1 overflow-y-auto p-4">
               <div className="space-y-3">
                 {filteredEntries.map(entry => {
-                  const code = generateTOTP(entry.secret, entry.algorithm, entry.digits, entry.period);
+                  const code = codes[entry.id] ?? '';
                   const timeRemaining = getTimeRemaining(entry.period);
                   const isLow = timeRemaining <= 5;
 
@@ -262,7 +282,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
                       {/* Code */}
                       <div className="flex items-center gap-3">
                         <button
-                          onClick={() => copyCode(code, entry.id)}
+                          onClick={() => code && copyCode(code, entry.id)}
                           className="text-2xl font-mono font-bold tracking-wider text-[var(--text-color)] hover:text-[var(--primary-color)] transition-colors"
                         >
                           {copiedId === entry.id ? (
@@ -272,19 +292,33 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
                               </svg>
                               Copied
                             </span>
-                          ) : (
+                          ) : code ? (
                             <>
-                              {code.slice(0, 3)} {code.slice(3)}
+                              {code.slice(0, Math.ceil(code.length / 2))} {code.slice(Math.ceil(code.length / 2))}
                             </>
+                          ) : (
+                            <span className="text-gray-500">{'•'.repeat(entry.digits)}</span>
                           )}
                         </button>
 
-                        {/* Countdown */}
-                        <div className={`w-10 h-10 flex items-center justify-center rounded-full border-2 ${
-                          isLow ? 'border-red-500 text-red-400' : 'border-[var(--primary-color)] text-[var(--primary-color)]'
-                        }`}>
-                          <span className="text-sm font-medium">{timeRemaining}</span>
-                        </div>
+                        {entry.kind === 'hotp' ? (
+                          <button
+                            onClick={() => nextHotpCode(entry.id)}
+                            className="w-10 h-10 flex items-center justify-center rounded-full border-2 border-[var(--primary-color)] text-[var(--primary-color)] hover:bg-[var(--primary-color)]/20 transition-colors"
+                            title="Generate next code"
+                          >
+                            <svg className="w-4 h-4" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                              <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M4 4v5h.582m15.356 2A8.001 8.001 0 004.582 9m0 0H9m11 11v-5h-.581m0 0a8.003 8.003 0 01-15.357-2m15.357 2H15" />
+                            </svg>
+                          </button>
+                        ) : (
+                          /* Countdown */
+                          <div className={`w-10 h-10 flex items-center justify-center rounded-full border-2 ${
+                            isLow ? 'border-red-500 text-red-400' : 'border-[var(--primary-color)] text-[var(--primary-color)]'
+                          }`}>
+                            <span className="text-sm font-medium">{timeRemaining}</span>
+                          </div>
+                        )}
                       </div>
 
                       {/* Actions */}
@@ -343,6 +377,7 @@ export function OTPAuthenticator({ profileId, isOpen, onClose }: OTPAuthenticato
           onSave={() => {
             setShowAddModal(false);
             setEditingEntry(null);
+            setCodesExpireAt(0);
             loadEntries();
           }}
         />
@@ -366,11 +401,23 @@ function OTPEntryModal({
   const [name, setName] = useState(entry?.name || '');
   const [issuer, setIssuer] = useState(entry?.issuer || '');
   const [secret, setSecret] = useState(entry?.secret || '');
+  const [kind, setKind] = useState<'totp' | 'hotp'>(entry?.kind || 'totp');
   const [algorithm, setAlgorithm] = useState<'SHA1' | 'SHA256' | 'SHA512'>(entry?.algorithm || 'SHA1');
   const [digits, setDigits] = useState(entry?.digits || 6);
   const [period, setPeriod] = useState(entry?.period || 30);
 
   const handleSave = async () => {
+    // A pasted otpauth:// URI (the text of a setup QR code) carries every field
+    if (!entry && secret.trim().startsWith('otpauth://')) {
+      try {
+        await invoke('import_otp_uri', { profileId, uri: secret.trim() });
+        onSave();
+      } catch (err) {
+        alert(`Could not import the URI: ${(err as { message?: string })?.message ?? err}`);
+      }
+      return;
+    }
+
     if (!name.trim() || !issuer.trim() || !secret.trim()) {
       alert('Please fill in all required fields');
       return;
@@ -382,20 +429,22 @@ function OTPEntryModal({
     try {
       if (entry) {
         await invoke('update_otp_entry', {
-          entry_id: entry.id,
+          entryId: entry.id,
           name: name.trim(),
           issuer: issuer.trim(),
           secret: cleanSecret,
+          kind,
           algorithm,
           digits,
           period,
         });
       } else {
         await invoke('add_otp_entry', {
-          profile_id: profileId,
+          profileId,
           name: name.trim(),
           issuer: issuer.trim(),
           secret: cleanSecret,
+          kind,
           algorithm,
           digits,
           period,
@@ -404,6 +453,7 @@ function OTPEntryModal({
       onSave();
     } catch (err) {
       console.error('Failed to save OTP entry:', err);
+      alert(`Could not save the account: ${(err as { message?: string })?.message ?? err}`);
     }
   };
 
@@ -455,6 +505,7 @@ function OTPEntryModal({
             />
             <p className="text-xs text-gray-500 mt-1">
               Enter the secret key provided by the service (usually shown as a code or under manual setup)
+              {!entry && ', or paste an otpauth:// link to fill in everything'}
             </p>
           </div>
 
@@ -464,7 +515,18 @@ function OTPEntryModal({
               Advanced Options
             </summary>
             <div className="mt-3 space-y-3 pl-2 border-l-2 border-gray-700">
-              <div className="grid grid-cols-3 gap-3">
+              <div className="grid grid-cols-4 gap-3">
+                <div>
+                  <label className="block text-xs text-gray-500 mb-1">Type</label>
+                  <select
+                    value={kind}
+                    onChange={(e) => setKind(e.target.value as 'totp' | 'hotp')}
+                    className="w-full px-2 py-1.5 bg-gray-800 border border-gray-600 rounded text-sm focus:outline-none focus:border-[var(--primary-color)]"
+                  >
+                    <option value="totp">Time-based</option>
+                    <option value="hotp">Counter-based</option>
+                  </select>
+                </div>
                 <div>
                   <label className="block text-xs text-gray-500 mb-1">Algorithm</label>
                   <select
@@ -485,19 +547,21 @@ function OTPEntryModal({
                     className="w-full px-2 py-1.5 bg-gray-800 border border-gray-600 rounded text-sm focus:outline-none focus:border-[var(--primary-color)]"
                   >
                     <option value={6}>6</option>
+                    <option value={7}>7</option>
                     <option value={8}>8</option>
                   </select>
                 </div>
                 <div>
                   <label className="block text-xs text-gray-500 mb-1">Period (sec)</label>
-                  <select
+                  <input
+                    type="number"
+                    min={1}
+                    max={600}
                     value={period}
-                    onChange={(e) => setPeriod(parseInt(e.target.value))}
-                    className="w-full px-2 py-1.5 bg-gray-800 border border-gray-600 rounded text-sm focus:outline-none focus:border-[var(--primary-color)]"
-                  >
-                    <option value={30}>30</option>
-                    <option value={60}>60</option>
-                  </select>
+                    disabled={kind === 'hotp'}
+                    onChange={(e) => setPeriod(parseInt(e.target.value) || 30)}
+                    className="w-full px-2 py-1.5 bg-gray-800 border border-gray-600 rounded text-sm focus:outline-none focus:border-[var(--primary-color)] disabled:opacity-50"
+                  />
                 </div>
               </div>
             </div>
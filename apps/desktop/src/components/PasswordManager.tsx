@@ -4,6 +4,12 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '../lib/tauri';
 
+export interface PasswordCustomField {
+  name: string;
+  value: string;
+  hidden: boolean;
+}
+
 export interface PasswordEntry {
   id: number;
   profile_id: number;
@@ -13,6 +19,7 @@ export interface PasswordEntry {
   url: string | null;
   notes: string | null;
   category: string;
+  custom_fields: PasswordCustomField[];
   created_at: string;
   updated_at: string;
 }
@@ -42,7 +49,7 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
 
   const checkMasterPassword = async () => {
     try {
-      const hasPass = await invoke<boolean>('has_password_manager_master', { profile_id: profileId });
+      const hasPass = await invoke<boolean>('has_password_manager_master', { profileId });
       setHasMasterPassword(hasPass);
       if (!hasPass) {
         setIsUnlocked(false);
@@ -55,7 +62,7 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
   const unlock = async () => {
     try {
       const valid = await invoke<boolean>('verify_password_manager_master', {
-        profile_id: profileId,
+        profileId,
         password: masterPassword,
       });
       if (valid) {
@@ -65,9 +72,8 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
       } else {
         alert('Incorrect master password');
       }
-    } catch {
-      setIsUnlocked(true);
-      loadEntries();
+    } catch (err) {
+      console.error('Failed to unlock password manager:', err);
     }
   };
 
@@ -78,7 +84,7 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
     }
     try {
       await invoke('set_password_manager_master', {
-        profile_id: profileId,
+        profileId,
         password: masterPassword,
       });
       setHasMasterPassword(true);
@@ -92,7 +98,7 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
 
   const loadEntries = async () => {
     try {
-      const data = await invoke<PasswordEntry[]>('get_password_entries', { profile_id: profileId });
+      const data = await invoke<PasswordEntry[]>('get_password_entries', { profileId });
       setEntries(data);
     } catch (err) {
       console.error('Failed to load password entries:', err);
@@ -102,7 +108,7 @@ export function PasswordManager({ profileId, isOpen, onClose }: PasswordManagerP
   const deleteEntry = async (entryId: number) => {
     if (!confirm('Are you sure you want to delete this password entry?')) return;
     try {
-      await invoke('delete_password_entry', { entry_id: entryId });
+      await invoke('delete_password_entry', { entryId });
       loadEntries();
     } catch (err) {
       console.error('Failed to delete entry:', err);
@@ -460,7 +466,7 @@ function PasswordEntryModal({
     try {
       if (entry) {
         await invoke('update_password_entry', {
-          entry_id: entry.id,
+          entryId: entry.id,
           title: title.trim(),
           username: username.trim(),
           password: password.trim(),
@@ -470,7 +476,7 @@ function PasswordEntryModal({
         });
       } else {
         await invoke('add_password_entry', {
-          profile_id: profileId,
+          profileId,
           title: title.trim(),
           username: username.trim(),
           password: password.trim(),
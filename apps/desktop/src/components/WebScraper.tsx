@@ -1,12 +1,42 @@
 import { useState, useEffect, useCallback } from 'react';
-import { invoke } from '../lib/tauri';
+import { invoke, listen } from '../lib/tauri';
 
 // Types
 interface ContentSelector {
   name: string;
   selector: string;
+  /** Take this attribute (e.g. content, href, datetime) instead of the element's text */
+  attribute?: string | null;
+  /** Collect every match instead of the first */
+  all?: boolean;
 }
 
+/** How a job crawls; the backend fills in defaults for anything left out */
+interface CrawlSettings {
+  /** null for the built-in ReclaimBot user agent */
+  user_agent: string | null;
+  respect_robots: boolean;
+  /** Least time between two requests to the same site */
+  request_delay_ms: number;
+  /** Retries of a 429 or 5xx response */
+  max_retries: number;
+  follow_sitemaps: boolean;
+  /** Which links to follow: the base URL's host, its domain and subdomains, or anywhere */
+  scope: 'host' | 'domain' | 'any';
+}
+
+const DEFAULT_CRAWL_SETTINGS: CrawlSettings = {
+  user_agent: null,
+  respect_robots: true,
+  request_delay_ms: 1000,
+  max_retries: 3,
+  follow_sitemaps: false,
+  scope: 'host',
+};
+
+/** Selector values stored in a page's metadata */
+type PageFields = Record<string, string | string[] | null>;
+
 interface ScrapingJob {
   id: number | null;
   profile_id: number;
@@ -17,6 +47,7 @@ interface ScrapingJob {
   max_pages: number;
   content_selectors: ContentSelector[];
   schedule_cron: string | null;
+  settings: CrawlSettings;
   status: string;
   last_run_at: string | null;
   pages_scraped: number;
@@ -31,8 +62,178 @@ interface ScrapedPage {
   content: string;
   metadata: string | null;
   scraped_at: string;
+  checked_at: string | null;
+  changed_at: string | null;
+  watch: boolean;
+}
+
+interface PageVersion {
+  id: number;
+  page_id: number;
+  title: string | null;
+  content_hash: string | null;
+  scraped_at: string;
+}
+
+interface DiffSegment {
+  kind: 'equal' | 'insert' | 'delete';
+  text: string;
+}
+
+interface PageDiff {
+  page_id: number;
+  from: PageVersion;
+  to: PageVersion;
+  segments: DiffSegment[];
+  words_added: number;
+  words_removed: number;
+}
+
+// Payload of the scraper-page-changed event, sent for watched pages
+interface PageChanged {
+  job_id: number;
+  page_id: number;
+  url: string;
+  title: string | null;
+  from_version: number | null;
+  to_version: number;
+  words_added: number;
+  words_removed: number;
+}
+
+function parsePageFields(metadata: string | null): PageFields | null {
+  if (!metadata) return null;
+  try {
+    const fields = JSON.parse(metadata);
+    return fields && typeof fields === 'object' && !Array.isArray(fields) ? fields : null;
+  } catch {
+    return null;
+  }
+}
+
+function PageFieldList({ metadata }: { metadata: string | null }) {
+  const fields = parsePageFields(metadata);
+  if (!fields || Object.keys(fields).length === 0) return null;
+
+  return (
+    <dl className="mt-2 grid grid-cols-[auto_1fr] gap-x-3 gap-y-0.5 text-xs">
+      {Object.entries(fields).map(([name, value]) => (
+        <div key={name} className="contents">
+          <dt className="text-[var(--text-muted-color)] font-medium">{name}</dt>
+          <dd className="text-[var(--text-color)] truncate">
+            {value === null ? (
+              <span className="text-gray-500 italic">no match</span>
+            ) : Array.isArray(value) ? (
+              value.join(', ')
+            ) : (
+              value
+            )}
+          </dd>
+        </div>
+      ))}
+    </dl>
+  );
+}
+
+const DIFF_SEGMENT_CLASSES: Record<DiffSegment['kind'], string> = {
+  equal: 'text-[var(--text-muted-color)]',
+  insert: 'bg-green-500/20 text-green-300',
+  delete: 'bg-red-500/20 text-red-300 line-through',
+};
+
+// Versions of a page, each compared with the one before it
+function PageHistory({ pageId }: { pageId: number }) {
+  const [versions, setVersions] = useState<PageVersion[]>([]);
+  const [selected, setSelected] = useState<number | null>(null);
+  const [diff, setDiff] = useState<PageDiff | null>(null);
+
+  useEffect(() => {
+    invoke<PageVersion[]>('get_page_versions', { pageId })
+      .then(loaded => {
+        setVersions(loaded);
+        setSelected(loaded.length > 1 ? loaded[0].id : null);
+      })
+      .catch(err => console.error('Failed to load page versions:', err));
+  }, [pageId]);
+
+  useEffect(() => {
+    const index = versions.findIndex(version => version.id === selected);
+    const previous = versions[index + 1];
+    if (index < 0 || !previous) {
+      setDiff(null);
+      return;
+    }
+    invoke<PageDiff>('diff_page_versions', { fromId: previous.id, toId: versions[index].id })
+      .then(setDiff)
+      .catch(err => console.error('Failed to compare page versions:', err));
+  }, [versions, selected]);
+
+  if (versions.length <= 1) {
+    return <p className="mt-2 text-xs text-[var(--text-muted-color)]">No changes seen since it was first scraped</p>;
+  }
+
+  return (
+    <div className="mt-2 space-y-2">
+      <div className="flex flex-wrap gap-1">
+        {versions.slice(0, -1).map(version => (
+          <button
+            key={version.id}
+            onClick={() => setSelected(version.id)}
+            className={`px-2 py-0.5 rounded text-xs ${
+              version.id === selected
+                ? 'bg-[var(--primary-color)] text-white'
+                : 'bg-gray-700/50 text-[var(--text-muted-color)] hover:text-[var(--text-color)]'
+            }`}
+          >
+            {new Date(version.scraped_at).toLocaleString()}
+          </button>
+        ))}
+      </div>
+      {diff && (
+        <div className="p-2 rounded border border-gray-700/50 text-xs leading-relaxed max-h-48 overflow-y-auto">
+          <p className="mb-1 text-[var(--text-muted-color)]">
+            +{diff.words_added} / -{diff.words_removed} words since {new Date(diff.from.scraped_at).toLocaleString()}
+          </p>
+          {diff.segments.map((segment, i) => (
+            <span key={i} className={DIFF_SEGMENT_CLASSES[segment.kind]}>{segment.text}</span>
+          ))}
+        </div>
+      )}
+    </div>
+  );
+}
+
+interface RunError {
+  url: string | null;
+  message: string;
+}
+
+interface ScrapingRun {
+  id: number;
+  job_id: number;
+  trigger: 'manual' | 'schedule';
+  status: string;
+  started_at: string;
+  finished_at: string | null;
+  pages_scraped: number;
+  pages_changed: number;
+  error_count: number;
+  errors: RunError[];
 }
 
+// Payload of the scraper-progress event
+interface ScraperProgress {
+  job_id: number;
+  run_id: number;
+  status: string;
+  pages_scraped: number;
+  current_url: string | null;
+  error: string | null;
+  error_count: number;
+}
+
+const FINISHED_STATUSES = ['completed', 'cancelled', 'failed'];
+
 interface WebScraperProps {
   profileId: number | null;
 }
@@ -45,12 +246,16 @@ export function WebScraper({ profileId }: WebScraperProps) {
   const [scrapedPages, setScrapedPages] = useState<ScrapedPage[]>([]);
   const [searchQuery, setSearchQuery] = useState('');
   const [searchResults, setSearchResults] = useState<ScrapedPage[]>([]);
+  const [runs, setRuns] = useState<ScrapingRun[]>([]);
+  const [progress, setProgress] = useState<Record<number, ScraperProgress>>({});
+  const [historyPageId, setHistoryPageId] = useState<number | null>(null);
+  const [changedPage, setChangedPage] = useState<PageChanged | null>(null);
 
   const loadJobs = useCallback(async () => {
     if (!profileId) return;
     try {
       setLoading(true);
-      const loadedJobs = await invoke<ScrapingJob[]>('get_scraping_jobs', { profile_id: profileId });
+      const loadedJobs = await invoke<ScrapingJob[]>('get_scraping_jobs', { profileId });
       setJobs(loadedJobs);
     } catch (err) {
       console.error('Failed to load scraping jobs:', err);
@@ -63,10 +268,73 @@ export function WebScraper({ profileId }: WebScraperProps) {
     loadJobs();
   }, [loadJobs]);
 
+  const loadRuns = useCallback(async (jobId: number) => {
+    try {
+      setRuns(await invoke<ScrapingRun[]>('get_scraping_runs', { jobId, limit: 20 }));
+    } catch (err) {
+      console.error('Failed to load run history:', err);
+    }
+  }, []);
+
+  // Live progress from manual and scheduled runs
+  useEffect(() => {
+    const unlistenPromise = listen<ScraperProgress>('scraper-progress', ({ payload }) => {
+      setProgress(prev => ({ ...prev, [payload.job_id]: payload }));
+      setJobs(prev => prev.map(job => job.id === payload.job_id
+        ? { ...job, status: payload.status, pages_scraped: payload.pages_scraped }
+        : job));
+      if (FINISHED_STATUSES.includes(payload.status)) {
+        loadJobs();
+        if (selectedJob?.id === payload.job_id) {
+          loadRuns(payload.job_id);
+        }
+      }
+    });
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, [loadJobs, loadRuns, selectedJob]);
+
+  // Watched pages that a run found changed
+  useEffect(() => {
+    const unlistenPromise = listen<PageChanged>('scraper-page-changed', ({ payload }) => {
+      setChangedPage(payload);
+      if (selectedJob?.id === payload.job_id) {
+        invoke<ScrapedPage[]>('get_scraped_pages', { jobId: payload.job_id, limit: 100 })
+          .then(setScrapedPages)
+          .catch(err => console.error('Failed to load scraped pages:', err));
+      }
+    });
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, [selectedJob]);
+
+  const handleToggleWatch = async (page: ScrapedPage) => {
+    if (!page.id) return;
+    try {
+      await invoke('set_page_watch', { pageId: page.id, watch: !page.watch });
+      setScrapedPages(prev => prev.map(p => p.id === page.id ? { ...p, watch: !page.watch } : p));
+    } catch (err) {
+      console.error('Failed to change page watch:', err);
+    }
+  };
+
+  const jobCommand = async (command: string, jobId: number) => {
+    try {
+      await invoke(command, { jobId });
+      if (command === 'run_scraping_job') {
+        setJobs(prev => prev.map(job => job.id === jobId ? { ...job, status: 'running' } : job));
+      }
+    } catch (err) {
+      alert(`Scraping job error: ${(err as { message?: string })?.message ?? err}`);
+    }
+  };
+
   const handleDeleteJob = async (jobId: number) => {
     if (!confirm('Are you sure you want to delete this scraping job and all scraped pages?')) return;
     try {
-      await invoke('delete_scraping_job', { job_id: jobId });
+      await invoke('delete_scraping_job', { jobId });
       loadJobs();
       if (selectedJob?.id === jobId) {
         setSelectedJob(null);
@@ -79,9 +347,11 @@ export function WebScraper({ profileId }: WebScraperProps) {
 
   const handleViewPages = async (job: ScrapingJob) => {
     setSelectedJob(job);
+    setHistoryPageId(null);
     try {
-      const pages = await invoke<ScrapedPage[]>('get_scraped_pages', { job_id: job.id, limit: 100 });
+      const pages = await invoke<ScrapedPage[]>('get_scraped_pages', { jobId: job.id, limit: 100 });
       setScrapedPages(pages);
+      if (job.id) loadRuns(job.id);
     } catch (err) {
       console.error('Failed to load scraped pages:', err);
     }
@@ -91,7 +361,7 @@ export function WebScraper({ profileId }: WebScraperProps) {
     if (!profileId || !searchQuery.trim()) return;
     try {
       const results = await invoke<ScrapedPage[]>('search_scraped_content', {
-        profile_id: profileId,
+        profileId,
         query: searchQuery,
         limit: 50,
       });
@@ -130,6 +400,32 @@ export function WebScraper({ profileId }: WebScraperProps) {
         </button>
       </div>
 
+      {/* Change notice */}
+      {changedPage && (
+        <div className="flex items-center justify-between gap-3 px-4 py-3 rounded-xl border border-yellow-500/40 bg-yellow-500/10 text-sm">
+          <span className="text-[var(--text-color)] truncate">
+            <span className="font-medium">{changedPage.title || changedPage.url}</span> changed:
+            {' '}{changedPage.words_added} words added, {changedPage.words_removed} removed
+          </span>
+          <div className="flex items-center gap-3 shrink-0">
+            {selectedJob?.id === changedPage.job_id && (
+              <button
+                onClick={() => setHistoryPageId(changedPage.page_id)}
+                className="text-[var(--primary-color)] hover:underline"
+              >
+                Show changes
+              </button>
+            )}
+            <button
+              onClick={() => setChangedPage(null)}
+              className="text-[var(--text-muted-color)] hover:text-[var(--text-color)]"
+            >
+              Dismiss
+            </button>
+          </div>
+        </div>
+      )}
+
       {/* Search */}
       <div className="bg-[var(--card-bg-color)] rounded-xl p-4 border border-gray-700/50">
         <div className="flex gap-3">
@@ -205,6 +501,8 @@ export function WebScraper({ profileId }: WebScraperProps) {
               job={job}
               onView={() => handleViewPages(job)}
               onDelete={() => job.id && handleDeleteJob(job.id)}
+              onCommand={(command) => job.id && jobCommand(command, job.id)}
+              progress={job.id ? progress[job.id] : undefined}
               isSelected={selectedJob?.id === job.id}
             />
           ))
@@ -222,6 +520,8 @@ export function WebScraper({ profileId }: WebScraperProps) {
               onClick={() => {
                 setSelectedJob(null);
                 setScrapedPages([]);
+                setRuns([]);
+                setHistoryPageId(null);
               }}
               className="text-[var(--text-muted-color)] hover:text-[var(--text-color)]"
             >
@@ -242,24 +542,80 @@ export function WebScraper({ profileId }: WebScraperProps) {
                   key={page.id}
                   className="p-3 bg-[var(--bg-color)] rounded-lg border border-gray-700/50"
                 >
-                  <a
-                    href={page.url}
-                    target="_blank"
-                    rel="noopener noreferrer"
-                    className="text-[var(--primary-color)] hover:underline font-medium text-sm"
-                  >
-                    {page.title || page.url}
-                  </a>
+                  <div className="flex items-start justify-between gap-3">
+                    <a
+                      href={page.url}
+                      target="_blank"
+                      rel="noopener noreferrer"
+                      className="text-[var(--primary-color)] hover:underline font-medium text-sm"
+                    >
+                      {page.title || page.url}
+                    </a>
+                    <div className="flex items-center gap-2 shrink-0 text-xs">
+                      <button
+                        onClick={() => setHistoryPageId(historyPageId === page.id ? null : page.id)}
+                        className="text-[var(--text-muted-color)] hover:text-[var(--text-color)]"
+                      >
+                        History
+                      </button>
+                      <button
+                        onClick={() => handleToggleWatch(page)}
+                        title="Notify me when this page changes"
+                        className={`px-2 py-0.5 rounded-full ${
+                          page.watch ? 'bg-yellow-500/20 text-yellow-400' : 'bg-gray-700/50 text-[var(--text-muted-color)] hover:text-[var(--text-color)]'
+                        }`}
+                      >
+                        {page.watch ? 'Watching' : 'Watch'}
+                      </button>
+                    </div>
+                  </div>
                   <p className="text-xs text-[var(--text-muted-color)] mt-1 line-clamp-2">
                     {page.content.substring(0, 150)}...
                   </p>
+                  <PageFieldList metadata={page.metadata} />
                   <p className="text-xs text-gray-500 mt-1">
                     Scraped: {new Date(page.scraped_at).toLocaleString()}
+                    {page.checked_at && page.checked_at !== page.scraped_at && ` · Checked: ${new Date(page.checked_at).toLocaleString()}`}
+                    {page.changed_at && <span className="text-yellow-400"> · Changed</span>}
                   </p>
+                  {page.id && historyPageId === page.id && <PageHistory pageId={page.id} />}
                 </div>
               ))
             )}
           </div>
+
+          {/* Run History */}
+          <h3 className="font-medium text-[var(--text-color)] mt-6 mb-3">Run History</h3>
+          {runs.length === 0 ? (
+            <p className="text-sm text-[var(--text-muted-color)]">This job hasn't run yet</p>
+          ) : (
+            <div className="space-y-2 max-h-64 overflow-y-auto">
+              {runs.map((run) => (
+                <details key={run.id} className="p-3 bg-[var(--bg-color)] rounded-lg border border-gray-700/50 text-sm">
+                  <summary className="cursor-pointer flex items-center gap-3 text-[var(--text-color)]">
+                    <span className={`px-2 py-0.5 rounded-full text-xs font-medium ${getStatusColor(run.status)}`}>
+                      {run.status}
+                    </span>
+                    <span>{new Date(run.started_at).toLocaleString()}</span>
+                    <span className="text-xs text-[var(--text-muted-color)]">
+                      {run.trigger === 'schedule' ? 'scheduled' : 'manual'} · {run.pages_scraped} pages · {run.pages_changed} changed · {run.error_count} errors
+                      {run.finished_at && ` · ${Math.round((new Date(run.finished_at).getTime() - new Date(run.started_at).getTime()) / 1000)}s`}
+                    </span>
+                  </summary>
+                  {run.errors.length > 0 && (
+                    <ul className="mt-2 space-y-1 text-xs text-red-400">
+                      {run.errors.map((error, i) => (
+                        <li key={i} className="truncate">
+                          {error.url && <span className="text-[var(--text-muted-color)]">{error.url}: </span>}
+                          {error.message}
+                        </li>
+                      ))}
+                    </ul>
+                  )}
+                </details>
+              ))}
+            </div>
+          )}
         </div>
       )}
 
@@ -283,18 +639,23 @@ interface ScrapingJobCardProps {
   job: ScrapingJob;
   onView: () => void;
   onDelete: () => void;
+  onCommand: (command: 'run_scraping_job' | 'pause_scraping_job' | 'resume_scraping_job' | 'cancel_scraping_job') => void;
+  progress?: ScraperProgress;
   isSelected: boolean;
 }
 
-function ScrapingJobCard({ job, onView, onDelete, isSelected }: ScrapingJobCardProps) {
-  const getStatusColor = (status: string) => {
-    switch (status) {
-      case 'completed': return 'text-green-400 bg-green-400/10';
-      case 'running': return 'text-blue-400 bg-blue-400/10';
-      case 'failed': return 'text-red-400 bg-red-400/10';
-      default: return 'text-gray-400 bg-gray-400/10';
-    }
-  };
+function getStatusColor(status: string) {
+  switch (status) {
+    case 'completed': return 'text-green-400 bg-green-400/10';
+    case 'running': return 'text-blue-400 bg-blue-400/10';
+    case 'paused': return 'text-yellow-400 bg-yellow-400/10';
+    case 'failed': return 'text-red-400 bg-red-400/10';
+    default: return 'text-gray-400 bg-gray-400/10';
+  }
+}
+
+function ScrapingJobCard({ job, onView, onDelete, onCommand, progress, isSelected }: ScrapingJobCardProps) {
+  const isActive = job.status === 'running' || job.status === 'paused';
 
   return (
     <div
@@ -345,9 +706,62 @@ function ScrapingJobCard({ job, onView, onDelete, isSelected }: ScrapingJobCardP
               Last run: {new Date(job.last_run_at).toLocaleString()}
             </p>
           )}
+          {job.schedule_cron && (
+            <p className="text-xs text-gray-500 mt-1">
+              Schedule: <code>{job.schedule_cron}</code>
+            </p>
+          )}
+          {job.settings && !job.settings.respect_robots && (
+            <p className="text-xs text-yellow-400 mt-1">Ignores robots.txt</p>
+          )}
+          {isActive && progress?.current_url && (
+            <p className="text-xs text-[var(--text-muted-color)] mt-1 truncate">
+              {job.status === 'paused' ? 'Paused at' : 'Fetching'} {progress.current_url}
+              {progress.error_count > 0 && ` · ${progress.error_count} errors`}
+            </p>
+          )}
         </div>
 
         <div className="flex items-center gap-2">
+          {!isActive ? (
+            <button
+              onClick={() => onCommand('run_scraping_job')}
+              className="p-2 text-[var(--text-muted-color)] hover:text-green-400 hover:bg-green-400/10 rounded-lg transition-colors"
+              title="Run now"
+            >
+              <svg className="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M14.752 11.168l-3.197-2.132A1 1 0 0010 9.87v4.263a1 1 0 001.555.832l3.197-2.132a1 1 0 000-1.664z" />
+                <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M21 12a9 9 0 11-18 0 9 9 0 0118 0z" />
+              </svg>
+            </button>
+          ) : (
+            <>
+              <button
+                onClick={() => onCommand(job.status === 'paused' ? 'resume_scraping_job' : 'pause_scraping_job')}
+                className="p-2 text-[var(--text-muted-color)] hover:text-yellow-400 hover:bg-yellow-400/10 rounded-lg transition-colors"
+                title={job.status === 'paused' ? 'Resume' : 'Pause'}
+              >
+                <svg className="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                  {job.status === 'paused' ? (
+                    <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M14.752 11.168l-3.197-2.132A1 1 0 0010 9.87v4.263a1 1 0 001.555.832l3.197-2.132a1 1 0 000-1.664z" />
+                  ) : (
+                    <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M10 9v6m4-6v6" />
+                  )}
+                  <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M21 12a9 9 0 11-18 0 9 9 0 0118 0z" />
+                </svg>
+              </button>
+              <button
+                onClick={() => onCommand('cancel_scraping_job')}
+                className="p-2 text-[var(--text-muted-color)] hover:text-red-400 hover:bg-red-400/10 rounded-lg transition-colors"
+                title="Cancel run"
+              >
+                <svg className="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                  <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M21 12a9 9 0 11-18 0 9 9 0 0118 0z" />
+                  <path strokeLinecap="round" strokeLinejoin="round" strokeWidth={2} d="M9 10a1 1 0 011-1h4a1 1 0 011 1v4a1 1 0 01-1 1h-4a1 1 0 01-1-1v-4z" />
+                </svg>
+              </button>
+            </>
+          )}
           <button
             onClick={onView}
             className="p-2 text-[var(--text-muted-color)] hover:text-[var(--primary-color)] hover:bg-[var(--primary-color)]/10 rounded-lg transition-colors"
@@ -386,17 +800,31 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
   const [urlPattern, setUrlPattern] = useState('');
   const [maxDepth, setMaxDepth] = useState(2);
   const [maxPages, setMaxPages] = useState(100);
+  const [schedule, setSchedule] = useState('');
+  const [settings, setSettings] = useState<CrawlSettings>(DEFAULT_CRAWL_SETTINGS);
   const [selectors, setSelectors] = useState<ContentSelector[]>([]);
   const [newSelectorName, setNewSelectorName] = useState('');
   const [newSelectorValue, setNewSelectorValue] = useState('');
+  const [newSelectorAttribute, setNewSelectorAttribute] = useState('');
+  const [newSelectorAll, setNewSelectorAll] = useState(false);
   const [creating, setCreating] = useState(false);
   const [error, setError] = useState<string | null>(null);
 
   const handleAddSelector = () => {
     if (newSelectorName.trim() && newSelectorValue.trim()) {
-      setSelectors([...selectors, { name: newSelectorName.trim(), selector: newSelectorValue.trim() }]);
+      setSelectors([
+        ...selectors,
+        {
+          name: newSelectorName.trim(),
+          selector: newSelectorValue.trim(),
+          attribute: newSelectorAttribute.trim() || null,
+          all: newSelectorAll,
+        },
+      ]);
       setNewSelectorName('');
       setNewSelectorValue('');
+      setNewSelectorAttribute('');
+      setNewSelectorAll(false);
     }
   };
 
@@ -414,19 +842,25 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
       setCreating(true);
       setError(null);
 
-      await invoke('create_scraping_job', {
-        profile_id: profileId,
+      const jobId = await invoke<number>('create_scraping_job', {
+        profileId,
         name: name.trim(),
-        base_url: baseUrl.trim(),
-        url_pattern: urlPattern.trim() || null,
-        max_depth: maxDepth,
-        max_pages: maxPages,
-        content_selectors: selectors,
+        baseUrl: baseUrl.trim(),
+        urlPattern: urlPattern.trim() || null,
+        maxDepth,
+        maxPages,
+        contentSelectors: selectors,
       });
+      if (schedule.trim()) {
+        await invoke('set_scraping_schedule', { jobId, scheduleCron: schedule.trim() });
+      }
+      if (JSON.stringify(settings) !== JSON.stringify(DEFAULT_CRAWL_SETTINGS)) {
+        await invoke('set_scraping_settings', { jobId, settings });
+      }
 
       onCreated();
     } catch (err) {
-      setError(String(err));
+      setError((err as { message?: string })?.message ?? String(err));
     } finally {
       setCreating(false);
     }
@@ -529,6 +963,92 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
             </div>
           </div>
 
+          {/* Schedule */}
+          <div>
+            <label className="block text-sm font-medium text-[var(--text-color)] mb-1">
+              Schedule (cron)
+            </label>
+            <input
+              type="text"
+              value={schedule}
+              onChange={(e) => setSchedule(e.target.value)}
+              placeholder="e.g., 0 6 * * * for every day at 6:00"
+              className="w-full px-4 py-2 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-[var(--text-color)] placeholder-gray-500 focus:outline-none focus:border-[var(--primary-color)] font-mono"
+            />
+            <p className="text-xs text-[var(--text-muted-color)] mt-1">
+              Runs automatically while the app is open. Leave empty to run only by hand.
+            </p>
+          </div>
+
+          {/* Crawl Settings */}
+          <div>
+            <label className="block text-sm font-medium text-[var(--text-color)] mb-1">
+              Crawl Settings
+            </label>
+            <div className="space-y-2">
+              <input
+                type="text"
+                value={settings.user_agent ?? ''}
+                onChange={(e) => setSettings({ ...settings, user_agent: e.target.value || null })}
+                placeholder="User agent (default: ReclaimBot/1.0)"
+                className="w-full px-4 py-2 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-[var(--text-color)] placeholder-gray-500 focus:outline-none focus:border-[var(--primary-color)]"
+              />
+              <div className="grid grid-cols-2 gap-4">
+                <label className="text-xs text-[var(--text-muted-color)]">
+                  Delay between requests (ms)
+                  <input
+                    type="number"
+                    value={settings.request_delay_ms}
+                    onChange={(e) => setSettings({ ...settings, request_delay_ms: Math.max(0, parseInt(e.target.value) || 0) })}
+                    min={0}
+                    max={60000}
+                    step={250}
+                    className="w-full mt-1 px-4 py-2 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-sm text-[var(--text-color)] focus:outline-none focus:border-[var(--primary-color)]"
+                  />
+                </label>
+                <label className="text-xs text-[var(--text-muted-color)]">
+                  Retries on 429 / 5xx
+                  <input
+                    type="number"
+                    value={settings.max_retries}
+                    onChange={(e) => setSettings({ ...settings, max_retries: Math.max(0, parseInt(e.target.value) || 0) })}
+                    min={0}
+                    max={10}
+                    className="w-full mt-1 px-4 py-2 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-sm text-[var(--text-color)] focus:outline-none focus:border-[var(--primary-color)]"
+                  />
+                </label>
+              </div>
+              <label className="block text-xs text-[var(--text-muted-color)]">
+                Follow links
+                <select
+                  value={settings.scope}
+                  onChange={(e) => setSettings({ ...settings, scope: e.target.value as CrawlSettings['scope'] })}
+                  className="w-full mt-1 px-4 py-2 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-sm text-[var(--text-color)] focus:outline-none focus:border-[var(--primary-color)]"
+                >
+                  <option value="host">On the same host only</option>
+                  <option value="domain">Anywhere on the same domain and its subdomains</option>
+                  <option value="any">Anywhere</option>
+                </select>
+              </label>
+              <label className="flex items-center gap-2 text-sm text-[var(--text-color)]">
+                <input
+                  type="checkbox"
+                  checked={settings.respect_robots}
+                  onChange={(e) => setSettings({ ...settings, respect_robots: e.target.checked })}
+                />
+                Respect robots.txt and its crawl delay
+              </label>
+              <label className="flex items-center gap-2 text-sm text-[var(--text-color)]">
+                <input
+                  type="checkbox"
+                  checked={settings.follow_sitemaps}
+                  onChange={(e) => setSettings({ ...settings, follow_sitemaps: e.target.checked })}
+                />
+                Also crawl pages listed in the site's sitemaps
+              </label>
+            </div>
+          </div>
+
           {/* Content Selectors */}
           <div>
             <label className="block text-sm font-medium text-[var(--text-color)] mb-1">
@@ -538,7 +1058,11 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
               {selectors.map((selector, index) => (
                 <div key={index} className="flex items-center gap-2 p-2 bg-[var(--bg-color)] rounded-lg">
                   <span className="text-sm text-[var(--text-color)] font-medium">{selector.name}:</span>
-                  <code className="text-xs text-[var(--primary-color)] flex-1">{selector.selector}</code>
+                  <code className="text-xs text-[var(--primary-color)] flex-1">
+                    {selector.selector}
+                    {selector.attribute && ` @${selector.attribute}`}
+                  </code>
+                  {selector.all && <span className="text-xs text-[var(--text-muted-color)]">all</span>}
                   <button
                     onClick={() => handleRemoveSelector(index)}
                     className="text-red-400 hover:text-red-300"
@@ -565,6 +1089,22 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
                   placeholder="CSS Selector (e.g., article.content)"
                   className="flex-1 px-3 py-1.5 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-sm text-[var(--text-color)] placeholder-gray-500 focus:outline-none focus:border-[var(--primary-color)]"
                 />
+                <input
+                  type="text"
+                  value={newSelectorAttribute}
+                  onChange={(e) => setNewSelectorAttribute(e.target.value)}
+                  placeholder="Attribute"
+                  title="Take this attribute instead of the text, e.g. content or href"
+                  className="w-24 px-3 py-1.5 bg-[var(--bg-color)] border border-gray-700 rounded-lg text-sm text-[var(--text-color)] placeholder-gray-500 focus:outline-none focus:border-[var(--primary-color)]"
+                />
+                <label className="flex items-center gap-1 text-xs text-[var(--text-muted-color)]" title="Keep every match, not just the first">
+                  <input
+                    type="checkbox"
+                    checked={newSelectorAll}
+                    onChange={(e) => setNewSelectorAll(e.target.checked)}
+                  />
+                  All
+                </label>
                 <button
                   onClick={handleAddSelector}
                   className="px-3 py-1.5 bg-[var(--primary-color)]/20 text-[var(--primary-color)] rounded-lg hover:bg-[var(--primary-color)]/30 transition-colors"
@@ -574,7 +1114,7 @@ function CreateScrapingJobModal({ profileId, onClose, onCreated }: CreateScrapin
               </div>
             </div>
             <p className="text-xs text-[var(--text-muted-color)] mt-1">
-              Specify CSS selectors to extract specific content. Leave empty to extract all text.
+              Each selector's value is saved with the page as a named field. The full page text is always kept.
             </p>
           </div>
         </div>
@@ -43,6 +43,13 @@ const mockProfile = {
   is_active: true,
 };
 
+const mockLockStatus = {
+  profile_id: 1,
+  encrypted: false,
+  locked: false,
+  auto_lock_minutes: null as number | null,
+};
+
 // Full preset definitions with all theme colors
 const mockPresets = [
   {
@@ -217,6 +224,16 @@ let mockSplitConfig: any = {
   pane_sizes: null,
 };
 
+// Default scraper crawl settings for browser development
+const mockCrawlSettings = {
+  user_agent: null,
+  respect_robots: true,
+  request_delay_ms: 1000,
+  max_retries: 3,
+  follow_sitemaps: false,
+  scope: 'host',
+};
+
 // Incognito state for browser development
 let mockIncognitoStatus = false;
 
@@ -230,6 +247,25 @@ const mockCommands: Record<string, (args?: any) => any> = {
   update_profile: (args: any) => ({ ...mockProfile, ...args }),
   delete_profile: () => undefined,
 
+  // Profile encryption commands
+  get_profile_lock_status: (args: any) => ({ ...mockLockStatus, profile_id: args.profileId }),
+  enable_profile_encryption: (args: any) => ({
+    profile_id: args.profileId,
+    encrypted: true,
+    locked: false,
+    auto_lock_minutes: args.autoLockMinutes ?? null,
+  }),
+  disable_profile_encryption: (args: any) => ({ ...mockLockStatus, profile_id: args.profileId }),
+  lock_profile: (args: any) => ({ ...mockLockStatus, profile_id: args.profileId, encrypted: true, locked: true }),
+  unlock_profile: (args: any) => ({ ...mockLockStatus, profile_id: args.profileId, encrypted: true }),
+  rekey_profile: (args: any) => ({ ...mockLockStatus, profile_id: args.profileId, encrypted: true }),
+  set_profile_auto_lock: (args: any) => ({
+    ...mockLockStatus,
+    profile_id: args.profileId,
+    encrypted: true,
+    auto_lock_minutes: args.minutes ?? null,
+  }),
+
   // Incognito commands
   get_incognito_status: () => mockIncognitoStatus,
   toggle_incognito: () => {
@@ -302,9 +338,11 @@ const mockCommands: Record<string, (args?: any) => any> = {
   get_indexed_pages: () => [],
   index_page: (args: any) => ({ id: Date.now(), ...args.page }),
   search_memory: () => [],
+  search_memory_paged: (args: any) => ({ hits: [], total: 0, limit: args.limit ?? 20, offset: args.offset ?? 0 }),
   get_favorite_pages: () => [],
   toggle_page_favorite: () => true,
   update_page_tags: () => undefined,
+  update_page_summary: () => undefined,
   delete_indexed_page: () => true,
   add_page_note: (args: any) => ({ id: Date.now(), page_id: args.pageId, content: args.content, created_at: Date.now().toString() }),
   get_page_notes: () => [],
@@ -557,27 +595,28 @@ const mockCommands: Record<string, (args?: any) => any> = {
   get_bookmarks_by_location: (args: any) => mockBookmarks.filter(b => b.location === args.location && b.profile_id === args.profile_id && !b.url.startsWith('earth://')),
 
   // Password Manager commands
-  has_password_manager_master: (args: any) => passwordManagerMaster[args.profile_id] !== undefined,
+  has_password_manager_master: (args: any) => passwordManagerMaster[args.profileId] !== undefined,
   verify_password_manager_master: (args: any) => {
-    const stored = passwordManagerMaster[args.profile_id];
+    const stored = passwordManagerMaster[args.profileId];
     if (!stored) return true;
     return args.password === stored;
   },
   set_password_manager_master: (args: any) => {
-    passwordManagerMaster[args.profile_id] = args.password;
+    passwordManagerMaster[args.profileId] = args.password;
     return true;
   },
-  get_password_entries: (args: any) => passwordEntries.filter(e => e.profile_id === args.profile_id),
+  get_password_entries: (args: any) => passwordEntries.filter(e => e.profile_id === args.profileId),
   add_password_entry: (args: any) => {
     const entry = {
       id: Date.now(),
-      profile_id: args.profile_id,
+      profile_id: args.profileId,
       title: args.title,
       username: args.username,
       password: args.password,
       url: args.url,
       notes: args.notes,
       category: args.category || 'General',
+      custom_fields: args.customFields || [],
       created_at: new Date().toISOString(),
       updated_at: new Date().toISOString(),
     };
@@ -585,7 +624,7 @@ const mockCommands: Record<string, (args?: any) => any> = {
     return entry;
   },
   update_password_entry: (args: any) => {
-    const entry = passwordEntries.find(e => e.id === args.entry_id);
+    const entry = passwordEntries.find(e => e.id === args.entryId);
     if (entry) {
       entry.title = args.title;
       entry.username = args.username;
@@ -593,48 +632,67 @@ const mockCommands: Record<string, (args?: any) => any> = {
       entry.url = args.url;
       entry.notes = args.notes;
       entry.category = args.category;
+      if (args.customFields) entry.custom_fields = args.customFields;
       entry.updated_at = new Date().toISOString();
     }
     return entry;
   },
   delete_password_entry: (args: any) => {
-    passwordEntries = passwordEntries.filter(e => e.id !== args.entry_id);
+    passwordEntries = passwordEntries.filter(e => e.id !== args.entryId);
     return true;
   },
+  lock_password_manager: () => undefined,
+  search_password_entries: (args: any) => {
+    const query = args.query.toLowerCase();
+    return passwordEntries.filter(e => e.profile_id === args.profileId &&
+      [e.title, e.username, e.url, e.notes, e.category].some(v => v && v.toLowerCase().includes(query)));
+  },
+  import_password_csv: () => ({ format: 'generic', imported: 0, skipped: 0 }),
+  generate_password: () => 'mock-Generated-Pass-123!',
+  estimate_password_strength: (args: any) => ({
+    score: Math.min(4, Math.floor(args.password.length / 4)),
+    label: 'fair',
+    entropy_bits: args.password.length * 4,
+    feedback: [],
+  }),
 
   // OTP Authenticator commands
-  has_otp_master: (args: any) => otpMaster[args.profile_id] !== undefined,
+  has_otp_master: (args: any) => otpMaster[args.profileId] !== undefined,
   verify_otp_master: (args: any) => {
-    const stored = otpMaster[args.profile_id];
+    const stored = otpMaster[args.profileId];
     if (!stored) return true;
     return args.password === stored;
   },
   set_otp_master: (args: any) => {
-    otpMaster[args.profile_id] = args.password;
+    otpMaster[args.profileId] = args.password;
     return true;
   },
-  get_otp_entries: (args: any) => otpEntries.filter(e => e.profile_id === args.profile_id),
+  get_otp_entries: (args: any) => otpEntries.filter(e => e.profile_id === args.profileId),
   add_otp_entry: (args: any) => {
     const entry = {
       id: Date.now(),
-      profile_id: args.profile_id,
+      profile_id: args.profileId,
       name: args.name,
       issuer: args.issuer,
       secret: args.secret,
+      kind: args.kind || 'totp',
       algorithm: args.algorithm || 'SHA1',
       digits: args.digits || 6,
       period: args.period || 30,
+      counter: args.counter || 0,
       created_at: new Date().toISOString(),
+      updated_at: new Date().toISOString(),
     };
     otpEntries.push(entry);
     return entry;
   },
   update_otp_entry: (args: any) => {
-    const entry = otpEntries.find(e => e.id === args.entry_id);
+    const entry = otpEntries.find(e => e.id === args.entryId);
     if (entry) {
       entry.name = args.name;
       entry.issuer = args.issuer;
       entry.secret = args.secret;
+      entry.kind = args.kind ?? entry.kind;
       entry.algorithm = args.algorithm;
       entry.digits = args.digits;
       entry.period = args.period;
@@ -642,9 +700,51 @@ const mockCommands: Record<string, (args?: any) => any> = {
     return entry;
   },
   delete_otp_entry: (args: any) => {
-    otpEntries = otpEntries.filter(e => e.id !== args.entry_id);
+    otpEntries = otpEntries.filter(e => e.id !== args.entryId);
     return true;
   },
+  lock_otp: () => null,
+  get_otp_codes: (args: any) => {
+    const now = Math.floor(Date.now() / 1000);
+    return otpEntries
+      .filter(e => e.profile_id === args.profileId && e.kind !== 'hotp')
+      .map(e => ({
+        entry_id: e.id,
+        code: String((e.id + Math.floor(now / e.period)) % 10 ** e.digits).padStart(e.digits, '0'),
+        remaining_seconds: e.period - (now % e.period),
+      }));
+  },
+  next_hotp_code: (args: any) => {
+    const entry = otpEntries.find(e => e.id === args.entryId);
+    const counter = entry ? entry.counter++ : 0;
+    return { entry_id: args.entryId, code: String(counter).padStart(6, '0'), remaining_seconds: null };
+  },
+  verify_otp_code: () => true,
+  import_otp_uri: (args: any) => {
+    const url = new URL(args.uri);
+    const label = decodeURIComponent(url.pathname.replace(/^\/+/, ''));
+    const [issuer, name] = label.includes(':') ? label.split(':', 2) : ['', label];
+    const entry = {
+      id: Date.now(),
+      profile_id: args.profileId,
+      name,
+      issuer: url.searchParams.get('issuer') || issuer,
+      secret: url.searchParams.get('secret') || '',
+      kind: url.host === 'hotp' ? 'hotp' : 'totp',
+      algorithm: url.searchParams.get('algorithm') || 'SHA1',
+      digits: Number(url.searchParams.get('digits')) || 6,
+      period: Number(url.searchParams.get('period')) || 30,
+      counter: Number(url.searchParams.get('counter')) || 0,
+      created_at: new Date().toISOString(),
+      updated_at: new Date().toISOString(),
+    };
+    otpEntries.push(entry);
+    return entry;
+  },
+  export_otp_uri: (args: any) => {
+    const e = otpEntries.find(entry => entry.id === args.entryId);
+    return e ? `otpauth://${e.kind}/${encodeURIComponent(e.issuer)}:${encodeURIComponent(e.name)}?secret=${e.secret}` : '';
+  },
 
   // Split view commands
   get_split_config: () => mockSplitConfig,
@@ -723,6 +823,7 @@ const mockCommands: Record<string, (args?: any) => any> = {
     thumbnail: args.thumbnail,
     position: 0,
     added_at: new Date().toISOString(),
+    encrypted: false,
   }),
   get_media_playlist_items: () => [],
   remove_from_media_playlist: () => undefined,
@@ -756,6 +857,7 @@ const mockCommands: Record<string, (args?: any) => any> = {
     max_pages: 100,
     content_selectors: [],
     schedule_cron: null,
+    settings: mockCrawlSettings,
     status: 'pending',
     last_run_at: null,
     pages_scraped: 0,
@@ -764,14 +866,119 @@ const mockCommands: Record<string, (args?: any) => any> = {
   delete_scraping_job: () => undefined,
   get_scraped_pages: () => [],
   search_scraped_content: () => [],
+  run_scraping_job: () => 'run_scraping_job-1',
+  pause_scraping_job: () => undefined,
+  resume_scraping_job: () => undefined,
+  cancel_scraping_job: () => true,
+  set_scraping_schedule: (args: any) => ({
+    id: args.jobId,
+    profile_id: 1,
+    name: 'Mock Job',
+    base_url: 'https://example.com',
+    url_pattern: null,
+    max_depth: 2,
+    max_pages: 100,
+    content_selectors: [],
+    schedule_cron: args.scheduleCron,
+    settings: mockCrawlSettings,
+    status: 'pending',
+    last_run_at: null,
+    pages_scraped: 0,
+    created_at: new Date().toISOString(),
+  }),
+  set_scraping_settings: (args: any) => ({
+    id: args.jobId,
+    profile_id: 1,
+    name: 'Mock Job',
+    base_url: 'https://example.com',
+    url_pattern: null,
+    max_depth: 2,
+    max_pages: 100,
+    content_selectors: [],
+    schedule_cron: null,
+    settings: { ...mockCrawlSettings, ...args.settings },
+    status: 'pending',
+    last_run_at: null,
+    pages_scraped: 0,
+    created_at: new Date().toISOString(),
+  }),
+  get_scraping_runs: () => [],
+  get_page_versions: () => [],
+  diff_page_versions: (args: any) => ({
+    page_id: 1,
+    from: { id: args.fromId, page_id: 1, title: null, content_hash: null, scraped_at: new Date().toISOString() },
+    to: { id: args.toId, page_id: 1, title: null, content_hash: null, scraped_at: new Date().toISOString() },
+    segments: [],
+    words_added: 0,
+    words_removed: 0,
+  }),
+  set_page_watch: () => undefined,
+
+  // Backup commands (create and restore run as tasks)
+  create_profile_backup: () => 'create_profile_backup-1',
+  inspect_backup: () => ({
+    format: 'earthservers-backup',
+    version: 1,
+    schema_version: 1,
+    created_at: Date.now().toString(),
+    profile: { name: mockProfile.name, icon: null, created_at: mockProfile.created_at },
+    tables: [],
+  }),
+  restore_profile_backup: () => 'restore_profile_backup-1',
+
+  // Task commands
+  cancel_task: () => false,
+  list_tasks: () => [],
 };
 
+export type ErrorKind =
+  | 'not_found'
+  | 'validation'
+  | 'crypto'
+  | 'locked'
+  | 'io'
+  | 'network'
+  | 'database'
+  | 'cancelled'
+  | 'internal';
+
+// Shape of every error returned by a backend command
+export interface AppErrorPayload {
+  kind: ErrorKind;
+  message: string;
+  details: Record<string, unknown> | null;
+}
+
+// Thrown by invoke() for backend errors; check `kind` rather than parsing the message
+export class CommandError extends Error {
+  kind: ErrorKind;
+  details: Record<string, unknown> | null;
+
+  constructor(payload: AppErrorPayload) {
+    super(payload.message);
+    this.name = 'CommandError';
+    this.kind = payload.kind;
+    this.details = payload.details;
+  }
+
+  toString() {
+    return this.message;
+  }
+}
+
+const isAppErrorPayload = (value: unknown): value is AppErrorPayload =>
+  typeof value === 'object' && value !== null && 'kind' in value && 'message' in value;
+
 // Wrapper for Tauri invoke that falls back to mock data in browser
 export async function invoke<T>(cmd: string, args?: Record<string, unknown>): Promise<T> {
   if (isTauri()) {
     // Use actual Tauri invoke
     const { invoke: tauriInvoke } = await import('@tauri-apps/api/tauri');
-    return tauriInvoke<T>(cmd, args);
+    try {
+      return await tauriInvoke<T>(cmd, args);
+    } catch (err) {
+      throw isAppErrorPayload(err) ? new CommandError(err) : err;
+    }
   }
 
   // Browser fallback with mock data
@@ -804,3 +1011,74 @@ export async function listen<T>(
     console.log(`[Mock] unlisten for event: ${event}`);
   };
 }
+
+// Returned by the profile encryption commands, and the payload of profile-lock-changed,
+// which is emitted when a profile locks itself after inactivity
+export interface ProfileLockStatus {
+  profile_id: number;
+  encrypted: boolean;
+  locked: boolean;
+  auto_lock_minutes: number | null;
+}
+
+export type TaskStatus = 'running' | 'completed' | 'cancelled' | 'failed';
+
+// Payload of the task-progress event emitted by background tasks
+export interface TaskEvent {
+  task_id: string;
+  kind: string;
+  status: TaskStatus;
+  done: number;
+  total: number | null;
+  result: unknown;
+  error: AppErrorPayload | null;
+}
+
+// Run a command that starts a background task and resolve with the task's result.
+// Rejects if the task fails; a cancelled task resolves with whatever it finished.
+// In the browser the mock returns the result directly.
+export async function runTask<T>(
+  cmd: string,
+  args?: Record<string, unknown>,
+  onProgress?: (event: TaskEvent) => void
+): Promise<T> {
+  if (!isTauri()) {
+    return invoke<T>(cmd, args);
+  }
+
+  // Listen before invoking so a fast task can't finish unseen
+  let taskId: string | null = null;
+  const early: TaskEvent[] = [];
+  let settle: ((event: TaskEvent) => void) | null = null;
+
+  const unlisten = await listen<TaskEvent>('task-progress', ({ payload }) => {
+    if (taskId === null) {
+      early.push(payload);
+    } else if (payload.task_id === taskId) {
+      settle?.(payload);
+    }
+  });
+
+  try {
+    return await new Promise<T>((resolve, reject) => {
+      settle = (event) => {
+        if (event.status === 'running') {
+          onProgress?.(event);
+        } else if (event.status === 'failed') {
+          reject(event.error ? new CommandError(event.error) : new Error('Task failed'));
+        } else {
+          resolve(event.result as T);
+        }
+      };
+
+      invoke<string>(cmd, args)
+        .then((id) => {
+          taskId = id;
+          early.filter((event) => event.task_id === id).forEach((event) => settle?.(event));
+        })
+        .catch(reject);
+    });
+  } finally {
+    unlisten();
+  }
+}